@@ -1 +1 @@
-aws dynamodb create-table --table-name TODO     --attribute-definitions         AttributeName=PK,AttributeType=S         AttributeName=SK,AttributeType=S     --key-schema AttributeName=PK,KeyType=HASH AttributeName=SK,KeyType=RANGE     --provisioned-throughput ReadCapacityUnits=1,WriteCapacityUnits=1     --table-class STANDARD     --endpoint-url http://localhost:8000    --region us-east-1
\ No newline at end of file
+aws dynamodb create-table --table-name TODO     --attribute-definitions         AttributeName=PK,AttributeType=S         AttributeName=SK,AttributeType=S         AttributeName=dueShard,AttributeType=S         AttributeName=dueDateEpoch,AttributeType=N     --key-schema AttributeName=PK,KeyType=HASH AttributeName=SK,KeyType=RANGE     --provisioned-throughput ReadCapacityUnits=1,WriteCapacityUnits=1     --global-secondary-indexes "IndexName=DueDateShardIndex,KeySchema=[{AttributeName=dueShard,KeyType=HASH},{AttributeName=dueDateEpoch,KeyType=RANGE}],Projection={ProjectionType=ALL},ProvisionedThroughput={ReadCapacityUnits=1,WriteCapacityUnits=1}"     --table-class STANDARD     --endpoint-url http://localhost:8000    --region us-east-1
\ No newline at end of file
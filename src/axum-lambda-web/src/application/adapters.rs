@@ -1,11 +1,75 @@
 use std::collections::HashMap;
-use crate::application::domain::{OwnerId, Title, ToDo, ToDoId, ToDoRepo};
-use crate::application::error_types::RepositoryError;
+use std::sync::Mutex;
+use crate::application::domain::{
+    Attachment, Description, DueDate, OwnerId, Position, Preferences, Priority, ReminderLeadTime,
+    TenantId, Timezone, Title, ToDo, ToDoId, ToDoList, ToDoPage, ToDoRepo, INCOMPLETE_STATUS,
+};
+use crate::application::error_types::{RepositoryError, ValidationError};
+use crate::application::reminders::{ReminderKind, ReminderStore, CLAIM_TIMEOUT_MINUTES};
+use crate::application::search::{indexed_terms, SearchPosting};
+use crate::application::timers::{TimeEntry, TimerRepo};
+use crate::application::webhooks::{Webhook, WebhookDelivery, WebhookRepo, DELIVERY_LOG_LIMIT};
 use async_trait::async_trait;
-use aws_sdk_dynamodb::error::ProvideErrorMetadata;
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{
+    AttributeValue, Delete, KeysAndAttributes, Put, ReturnValuesOnConditionCheckFailure,
+    TableStatus, TransactWriteItem, Update,
+};
 use aws_sdk_dynamodb::Client;
-use chrono::{DateTime};
+use chrono::{DateTime, Duration, Utc};
+
+// A ToDo whose search terms keep changing while it is being stored is given up on after this
+// many attempts.
+const MAX_WRITE_ATTEMPTS: usize = 3;
+
+// The most keys a single BatchGetItem request can read.
+const MAX_BATCH_GET_KEYS: usize = 100;
+
+// Open ToDos with a due date are found through this index, sorted by `dueDateEpoch`, so the
+// reminder scheduler never has to scan the table. They are spread over `DUE_DATE_SHARDS`
+// partitions by their `dueShard`, rather than all sharing one, and the scheduler queries each.
+const DUE_DATE_INDEX: &str = "DueDateShardIndex";
+const DUE_DATE_SHARDS: u32 = 16;
+
+// Each owner's preferences are stored in a single item alongside their ToDos.
+const PREFERENCES_SK: &str = "PREFERENCES";
+
+// Each owner's count of open ToDos is kept in a single item, so a create that would take them
+// over their quota fails the condition on updating it. The same item holds the last position
+// reserved for them, so adding a ToDo to the end of their list doesn't read every ToDo.
+const OWNER_LIST_SK: &str = "OWNER_LIST";
+
+// Each owner's running timer is kept in a single item, so starting a second one fails the
+// condition on writing it.
+const RUNNING_TIMER_SK: &str = "RUNNING_TIMER";
+
+// Deliveries are removed by the table's TTL once they are older than this, the log only ever
+// shows the most recent `DELIVERY_LOG_LIMIT` of them.
+const DELIVERY_LOG_RETENTION_DAYS: i64 = 7;
+
+// The shape of the ToDo items written by this version. Bump it, and add an upcaster to
+// `UPCASTERS`, whenever the attributes of a ToDo item change.
+const SCHEMA_VERSION: usize = 7;
+
+type Item = HashMap<String, AttributeValue>;
+
+// What storing a ToDo reads of the version it replaces.
+struct StoredToDo {
+    search_terms: Vec<String>,
+    status: String,
+    version: u64,
+}
+
+// Each upcaster migrates an item from the version before it, `UPCASTERS[0]` migrates a
+// version 1 item to version 2. Items written before `schemaVersion` existed are version 1.
+const UPCASTERS: [fn(&mut Item); SCHEMA_VERSION - 1] = [
+    add_default_priority,
+    add_default_tenant,
+    add_empty_attachments,
+    add_no_tracked_time,
+    confirm_existing_attachments,
+    add_initial_version,
+];
 
 pub struct DynamoDbToDoRepo {
     client: Client,
@@ -20,112 +84,2609 @@ impl DynamoDbToDoRepo {
 
 #[async_trait]
 impl ToDoRepo for DynamoDbToDoRepo {
-    async fn list(&self, user_id: &str) -> Result<Vec<ToDo>, RepositoryError> {
+    async fn list(&self, tenant: &TenantId, user_id: &str) -> Result<ToDoList, RepositoryError> {
         let res = self
             .client
             .query()
             .table_name(&self.table_name)
-            .key_condition_expression("PK = :hashKey")
-            .expression_attribute_values(":hashKey", generate_pk(user_id))
+            .key_condition_expression("PK = :hashKey AND begins_with(SK, :sortKeyPrefix)")
+            .expression_attribute_values(":hashKey", generate_pk(tenant, user_id))
+            .expression_attribute_values(":sortKeyPrefix", generate_sk(tenant, ""))
             .send()
             .await;
 
         match res {
-            Ok(query_res) => Ok({
-                let mut items: Vec<ToDo> = Vec::new();
+            Ok(query_res) => Ok(parse_todo_list(query_res.items())),
+            Err(e) => Err(RepositoryError::new(e.to_string())),
+        }
+    }
 
-                for item in query_res.items() {
-                    items.push(
-                        parse_todo_from_item(item)
-                    )
-                }
+    async fn list_page(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        start: Option<&str>,
+        page_size: usize,
+    ) -> Result<ToDoPage, RepositoryError> {
+        let res = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :hashKey AND begins_with(SK, :sortKeyPrefix)")
+            .expression_attribute_values(":hashKey", generate_pk(tenant, user_id))
+            .expression_attribute_values(":sortKeyPrefix", generate_sk(tenant, ""))
+            .set_exclusive_start_key(start.map(|start| {
+                HashMap::from([
+                    ("PK".to_string(), generate_pk(tenant, user_id)),
+                    ("SK".to_string(), AttributeValue::S(start.to_string())),
+                ])
+            }))
+            .limit(page_size as i32)
+            .send()
+            .await;
 
-                items
+        match res {
+            Ok(query_res) => Ok(ToDoPage {
+                list: parse_todo_list(query_res.items()),
+                // The sort key of the last item read, which is also where the next page starts.
+                next_page: query_res
+                    .last_evaluated_key()
+                    .and_then(|key| key.get("SK"))
+                    .and_then(|val| val.as_s().ok())
+                    .cloned(),
             }),
             Err(e) => Err(RepositoryError::new(e.to_string())),
         }
     }
 
     async fn create(&self, todo: &ToDo) -> Result<(), RepositoryError> {
-        let mut dynamo_request_builder = self
+        self.store(todo, None).await.map(|_| ())
+    }
+
+    async fn create_within_quota(
+        &self,
+        todo: &ToDo,
+        max_open_todos: usize,
+    ) -> Result<bool, RepositoryError> {
+        self.store(todo, Some(max_open_todos)).await
+    }
+
+    async fn get(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        self.read(tenant, user_id, todo_id, false).await
+    }
+
+    async fn get_for_update(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        self.read(tenant, user_id, todo_id, true).await
+    }
+
+    async fn get_many(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> Result<ToDoList, RepositoryError> {
+        let mut items: Vec<Item> = Vec::new();
+
+        for chunk in todo_ids.chunks(MAX_BATCH_GET_KEYS) {
+            let keys = chunk
+                .iter()
+                .map(|todo_id| {
+                    HashMap::from([
+                        ("PK".to_string(), generate_pk(tenant, user_id)),
+                        ("SK".to_string(), generate_sk(tenant, todo_id)),
+                    ])
+                })
+                .collect();
+
+            let mut pending = Some(
+                KeysAndAttributes::builder()
+                    .set_keys(Some(keys))
+                    .build()
+                    .map_err(|e| RepositoryError::new(e.to_string()))?,
+            );
+
+            // Keys the table was too busy to read are returned to be requested again.
+            for _ in 0..MAX_WRITE_ATTEMPTS {
+                let Some(keys) = pending.take() else {
+                    break;
+                };
+
+                let res = self
+                    .client
+                    .batch_get_item()
+                    .request_items(&self.table_name, keys)
+                    .send()
+                    .await
+                    .map_err(|e| RepositoryError::new(e.into_service_error().to_string()))?;
+
+                if let Some(read) = res.responses().and_then(|res| res.get(&self.table_name)) {
+                    items.extend(read.iter().cloned());
+                }
+
+                pending = res
+                    .unprocessed_keys()
+                    .and_then(|unprocessed| unprocessed.get(&self.table_name))
+                    .filter(|unprocessed| !unprocessed.keys().is_empty())
+                    .cloned();
+            }
+
+            if pending.is_some() {
+                return Err(RepositoryError::new(format!(
+                    "ToDos for {0} could not all be read, the table is too busy",
+                    user_id
+                )));
+            }
+        }
+
+        Ok(parse_todo_list(&items))
+    }
+
+    async fn search(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        terms: &[String],
+    ) -> Result<Vec<SearchPosting>, RepositoryError> {
+        let mut postings: Vec<SearchPosting> = Vec::new();
+
+        for term in terms {
+            let res = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :hashKey AND begins_with(SK, :sortKeyPrefix)")
+                .expression_attribute_values(":hashKey", generate_pk(tenant, user_id))
+                .expression_attribute_values(
+                    ":sortKeyPrefix",
+                    AttributeValue::S(format!("SEARCH#{0}#", term)),
+                )
+                .send()
+                .await
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            for item in res.items() {
+                postings.push(parse_posting_from_item(item)?);
+            }
+        }
+
+        Ok(postings)
+    }
+
+    async fn reserve_positions(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        count: usize,
+    ) -> Result<Vec<Position>, RepositoryError> {
+        for _ in 0..MAX_WRITE_ATTEMPTS {
+            let res = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key("PK", generate_pk(tenant, user_id))
+                .key("SK", AttributeValue::S(OWNER_LIST_SK.to_string()))
+                .projection_expression("lastPosition")
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            let reserved = res
+                .item()
+                .and_then(|item| item.get("lastPosition"))
+                .map(|val| {
+                    val.as_s()
+                        .ok()
+                        .and_then(|val| Position::parse(val).ok())
+                        .ok_or_else(|| {
+                            RepositoryError::new(format!("'lastPosition' is not valid: {0:?}", val))
+                        })
+                })
+                .transpose()?;
+
+            // Owners whose ToDos were added before positions were reserved start after the last
+            // of their ToDos.
+            let last = match &reserved {
+                Some(reserved) => Some(reserved.clone()),
+                None => self
+                    .list(tenant, user_id)
+                    .await?
+                    .todos
+                    .iter()
+                    .filter_map(ToDo::get_position)
+                    .max()
+                    .cloned(),
+            };
+
+            let positions = Position::sequence(last.as_ref(), count);
+
+            let next_last = match positions.last() {
+                Some(next_last) => next_last,
+                None => return Ok(positions),
+            };
+
+            let update = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", generate_pk(tenant, user_id))
+                .key("SK", AttributeValue::S(OWNER_LIST_SK.to_string()))
+                .update_expression("SET lastPosition = :nextLast")
+                .expression_attribute_values(
+                    ":nextLast",
+                    AttributeValue::S(next_last.to_string().into()),
+                );
+
+            let update = match &reserved {
+                Some(reserved) => update
+                    .condition_expression("lastPosition = :lastPosition")
+                    .expression_attribute_values(
+                        ":lastPosition",
+                        AttributeValue::S(reserved.to_string().into()),
+                    ),
+                None => update.condition_expression("attribute_not_exists(lastPosition)"),
+            };
+
+            match update.send().await {
+                Ok(_) => return Ok(positions),
+                Err(e) => {
+                    let service_error = e.into_service_error();
+
+                    // Another request reserved a position first, start again from it.
+                    if !service_error.is_conditional_check_failed_exception() {
+                        return Err(RepositoryError::new(service_error.to_string()));
+                    }
+                }
+            }
+        }
+
+        Err(RepositoryError::new(format!(
+            "Positions for {0} were reserved by other requests while being reserved",
+            user_id
+        )))
+    }
+
+    async fn set_position(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+        position: &Position,
+    ) -> Result<(), RepositoryError> {
+        let res = self
             .client
-            .put_item()
+            .update_item()
             .table_name(&self.table_name)
-            .item("PK", generate_pk(todo.get_owner()))
-            .item("SK", generate_sk(todo.get_id()))
-            .item("id", AttributeValue::S(todo.get_id().into()))
-            .item("title", AttributeValue::S(todo.get_title().into()))
-            .item("status", AttributeValue::S(todo.get_status()))
-            .item("ownerId", AttributeValue::S(todo.get_owner().into()));
-
-        if !todo.get_completed_on().is_empty() {
-            dynamo_request_builder = dynamo_request_builder.item(
-                "completedOn",
-                AttributeValue::S(todo.get_completed_on()),
-            );
+            .key("PK", generate_pk(tenant, user_id))
+            .key("SK", generate_sk(tenant, todo_id))
+            .update_expression("SET #position = :position ADD #version :one")
+            .condition_expression("attribute_exists(SK)")
+            .expression_attribute_names("#position", "position")
+            .expression_attribute_names("#version", "version")
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+            .expression_attribute_values(":position", AttributeValue::S(position.to_string().into()))
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_error = e.into_service_error();
+
+                if service_error.is_conditional_check_failed_exception() {
+                    Err(RepositoryError::not_found(format!("ToDo {0} not found", todo_id)))
+                } else {
+                    Err(RepositoryError::new(service_error.to_string()))
+                }
+            }
         }
+    }
+
+    async fn health_check(&self) -> Result<(), RepositoryError> {
+        let res = self
+            .client
+            .describe_table()
+            .table_name(&self.table_name)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.into_service_error().to_string()))?;
 
-        if !todo.get_description().is_empty() {
-            dynamo_request_builder = dynamo_request_builder
-                .item("description", AttributeValue::S(todo.get_description().to_string()));
+        match res.table().and_then(|table| table.table_status()) {
+            Some(TableStatus::Active) | Some(TableStatus::Updating) => Ok(()),
+            status => Err(RepositoryError::new(format!(
+                "Table {} is not active, status {:?}",
+                self.table_name, status
+            ))),
         }
+    }
 
-        if !todo.get_due_date().is_empty() {
-            dynamo_request_builder = dynamo_request_builder.item(
-                "dueDate",
-                AttributeValue::S(todo.get_due_date().to_string()),
-            );
+    async fn get_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+    ) -> Result<Preferences, RepositoryError> {
+        let res = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(tenant, user_id))
+            .key("SK", AttributeValue::S(PREFERENCES_SK.to_string()))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        match res.item() {
+            Some(item) => parse_preferences_from_item(item),
+            None => Ok(Preferences::default()),
         }
+    }
 
-        let _ = dynamo_request_builder.send().await;
+    async fn set_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        preferences: &Preferences,
+    ) -> Result<(), RepositoryError> {
+        let mut item = preferences_to_item(preferences);
+        item.insert("PK".to_string(), generate_pk(tenant, user_id));
+        item.insert("SK".to_string(), AttributeValue::S(PREFERENCES_SK.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
 
         Ok(())
     }
+}
 
-    async fn get(&self, user_id: &str, todo_id: &str) -> Result<ToDo, RepositoryError> {
+impl DynamoDbToDoRepo {
+    async fn read(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+        consistent_read: bool,
+    ) -> Result<ToDo, RepositoryError> {
         let res = self
             .client
             .get_item()
             .table_name(&self.table_name)
-            .key("PK", generate_pk(user_id))
-            .key("SK", generate_sk(todo_id))
+            .key("PK", generate_pk(tenant, user_id))
+            .key("SK", generate_sk(tenant, todo_id))
+            .consistent_read(consistent_read)
             .send()
             .await;
 
         match res {
-            Ok(item) => Ok({
-                let attributes = item.item().unwrap().clone();
+            Ok(item) => match item.item() {
+                Some(attributes) => parse_todo_from_item(attributes),
+                None => Err(RepositoryError::not_found(format!("ToDo {0} not found", todo_id))),
+            },
+            Err(e) => Err(RepositoryError::new(e.into_service_error().to_string())),
+        }
+    }
 
-                parse_todo_from_item(&attributes)
-            }),
-            Err(e) => Err(RepositoryError::new(
-                e.into_service_error().message().unwrap().to_string(),
-            )),
+    /// Store a ToDo, its search index and its owner's count of open ToDos in a single
+    /// transaction. Fails with a `Conflict` error if the stored ToDo is no longer the version
+    /// the ToDo was read at, checked again if it changes between reading and writing it. With a
+    /// `max_open_todos` nothing is stored, and `false` returned, if the ToDo would take its
+    /// owner over it.
+    async fn store(
+        &self,
+        todo: &ToDo,
+        max_open_todos: Option<usize>,
+    ) -> Result<bool, RepositoryError> {
+        for _ in 0..MAX_WRITE_ATTEMPTS {
+            let previous = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key("PK", generate_pk(todo.get_tenant(), todo.get_owner()))
+                .key("SK", generate_sk(todo.get_tenant(), todo.get_id()))
+                .projection_expression("SK, searchTerms, #status, #version")
+                .expression_attribute_names("#status", "status")
+                .expression_attribute_names("#version", "version")
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            let previous = previous.item().map(|item| StoredToDo {
+                search_terms: item
+                    .get("searchTerms")
+                    .and_then(|val| val.as_ss().ok())
+                    .cloned()
+                    .unwrap_or_default(),
+                status: item
+                    .get("status")
+                    .and_then(|val| val.as_s().ok())
+                    .cloned()
+                    .unwrap_or_default(),
+                // ToDos written before versions were stored are still at their first.
+                version: item
+                    .get("version")
+                    .and_then(|val| val.as_n().ok())
+                    .and_then(|val| val.parse::<u64>().ok())
+                    .unwrap_or_default(),
+            });
+
+            let stored_version = previous.as_ref().map_or(0, |previous| previous.version);
+
+            if stored_version != todo.get_version() {
+                return Err(RepositoryError::conflict(format!(
+                    "ToDo {0} was changed by another request since it was read",
+                    todo.get_id()
+                )));
+            }
+
+            let mut writes = self.todo_writes(todo, previous.as_ref())?;
+
+            let was_open = previous
+                .as_ref()
+                .is_some_and(|previous| previous.status == INCOMPLETE_STATUS);
+            let open_change = todo.is_open() as i64 - was_open as i64;
+            let counted_at = writes.len();
+
+            if open_change != 0 {
+                writes.push(self.open_todos_update(todo, open_change, max_open_todos)?);
+            }
+
+            let res = self
+                .client
+                .transact_write_items()
+                .set_transact_items(Some(writes))
+                .send()
+                .await;
+
+            let error = match res {
+                Ok(_) => return Ok(true),
+                Err(e) => e.into_service_error(),
+            };
+
+            // The count only fails its condition if it is missing, or the owner is at their
+            // quota. Any other condition fails because the ToDo changed since it was read, in
+            // which case it is read again.
+            match failed_condition(&error) {
+                Some(FailedCondition { index, item }) if index == counted_at => {
+                    if item.is_some_and(|item| item.contains_key("openTodos")) {
+                        return Ok(false);
+                    }
+
+                    self.start_open_todos_count(todo.get_tenant(), todo.get_owner())
+                        .await?;
+                }
+                Some(_) => {}
+                None => return Err(RepositoryError::new(error.to_string())),
+            }
         }
+
+        Err(RepositoryError::new(format!(
+            "ToDo {0} was changed by another request while being stored",
+            todo.get_id()
+        )))
     }
-}
 
-fn parse_todo_from_item(item: &HashMap<String, AttributeValue>) -> ToDo {
-    ToDo::parse(
-        Title::new(item.get("title").unwrap().as_s().unwrap()).unwrap(),
-        OwnerId::new(item.get("ownerId").unwrap().as_s().unwrap())
-            .unwrap(),
-        Some(item.get("status").unwrap().as_s().unwrap().clone()),
-        Some(
-            ToDoId::parse(item.get("id").unwrap().as_s().unwrap())
-                .unwrap(),
-        ),
-        item.get("description").map(|val| val.as_s().unwrap().clone()),
-        item.get("dueDate").map(|val| DateTime::parse_from_rfc3339(val.as_s().unwrap()).unwrap()),
-        item.get("completedOn").map(|val| DateTime::parse_from_rfc3339(val.as_s().unwrap()).unwrap()),
-    )
-        .unwrap()
+    /// Add `change` to the owner's count of open ToDos, which must already exist. When adding
+    /// to it with a `max_open_todos` the count must also be below it.
+    fn open_todos_update(
+        &self,
+        todo: &ToDo,
+        change: i64,
+        max_open_todos: Option<usize>,
+    ) -> Result<TransactWriteItem, RepositoryError> {
+        let update = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(todo.get_tenant(), todo.get_owner()))
+            .key("SK", AttributeValue::S(OWNER_LIST_SK.to_string()))
+            .update_expression("ADD openTodos :change")
+            .expression_attribute_values(":change", AttributeValue::N(change.to_string()))
+            .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld);
+
+        let update = match max_open_todos {
+            Some(max_open_todos) if change > 0 => update
+                .condition_expression("attribute_exists(openTodos) AND openTodos < :maxOpenTodos")
+                .expression_attribute_values(
+                    ":maxOpenTodos",
+                    AttributeValue::N(max_open_todos.to_string()),
+                ),
+            _ => update.condition_expression("attribute_exists(openTodos)"),
+        };
+
+        Ok(TransactWriteItem::builder()
+            .update(update.build().map_err(|e| RepositoryError::new(e.to_string()))?)
+            .build())
+    }
+
+    /// Owners whose ToDos were stored before open ToDos were counted have no count yet, it is
+    /// started from the ToDos they already have. If another request has started it first,
+    /// that count is kept.
+    async fn start_open_todos_count(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<(), RepositoryError> {
+        let open_todos = self
+            .list(tenant, owner)
+            .await?
+            .todos
+            .iter()
+            .filter(|todo| todo.is_open())
+            .count();
+
+        let res = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(tenant, owner))
+            .key("SK", AttributeValue::S(OWNER_LIST_SK.to_string()))
+            .update_expression("SET openTodos = :openTodos")
+            .condition_expression("attribute_not_exists(openTodos)")
+            .expression_attribute_values(":openTodos", AttributeValue::N(open_todos.to_string()))
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let service_error = e.into_service_error();
+
+                if service_error.is_conditional_check_failed_exception() {
+                    Ok(())
+                } else {
+                    Err(RepositoryError::new(service_error.to_string()))
+                }
+            }
+        }
+    }
+
+    /// The writes that store a ToDo and keep its search index in step, applied as a single
+    /// transaction. An index entry is written for every term in the ToDo, and the entries for
+    /// any terms of the `previous` stored version no longer present are removed. The ToDo is
+    /// only written if its stored terms, status and version are still those of `previous`, or
+    /// if it doesn't exist when that is `None`.
+    fn todo_writes(
+        &self,
+        todo: &ToDo,
+        previous: Option<&StoredToDo>,
+    ) -> Result<Vec<TransactWriteItem>, RepositoryError> {
+        let terms = indexed_terms(todo);
+        let indexed_on = Utc::now().timestamp_millis();
+
+        let mut item = todo_to_item(todo);
+
+        if !terms.is_empty() {
+            item.insert(
+                "searchTerms".to_string(),
+                AttributeValue::Ss(terms.keys().cloned().collect()),
+            );
+        }
+
+        let put = Put::builder().table_name(&self.table_name).set_item(Some(item));
+
+        let put = match previous {
+            None => put.condition_expression("attribute_not_exists(SK)"),
+            Some(previous) => {
+                let terms_condition = match previous.search_terms.is_empty() {
+                    true => "attribute_not_exists(searchTerms)",
+                    false => "searchTerms = :previousTerms",
+                };
+                let version_condition = match previous.version {
+                    0 => "attribute_not_exists(#version)",
+                    _ => "#version = :previousVersion",
+                };
+
+                let put = put
+                    .condition_expression(format!(
+                        "#status = :previousStatus AND {0} AND {1}",
+                        terms_condition, version_condition
+                    ))
+                    .expression_attribute_names("#status", "status")
+                    .expression_attribute_names("#version", "version")
+                    .expression_attribute_values(
+                        ":previousStatus",
+                        AttributeValue::S(previous.status.clone()),
+                    );
+
+                let put = match previous.search_terms.is_empty() {
+                    true => put,
+                    false => put.expression_attribute_values(
+                        ":previousTerms",
+                        AttributeValue::Ss(previous.search_terms.clone()),
+                    ),
+                };
+
+                match previous.version {
+                    0 => put,
+                    version => put.expression_attribute_values(
+                        ":previousVersion",
+                        AttributeValue::N(version.to_string()),
+                    ),
+                }
+            }
+        };
+
+        let mut writes = vec![TransactWriteItem::builder()
+            .put(put.build().map_err(|e| RepositoryError::new(e.to_string()))?)
+            .build()];
+
+        for term in previous
+            .map(|previous| previous.search_terms.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|term| !terms.contains_key(*term))
+        {
+            let delete = Delete::builder()
+                .table_name(&self.table_name)
+                .key("PK", generate_pk(todo.get_tenant(), todo.get_owner()))
+                .key("SK", generate_search_sk(term, todo.get_id()))
+                .build()
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            writes.push(TransactWriteItem::builder().delete(delete).build());
+        }
+
+        for (term, frequency) in &terms {
+            let put = Put::builder()
+                .table_name(&self.table_name)
+                .item("PK", generate_pk(todo.get_tenant(), todo.get_owner()))
+                .item("SK", generate_search_sk(term, todo.get_id()))
+                .item("id", AttributeValue::S(todo.get_id().into()))
+                .item("term", AttributeValue::S(term.clone()))
+                .item("frequency", AttributeValue::N(frequency.to_string()))
+                .item("indexedOn", AttributeValue::N(indexed_on.to_string()))
+                .build()
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            writes.push(TransactWriteItem::builder().put(put).build());
+        }
+
+        Ok(writes)
+    }
 }
 
-fn generate_pk(user_id: &str) -> AttributeValue {
-    AttributeValue::S(format!("USER#{0}", user_id.to_uppercase()))
+#[async_trait]
+impl ReminderStore for DynamoDbToDoRepo {
+    async fn list_due_before(&self, due_before: DateTime<Utc>) -> Result<Vec<ToDo>, RepositoryError> {
+        let mut items: Vec<ToDo> = Vec::new();
+
+        for shard in 0..DUE_DATE_SHARDS {
+            let mut exclusive_start_key: Option<HashMap<String, AttributeValue>> = None;
+
+            loop {
+                let res = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    // Only incomplete ToDo items have a shard, whichever tenant they belong to.
+                    .index_name(DUE_DATE_INDEX)
+                    .key_condition_expression("dueShard = :dueShard AND dueDateEpoch <= :dueBefore")
+                    .expression_attribute_values(":dueShard", due_shard_key(shard))
+                    .expression_attribute_values(
+                        ":dueBefore",
+                        AttributeValue::N(due_before.timestamp().to_string()),
+                    )
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+                for item in res.items() {
+                    match parse_todo_from_item(item) {
+                        Ok(todo) => items.push(todo),
+                        Err(e) => tracing::warn!("Skipping unreadable ToDo when sending reminders: {}", e),
+                    }
+                }
+
+                exclusive_start_key = res.last_evaluated_key().cloned();
+
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    async fn index_due_dates(&self) -> Result<usize, RepositoryError> {
+        let mut indexed = 0;
+        let mut exclusive_start_key: Option<HashMap<String, AttributeValue>> = None;
+
+        loop {
+            let res = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression(
+                    "#status = :status AND attribute_exists(dueDateEpoch) AND attribute_not_exists(dueShard)",
+                )
+                .projection_expression("PK, SK, id")
+                .expression_attribute_names("#status", "status")
+                .expression_attribute_values(":status", AttributeValue::S(INCOMPLETE_STATUS.to_string()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+            for item in res.items() {
+                let (Some(pk), Some(sk), Some(id)) = (
+                    item.get("PK"),
+                    item.get("SK"),
+                    item.get("id").and_then(|val| val.as_s().ok()),
+                ) else {
+                    continue;
+                };
+
+                // Left alone if it was completed, or stored again with its shard, since the scan.
+                let res = self
+                    .client
+                    .update_item()
+                    .table_name(&self.table_name)
+                    .key("PK", pk.clone())
+                    .key("SK", sk.clone())
+                    .update_expression("SET dueShard = :dueShard")
+                    .condition_expression("#status = :status AND attribute_not_exists(dueShard)")
+                    .expression_attribute_names("#status", "status")
+                    .expression_attribute_values(":status", AttributeValue::S(INCOMPLETE_STATUS.to_string()))
+                    .expression_attribute_values(":dueShard", due_shard_key(due_shard(id)))
+                    .send()
+                    .await;
+
+                match res {
+                    Ok(_) => indexed += 1,
+                    Err(e) => {
+                        let service_error = e.into_service_error();
+
+                        if !service_error.is_conditional_check_failed_exception() {
+                            return Err(RepositoryError::new(service_error.to_string()));
+                        }
+                    }
+                }
+            }
+
+            exclusive_start_key = res.last_evaluated_key().cloned();
+
+            if exclusive_start_key.is_none() {
+                return Ok(indexed);
+            }
+        }
+    }
+
+    async fn owner_preferences(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Preferences, RepositoryError> {
+        self.get_preferences(tenant, owner).await
+    }
+
+    async fn claim_reminder(
+        &self,
+        to_do: &ToDo,
+        kind: ReminderKind,
+        now: DateTime<Utc>,
+    ) -> Result<bool, RepositoryError> {
+        let claim_expired_before = now - Duration::minutes(CLAIM_TIMEOUT_MINUTES);
+
+        // Reminders recorded before claims were confirmed have no `claimedAt`, so are never
+        // claimed again.
+        let res = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("PK", generate_pk(to_do.get_tenant(), to_do.get_owner()))
+            .item("SK", generate_reminder_sk(to_do, kind))
+            .item("id", AttributeValue::S(to_do.get_id().into()))
+            .item("firedOn", AttributeValue::S(now.to_rfc3339()))
+            .item("claimedAt", AttributeValue::N(now.timestamp().to_string()))
+            .condition_expression("attribute_not_exists(SK) OR claimedAt < :claimExpiredBefore")
+            .expression_attribute_values(
+                ":claimExpiredBefore",
+                AttributeValue::N(claim_expired_before.timestamp().to_string()),
+            )
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_error = e.into_service_error();
+
+                if service_error.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(RepositoryError::new(service_error.to_string()))
+                }
+            }
+        }
+    }
+
+    async fn confirm_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError> {
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(to_do.get_tenant(), to_do.get_owner()))
+            .key("SK", generate_reminder_sk(to_do, kind))
+            .update_expression("SET sentOn = :sentOn REMOVE claimedAt")
+            .expression_attribute_values(":sentOn", AttributeValue::S(Utc::now().to_rfc3339()))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn release_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(to_do.get_tenant(), to_do.get_owner()))
+            .key("SK", generate_reminder_sk(to_do, kind))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        Ok(())
+    }
 }
 
-fn generate_sk(todo_id: &str) -> AttributeValue {
-    AttributeValue::S(format!("TODO#{0}", todo_id.to_uppercase()))
+#[async_trait]
+impl WebhookRepo for DynamoDbToDoRepo {
+    async fn list_webhooks(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Vec<Webhook>, RepositoryError> {
+        let res = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :hashKey AND begins_with(SK, :sortKeyPrefix)")
+            .expression_attribute_values(":hashKey", generate_pk(tenant, owner))
+            .expression_attribute_values(":sortKeyPrefix", generate_webhook_sk(""))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        res.items().iter().map(parse_webhook_from_item).collect()
+    }
+
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Option<Webhook>, RepositoryError> {
+        let res = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(tenant, owner))
+            .key("SK", generate_webhook_sk(webhook_id))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        res.item().map(parse_webhook_from_item).transpose()
+    }
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), RepositoryError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(webhook_to_item(webhook)))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<bool, RepositoryError> {
+        // The webhook's deliveries are left for the table's TTL to remove.
+        let res = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(tenant, owner))
+            .key("SK", generate_webhook_sk(webhook_id))
+            .condition_expression("attribute_exists(SK)")
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_error = e.into_service_error();
+
+                if service_error.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(RepositoryError::new(service_error.to_string()))
+                }
+            }
+        }
+    }
+
+    async fn record_delivery_result(
+        &self,
+        webhook: &Webhook,
+        succeeded: bool,
+        failure_threshold: u32,
+    ) -> Result<Option<Webhook>, RepositoryError> {
+        let mut current = webhook.clone();
+
+        for _ in 0..MAX_WRITE_ATTEMPTS {
+            let updated = match succeeded {
+                true => current.clone().record_success(),
+                false => current.clone().record_failure(failure_threshold),
+            };
+
+            if updated == current {
+                return Ok(Some(current));
+            }
+
+            // Only written if neither attribute has changed since the webhook was read, so a
+            // concurrent delivery or update is never lost.
+            let res = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", generate_pk(webhook.tenant(), webhook.owner()))
+                .key("SK", generate_webhook_sk(webhook.id()))
+                .update_expression("SET consecutiveFailures = :failures, enabled = :enabled")
+                .condition_expression(
+                    "attribute_exists(SK) AND consecutiveFailures = :expectedFailures AND enabled = :expectedEnabled",
+                )
+                .expression_attribute_values(
+                    ":failures",
+                    AttributeValue::N(updated.consecutive_failures().to_string()),
+                )
+                .expression_attribute_values(":enabled", AttributeValue::Bool(updated.is_enabled()))
+                .expression_attribute_values(
+                    ":expectedFailures",
+                    AttributeValue::N(current.consecutive_failures().to_string()),
+                )
+                .expression_attribute_values(
+                    ":expectedEnabled",
+                    AttributeValue::Bool(current.is_enabled()),
+                )
+                .send()
+                .await;
+
+            match res {
+                Ok(_) => return Ok(Some(updated)),
+                Err(e) => {
+                    let service_error = e.into_service_error();
+
+                    if !service_error.is_conditional_check_failed_exception() {
+                        return Err(RepositoryError::new(service_error.to_string()));
+                    }
+                }
+            }
+
+            current = match self
+                .get_webhook(webhook.tenant(), webhook.owner(), webhook.id())
+                .await?
+            {
+                Some(reloaded) => reloaded,
+                None => return Ok(None),
+            };
+        }
+
+        Err(RepositoryError::new(format!(
+            "Webhook {0} changed during every attempt to record a delivery",
+            webhook.id()
+        )))
+    }
+
+    async fn record_delivery(
+        &self,
+        webhook: &Webhook,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), RepositoryError> {
+        let expires_at = delivery.delivered_on + Duration::days(DELIVERY_LOG_RETENTION_DAYS);
+
+        let mut item = Item::from([
+            ("PK".to_string(), generate_pk(webhook.tenant(), webhook.owner())),
+            ("SK".to_string(), generate_delivery_sk(webhook.id(), delivery)),
+            ("id".to_string(), AttributeValue::S(delivery.delivery_id.clone())),
+            ("webhookId".to_string(), AttributeValue::S(delivery.webhook_id.clone())),
+            ("eventId".to_string(), AttributeValue::S(delivery.event_id.clone())),
+            ("eventType".to_string(), AttributeValue::S(delivery.event_type.clone())),
+            ("deliveredOn".to_string(), AttributeValue::S(delivery.delivered_on.to_rfc3339())),
+            ("attempts".to_string(), AttributeValue::N(delivery.attempts.to_string())),
+            ("expiresAt".to_string(), AttributeValue::N(expires_at.timestamp().to_string())),
+        ]);
+
+        if let Some(status_code) = delivery.status_code {
+            item.insert("statusCode".to_string(), AttributeValue::N(status_code.to_string()));
+        }
+
+        if let Some(error) = &delivery.error {
+            item.insert("error".to_string(), AttributeValue::S(error.clone()));
+        }
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError> {
+        let res = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :hashKey AND begins_with(SK, :sortKeyPrefix)")
+            .expression_attribute_values(":hashKey", generate_pk(tenant, owner))
+            .expression_attribute_values(
+                ":sortKeyPrefix",
+                AttributeValue::S(format!("WEBHOOK_DELIVERY#{0}#", webhook_id.to_uppercase())),
+            )
+            .scan_index_forward(false)
+            .limit(DELIVERY_LOG_LIMIT as i32)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        res.items().iter().map(parse_delivery_from_item).collect()
+    }
+}
+
+#[async_trait]
+impl TimerRepo for DynamoDbToDoRepo {
+    async fn running_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Option<TimeEntry>, RepositoryError> {
+        let res = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", generate_pk(tenant, owner))
+            .key("SK", AttributeValue::S(RUNNING_TIMER_SK.to_string()))
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        res.item().map(parse_time_entry_from_item).transpose()
+    }
+
+    async fn start_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError> {
+        let mut running_timer = time_entry_to_item(entry);
+        running_timer.insert("PK".to_string(), generate_pk(tenant, owner));
+        running_timer.insert("SK".to_string(), AttributeValue::S(RUNNING_TIMER_SK.to_string()));
+
+        let mut time_entry = time_entry_to_item(entry);
+        time_entry.insert("PK".to_string(), generate_pk(tenant, owner));
+        time_entry.insert("SK".to_string(), generate_time_entry_sk(entry));
+
+        let res = self
+            .client
+            .transact_write_items()
+            .transact_items(
+                TransactWriteItem::builder()
+                    .put(
+                        Put::builder()
+                            .table_name(&self.table_name)
+                            .set_item(Some(running_timer))
+                            .condition_expression("attribute_not_exists(SK)")
+                            .build()
+                            .map_err(|e| RepositoryError::new(e.to_string()))?,
+                    )
+                    .build(),
+            )
+            .transact_items(
+                TransactWriteItem::builder()
+                    .put(
+                        Put::builder()
+                            .table_name(&self.table_name)
+                            .set_item(Some(time_entry))
+                            .build()
+                            .map_err(|e| RepositoryError::new(e.to_string()))?,
+                    )
+                    .build(),
+            )
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            Err(e) => condition_failed(e.into_service_error()),
+        }
+    }
+
+    async fn stop_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError> {
+        let mut time_entry = time_entry_to_item(entry);
+        time_entry.insert("PK".to_string(), generate_pk(tenant, owner));
+        time_entry.insert("SK".to_string(), generate_time_entry_sk(entry));
+
+        let res = self
+            .client
+            .transact_write_items()
+            .transact_items(
+                TransactWriteItem::builder()
+                    .delete(
+                        Delete::builder()
+                            .table_name(&self.table_name)
+                            .key("PK", generate_pk(tenant, owner))
+                            .key("SK", AttributeValue::S(RUNNING_TIMER_SK.to_string()))
+                            .condition_expression("id = :entryId")
+                            .expression_attribute_values(
+                                ":entryId",
+                                AttributeValue::S(entry.id().to_string()),
+                            )
+                            .build()
+                            .map_err(|e| RepositoryError::new(e.to_string()))?,
+                    )
+                    .build(),
+            )
+            .transact_items(
+                TransactWriteItem::builder()
+                    .put(
+                        Put::builder()
+                            .table_name(&self.table_name)
+                            .set_item(Some(time_entry))
+                            .build()
+                            .map_err(|e| RepositoryError::new(e.to_string()))?,
+                    )
+                    .build(),
+            )
+            // Added rather than set, so time tracked by other timers is never overwritten. The
+            // version is bumped so an update that read the ToDo before this can't store it again.
+            .transact_items(
+                TransactWriteItem::builder()
+                    .update(
+                        Update::builder()
+                            .table_name(&self.table_name)
+                            .key("PK", generate_pk(tenant, owner))
+                            .key("SK", generate_sk(tenant, entry.to_do_id()))
+                            .update_expression("ADD trackedSeconds :duration, #version :one")
+                            .condition_expression("attribute_exists(SK)")
+                            .expression_attribute_names("#version", "version")
+                            .expression_attribute_values(
+                                ":duration",
+                                AttributeValue::N(entry.duration_seconds().to_string()),
+                            )
+                            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+                            .build()
+                            .map_err(|e| RepositoryError::new(e.to_string()))?,
+                    )
+                    .build(),
+            )
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let service_error = e.into_service_error();
+
+                match failed_condition(&service_error) {
+                    Some(FailedCondition { index: 2, .. }) => Err(RepositoryError::new(format!(
+                        "ToDo {0} was deleted while its timer was running",
+                        entry.to_do_id()
+                    ))),
+                    _ => condition_failed(service_error),
+                }
+            }
+        }
+    }
+}
+
+// The first write of a cancelled transaction whose condition did not hold, with the item it
+// was checked against when the write asked for it.
+struct FailedCondition<'a> {
+    index: usize,
+    item: Option<&'a Item>,
+}
+
+fn failed_condition(error: &TransactWriteItemsError) -> Option<FailedCondition<'_>> {
+    match error {
+        TransactWriteItemsError::TransactionCanceledException(cancelled) => cancelled
+            .cancellation_reasons()
+            .iter()
+            .enumerate()
+            .find(|(_, reason)| reason.code() == Some("ConditionalCheckFailed"))
+            .map(|(index, reason)| FailedCondition {
+                index,
+                item: reason.item(),
+            }),
+        _ => None,
+    }
+}
+
+// A transaction cancelled because one of its conditions did not hold is an expected outcome,
+// reported as `false`. Any other failure is an error.
+fn condition_failed(error: TransactWriteItemsError) -> Result<bool, RepositoryError> {
+    match &error {
+        TransactWriteItemsError::TransactionCanceledException(cancelled)
+            if cancelled
+                .cancellation_reasons()
+                .iter()
+                .any(|reason| reason.code() == Some("ConditionalCheckFailed")) =>
+        {
+            Ok(false)
+        }
+        _ => Err(RepositoryError::new(error.to_string())),
+    }
+}
+
+// A reminder is claimed, with when, until it is confirmed as sent.
+enum ReminderClaim {
+    Claimed(DateTime<Utc>),
+    Sent,
+}
+
+/// Stores ToDos, their search index and sent reminders in memory, keyed the same way as the
+/// DynamoDB adapter.
+pub struct InMemoryToDoRepo {
+    todos: Mutex<HashMap<(String, String), ToDo>>,
+    postings: Mutex<HashMap<String, Vec<SearchPosting>>>,
+    reminders: Mutex<HashMap<(String, String), ReminderClaim>>,
+    preferences: Mutex<HashMap<String, Preferences>>,
+    webhooks: Mutex<HashMap<(String, String), Webhook>>,
+    deliveries: Mutex<HashMap<(String, String), Vec<WebhookDelivery>>>,
+    running_timers: Mutex<HashMap<String, TimeEntry>>,
+    time_entries: Mutex<HashMap<(String, String), TimeEntry>>,
+    last_positions: Mutex<HashMap<String, Position>>,
+}
+
+impl InMemoryToDoRepo {
+    pub fn new() -> Self {
+        Self {
+            todos: Mutex::new(HashMap::new()),
+            postings: Mutex::new(HashMap::new()),
+            reminders: Mutex::new(HashMap::new()),
+            preferences: Mutex::new(HashMap::new()),
+            webhooks: Mutex::new(HashMap::new()),
+            deliveries: Mutex::new(HashMap::new()),
+            running_timers: Mutex::new(HashMap::new()),
+            time_entries: Mutex::new(HashMap::new()),
+            last_positions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn webhook_key(tenant: &TenantId, owner: &str, webhook_id: &str) -> (String, String) {
+        (owner_key(tenant, owner), webhook_id.to_uppercase())
+    }
+
+    fn reminder_key(to_do: &ToDo, kind: ReminderKind) -> (String, String) {
+        (
+            owner_key(to_do.get_tenant(), to_do.get_owner()),
+            reminder_sort_key(to_do, kind),
+        )
+    }
+
+    // The ToDos lock is held until the ToDo is stored, so the version and quota checks and the
+    // write can't be interleaved with another write.
+    fn store(&self, todo: &ToDo, max_open_todos: Option<usize>) -> Result<bool, RepositoryError> {
+        let owner = owner_key(todo.get_tenant(), todo.get_owner());
+        let key = (owner.clone(), todo.get_id().to_uppercase());
+        let indexed_on = Utc::now().timestamp_millis();
+
+        let mut todos = self.todos.lock().unwrap();
+
+        if todos.get(&key).map_or(0, ToDo::get_version) != todo.get_version() {
+            return Err(RepositoryError::conflict(format!(
+                "ToDo {0} was changed by another request since it was read",
+                todo.get_id()
+            )));
+        }
+
+        if let Some(max_open_todos) = max_open_todos {
+            let was_open = todos.get(&key).is_some_and(ToDo::is_open);
+            let open_todos = todos
+                .iter()
+                .filter(|((todo_owner, _), todo)| todo_owner == &owner && todo.is_open())
+                .count();
+
+            if todo.is_open() && !was_open && open_todos >= max_open_todos {
+                return Ok(false);
+            }
+        }
+
+        todos.insert(key, todo.clone().with_version(todo.get_version() + 1));
+
+        let mut postings = self.postings.lock().unwrap();
+        let owner_postings = postings.entry(owner).or_default();
+
+        owner_postings.retain(|posting| posting.to_do_id != todo.get_id());
+
+        for (term, frequency) in indexed_terms(todo) {
+            owner_postings.push(SearchPosting {
+                to_do_id: todo.get_id().to_string(),
+                term,
+                frequency,
+                indexed_on,
+            });
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl ToDoRepo for InMemoryToDoRepo {
+    async fn list(&self, tenant: &TenantId, user_id: &str) -> Result<ToDoList, RepositoryError> {
+        let todos = self.todos.lock().unwrap();
+        let user_key = owner_key(tenant, user_id);
+
+        Ok(ToDoList::from(
+            todos
+                .iter()
+                .filter(|((owner, _), _)| owner == &user_key)
+                .map(|(_, todo)| todo.clone())
+                .collect::<Vec<ToDo>>(),
+        ))
+    }
+
+    async fn list_page(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        start: Option<&str>,
+        page_size: usize,
+    ) -> Result<ToDoPage, RepositoryError> {
+        let todos = self.todos.lock().unwrap();
+        let user_key = owner_key(tenant, user_id);
+
+        // Ordered by id, as the table orders them by sort key.
+        let mut owned: Vec<(&String, &ToDo)> = todos
+            .iter()
+            .filter(|((owner, todo_id), _)| {
+                owner == &user_key && start.is_none_or(|start| todo_id.as_str() > start)
+            })
+            .map(|((_, todo_id), todo)| (todo_id, todo))
+            .collect();
+        owned.sort_by_key(|(todo_id, _)| *todo_id);
+
+        let next_page = match owned.len() > page_size {
+            true => Some(owned[page_size - 1].0.clone()),
+            false => None,
+        };
+
+        Ok(ToDoPage {
+            list: ToDoList::from(
+                owned
+                    .into_iter()
+                    .take(page_size)
+                    .map(|(_, todo)| todo.clone())
+                    .collect::<Vec<ToDo>>(),
+            ),
+            next_page,
+        })
+    }
+
+    async fn create(&self, todo: &ToDo) -> Result<(), RepositoryError> {
+        self.store(todo, None).map(|_| ())
+    }
+
+    async fn create_within_quota(
+        &self,
+        todo: &ToDo,
+        max_open_todos: usize,
+    ) -> Result<bool, RepositoryError> {
+        self.store(todo, Some(max_open_todos))
+    }
+
+    async fn get(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        let todos = self.todos.lock().unwrap();
+
+        match todos.get(&(owner_key(tenant, user_id), todo_id.to_uppercase())) {
+            Some(todo) => Ok(todo.clone()),
+            None => Err(RepositoryError::not_found(format!("ToDo {0} not found", todo_id))),
+        }
+    }
+
+    async fn get_many(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> Result<ToDoList, RepositoryError> {
+        let todos = self.todos.lock().unwrap();
+        let user_key = owner_key(tenant, user_id);
+
+        Ok(ToDoList::from(
+            todo_ids
+                .iter()
+                .filter_map(|todo_id| todos.get(&(user_key.clone(), todo_id.to_uppercase())))
+                .cloned()
+                .collect::<Vec<ToDo>>(),
+        ))
+    }
+
+    async fn search(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        terms: &[String],
+    ) -> Result<Vec<SearchPosting>, RepositoryError> {
+        let postings = self.postings.lock().unwrap();
+
+        Ok(postings
+            .get(&owner_key(tenant, user_id))
+            .map(|owner_postings| {
+                owner_postings
+                    .iter()
+                    .filter(|posting| terms.contains(&posting.term))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn reserve_positions(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        count: usize,
+    ) -> Result<Vec<Position>, RepositoryError> {
+        let user_key = owner_key(tenant, user_id);
+        let todos = self.todos.lock().unwrap();
+        let mut last_positions = self.last_positions.lock().unwrap();
+
+        let last = last_positions.get(&user_key).cloned().or_else(|| {
+            todos
+                .iter()
+                .filter(|((owner, _), _)| owner == &user_key)
+                .filter_map(|(_, todo)| todo.get_position())
+                .max()
+                .cloned()
+        });
+
+        let positions = Position::sequence(last.as_ref(), count);
+
+        if let Some(next_last) = positions.last() {
+            last_positions.insert(user_key, next_last.clone());
+        }
+
+        Ok(positions)
+    }
+
+    async fn set_position(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+        position: &Position,
+    ) -> Result<(), RepositoryError> {
+        let mut todos = self.todos.lock().unwrap();
+
+        match todos.get_mut(&(owner_key(tenant, user_id), todo_id.to_uppercase())) {
+            Some(todo) => {
+                *todo = todo.clone().move_to(position.clone()).with_version(todo.get_version() + 1);
+                Ok(())
+            }
+            None => Err(RepositoryError::not_found(format!("ToDo {0} not found", todo_id))),
+        }
+    }
+
+    async fn health_check(&self) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn get_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+    ) -> Result<Preferences, RepositoryError> {
+        let preferences = self.preferences.lock().unwrap();
+
+        Ok(preferences
+            .get(&owner_key(tenant, user_id))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn set_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        preferences: &Preferences,
+    ) -> Result<(), RepositoryError> {
+        self.preferences
+            .lock()
+            .unwrap()
+            .insert(owner_key(tenant, user_id), *preferences);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReminderStore for InMemoryToDoRepo {
+    async fn list_due_before(&self, due_before: DateTime<Utc>) -> Result<Vec<ToDo>, RepositoryError> {
+        let todos = self.todos.lock().unwrap();
+
+        Ok(todos
+            .values()
+            .filter(|todo| matches!(todo, ToDo::Incomplete(_)))
+            .filter(|todo| {
+                todo.get_due_date_time()
+                    .is_some_and(|due_date| due_date <= due_before)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn owner_preferences(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Preferences, RepositoryError> {
+        self.get_preferences(tenant, owner).await
+    }
+
+    async fn claim_reminder(
+        &self,
+        to_do: &ToDo,
+        kind: ReminderKind,
+        now: DateTime<Utc>,
+    ) -> Result<bool, RepositoryError> {
+        let mut reminders = self.reminders.lock().unwrap();
+        let key = InMemoryToDoRepo::reminder_key(to_do, kind);
+
+        match reminders.get(&key) {
+            None => {}
+            Some(ReminderClaim::Claimed(claimed_at))
+                if *claimed_at < now - Duration::minutes(CLAIM_TIMEOUT_MINUTES) => {}
+            Some(_) => return Ok(false),
+        }
+
+        reminders.insert(key, ReminderClaim::Claimed(now));
+
+        Ok(true)
+    }
+
+    async fn confirm_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError> {
+        self.reminders
+            .lock()
+            .unwrap()
+            .insert(InMemoryToDoRepo::reminder_key(to_do, kind), ReminderClaim::Sent);
+
+        Ok(())
+    }
+
+    async fn release_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError> {
+        let mut reminders = self.reminders.lock().unwrap();
+
+        reminders.remove(&InMemoryToDoRepo::reminder_key(to_do, kind));
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl WebhookRepo for InMemoryToDoRepo {
+    async fn list_webhooks(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Vec<Webhook>, RepositoryError> {
+        let webhooks = self.webhooks.lock().unwrap();
+        let user_key = owner_key(tenant, owner);
+
+        let mut owner_webhooks: Vec<Webhook> = webhooks
+            .iter()
+            .filter(|((owner, _), _)| owner == &user_key)
+            .map(|(_, webhook)| webhook.clone())
+            .collect();
+
+        owner_webhooks.sort_by_key(|webhook| webhook.created_on());
+
+        Ok(owner_webhooks)
+    }
+
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Option<Webhook>, RepositoryError> {
+        let webhooks = self.webhooks.lock().unwrap();
+
+        Ok(webhooks
+            .get(&InMemoryToDoRepo::webhook_key(tenant, owner, webhook_id))
+            .cloned())
+    }
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), RepositoryError> {
+        self.webhooks.lock().unwrap().insert(
+            InMemoryToDoRepo::webhook_key(webhook.tenant(), webhook.owner(), webhook.id()),
+            webhook.clone(),
+        );
+
+        Ok(())
+    }
+
+    async fn delete_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<bool, RepositoryError> {
+        let key = InMemoryToDoRepo::webhook_key(tenant, owner, webhook_id);
+
+        self.deliveries.lock().unwrap().remove(&key);
+
+        Ok(self.webhooks.lock().unwrap().remove(&key).is_some())
+    }
+
+    async fn record_delivery_result(
+        &self,
+        webhook: &Webhook,
+        succeeded: bool,
+        failure_threshold: u32,
+    ) -> Result<Option<Webhook>, RepositoryError> {
+        let mut webhooks = self.webhooks.lock().unwrap();
+
+        let current = match webhooks.get_mut(&InMemoryToDoRepo::webhook_key(
+            webhook.tenant(),
+            webhook.owner(),
+            webhook.id(),
+        )) {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+
+        *current = match succeeded {
+            true => current.clone().record_success(),
+            false => current.clone().record_failure(failure_threshold),
+        };
+
+        Ok(Some(current.clone()))
+    }
+
+    async fn record_delivery(
+        &self,
+        webhook: &Webhook,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), RepositoryError> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let log = deliveries
+            .entry(InMemoryToDoRepo::webhook_key(webhook.tenant(), webhook.owner(), webhook.id()))
+            .or_default();
+
+        log.insert(0, delivery.clone());
+        log.truncate(DELIVERY_LOG_LIMIT);
+
+        Ok(())
+    }
+
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError> {
+        let deliveries = self.deliveries.lock().unwrap();
+
+        Ok(deliveries
+            .get(&InMemoryToDoRepo::webhook_key(tenant, owner, webhook_id))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl TimerRepo for InMemoryToDoRepo {
+    async fn running_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Option<TimeEntry>, RepositoryError> {
+        let running_timers = self.running_timers.lock().unwrap();
+
+        Ok(running_timers.get(&owner_key(tenant, owner)).cloned())
+    }
+
+    async fn start_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError> {
+        let owner = owner_key(tenant, owner);
+        let mut running_timers = self.running_timers.lock().unwrap();
+
+        if running_timers.contains_key(&owner) {
+            return Ok(false);
+        }
+
+        running_timers.insert(owner.clone(), entry.clone());
+        self.time_entries
+            .lock()
+            .unwrap()
+            .insert((owner, entry.id().to_string()), entry.clone());
+
+        Ok(true)
+    }
+
+    async fn stop_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError> {
+        let owner = owner_key(tenant, owner);
+        let mut running_timers = self.running_timers.lock().unwrap();
+
+        if running_timers.get(&owner).map(TimeEntry::id) != Some(entry.id()) {
+            return Ok(false);
+        }
+
+        let mut todos = self.todos.lock().unwrap();
+        let to_do = todos
+            .get_mut(&(owner.clone(), entry.to_do_id().to_uppercase()))
+            .ok_or_else(|| {
+                RepositoryError::new(format!(
+                    "ToDo {0} was deleted while its timer was running",
+                    entry.to_do_id()
+                ))
+            })?;
+
+        *to_do = to_do
+            .clone()
+            .add_tracked_time(entry.duration_seconds())
+            .with_version(to_do.get_version() + 1);
+
+        running_timers.remove(&owner);
+        self.time_entries
+            .lock()
+            .unwrap()
+            .insert((owner, entry.id().to_string()), entry.clone());
+
+        Ok(true)
+    }
+}
+
+/// Map a ToDo to the item it is stored as, in the current schema version.
+fn todo_to_item(todo: &ToDo) -> Item {
+    let mut item: Item = HashMap::new();
+
+    item.insert("PK".to_string(), generate_pk(todo.get_tenant(), todo.get_owner()));
+    item.insert("SK".to_string(), generate_sk(todo.get_tenant(), todo.get_id()));
+    item.insert("schemaVersion".to_string(), AttributeValue::N(SCHEMA_VERSION.to_string()));
+    item.insert("id".to_string(), AttributeValue::S(todo.get_id().into()));
+    item.insert("title".to_string(), AttributeValue::S(todo.get_title().into()));
+    item.insert("status".to_string(), AttributeValue::S(todo.get_status()));
+    item.insert("ownerId".to_string(), AttributeValue::S(todo.get_owner().into()));
+    item.insert("tenantId".to_string(), AttributeValue::S(todo.get_tenant().to_string().into()));
+    item.insert("priority".to_string(), AttributeValue::S(todo.get_priority().as_str().into()));
+    item.insert(
+        "attachments".to_string(),
+        AttributeValue::L(todo.get_attachments().iter().map(attachment_to_item).collect()),
+    );
+    item.insert(
+        "trackedSeconds".to_string(),
+        AttributeValue::N(todo.get_tracked_seconds().to_string()),
+    );
+    // Every write stores the version after the one that was read.
+    item.insert(
+        "version".to_string(),
+        AttributeValue::N((todo.get_version() + 1).to_string()),
+    );
+
+    if let Some(position) = todo.get_position() {
+        item.insert("position".to_string(), AttributeValue::S(position.to_string().into()));
+    }
+
+    if !todo.get_completed_on().is_empty() {
+        item.insert("completedOn".to_string(), AttributeValue::S(todo.get_completed_on()));
+    }
+
+    if !todo.get_description().is_empty() {
+        item.insert(
+            "description".to_string(),
+            AttributeValue::S(todo.get_description().to_string()),
+        );
+    }
+
+    if !todo.get_due_date().is_empty() {
+        item.insert("dueDate".to_string(), AttributeValue::S(todo.get_due_date().to_string()));
+    }
+
+    // Stored as a number so the reminder scheduler can compare due dates across timezones. All-day
+    // due dates are stored as due at the end of the day in UTC, the scheduler looks far enough
+    // ahead to find them for owners in any timezone.
+    if let Some(due_date) = todo.get_due_date_time() {
+        item.insert(
+            "dueDateEpoch".to_string(),
+            AttributeValue::N(due_date.timestamp().to_string()),
+        );
+
+        // Completed ToDos are left out of the due date index, they are never reminded.
+        if todo.is_open() {
+            item.insert("dueShard".to_string(), due_shard_key(due_shard(todo.get_id())));
+        }
+    }
+
+    item
+}
+
+/// The due date index partition a ToDo is stored in, spread evenly by its id.
+fn due_shard(todo_id: &str) -> u32 {
+    todo_id
+        .to_uppercase()
+        .bytes()
+        .fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32))
+        % DUE_DATE_SHARDS
+}
+
+fn due_shard_key(shard: u32) -> AttributeValue {
+    AttributeValue::S(format!("DUE#{0}", shard))
+}
+
+fn parse_todo_list(items: &[Item]) -> ToDoList {
+    let mut list = ToDoList::default();
+
+    for item in items {
+        match parse_todo_from_item(item) {
+            Ok(todo) => list.todos.push(todo),
+            Err(e) => list.skipped.push(e),
+        }
+    }
+
+    list
+}
+
+/// Map a ToDo item to a ToDo, migrating it to the current schema version first. Returns an
+/// `InvalidItem` error naming the item's key if it is malformed.
+fn parse_todo_from_item(item: &Item) -> Result<ToDo, RepositoryError> {
+    let item = upcast(item.clone())?;
+    let invalid = |message: String| RepositoryError::invalid_item(item_key(&item), message);
+
+    let read_optional = |name: &str| -> Result<Option<&String>, RepositoryError> {
+        match item.get(name) {
+            None => Ok(None),
+            Some(val) => val
+                .as_s()
+                .map(Some)
+                .map_err(|_| invalid(format!("'{0}' is not a string", name))),
+        }
+    };
+    let read_string = |name: &str| -> Result<&String, RepositoryError> {
+        read_optional(name)?.ok_or_else(|| invalid(format!("'{0}' is missing", name)))
+    };
+
+    let tenant = TenantId::parse(read_string("tenantId")?).map_err(|e| invalid(e.to_string()))?;
+    let title = Title::new(read_string("title")?).map_err(|e| invalid(e.to_string()))?;
+    let owner = OwnerId::new(read_string("ownerId")?)
+        .map_err(|e| invalid(e.to_string()))?
+        .in_tenant(&tenant);
+    let id = ToDoId::parse(read_string("id")?).map_err(|e| invalid(e.to_string()))?;
+    let description = read_optional("description")?
+        .map(|val| Description::new(val).map_err(|e| invalid(e.to_string())))
+        .transpose()?;
+    let due_date = read_optional("dueDate")?
+        .map(|val| DueDate::new(val).map_err(|e| invalid(e.to_string())))
+        .transpose()?;
+    let completed_on = read_optional("completedOn")?
+        .map(|val| {
+            DateTime::parse_from_rfc3339(val)
+                .map_err(|_| invalid(format!("'completedOn' is not a valid date: {0}", val)))
+        })
+        .transpose()?;
+    let priority = Priority::new(read_string("priority")?).map_err(|e| invalid(e.to_string()))?;
+    let position = read_optional("position")?
+        .map(|val| Position::parse(val).map_err(|e| invalid(e.to_string())))
+        .transpose()?;
+    let attachments = item
+        .get("attachments")
+        .and_then(|val| val.as_l().ok())
+        .ok_or_else(|| invalid("'attachments' is not a list".to_string()))?
+        .iter()
+        .map(|val| parse_attachment(val).map_err(invalid))
+        .collect::<Result<Vec<Attachment>, RepositoryError>>()?;
+    let tracked_seconds = item
+        .get("trackedSeconds")
+        .and_then(|val| val.as_n().ok())
+        .and_then(|val| val.parse::<u64>().ok())
+        .ok_or_else(|| invalid("'trackedSeconds' is not a valid number".to_string()))?;
+    let version = item
+        .get("version")
+        .and_then(|val| val.as_n().ok())
+        .and_then(|val| val.parse::<u64>().ok())
+        .ok_or_else(|| invalid("'version' is not a valid number".to_string()))?;
+
+    let todo = ToDo::parse(
+        title,
+        owner,
+        Some(read_string("status")?.clone()),
+        Some(id),
+        description,
+        due_date,
+        completed_on,
+    )
+    .map_err(|errors| {
+        invalid(
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        )
+    })?;
+
+    Ok(todo
+        .with_ordering(priority, position)
+        .with_attachments(attachments)
+        .with_tracked_seconds(tracked_seconds)
+        .with_version(version))
+}
+
+fn parse_attachment(val: &AttributeValue) -> Result<Attachment, String> {
+    let attributes = val
+        .as_m()
+        .map_err(|_| "An attachment is not a map".to_string())?;
+    let read = |name: &str| -> Result<&String, String> {
+        attributes
+            .get(name)
+            .and_then(|val| val.as_s().or_else(|_| val.as_n()).ok())
+            .ok_or_else(|| format!("An attachment is missing '{0}'", name))
+    };
+
+    let size = read("size")?
+        .parse::<u64>()
+        .map_err(|_| "An attachment has an invalid size".to_string())?;
+    let added_on = DateTime::parse_from_rfc3339(read("addedOn")?)
+        .map_err(|_| "An attachment has an invalid addedOn".to_string())?;
+    // Pending attachments have no confirmedOn.
+    let confirmed_on = match attributes.contains_key("confirmedOn") {
+        true => Some(
+            DateTime::parse_from_rfc3339(read("confirmedOn")?)
+                .map_err(|_| "An attachment has an invalid confirmedOn".to_string())?
+                .with_timezone(&Utc),
+        ),
+        false => None,
+    };
+
+    Attachment::parse(
+        read("id")?,
+        read("fileName")?,
+        read("contentType")?,
+        size,
+        added_on.with_timezone(&Utc),
+    )
+    .map(|attachment| attachment.with_confirmed_on(confirmed_on))
+    .map_err(|e| e.to_string())
+}
+
+fn attachment_to_item(attachment: &Attachment) -> AttributeValue {
+    let mut attributes = HashMap::from([
+        ("id".to_string(), AttributeValue::S(attachment.id().to_string())),
+        ("fileName".to_string(), AttributeValue::S(attachment.file_name().to_string())),
+        ("contentType".to_string(), AttributeValue::S(attachment.content_type().to_string())),
+        ("size".to_string(), AttributeValue::N(attachment.size().to_string())),
+        ("addedOn".to_string(), AttributeValue::S(attachment.added_on().to_rfc3339())),
+    ]);
+
+    if let Some(confirmed_on) = attachment.confirmed_on() {
+        attributes.insert(
+            "confirmedOn".to_string(),
+            AttributeValue::S(confirmed_on.to_rfc3339()),
+        );
+    }
+
+    AttributeValue::M(attributes)
+}
+
+/// Migrate an item written by an older version to the current schema version, by running each
+/// upcaster after the item's version in turn. Items written by a newer version are rejected
+/// rather than guessed at.
+fn upcast(mut item: Item) -> Result<Item, RepositoryError> {
+    let version = match item.get("schemaVersion") {
+        None => 1,
+        Some(val) => val
+            .as_n()
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| {
+                RepositoryError::invalid_item(
+                    item_key(&item),
+                    "'schemaVersion' is not a valid version".to_string(),
+                )
+            })?,
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(RepositoryError::invalid_item(
+            item_key(&item),
+            format!(
+                "Schema version {0} is newer than the supported version {1}",
+                version, SCHEMA_VERSION
+            ),
+        ));
+    }
+
+    for upcaster in &UPCASTERS[version - 1..] {
+        upcaster(&mut item);
+    }
+
+    item.insert(
+        "schemaVersion".to_string(),
+        AttributeValue::N(SCHEMA_VERSION.to_string()),
+    );
+
+    Ok(item)
+}
+
+// Version 1 to 2, ToDos written before priorities were added have the default priority.
+fn add_default_priority(item: &mut Item) {
+    item.entry("priority".to_string())
+        .or_insert_with(|| AttributeValue::S(Priority::default().as_str().to_string()));
+}
+
+// Version 2 to 3, ToDos written before tenants were added belong to the default tenant.
+fn add_default_tenant(item: &mut Item) {
+    item.entry("tenantId".to_string())
+        .or_insert_with(|| AttributeValue::S(TenantId::default().to_string().into()));
+}
+
+// Version 3 to 4, ToDos written before attachments were added have none.
+fn add_empty_attachments(item: &mut Item) {
+    item.entry("attachments".to_string())
+        .or_insert_with(|| AttributeValue::L(Vec::new()));
+}
+
+// Version 4 to 5, ToDos written before time tracking was added have no time tracked.
+fn add_no_tracked_time(item: &mut Item) {
+    item.entry("trackedSeconds".to_string())
+        .or_insert_with(|| AttributeValue::N("0".to_string()));
+}
+
+// Version 5 to 6, attachments recorded before uploads were confirmed are treated as uploaded
+// when they were added.
+fn confirm_existing_attachments(item: &mut Item) {
+    if let Some(AttributeValue::L(attachments)) = item.get_mut("attachments") {
+        for attachment in attachments.iter_mut() {
+            if let AttributeValue::M(attributes) = attachment {
+                if let Some(added_on) = attributes.get("addedOn").cloned() {
+                    attributes.entry("confirmedOn".to_string()).or_insert(added_on);
+                }
+            }
+        }
+    }
+}
+
+// Version 6 to 7, ToDos written before versions were stored are at their first. Items written by
+// a version 6 writer but changed since, by a timer or a move, already have one.
+fn add_initial_version(item: &mut Item) {
+    item.entry("version".to_string())
+        .or_insert_with(|| AttributeValue::N("0".to_string()));
+}
+
+// Identifies an item in errors, by its key if it has one.
+fn item_key(item: &Item) -> String {
+    let read = |name: &str| {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .map(String::as_str)
+            .unwrap_or("?")
+    };
+
+    format!("{0}/{1}", read("PK"), read("SK"))
+}
+
+fn parse_webhook_from_item(item: &Item) -> Result<Webhook, RepositoryError> {
+    let invalid = |message: String| RepositoryError::invalid_item(item_key(item), message);
+    let read_string = |name: &str| -> Result<&String, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .ok_or_else(|| invalid(format!("'{0}' is missing", name)))
+    };
+    let read_number = |name: &str| -> Result<u32, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_n().ok())
+            .and_then(|val| val.parse::<u32>().ok())
+            .ok_or_else(|| invalid(format!("'{0}' is not a valid number", name)))
+    };
+
+    let tenant = TenantId::parse(read_string("tenantId")?).map_err(|e| invalid(e.to_string()))?;
+    let event_types = item
+        .get("eventTypes")
+        .and_then(|val| val.as_l().ok())
+        .ok_or_else(|| invalid("'eventTypes' is not a list".to_string()))?
+        .iter()
+        .map(|val| {
+            val.as_s()
+                .cloned()
+                .map_err(|_| invalid("'eventTypes' contains a value that is not a string".to_string()))
+        })
+        .collect::<Result<Vec<String>, RepositoryError>>()?;
+    let enabled = item
+        .get("enabled")
+        .and_then(|val| val.as_bool().ok())
+        .copied()
+        .ok_or_else(|| invalid("'enabled' is missing".to_string()))?;
+    let created_on = DateTime::parse_from_rfc3339(read_string("createdOn")?)
+        .map_err(|_| invalid("'createdOn' is not a valid date".to_string()))?;
+
+    Ok(Webhook::parse(
+        read_string("id")?,
+        &tenant,
+        read_string("ownerId")?,
+        read_string("url")?,
+        event_types,
+        read_string("secret")?,
+        enabled,
+        read_number("consecutiveFailures")?,
+        created_on.with_timezone(&Utc),
+    ))
+}
+
+fn webhook_to_item(webhook: &Webhook) -> Item {
+    Item::from([
+        ("PK".to_string(), generate_pk(webhook.tenant(), webhook.owner())),
+        ("SK".to_string(), generate_webhook_sk(webhook.id())),
+        ("id".to_string(), AttributeValue::S(webhook.id().to_string())),
+        ("tenantId".to_string(), AttributeValue::S(webhook.tenant().to_string().into())),
+        ("ownerId".to_string(), AttributeValue::S(webhook.owner().to_string())),
+        ("url".to_string(), AttributeValue::S(webhook.url().to_string())),
+        (
+            "eventTypes".to_string(),
+            AttributeValue::L(
+                webhook
+                    .event_types()
+                    .iter()
+                    .map(|val| AttributeValue::S(val.clone()))
+                    .collect(),
+            ),
+        ),
+        ("secret".to_string(), AttributeValue::S(webhook.secret().to_string())),
+        ("enabled".to_string(), AttributeValue::Bool(webhook.is_enabled())),
+        (
+            "consecutiveFailures".to_string(),
+            AttributeValue::N(webhook.consecutive_failures().to_string()),
+        ),
+        ("createdOn".to_string(), AttributeValue::S(webhook.created_on().to_rfc3339())),
+    ])
+}
+
+fn parse_delivery_from_item(item: &Item) -> Result<WebhookDelivery, RepositoryError> {
+    let invalid = |message: String| RepositoryError::invalid_item(item_key(item), message);
+    let read_string = |name: &str| -> Result<String, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .cloned()
+            .ok_or_else(|| invalid(format!("'{0}' is missing", name)))
+    };
+
+    let delivered_on = DateTime::parse_from_rfc3339(&read_string("deliveredOn")?)
+        .map_err(|_| invalid("'deliveredOn' is not a valid date".to_string()))?;
+
+    Ok(WebhookDelivery {
+        delivery_id: read_string("id")?,
+        webhook_id: read_string("webhookId")?,
+        event_id: read_string("eventId")?,
+        event_type: read_string("eventType")?,
+        delivered_on: delivered_on.with_timezone(&Utc),
+        attempts: item
+            .get("attempts")
+            .and_then(|val| val.as_n().ok())
+            .and_then(|val| val.parse().ok())
+            .ok_or_else(|| invalid("'attempts' is not a valid number".to_string()))?,
+        status_code: item
+            .get("statusCode")
+            .and_then(|val| val.as_n().ok())
+            .and_then(|val| val.parse().ok()),
+        error: item.get("error").and_then(|val| val.as_s().ok()).cloned(),
+    })
+}
+
+fn preferences_to_item(preferences: &Preferences) -> Item {
+    let mut item: Item = HashMap::new();
+
+    item.insert(
+        "timezone".to_string(),
+        AttributeValue::S(preferences.timezone.name().to_string()),
+    );
+
+    if let Some(lead_time) = preferences.reminder_lead_time {
+        item.insert(
+            "reminderLeadTimeMinutes".to_string(),
+            AttributeValue::N(lead_time.minutes().to_string()),
+        );
+    }
+
+    item
+}
+
+fn parse_preferences_from_item(item: &Item) -> Result<Preferences, RepositoryError> {
+    let timezone = match item.get("timezone").and_then(|val| val.as_s().ok()) {
+        Some(timezone) => Timezone::new(timezone),
+        None => Ok(Timezone::default()),
+    };
+
+    let reminder_lead_time = item
+        .get("reminderLeadTimeMinutes")
+        .and_then(|val| val.as_n().ok())
+        .map(|minutes| {
+            minutes
+                .parse::<i64>()
+                .map_err(|e| ValidationError::new(e.to_string()))
+                .and_then(ReminderLeadTime::new)
+        })
+        .transpose();
+
+    match (timezone, reminder_lead_time) {
+        (Ok(timezone), Ok(reminder_lead_time)) => Ok(Preferences {
+            timezone,
+            reminder_lead_time,
+        }),
+        (Err(e), _) | (_, Err(e)) => Err(RepositoryError::invalid_item(item_key(item), e.to_string())),
+    }
+}
+
+// The attributes shared by a time entry and the running timer item that points at it.
+fn time_entry_to_item(entry: &TimeEntry) -> Item {
+    let mut item = Item::from([
+        ("id".to_string(), AttributeValue::S(entry.id().to_string())),
+        ("toDoId".to_string(), AttributeValue::S(entry.to_do_id().to_string())),
+        ("startedOn".to_string(), AttributeValue::S(entry.started_on().to_rfc3339())),
+    ]);
+
+    if let Some(stopped_on) = entry.stopped_on() {
+        item.insert("stoppedOn".to_string(), AttributeValue::S(stopped_on.to_rfc3339()));
+        item.insert(
+            "durationSeconds".to_string(),
+            AttributeValue::N(entry.duration_seconds().to_string()),
+        );
+    }
+
+    item
+}
+
+fn parse_time_entry_from_item(item: &Item) -> Result<TimeEntry, RepositoryError> {
+    let invalid = |message: String| RepositoryError::invalid_item(item_key(item), message);
+    let read_string = |name: &str| -> Result<&String, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .ok_or_else(|| invalid(format!("'{0}' is missing", name)))
+    };
+    let read_date = |val: &String, name: &str| -> Result<DateTime<Utc>, RepositoryError> {
+        DateTime::parse_from_rfc3339(val)
+            .map(|val| val.with_timezone(&Utc))
+            .map_err(|_| invalid(format!("'{0}' is not a valid date", name)))
+    };
+
+    let stopped_on = item
+        .get("stoppedOn")
+        .and_then(|val| val.as_s().ok())
+        .map(|val| read_date(val, "stoppedOn"))
+        .transpose()?;
+
+    Ok(TimeEntry::parse(
+        read_string("id")?,
+        read_string("toDoId")?,
+        read_date(read_string("startedOn")?, "startedOn")?,
+        stopped_on,
+    ))
+}
+
+fn parse_posting_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<SearchPosting, RepositoryError> {
+    let read_string = |name: &str| -> Result<String, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .cloned()
+            .ok_or_else(|| RepositoryError::new(format!("Search entry is missing '{0}'", name)))
+    };
+    let read_number = |name: &str| -> Result<String, RepositoryError> {
+        item.get(name)
+            .and_then(|val| val.as_n().ok())
+            .cloned()
+            .ok_or_else(|| RepositoryError::new(format!("Search entry is missing '{0}'", name)))
+    };
+
+    Ok(SearchPosting {
+        to_do_id: read_string("id")?,
+        term: read_string("term")?,
+        frequency: read_number("frequency")?
+            .parse()
+            .map_err(|_| RepositoryError::new("Search entry has an invalid frequency".to_string()))?,
+        indexed_on: read_number("indexedOn")?
+            .parse()
+            .map_err(|_| RepositoryError::new("Search entry has an invalid indexedOn".to_string()))?,
+    })
+}
+
+// Keys in the default tenant have no prefix, so tables written before tenants existed can
+// still be read by a single-tenant deployment.
+fn tenant_prefix(tenant: &TenantId) -> String {
+    match tenant.is_default() {
+        true => String::new(),
+        false => format!("TENANT#{0}#", tenant.to_string().to_uppercase()),
+    }
+}
+
+// The partition key for an owner, the in-memory adapter keys its maps the same way.
+fn owner_key(tenant: &TenantId, user_id: &str) -> String {
+    format!("{0}USER#{1}", tenant_prefix(tenant), user_id.to_uppercase())
+}
+
+fn generate_pk(tenant: &TenantId, user_id: &str) -> AttributeValue {
+    AttributeValue::S(owner_key(tenant, user_id))
+}
+
+fn generate_sk(tenant: &TenantId, todo_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("{0}TODO#{1}", tenant_prefix(tenant), todo_id.to_uppercase()))
+}
+
+
+fn generate_reminder_sk(to_do: &ToDo, kind: ReminderKind) -> AttributeValue {
+    AttributeValue::S(reminder_sort_key(to_do, kind))
+}
+
+// The due date is part of the key so a reminder is sent again if the due date changes.
+fn reminder_sort_key(to_do: &ToDo, kind: ReminderKind) -> String {
+    let due_date = to_do
+        .get_due_date_time()
+        .map(|val| val.timestamp())
+        .unwrap_or_default();
+
+    format!(
+        "REMINDER#{0}#{1}#{2}",
+        kind.as_str(),
+        to_do.get_id().to_uppercase(),
+        due_date
+    )
+}
+
+fn generate_search_sk(term: &str, todo_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("SEARCH#{0}#{1}", term, todo_id.to_uppercase()))
+}
+
+// Time entries sort by the ToDo they were recorded against, so a ToDo's entries can be read
+// together.
+fn generate_time_entry_sk(entry: &TimeEntry) -> AttributeValue {
+    AttributeValue::S(format!(
+        "TIME_ENTRY#{0}#{1}",
+        entry.to_do_id().to_uppercase(),
+        entry.id()
+    ))
+}
+
+fn generate_webhook_sk(webhook_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("WEBHOOK#{0}", webhook_id.to_uppercase()))
+}
+
+// Deliveries sort by the time they were made, so the log can be read newest first.
+fn generate_delivery_sk(webhook_id: &str, delivery: &WebhookDelivery) -> AttributeValue {
+    AttributeValue::S(format!(
+        "WEBHOOK_DELIVERY#{0}#{1:013}#{2}",
+        webhook_id.to_uppercase(),
+        delivery.delivered_on.timestamp_millis(),
+        delivery.delivery_id
+    ))
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{
+        attachment_to_item, parse_todo_from_item, parse_webhook_from_item, todo_to_item,
+        webhook_to_item, Item, DUE_DATE_SHARDS, SCHEMA_VERSION,
+    };
+    use crate::application::domain::{Attachment, OwnerId, Priority, TenantId, Title, ToDo};
+    use crate::application::webhooks::Webhook;
+    use crate::application::error_types::RepositoryErrorKind;
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    fn item(attributes: &[(&str, &str)]) -> Item {
+        attributes
+            .iter()
+            .map(|(name, val)| (name.to_string(), AttributeValue::S(val.to_string())))
+            .collect()
+    }
+
+    // A ToDo as it was written before priorities, tenants, attachments or schema versions existed.
+    fn version_one_item() -> Item {
+        item(&[
+            ("PK", "USER#JAMES"),
+            ("SK", "TODO#ABC"),
+            ("id", "abc"),
+            ("title", "Buy milk"),
+            ("status", "INCOMPLETE"),
+            ("ownerId", "james"),
+            ("dueDate", "2023-08-12T00:00:00+00:00"),
+        ])
+    }
+
+    #[test]
+    fn only_incomplete_todos_with_a_due_date_should_be_sharded_into_the_due_date_index() {
+        let parsed = parse_todo_from_item(&version_one_item()).unwrap();
+        let shard = |todo: &ToDo| {
+            todo_to_item(todo)
+                .get("dueShard")
+                .map(|val| val.as_s().unwrap().clone())
+        };
+
+        let incomplete = shard(&parsed);
+
+        assert!((0..DUE_DATE_SHARDS).any(|n| incomplete == Some(format!("DUE#{0}", n))));
+        assert_eq!(shard(&parsed.clone().set_completed()), None);
+        // Every write of the same ToDo lands in the same shard.
+        assert_eq!(shard(&parse_todo_from_item(&version_one_item()).unwrap()), incomplete);
+
+        let undated = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("james").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(shard(&undated), None);
+    }
+
+    #[test]
+    fn version_one_item_should_be_upcast_to_current_version() {
+        let todo = parse_todo_from_item(&version_one_item()).unwrap();
+
+        assert_eq!(todo.get_title(), "Buy milk");
+        assert_eq!(todo.get_priority(), Priority::Normal);
+        assert!(todo.get_tenant().is_default());
+        assert_eq!(todo.get_due_date(), "2023-08-12T00:00:00+00:00");
+        assert!(todo.get_attachments().is_empty());
+        assert_eq!(todo.get_tracked_seconds(), 0);
+        assert_eq!(todo.get_version(), 0);
+    }
+
+    #[test]
+    fn current_version_item_should_keep_its_attributes() {
+        let mut current = version_one_item();
+        current.insert(
+            "schemaVersion".to_string(),
+            AttributeValue::N(SCHEMA_VERSION.to_string()),
+        );
+        current.insert("priority".to_string(), AttributeValue::S("urgent".to_string()));
+        current.insert("tenantId".to_string(), AttributeValue::S("acme".to_string()));
+        current.insert(
+            "attachments".to_string(),
+            AttributeValue::L(vec![attachment_to_item(
+                &Attachment::new("scan.pdf", "application/pdf", 2048).unwrap(),
+            )]),
+        );
+        current.insert("trackedSeconds".to_string(), AttributeValue::N("5400".to_string()));
+        current.insert("version".to_string(), AttributeValue::N("3".to_string()));
+
+        let todo = parse_todo_from_item(&current).unwrap();
+
+        assert_eq!(todo.get_priority(), Priority::Urgent);
+        assert_eq!(todo.get_tenant(), &TenantId::new("acme").unwrap());
+        assert_eq!(todo.get_attachments().len(), 1);
+        assert_eq!(todo.get_attachments()[0].file_name(), "scan.pdf");
+        assert!(!todo.get_attachments()[0].is_confirmed());
+        assert_eq!(todo.get_tracked_seconds(), 5400);
+        assert_eq!(todo.get_version(), 3);
+    }
+
+    #[test]
+    fn attachments_written_before_confirmation_should_be_upcast_as_confirmed() {
+        let attachment = Attachment::new("scan.pdf", "application/pdf", 2048).unwrap();
+
+        let mut version_five = version_one_item();
+        version_five.insert("schemaVersion".to_string(), AttributeValue::N("5".to_string()));
+        version_five.insert("priority".to_string(), AttributeValue::S("normal".to_string()));
+        version_five.insert("tenantId".to_string(), AttributeValue::S("default".to_string()));
+        version_five.insert("trackedSeconds".to_string(), AttributeValue::N("0".to_string()));
+        version_five.insert(
+            "attachments".to_string(),
+            AttributeValue::L(vec![attachment_to_item(&attachment)]),
+        );
+
+        let todo = parse_todo_from_item(&version_five).unwrap();
+
+        assert_eq!(
+            todo.get_attachments()[0].confirmed_on().map(|confirmed_on| confirmed_on.timestamp()),
+            Some(attachment.added_on().timestamp())
+        );
+    }
+
+    #[test]
+    fn malformed_item_should_return_invalid_item_error() {
+        let mut missing_title = version_one_item();
+        missing_title.remove("title");
+
+        let mut invalid_due_date = version_one_item();
+        invalid_due_date.insert("dueDate".to_string(), AttributeValue::S("soon".to_string()));
+
+        let mut wrong_type = version_one_item();
+        wrong_type.insert("status".to_string(), AttributeValue::N("1".to_string()));
+
+        for malformed in [missing_title, invalid_due_date, wrong_type] {
+            let err = parse_todo_from_item(&malformed).err().unwrap();
+
+            assert_eq!(err.kind(), &RepositoryErrorKind::InvalidItem);
+            assert_eq!(err.item_key(), Some("USER#JAMES/TODO#ABC"));
+        }
+    }
+
+    #[test]
+    fn item_from_newer_schema_version_should_not_be_read() {
+        let mut newer = version_one_item();
+        newer.insert(
+            "schemaVersion".to_string(),
+            AttributeValue::N((SCHEMA_VERSION + 1).to_string()),
+        );
+
+        let err = parse_todo_from_item(&newer).err().unwrap();
+
+        assert_eq!(err.kind(), &RepositoryErrorKind::InvalidItem);
+    }
+
+    #[test]
+    fn webhook_item_should_round_trip() {
+        let webhook = Webhook::new(
+            &TenantId::new("acme").unwrap(),
+            "james",
+            "https://example.com/hooks/todo",
+            vec!["ToDoCompleted".to_string()],
+        )
+        .unwrap()
+        .record_failure(5);
+
+        let item = webhook_to_item(&webhook);
+        let parsed = parse_webhook_from_item(&item).unwrap();
+
+        assert_eq!(parsed, webhook);
+        assert_eq!(
+            item["SK"].as_s().unwrap(),
+            &format!("WEBHOOK#{0}", webhook.id().to_uppercase())
+        );
+    }
 }
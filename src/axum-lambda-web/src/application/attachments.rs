@@ -0,0 +1,437 @@
+use crate::application::domain::{Attachment, TenantId};
+use crate::application::error_types::{RepositoryError, ValidationError};
+use aws_config::SdkConfig;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_sigv4::http_request::{
+    sign, PercentEncodingMode, SignableBody, SignableRequest, SignatureLocation, SigningSettings,
+    UriPathNormalizationMode,
+};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::env;
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_MAX_SIZE_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_CONTENT_TYPES: &str = "image/png,image/jpeg,image/gif,image/webp,application/pdf";
+const DEFAULT_URL_EXPIRY_SECONDS: u64 = 900;
+
+// S3 expects everything except the unreserved characters to be percent encoded, in both the
+// object key and the query string.
+const URI_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// A request signed into its URL, it can be made without AWS credentials until it expires.
+pub struct PresignedUrl {
+    pub url: String,
+    /// Headers that must be sent with the request exactly as given, they are part of the
+    /// signature.
+    pub headers: Vec<(String, String)>,
+    pub expires_on: DateTime<Utc>,
+}
+
+/// Keeps the files attached to ToDos in an S3 bucket. Files never pass through the service,
+/// owners upload and download them directly using presigned URLs.
+pub struct AttachmentStore {
+    credentials: SharedCredentialsProvider,
+    region: String,
+    bucket: String,
+    endpoint: Option<String>,
+    max_size_bytes: u64,
+    content_types: Vec<String>,
+    url_expiry: Duration,
+    http_client: reqwest::Client,
+}
+
+impl AttachmentStore {
+    /// When `endpoint` is set objects are addressed path style, as most S3 compatible
+    /// stand-ins expect, otherwise the bucket's virtual hosted endpoint is used.
+    pub fn new(
+        credentials: SharedCredentialsProvider,
+        region: &str,
+        bucket: &str,
+        endpoint: Option<&str>,
+    ) -> Self {
+        Self {
+            credentials,
+            region: region.to_string(),
+            bucket: bucket.to_string(),
+            endpoint: endpoint.map(|endpoint| endpoint.trim_end_matches('/').to_string()),
+            max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
+            content_types: parse_content_types(DEFAULT_CONTENT_TYPES),
+            url_expiry: Duration::from_secs(DEFAULT_URL_EXPIRY_SECONDS),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Limit the size of attachments, and the content types that can be attached.
+    pub fn with_limits(self, max_size_bytes: u64, content_types: &str) -> Self {
+        Self {
+            max_size_bytes,
+            content_types: parse_content_types(content_types),
+            ..self
+        }
+    }
+
+    /// Configure the store from `ATTACHMENT_BUCKET_NAME`, returns `None` when it is not set and
+    /// attachments are turned off. `S3_ENDPOINT` overrides the S3 endpoint, `ATTACHMENT_MAX_BYTES`
+    /// and `ATTACHMENT_CONTENT_TYPES` set the limits, and `ATTACHMENT_URL_EXPIRY_SECONDS` how long
+    /// presigned URLs can be used for. Fails when a bucket is set but there are no AWS
+    /// credentials to sign URLs with.
+    pub fn from_env(config: &SdkConfig) -> Result<Option<Self>, String> {
+        let bucket = match env::var("ATTACHMENT_BUCKET_NAME") {
+            Ok(bucket) => bucket,
+            Err(_) => return Ok(None),
+        };
+
+        let credentials = config.credentials_provider().ok_or_else(|| {
+            "AWS credentials must be configured when ATTACHMENT_BUCKET_NAME is set".to_string()
+        })?;
+
+        let region = config
+            .region()
+            .map(|region| region.to_string())
+            .unwrap_or_else(|| String::from("us-east-1"));
+
+        let max_size_bytes = env::var("ATTACHMENT_MAX_BYTES")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_MAX_SIZE_BYTES);
+        let content_types = env::var("ATTACHMENT_CONTENT_TYPES")
+            .unwrap_or_else(|_| DEFAULT_CONTENT_TYPES.to_string());
+        let url_expiry_seconds = env::var("ATTACHMENT_URL_EXPIRY_SECONDS")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_URL_EXPIRY_SECONDS);
+
+        let store = Self::new(
+            credentials,
+            region.as_str(),
+            bucket.as_str(),
+            env::var("S3_ENDPOINT").ok().as_deref(),
+        )
+        .with_limits(max_size_bytes, content_types.as_str());
+
+        Ok(Some(Self {
+            url_expiry: Duration::from_secs(url_expiry_seconds),
+            ..store
+        }))
+    }
+
+    /// Pending attachments added before this were never uploaded. An upload can start just
+    /// before its URL expires, so it is given as long again to finish.
+    pub fn abandoned_before(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - self.url_expiry * 2
+    }
+
+    /// Check the attachment is within the size limit and has a content type that can be
+    /// attached.
+    pub fn check_limits(&self, attachment: &Attachment) -> Result<(), ValidationError> {
+        if attachment.size() > self.max_size_bytes {
+            return Err(ValidationError::new(format!(
+                "Attachments can be at most {} bytes",
+                self.max_size_bytes
+            ))
+            .with_field("size"));
+        }
+
+        let essence = attachment
+            .content_type()
+            .parse::<mime::Mime>()
+            .map(|content_type| content_type.essence_str().to_string())
+            .unwrap_or_default();
+
+        if !self.content_types.contains(&essence) {
+            return Err(ValidationError::new(format!(
+                "'{}' files cannot be attached, use one of {}",
+                attachment.content_type(),
+                self.content_types.join(", ")
+            ))
+            .with_field("content_type"));
+        }
+
+        Ok(())
+    }
+
+    /// A URL the file can be uploaded to with a single `PUT`. The content type and length are
+    /// signed, so S3 rejects an upload that differs from what was checked against the limits.
+    pub async fn presign_upload(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        to_do_id: &str,
+        attachment: &Attachment,
+    ) -> Result<PresignedUrl, RepositoryError> {
+        let headers = vec![
+            ("content-length".to_string(), attachment.size().to_string()),
+            ("content-type".to_string(), attachment.content_type().to_string()),
+        ];
+
+        let url = self.object_url(&object_key(tenant, owner, to_do_id, attachment));
+
+        self.presign("PUT", url, headers).await
+    }
+
+    /// A URL the file can be downloaded from, named after the file that was attached.
+    pub async fn presign_download(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        to_do_id: &str,
+        attachment: &Attachment,
+    ) -> Result<PresignedUrl, RepositoryError> {
+        let content_disposition = format!(
+            "attachment; filename*=UTF-8''{}",
+            uri_encode(attachment.file_name())
+        );
+
+        let url = format!(
+            "{}?response-content-disposition={}&response-content-type={}",
+            self.object_url(&object_key(tenant, owner, to_do_id, attachment)),
+            uri_encode(&content_disposition),
+            uri_encode(attachment.content_type())
+        );
+
+        self.presign("GET", url, Vec::new()).await
+    }
+
+    /// Check the attachment's file has been uploaded, with a signed `HEAD` of the object.
+    pub async fn object_exists(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        to_do_id: &str,
+        attachment: &Attachment,
+    ) -> Result<bool, RepositoryError> {
+        let url = self.object_url(&object_key(tenant, owner, to_do_id, attachment));
+        let head = self.presign("HEAD", url, Vec::new()).await?;
+
+        let response = self
+            .http_client
+            .head(head.url)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => Err(RepositoryError::new(format!(
+                "Checking for attachment '{}' returned {}",
+                attachment.id(),
+                status
+            ))),
+        }
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        match &self.endpoint {
+            Some(endpoint) => format!("{}/{}/{}", endpoint, self.bucket, key),
+            None => format!("https://{}.s3.{}.amazonaws.com/{}", self.bucket, self.region, key),
+        }
+    }
+
+    async fn presign(
+        &self,
+        method: &str,
+        url: String,
+        headers: Vec<(String, String)>,
+    ) -> Result<PresignedUrl, RepositoryError> {
+        let credentials = self
+            .credentials
+            .provide_credentials()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+        let identity: Identity = credentials.into();
+
+        let mut settings = SigningSettings::default();
+        settings.signature_location = SignatureLocation::QueryParams;
+        settings.expires_in = Some(self.url_expiry);
+        // The key is already encoded once, S3 does not expect it to be encoded again.
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+
+        let signing_params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&self.region)
+            .name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()
+            .map_err(|e| RepositoryError::new(e.to_string()))?
+            .into();
+
+        let signable_request = SignableRequest::new(
+            method,
+            url.as_str(),
+            headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+            SignableBody::UnsignedPayload,
+        )
+        .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        let (signing_instructions, _signature) = sign(signable_request, &signing_params)
+            .map_err(|e| RepositoryError::new(e.to_string()))?
+            .into_parts();
+
+        let mut presigned_url = url;
+
+        for (name, value) in signing_instructions.params() {
+            presigned_url.push(match presigned_url.contains('?') {
+                true => '&',
+                false => '?',
+            });
+            presigned_url.push_str(&format!("{}={}", uri_encode(name), uri_encode(value)));
+        }
+
+        Ok(PresignedUrl {
+            url: presigned_url,
+            headers,
+            expires_on: Utc::now() + self.url_expiry,
+        })
+    }
+}
+
+// Objects are grouped by tenant, owner and ToDo, so everything attached to a ToDo can be found
+// by prefix.
+fn object_key(tenant: &TenantId, owner: &str, to_do_id: &str, attachment: &Attachment) -> String {
+    [tenant.to_string(), owner, to_do_id, attachment.id()]
+        .iter()
+        .map(|segment| uri_encode(segment))
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+fn uri_encode(value: &str) -> String {
+    utf8_percent_encode(value, URI_ENCODE_SET).to_string()
+}
+
+fn parse_content_types(content_types: &str) -> Vec<String> {
+    content_types
+        .split(',')
+        .map(|content_type| content_type.trim().to_lowercase())
+        .filter(|content_type| !content_type.is_empty())
+        .collect()
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::AttachmentStore;
+    use crate::application::domain::{Attachment, TenantId};
+    use aws_credential_types::provider::SharedCredentialsProvider;
+    use aws_credential_types::Credentials;
+    use axum::http::{StatusCode, Uri};
+    use axum::Router;
+
+    fn store(endpoint: Option<&str>) -> AttachmentStore {
+        AttachmentStore::new(
+            SharedCredentialsProvider::new(Credentials::new("AKIDEXAMPLE", "secret", None, None, "test")),
+            "eu-west-1",
+            "todo-attachments",
+            endpoint,
+        )
+        .with_limits(1024, "image/png, application/pdf")
+    }
+
+    #[test]
+    fn attachment_over_size_limit_should_be_rejected() {
+        let attachment = Attachment::new("scan.pdf", "application/pdf", 1025).unwrap();
+
+        let err = store(None).check_limits(&attachment).err().unwrap();
+
+        assert_eq!(err.field(), Some("size"));
+    }
+
+    #[test]
+    fn attachment_with_content_type_not_allowed_should_be_rejected() {
+        let attachment = Attachment::new("notes.txt", "text/plain", 10).unwrap();
+
+        let err = store(None).check_limits(&attachment).err().unwrap();
+
+        assert_eq!(err.field(), Some("content_type"));
+    }
+
+    #[test]
+    fn content_type_parameters_should_not_affect_limits() {
+        let attachment = Attachment::new("screenshot.png", "image/PNG; q=1", 10).unwrap();
+
+        assert!(store(None).check_limits(&attachment).is_ok());
+    }
+
+    #[tokio::test]
+    async fn upload_url_should_sign_content_type_and_length() {
+        let attachment = Attachment::new("screenshot.png", "image/png", 512).unwrap();
+
+        let upload = store(None)
+            .presign_upload(&TenantId::default(), "james eastham", "abc", &attachment)
+            .await
+            .unwrap();
+
+        assert!(upload.url.starts_with(&format!(
+            "https://todo-attachments.s3.eu-west-1.amazonaws.com/default/james%20eastham/abc/{}?",
+            attachment.id()
+        )));
+        assert!(upload
+            .url
+            .contains("X-Amz-SignedHeaders=content-length%3Bcontent-type%3Bhost"));
+        assert!(upload.url.contains("X-Amz-Expires=900"));
+        assert!(upload.url.contains("X-Amz-Signature="));
+        assert!(upload
+            .headers
+            .contains(&("content-length".to_string(), "512".to_string())));
+    }
+
+    #[tokio::test]
+    async fn download_url_should_use_configured_endpoint() {
+        let attachment = Attachment::new("my scan.pdf", "application/pdf", 512).unwrap();
+
+        let download = store(Some("http://localhost:4566/"))
+            .presign_download(&TenantId::new("acme").unwrap(), "james", "abc", &attachment)
+            .await
+            .unwrap();
+
+        assert!(download.url.starts_with(&format!(
+            "http://localhost:4566/todo-attachments/acme/james/abc/{}?",
+            attachment.id()
+        )));
+        assert!(download.url.contains(
+            "response-content-disposition=attachment%3B%20filename%2A%3DUTF-8%27%27my%2520scan.pdf"
+        ));
+        assert!(download.url.contains("X-Amz-SignedHeaders=host"));
+        assert!(download.headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn object_exists_should_only_find_uploaded_files() {
+        let uploaded = Attachment::new("scan.pdf", "application/pdf", 512).unwrap();
+        let pending = Attachment::new("scan.pdf", "application/pdf", 512).unwrap();
+
+        let uploaded_path = format!("/todo-attachments/default/james/abc/{}", uploaded.id());
+        let s3 = Router::new().fallback(move |uri: Uri| {
+            let found = uri.path() == uploaded_path;
+            async move {
+                match found {
+                    true => StatusCode::OK,
+                    false => StatusCode::NOT_FOUND,
+                }
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, s3).await });
+
+        let store = store(Some(endpoint.as_str()));
+        let tenant = TenantId::default();
+
+        assert!(store.object_exists(&tenant, "james", "abc", &uploaded).await.unwrap());
+        assert!(!store.object_exists(&tenant, "james", "abc", &pending).await.unwrap());
+    }
+}
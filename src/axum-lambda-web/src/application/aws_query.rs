@@ -0,0 +1,125 @@
+use aws_config::SdkConfig;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use std::time::SystemTime;
+
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded; charset=utf-8";
+
+/// A minimal client for AWS services that speak the Query protocol, such as SNS and SQS.
+/// Each call is a form encoded POST signed with SigV4.
+pub(crate) struct AwsQueryClient {
+    http_client: reqwest::Client,
+    credentials: SharedCredentialsProvider,
+    region: String,
+    service: &'static str,
+    endpoint: String,
+}
+
+impl AwsQueryClient {
+    pub(crate) fn new(
+        credentials: SharedCredentialsProvider,
+        region: &str,
+        service: &'static str,
+        endpoint: &str,
+    ) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            credentials,
+            region: region.to_string(),
+            service,
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    /// Create a client using the credentials and region of the shared AWS configuration. When
+    /// no endpoint is given the public regional endpoint of the service is used. Returns an
+    /// error if the configuration has no credentials.
+    pub(crate) fn from_config(
+        config: &SdkConfig,
+        service: &'static str,
+        endpoint: Option<String>,
+    ) -> Result<Self, String> {
+        let region = config
+            .region()
+            .map(|region| region.to_string())
+            .unwrap_or_else(|| String::from("us-east-1"));
+
+        let endpoint =
+            endpoint.unwrap_or_else(|| format!("https://{}.{}.amazonaws.com", service, region));
+
+        let credentials = config
+            .credentials_provider()
+            .ok_or_else(|| format!("AWS credentials must be configured to publish to {}", service))?;
+
+        Ok(Self::new(credentials, region.as_str(), service, endpoint.as_str()))
+    }
+
+    /// Call `action` with the given parameters. Returns the error message if the request could
+    /// not be sent or the service responded with an error.
+    pub(crate) async fn call(&self, action: &str, params: &[(&str, &str)]) -> Result<(), String> {
+        let mut form: Vec<(&str, &str)> = vec![("Action", action), ("Version", self.version())];
+        form.extend_from_slice(params);
+
+        let body = serde_urlencoded::to_string(&form).map_err(|e| e.to_string())?;
+
+        let credentials = self
+            .credentials
+            .provide_credentials()
+            .await
+            .map_err(|e| e.to_string())?;
+        let identity: Identity = credentials.into();
+
+        let signing_params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&self.region)
+            .name(self.service)
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()
+            .map_err(|e| e.to_string())?
+            .into();
+
+        let signable_request = SignableRequest::new(
+            "POST",
+            self.endpoint.as_str(),
+            [("content-type", FORM_CONTENT_TYPE)].into_iter(),
+            SignableBody::Bytes(body.as_bytes()),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let (signing_instructions, _signature) = sign(signable_request, &signing_params)
+            .map_err(|e| e.to_string())?
+            .into_parts();
+
+        let mut request = self
+            .http_client
+            .post(self.endpoint.as_str())
+            .header("content-type", FORM_CONTENT_TYPE);
+
+        for (name, value) in signing_instructions.headers() {
+            request = request.header(name, value);
+        }
+
+        let response = request.body(body).send().await.map_err(|e| e.to_string())?;
+
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(format!(
+                "{} {} returned {}: {}",
+                self.service,
+                action,
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )),
+        }
+    }
+
+    fn version(&self) -> &'static str {
+        match self.service {
+            "sqs" => "2012-11-05",
+            _ => "2010-03-31",
+        }
+    }
+}
@@ -0,0 +1,530 @@
+use crate::application::domain::{Position, Preferences, TenantId, ToDo, ToDoList, ToDoPage, ToDoRepo};
+use crate::application::error_types::RepositoryError;
+use crate::application::search::SearchPosting;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_OWNERS: usize = 1000;
+
+/// The number of `get` and `list` calls answered from the cache, and the number passed on to
+/// the wrapped repository.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct OwnerEntry {
+    list: Option<(Instant, ToDoList)>,
+    items: HashMap<String, (Instant, ToDo)>,
+    last_used: Option<Instant>,
+    // Changes whenever the owner's entries are invalidated, so a load that started before a
+    // write is not stored after it.
+    generation: u64,
+}
+
+/// Caches the results of `get` and `list` per owner, within their tenant, in front of another
+/// `ToDoRepo`. Entries expire after `ttl`, and once `max_owners` owners are cached the least
+/// recently used owner is evicted. Creating or updating a ToDo clears its owner's entries, and
+/// results loaded before the clear are discarded rather than stored.
+///
+/// The cache is held in memory, so other instances of the service can return a stale ToDo for
+/// up to `ttl` after it changes.
+pub struct CachingToDoRepo {
+    inner: Arc<dyn ToDoRepo + Send + Sync>,
+    ttl: Duration,
+    max_owners: usize,
+    owners: Mutex<HashMap<String, OwnerEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    next_generation: AtomicU64,
+}
+
+impl CachingToDoRepo {
+    pub fn new(inner: Arc<dyn ToDoRepo + Send + Sync>, ttl: Duration, max_owners: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            max_owners,
+            owners: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            next_generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Wrap `inner` when `CACHE_TTL_SECONDS` is set, bounded by `CACHE_MAX_OWNERS`. Returns
+    /// `inner` unchanged when caching is not configured.
+    pub fn wrap_from_env(inner: Arc<dyn ToDoRepo + Send + Sync>) -> Arc<dyn ToDoRepo + Send + Sync> {
+        let ttl_seconds: Option<u64> = env::var("CACHE_TTL_SECONDS")
+            .ok()
+            .and_then(|val| val.parse().ok());
+
+        match ttl_seconds {
+            Some(ttl_seconds) if ttl_seconds > 0 => {
+                let max_owners = env::var("CACHE_MAX_OWNERS")
+                    .ok()
+                    .and_then(|val| val.parse().ok())
+                    .unwrap_or(DEFAULT_MAX_OWNERS);
+
+                Arc::new(CachingToDoRepo::new(
+                    inner,
+                    Duration::from_secs(ttl_seconds),
+                    max_owners,
+                ))
+            }
+            _ => inner,
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Find a cached value for the owner. On a miss returns the owner's current generation,
+    /// which must be passed to `store` with the value loaded from the wrapped repository.
+    fn lookup<T>(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        find: impl FnOnce(&OwnerEntry, Instant) -> Option<T>,
+    ) -> Result<T, u64> {
+        let now = Instant::now();
+        let mut owners = self.owners.lock().unwrap();
+        let entry = self.entry(&mut owners, owner_key(tenant, user_id));
+
+        entry.last_used = Some(now);
+
+        match find(entry, now) {
+            Some(found) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(found)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Err(entry.generation)
+            }
+        }
+    }
+
+    /// Update the owner's entry, unless it has been invalidated or evicted since `generation`
+    /// was returned by `lookup`.
+    fn store(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        generation: u64,
+        update: impl FnOnce(&mut OwnerEntry, Instant),
+    ) {
+        let mut owners = self.owners.lock().unwrap();
+
+        if let Some(entry) = owners.get_mut(&owner_key(tenant, user_id)) {
+            if entry.generation == generation {
+                update(entry, Instant::now());
+            }
+        }
+    }
+
+    fn invalidate(&self, tenant: &TenantId, user_id: &str) {
+        let mut owners = self.owners.lock().unwrap();
+
+        if let Some(entry) = owners.get_mut(&owner_key(tenant, user_id)) {
+            entry.list = None;
+            entry.items.clear();
+            entry.generation = self.next_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        }
+    }
+
+    fn entry<'a>(
+        &self,
+        owners: &'a mut HashMap<String, OwnerEntry>,
+        owner: String,
+    ) -> &'a mut OwnerEntry {
+        if !owners.contains_key(&owner) && owners.len() >= self.max_owners {
+            let least_recently_used = owners
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(owner, _)| owner.clone());
+
+            if let Some(least_recently_used) = least_recently_used {
+                owners.remove(&least_recently_used);
+            }
+        }
+
+        owners.entry(owner).or_insert_with(|| OwnerEntry {
+            list: None,
+            items: HashMap::new(),
+            last_used: None,
+            generation: self.next_generation.fetch_add(1, Ordering::Relaxed) + 1,
+        })
+    }
+
+    fn is_fresh(&self, stored_at: &Instant, now: Instant) -> bool {
+        now.saturating_duration_since(*stored_at) < self.ttl
+    }
+}
+
+#[async_trait]
+impl ToDoRepo for CachingToDoRepo {
+    async fn list(&self, tenant: &TenantId, user_id: &str) -> Result<ToDoList, RepositoryError> {
+        let cached = self.lookup(tenant, user_id, |entry, now| match &entry.list {
+            Some((stored_at, todos)) if self.is_fresh(stored_at, now) => Some(todos.clone()),
+            _ => None,
+        });
+
+        let generation = match cached {
+            Ok(todos) => return Ok(todos),
+            Err(generation) => generation,
+        };
+
+        let todos = self.inner.list(tenant, user_id).await?;
+
+        self.store(tenant, user_id, generation, |entry, now| {
+            entry.list = Some((now, todos.clone()))
+        });
+
+        Ok(todos)
+    }
+
+    async fn list_page(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        start: Option<&str>,
+        page_size: usize,
+    ) -> Result<ToDoPage, RepositoryError> {
+        // Pages are read once by an export, there is nothing to gain from keeping them.
+        self.inner.list_page(tenant, user_id, start, page_size).await
+    }
+
+    async fn create(&self, to_do: &ToDo) -> Result<(), RepositoryError> {
+        let res = self.inner.create(to_do).await;
+
+        // Clear the owner's entries even if the write failed, it may still have been applied.
+        self.invalidate(to_do.get_tenant(), to_do.get_owner());
+
+        res
+    }
+
+    async fn create_within_quota(
+        &self,
+        to_do: &ToDo,
+        max_open_todos: usize,
+    ) -> Result<bool, RepositoryError> {
+        let res = self.inner.create_within_quota(to_do, max_open_todos).await;
+
+        self.invalidate(to_do.get_tenant(), to_do.get_owner());
+
+        res
+    }
+
+    async fn get(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        let todo_key = todo_id.to_uppercase();
+
+        let cached = self.lookup(tenant, user_id, |entry, now| {
+            if let Some((stored_at, todo)) = entry.items.get(&todo_key) {
+                if self.is_fresh(stored_at, now) {
+                    return Some(todo.clone());
+                }
+            }
+
+            match &entry.list {
+                Some((stored_at, list)) if self.is_fresh(stored_at, now) => list
+                    .todos
+                    .iter()
+                    .find(|todo| todo.get_id().to_uppercase() == todo_key)
+                    .cloned(),
+                _ => None,
+            }
+        });
+
+        let generation = match cached {
+            Ok(todo) => return Ok(todo),
+            Err(generation) => generation,
+        };
+
+        let todo = self.inner.get(tenant, user_id, todo_id).await?;
+
+        self.store(tenant, user_id, generation, |entry, now| {
+            entry.items.insert(todo_key, (now, todo.clone()));
+        });
+
+        Ok(todo)
+    }
+
+    async fn get_for_update(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        self.inner.get_for_update(tenant, user_id, todo_id).await
+    }
+
+    async fn get_many(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> Result<ToDoList, RepositoryError> {
+        self.inner.get_many(tenant, user_id, todo_ids).await
+    }
+
+    async fn search(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        terms: &[String],
+    ) -> Result<Vec<SearchPosting>, RepositoryError> {
+        self.inner.search(tenant, user_id, terms).await
+    }
+
+    async fn reserve_positions(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        count: usize,
+    ) -> Result<Vec<Position>, RepositoryError> {
+        self.inner.reserve_positions(tenant, user_id, count).await
+    }
+
+    async fn set_position(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+        position: &Position,
+    ) -> Result<(), RepositoryError> {
+        let res = self.inner.set_position(tenant, user_id, todo_id, position).await;
+
+        self.invalidate(tenant, user_id);
+
+        res
+    }
+
+    async fn health_check(&self) -> Result<(), RepositoryError> {
+        self.inner.health_check().await
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        Some(self.stats())
+    }
+
+    fn invalidate_owner(&self, tenant: &TenantId, user_id: &str) {
+        self.invalidate(tenant, user_id);
+    }
+
+    async fn get_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+    ) -> Result<Preferences, RepositoryError> {
+        self.inner.get_preferences(tenant, user_id).await
+    }
+
+    async fn set_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        preferences: &Preferences,
+    ) -> Result<(), RepositoryError> {
+        self.inner.set_preferences(tenant, user_id, preferences).await
+    }
+}
+
+// Owner ids are only unique within a tenant.
+fn owner_key(tenant: &TenantId, user_id: &str) -> String {
+    format!("{0}#{1}", tenant.to_string(), user_id.to_uppercase())
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{owner_key, CacheStats, CachingToDoRepo};
+    use crate::application::adapters::InMemoryToDoRepo;
+    use crate::application::domain::{OwnerId, TenantId, Title, ToDo, ToDoRepo};
+    use crate::application::error_types::RepositoryErrorKind;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn to_do(owner: &str, title: &str) -> ToDo {
+        ToDo::new(
+            Title::new(title).unwrap(),
+            OwnerId::new(owner).unwrap(),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn caching_repo(
+        inner: Arc<InMemoryToDoRepo>,
+        ttl: Duration,
+        max_owners: usize,
+    ) -> CachingToDoRepo {
+        CachingToDoRepo::new(inner, ttl, max_owners)
+    }
+
+    #[tokio::test]
+    async fn repeated_list_should_be_served_from_cache() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        inner.create(&to_do("owner", "First")).await.unwrap();
+
+        let repo = caching_repo(inner.clone(), Duration::from_secs(60), 10);
+
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+
+        // Written directly to the inner repository, so the cached list does not see it.
+        inner.create(&to_do("owner", "Second")).await.unwrap();
+
+        let todos = repo.list(&TenantId::default(), "owner").await.unwrap().todos;
+
+        assert_eq!(todos.len(), 1);
+        assert_eq!(repo.stats(), CacheStats { hits: 1, misses: 1 });
+    }
+
+    #[tokio::test]
+    async fn create_should_invalidate_owner() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let repo = caching_repo(inner, Duration::from_secs(60), 10);
+
+        repo.create(&to_do("owner", "First")).await.unwrap();
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+        repo.create(&to_do("owner", "Second")).await.unwrap();
+
+        let todos = repo.list(&TenantId::default(), "owner").await.unwrap().todos;
+
+        assert_eq!(todos.len(), 2);
+        assert_eq!(repo.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn invalidate_owner_should_drop_cached_list() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        inner.create(&to_do("owner", "First")).await.unwrap();
+
+        let repo = caching_repo(inner.clone(), Duration::from_secs(60), 10);
+
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+        inner.create(&to_do("owner", "Second")).await.unwrap();
+        repo.invalidate_owner(&TenantId::default(), "owner");
+
+        let todos = repo.list(&TenantId::default(), "owner").await.unwrap().todos;
+
+        assert_eq!(todos.len(), 2);
+        assert_eq!(repo.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn get_should_use_cached_list() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let first = to_do("owner", "First");
+        let repo = caching_repo(inner, Duration::from_secs(60), 10);
+
+        repo.create(&first).await.unwrap();
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+
+        let todo = repo.get(&TenantId::default(), "OWNER", first.get_id()).await.unwrap();
+
+        assert_eq!(todo.get_title(), "First");
+        assert_eq!(repo.stats(), CacheStats { hits: 1, misses: 1 });
+    }
+
+    #[tokio::test]
+    async fn get_for_update_should_bypass_cache() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let first = to_do("owner", "First");
+        let repo = caching_repo(inner.clone(), Duration::from_secs(60), 10);
+
+        repo.create(&first).await.unwrap();
+        let cached = repo.get(&TenantId::default(), "owner", first.get_id()).await.unwrap();
+
+        // Written directly to the inner repository, so the cached copy is now stale.
+        inner.create(&cached.clone().update_title("Renamed").unwrap()).await.unwrap();
+
+        let todo = repo
+            .get_for_update(&TenantId::default(), "owner", first.get_id())
+            .await
+            .unwrap();
+
+        assert_eq!(todo.get_title(), "Renamed");
+        let stale = repo.create(&cached.update_title("Stale").unwrap()).await.unwrap_err();
+
+        assert_eq!(stale.kind(), &RepositoryErrorKind::Conflict);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_should_be_reloaded() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let repo = caching_repo(inner, Duration::ZERO, 10);
+
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+
+        assert_eq!(repo.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_owner_should_be_evicted() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let repo = caching_repo(inner, Duration::from_secs(60), 2);
+
+        repo.list(&TenantId::default(), "first").await.unwrap();
+        repo.list(&TenantId::default(), "second").await.unwrap();
+        repo.list(&TenantId::default(), "first").await.unwrap();
+        repo.list(&TenantId::default(), "third").await.unwrap();
+
+        repo.list(&TenantId::default(), "first").await.unwrap();
+        repo.list(&TenantId::default(), "second").await.unwrap();
+
+        assert_eq!(repo.stats(), CacheStats { hits: 2, misses: 4 });
+    }
+
+    #[tokio::test]
+    async fn owners_in_different_tenants_should_not_share_entries() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let repo = caching_repo(inner, Duration::from_secs(60), 10);
+        let acme = TenantId::new("acme").unwrap();
+
+        repo.create(&to_do("owner", "First")).await.unwrap();
+        repo.list(&TenantId::default(), "owner").await.unwrap();
+
+        let todos = repo.list(&acme, "owner").await.unwrap().todos;
+
+        assert!(todos.is_empty());
+        assert_eq!(repo.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn list_loaded_before_create_should_not_be_stored() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let repo = caching_repo(inner.clone(), Duration::from_secs(60), 10);
+        let tenant = TenantId::default();
+
+        // A list misses and loads, then a create invalidates the owner before the load is stored.
+        let generation = repo.lookup(&tenant, "owner", |_, _| None::<()>).unwrap_err();
+        let stale = inner.list(&tenant, "owner").await.unwrap();
+        repo.create(&to_do("owner", "First")).await.unwrap();
+        repo.store(&tenant, "owner", generation, |entry, now| entry.list = Some((now, stale)));
+
+        let todos = repo.list(&tenant, "owner").await.unwrap().todos;
+
+        assert_eq!(todos.len(), 1);
+        assert!(repo.owners.lock().unwrap()[&owner_key(&tenant, "owner")].list.is_some());
+    }
+}
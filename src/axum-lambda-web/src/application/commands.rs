@@ -1,69 +1,104 @@
+use crate::application::attachments::AttachmentStore;
 use crate::application::domain::ToDoRepo;
 use crate::application::error_types::ValidationError;
-use crate::application::events::{MessageType, ToDoCompleted, ToDoCreated, ToDoUpdated};
+use crate::application::events::{
+    AttachmentAdded, MessageType, TimerStarted, TimerStopped, ToDoCompleted, ToDoCreated,
+    ToDoUpdated,
+};
 use crate::application::messaging::MessagePublisher;
+use crate::application::timers::{TimeEntry, TimerRepo};
+use crate::application::transfer::{parse_import, ImportRow, TransferFormat};
+use crate::application::webhooks::{Webhook, WebhookRepo, MAX_WEBHOOKS_PER_OWNER};
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 
 use super::{
-    domain::{OwnerId, Title, ToDo},
+    domain::{
+        sort_by_position, Attachment, Description, DueDate, OwnerId, Position, Preferences, Priority,
+        ReminderLeadTime, TenantId, Timezone,
+        Title, ToDo,
+    },
     error_types::ServiceError,
-    public_types::{CreateToDoCommand, ToDoItem, UpdateToDoCommand},
+    public_types::{
+        AddAttachmentCommand, AttachmentItem, AttachmentUpload, CreateToDoCommand, ImportReport,
+        ImportRowError, MoveToDoCommand, RegisterWebhookCommand, TimeEntryItem, ToDoItem,
+        UpdateToDoCommand, UpdateWebhookCommand, UserPreferences, WebhookItem,
+    },
 };
 
 pub async fn create_to_do(
     owner: String,
+    tenant: &TenantId,
     input: CreateToDoCommand,
+    max_open_todos: Option<usize>,
     client: &Arc<dyn ToDoRepo + Send + Sync>,
     message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
 ) -> Result<ToDoItem, ServiceError> {
     let parsed_title = Title::new(input.title.as_str());
-    let parsed_ownerid = OwnerId::new(owner.as_str());
+    let parsed_ownerid =
+        OwnerId::new(owner.as_str()).map(|owner_id| owner_id.in_tenant(tenant));
+    let parsed_description = input
+        .description
+        .map(|description| Description::new(description.as_str()))
+        .transpose();
+    let parsed_duedate = DueDate::parse_optional(input.due_date);
+    let parsed_priority = input
+        .priority
+        .map(|priority| Priority::new(priority.as_str()))
+        .transpose();
 
-    if parsed_title.is_err() || parsed_ownerid.is_err() {
-        let errors = vec![parsed_title.err(), parsed_ownerid.err()];
+    if parsed_title.is_err()
+        || parsed_ownerid.is_err()
+        || parsed_description.is_err()
+        || parsed_duedate.is_err()
+        || parsed_priority.is_err()
+    {
+        let errors = vec![
+            parsed_title.err(),
+            parsed_ownerid.err(),
+            parsed_description.err(),
+            parsed_duedate.err(),
+            parsed_priority.err(),
+        ];
 
         return Err(combine_errors(errors));
     }
 
-    let parsed_duedate = match input.due_date {
-        None => None,
-        Some(due_date) => {
-            let parsed_due_date = chrono::DateTime::parse_from_rfc3339(due_date.as_str());
-
-            match parsed_due_date {
-                Ok(date) => Some(date),
-                Err(_) => None,
-            }
-        }
-    };
+    let position = reserve_position(&owner, tenant, client).await?;
 
     let to_do = ToDo::new(
         parsed_title.unwrap(),
         parsed_ownerid.unwrap(),
-        input.description,
-        parsed_duedate,
-    )?;
+        parsed_description.unwrap(),
+        parsed_duedate.unwrap(),
+    )?
+    .update_priority(parsed_priority.unwrap())
+    .move_to(position);
 
-    let _ = client.create(&to_do).await?;
+    create_within_quota(&to_do, &owner, max_open_todos, client).await?;
 
     let _ = message_publisher
         .publish(MessageType::Created(ToDoCreated::new(
             to_do.get_id(),
             to_do.get_owner(),
+            to_do.get_tenant().to_string(),
         )))
         .await;
 
+    flush_messages(message_publisher).await;
+
     Ok(ToDoItem::from(to_do))
 }
 
 pub async fn update_todo(
     owner: String,
+    tenant: &TenantId,
     to_do_id: String,
     update_command: UpdateToDoCommand,
     client: &Arc<dyn ToDoRepo + Send + Sync>,
     message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
 ) -> Result<ToDoItem, ServiceError> {
-    let todo = client.get(&owner, &to_do_id).await?;
+    let todo = client.get_for_update(tenant, &owner, &to_do_id).await?;
 
     let updated_status = match update_command.set_as_complete {
         true => todo.set_completed(),
@@ -73,14 +108,20 @@ pub async fn update_todo(
     let mut updated_todo = updated_status.update_title(update_command.title.as_str())?;
 
     updated_todo = updated_todo
-        .update_description(update_command.description)
-        .update_due_date(update_command.due_date);
+        .update_description(update_command.description)?
+        .update_due_date(update_command.due_date)?
+        .update_priority(
+            update_command
+                .priority
+                .map(|priority| Priority::new(priority.as_str()))
+                .transpose()?,
+        );
 
     if !updated_todo.has_changes() {
         return Ok(ToDoItem::from(updated_todo));
     }
 
-    let _ = client.create(&updated_todo).await?;
+    client.create(&updated_todo).await?;
 
     let _ = match update_command.set_as_complete {
         true => {
@@ -88,6 +129,7 @@ pub async fn update_todo(
                 .publish(MessageType::Completed(ToDoCompleted::new(
                     updated_todo.get_id(),
                     updated_todo.get_owner(),
+                    updated_todo.get_tenant().to_string(),
                 )))
                 .await
         }
@@ -96,47 +138,583 @@ pub async fn update_todo(
                 .publish(MessageType::Updated(ToDoUpdated::new(
                     updated_todo.get_id(),
                     updated_todo.get_owner(),
+                    updated_todo.get_tenant().to_string(),
                 )))
                 .await
         }
     };
 
+    flush_messages(message_publisher).await;
+
     Ok(ToDoItem::from(updated_todo))
 }
 
-fn combine_errors(err: Vec<Option<ValidationError>>) -> ServiceError {
-    let mut error_builder = String::from("");
-
-    for ele in err {
-        error_builder = format!(
-            "{} {}",
-            error_builder,
-            match ele {
-                None => String::from(""),
-                Some(val) => val.to_string(),
+pub async fn update_preferences(
+    owner: String,
+    tenant: &TenantId,
+    input: UserPreferences,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<UserPreferences, ServiceError> {
+    let parsed_ownerid = OwnerId::new(owner.as_str())?.in_tenant(tenant);
+    let timezone = Timezone::new(input.timezone.as_str());
+    let reminder_lead_time = input
+        .reminder_lead_time_minutes
+        .map(ReminderLeadTime::new)
+        .transpose();
+
+    if timezone.is_err() || reminder_lead_time.is_err() {
+        return Err(combine_errors(vec![
+            timezone.err(),
+            reminder_lead_time.err(),
+        ]));
+    }
+
+    let preferences = Preferences {
+        timezone: timezone.unwrap(),
+        reminder_lead_time: reminder_lead_time.unwrap(),
+    };
+
+    client
+        .set_preferences(tenant, parsed_ownerid.to_string(), &preferences)
+        .await?;
+
+    Ok(UserPreferences::from(preferences))
+}
+
+/// Move a ToDo directly before or after another of the owner's ToDos. Only the moved ToDo's
+/// position is written, unless some of the owner's ToDos have never been placed, in which case
+/// those are given positions at the end of the list in their current order first.
+pub async fn move_todo(
+    owner: String,
+    tenant: &TenantId,
+    to_do_id: String,
+    move_command: MoveToDoCommand,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<ToDoItem, ServiceError> {
+    let (target_id, place_after, field) = match (move_command.before, move_command.after) {
+        (Some(target_id), None) => (target_id, false, "before"),
+        (None, Some(target_id)) => (target_id, true, "after"),
+        _ => {
+            return Err(ValidationError::new(
+                "Set either 'before' or 'after' to the id of another ToDo".to_string(),
+            )
+            .with_field("before")
+            .into())
+        }
+    };
+
+    if target_id.eq_ignore_ascii_case(&to_do_id) {
+        return Err(ValidationError::new("A ToDo cannot be moved next to itself".to_string())
+            .with_field(field)
+            .into());
+    }
+
+    let to_do = client.get(tenant, &owner, &to_do_id).await?;
+
+    let mut todos = client.list(tenant, &owner).await?.todos;
+    sort_by_position(&mut todos);
+    todos.retain(|todo| !todo.get_id().eq_ignore_ascii_case(&to_do_id));
+
+    let target_index = todos
+        .iter()
+        .position(|todo| todo.get_id().eq_ignore_ascii_case(&target_id))
+        .ok_or_else(|| ServiceError::not_found(format!("ToDo '{}' not found", target_id)))?;
+
+    // Unplaced ToDos are sorted last, so placing them at the end keeps the order the owner sees.
+    let unplaced: Vec<usize> = (0..todos.len())
+        .filter(|index| todos[*index].get_position().is_none())
+        .collect();
+
+    if !unplaced.is_empty() {
+        let positions = client
+            .reserve_positions(tenant, &owner, unplaced.len())
+            .await?;
+
+        for (index, position) in unplaced.into_iter().zip(positions) {
+            client
+                .set_position(tenant, &owner, todos[index].get_id(), &position)
+                .await?;
+
+            todos[index] = todos[index].clone().move_to(position);
+        }
+    }
+
+    let insert_at = match place_after {
+        true => target_index + 1,
+        false => target_index,
+    };
+
+    let lower = insert_at.checked_sub(1).and_then(|index| todos[index].get_position());
+
+    let position = match todos.get(insert_at).and_then(ToDo::get_position) {
+        Some(upper) => Position::between(lower, Some(upper)).ok_or_else(|| {
+            ServiceError::conflict(format!(
+                "ToDo '{}' shares its position with the ToDo next to it, move one of them first",
+                target_id
+            ))
+        })?,
+        // Moving to the end of the list takes the next position there, so a ToDo created
+        // afterwards is still added after it.
+        None => reserve_position(&owner, tenant, client).await?,
+    };
+
+    client.set_position(tenant, &owner, to_do.get_id(), &position).await?;
+
+    let moved_todo = to_do.move_to(position);
+
+    let _ = message_publisher
+        .publish(MessageType::Updated(ToDoUpdated::new(
+            moved_todo.get_id(),
+            moved_todo.get_owner(),
+            moved_todo.get_tenant().to_string(),
+        )))
+        .await;
+
+    flush_messages(message_publisher).await;
+
+    Ok(ToDoItem::from(moved_todo))
+}
+
+/// Import every ToDo in the body. Rows that fail validation are reported in the
+/// `ImportReport` and do not stop the remaining rows from being imported.
+pub async fn import_todos(
+    owner: String,
+    tenant: &TenantId,
+    format: TransferFormat,
+    body: &str,
+    max_open_todos: Option<usize>,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<ImportReport, ServiceError> {
+    let rows = parse_import(format, body)?;
+
+    // Rows that fail leave a gap in the reserved positions, which doesn't change the order.
+    let positions = client.reserve_positions(tenant, &owner, rows.len()).await?;
+
+    let mut report = ImportReport::default();
+
+    for (index, (row, position)) in rows.into_iter().zip(positions).enumerate() {
+        let imported = match row {
+            Ok(row) => {
+                import_row(
+                    &owner,
+                    tenant,
+                    row,
+                    position,
+                    max_open_todos,
+                    client,
+                    message_publisher,
+                )
+                .await
             }
-        )
+            Err(message) => Err(ServiceError::validation(message)),
+        };
+
+        match imported {
+            Ok(to_do_item) => report.imported.push(to_do_item),
+            Err(e) => report.errors.push(ImportRowError {
+                row: index + 1,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    flush_messages(message_publisher).await;
+
+    Ok(report)
+}
+
+async fn import_row(
+    owner: &str,
+    tenant: &TenantId,
+    row: ImportRow,
+    position: Position,
+    max_open_todos: Option<usize>,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<ToDoItem, ServiceError> {
+    let parsed_title = Title::new(row.title.as_str())?;
+    let parsed_ownerid = OwnerId::new(owner)?.in_tenant(tenant);
+    let parsed_description = row
+        .description
+        .map(|description| Description::new(description.as_str()))
+        .transpose()?;
+
+    let parsed_duedate = DueDate::parse_optional(row.due_date)?;
+    let parsed_completed_on = row
+        .completed_on
+        .map(|completed_on| {
+            DateTime::parse_from_rfc3339(&completed_on).map_err(|_| {
+                ValidationError::new(format!(
+                    "Completed on '{}' must be an RFC3339 date and time",
+                    completed_on
+                ))
+                .with_field("completed_on")
+            })
+        })
+        .transpose()?;
+
+    let mut to_do = ToDo::new(
+        parsed_title,
+        parsed_ownerid,
+        parsed_description,
+        parsed_duedate,
+    )?
+    .move_to(position);
+
+    // Completed ToDos keep the date they were completed on, if the file has one
+    if row.is_complete {
+        to_do = match parsed_completed_on {
+            Some(completed_on) => to_do.set_completed_on(completed_on),
+            None => to_do.set_completed(),
+        };
+    }
+
+    create_within_quota(&to_do, owner, max_open_todos, client).await?;
+
+    let _ = message_publisher
+        .publish(MessageType::Created(ToDoCreated::new(
+            to_do.get_id(),
+            to_do.get_owner(),
+            to_do.get_tenant().to_string(),
+        )))
+        .await;
+
+    if row.is_complete {
+        let _ = message_publisher
+            .publish(MessageType::Completed(ToDoCompleted::new(
+                to_do.get_id(),
+                to_do.get_owner(),
+                to_do.get_tenant().to_string(),
+            )))
+            .await;
+    }
+
+    Ok(ToDoItem::from(to_do))
+}
+
+/// Record a file attached to a ToDo and return a presigned URL it can be uploaded to. The
+/// attachment is recorded as pending when the URL is issued, the file itself is uploaded
+/// directly to the attachment store and then confirmed with [`confirm_attachment`].
+pub async fn add_attachment(
+    owner: String,
+    tenant: &TenantId,
+    to_do_id: String,
+    input: AddAttachmentCommand,
+    attachment_store: &AttachmentStore,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<AttachmentUpload, ServiceError> {
+    let attachment = Attachment::new(
+        input.file_name.as_str(),
+        input.content_type.as_str(),
+        input.size,
+    )?;
+
+    attachment_store.check_limits(&attachment)?;
+
+    let to_do = client.get_for_update(tenant, &owner, &to_do_id).await?;
+
+    let upload = attachment_store
+        .presign_upload(tenant, to_do.get_owner(), to_do.get_id(), &attachment)
+        .await?;
+
+    let updated_todo = to_do.add_attachment(
+        attachment.clone(),
+        attachment_store.abandoned_before(Utc::now()),
+    )?;
+
+    client.create(&updated_todo).await?;
+
+    Ok(AttachmentUpload {
+        attachment: AttachmentItem::from(&attachment),
+        upload_url: upload.url,
+        headers: upload.headers.into_iter().collect(),
+        expires_on: upload.expires_on.to_rfc3339(),
+    })
+}
+
+/// Confirm a pending attachment once its file has been uploaded, and publish that it was added.
+/// Confirming an attachment again returns it unchanged.
+pub async fn confirm_attachment(
+    owner: String,
+    tenant: &TenantId,
+    to_do_id: String,
+    attachment_id: String,
+    attachment_store: &AttachmentStore,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<AttachmentItem, ServiceError> {
+    let to_do = client.get_for_update(tenant, &owner, &to_do_id).await?;
+
+    let attachment = match to_do
+        .get_attachments()
+        .iter()
+        .find(|attachment| attachment.id() == attachment_id)
+    {
+        Some(attachment) => attachment.clone(),
+        None => {
+            return Err(ServiceError::not_found(format!(
+                "Attachment '{}' not found",
+                attachment_id
+            )))
+        }
+    };
+
+    if attachment.is_confirmed() {
+        return Ok(AttachmentItem::from(&attachment));
+    }
+
+    if !attachment_store
+        .object_exists(tenant, to_do.get_owner(), to_do.get_id(), &attachment)
+        .await?
+    {
+        return Err(ServiceError::conflict(format!(
+            "Attachment '{}' has not been uploaded",
+            attachment_id
+        )));
+    }
+
+    let confirmed_on = Utc::now();
+    let updated_todo = to_do.confirm_attachment(&attachment_id, confirmed_on);
+
+    client.create(&updated_todo).await?;
+
+    let confirmed = attachment.with_confirmed_on(Some(confirmed_on));
+
+    let _ = message_publisher
+        .publish(MessageType::AttachmentAdded(AttachmentAdded::new(
+            updated_todo.get_id(),
+            updated_todo.get_owner(),
+            updated_todo.get_tenant().to_string(),
+            &confirmed,
+        )))
+        .await;
+
+    flush_messages(message_publisher).await;
+
+    Ok(AttachmentItem::from(&confirmed))
+}
+
+/// Start a timer on an incomplete ToDo. An owner can only have one timer running, so this fails
+/// with a conflict while another is running, even on the same ToDo.
+pub async fn start_timer(
+    owner: String,
+    tenant: &TenantId,
+    to_do_id: String,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    timer_repo: &Arc<dyn TimerRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<TimeEntryItem, ServiceError> {
+    let to_do = client.get(tenant, &owner, &to_do_id).await?;
+
+    if let ToDo::Complete(_) = to_do {
+        return Err(ServiceError::validation(
+            "A timer cannot be started on a completed ToDo".to_string(),
+        ));
+    }
+
+    let entry = TimeEntry::start(to_do.get_id());
+
+    if !timer_repo.start_timer(tenant, &owner, &entry).await? {
+        return Err(ServiceError::conflict(
+            "A timer is already running, stop it before starting another".to_string(),
+        ));
+    }
+
+    let _ = message_publisher
+        .publish(MessageType::TimerStarted(TimerStarted::new(
+            &owner,
+            tenant.to_string(),
+            &entry,
+        )))
+        .await;
+
+    flush_messages(message_publisher).await;
+
+    Ok(TimeEntryItem::from(&entry))
+}
+
+/// Stop the timer running on a ToDo and add the time it ran for to the ToDo's total.
+pub async fn stop_timer(
+    owner: String,
+    tenant: &TenantId,
+    to_do_id: String,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+    timer_repo: &Arc<dyn TimerRepo + Send + Sync>,
+    message_publisher: &Arc<dyn MessagePublisher + Send + Sync>,
+) -> Result<TimeEntryItem, ServiceError> {
+    let not_running = || ServiceError::conflict("No timer is running on this ToDo".to_string());
+
+    let entry = timer_repo
+        .running_timer(tenant, &owner)
+        .await?
+        .filter(|entry| entry.is_for(&to_do_id))
+        .ok_or_else(not_running)?;
+
+    let entry = entry.stop(Utc::now());
+
+    // The time is added in the same write that clears the running timer, so stopping the same
+    // timer twice can't count it twice.
+    if !timer_repo.stop_timer(tenant, &owner, &entry).await? {
+        return Err(not_running());
+    }
+
+    // The ToDo was changed without going through `client`, so anything it cached is stale.
+    client.invalidate_owner(tenant, &owner);
+
+    let _ = message_publisher
+        .publish(MessageType::TimerStopped(TimerStopped::new(
+            &owner,
+            tenant.to_string(),
+            &entry,
+        )))
+        .await;
+
+    flush_messages(message_publisher).await;
+
+    Ok(TimeEntryItem::from(&entry))
+}
+
+/// Register a webhook for the owner. The response is the only time the webhook's signing secret
+/// is returned.
+pub async fn register_webhook(
+    owner: String,
+    tenant: &TenantId,
+    input: RegisterWebhookCommand,
+    client: &Arc<dyn WebhookRepo + Send + Sync>,
+) -> Result<WebhookItem, ServiceError> {
+    let webhook = Webhook::new(tenant, &owner, input.url.as_str(), input.event_types)?;
+
+    if client.list_webhooks(tenant, &owner).await?.len() >= MAX_WEBHOOKS_PER_OWNER {
+        return Err(ServiceError::quota_exceeded(format!(
+            "An owner can register at most {} webhooks",
+            MAX_WEBHOOKS_PER_OWNER
+        )));
+    }
+
+    client.save_webhook(&webhook).await?;
+
+    Ok(WebhookItem::from(&webhook).with_secret(&webhook))
+}
+
+pub async fn update_webhook(
+    owner: String,
+    tenant: &TenantId,
+    webhook_id: String,
+    input: UpdateWebhookCommand,
+    client: &Arc<dyn WebhookRepo + Send + Sync>,
+) -> Result<WebhookItem, ServiceError> {
+    let mut webhook = client
+        .get_webhook(tenant, &owner, &webhook_id)
+        .await?
+        .ok_or_else(|| webhook_not_found(&webhook_id))?;
+
+    if let Some(url) = input.url {
+        webhook = webhook.update_url(url.as_str())?;
+    }
+
+    if let Some(event_types) = input.event_types {
+        webhook = webhook.update_event_types(event_types)?;
+    }
+
+    webhook = match input.enabled {
+        Some(true) if !webhook.is_enabled() => webhook.enable(),
+        Some(false) => webhook.disable(),
+        _ => webhook,
+    };
+
+    client.save_webhook(&webhook).await?;
+
+    Ok(WebhookItem::from(&webhook))
+}
+
+pub async fn delete_webhook(
+    owner: String,
+    tenant: &TenantId,
+    webhook_id: String,
+    client: &Arc<dyn WebhookRepo + Send + Sync>,
+) -> Result<(), ServiceError> {
+    match client.delete_webhook(tenant, &owner, &webhook_id).await? {
+        true => Ok(()),
+        false => Err(webhook_not_found(&webhook_id)),
+    }
+}
+
+fn webhook_not_found(webhook_id: &str) -> ServiceError {
+    ServiceError::not_found(format!("Webhook '{}' not found", webhook_id))
+}
+
+// The ToDo has already been stored, so a failure to deliver its events is logged rather than
+// returned to the caller. Undelivered events are kept by the publisher's dead-letter sink.
+async fn flush_messages(message_publisher: &Arc<dyn MessagePublisher + Send + Sync>) {
+    if let Err(e) = message_publisher.flush().await {
+        tracing::error!(undelivered_events = e.undelivered_events(), "{}", e);
     }
+}
+
+// A single position at the end of the owner's list, where new ToDos are added.
+async fn reserve_position(
+    owner: &str,
+    tenant: &TenantId,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<Position, ServiceError> {
+    let mut positions = client.reserve_positions(tenant, owner, 1).await?;
 
-    ServiceError::new(error_builder)
+    Ok(positions.remove(0))
+}
+
+// The quota is checked by the repository as part of storing the ToDo, so concurrent creates
+// can't take the owner over it.
+async fn create_within_quota(
+    to_do: &ToDo,
+    owner: &str,
+    max_open_todos: Option<usize>,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<(), ServiceError> {
+    match max_open_todos {
+        Some(max_open_todos) => match client.create_within_quota(to_do, max_open_todos).await? {
+            true => Ok(()),
+            false => Err(quota_exceeded_error(owner, max_open_todos)),
+        },
+        None => Ok(client.create(to_do).await?),
+    }
+}
+
+fn quota_exceeded_error(owner: &str, max_open_todos: usize) -> ServiceError {
+    ServiceError::quota_exceeded(format!(
+        "Quota exceeded: {} already has the maximum of {} open ToDos, complete some before creating more",
+        owner, max_open_todos
+    ))
+}
+
+fn combine_errors(err: Vec<Option<ValidationError>>) -> ServiceError {
+    ServiceError::from(err.into_iter().flatten().collect::<Vec<ValidationError>>())
 }
 
 /// Unit tests
 ///
 /// These tests are run using the `cargo test` command.
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
 mod tests {
     use async_trait::async_trait;
-    use chrono::{DateTime, Utc};
+    use chrono::{DateTime, Duration, Utc};
     use std::sync::Arc;
 
+    use crate::application::adapters::InMemoryToDoRepo;
     use crate::application::domain::AppState;
-    use crate::application::messaging::InMemoryMessagePublisher;
+    use crate::application::messaging::{InMemoryMessagePublisher, MessagePublisher};
     use crate::application::{
         commands,
-        domain::{OwnerId, Title, ToDo, ToDoId, ToDoRepo},
-        error_types::RepositoryError,
-        public_types::UpdateToDoCommand,
+        domain::{
+            sort_by_position, Description, DueDate, OwnerId, Position, Preferences, TenantId,
+            Title, ToDo, ToDoId, ToDoList, ToDoRepo,
+        },
+        error_types::{RepositoryError, ServiceErrorKind},
+        public_types::{CreateToDoCommand, MoveToDoCommand, UpdateToDoCommand},
+        search::SearchPosting,
+        timers::{TimeEntry, TimerRepo},
     };
 
     struct MockRepository {
@@ -146,7 +724,11 @@ mod tests {
 
     #[async_trait]
     impl ToDoRepo for MockRepository {
-        async fn list(&self, _user_id: &str) -> Result<Vec<ToDo>, RepositoryError> {
+        async fn list(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+        ) -> Result<ToDoList, RepositoryError> {
             if self.should_fail {
                 return Err(RepositoryError::new("Forced failure!".to_string()));
             }
@@ -159,8 +741,10 @@ mod tests {
                     OwnerId::new("owner").unwrap(),
                     Some(self.to_do_status_to_return.to_string()),
                     Some(ToDoId::parse("id").unwrap()),
-                    Some(String::from("Description")),
-                    Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
+                    Some(Description::new("Description").unwrap()),
+                    Some(DueDate::At(
+                        DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+                    )),
                     match self.to_do_status_to_return.as_str() {
                         "COMPLETE" => {
                             Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap())
@@ -171,7 +755,7 @@ mod tests {
                 .unwrap(),
             );
 
-            Ok(todos)
+            Ok(ToDoList::from(todos))
         }
 
         async fn create(&self, _to_do: &ToDo) -> Result<(), RepositoryError> {
@@ -182,7 +766,28 @@ mod tests {
             }
         }
 
-        async fn get(&self, _user_id: &str, _todo_id: &str) -> Result<ToDo, RepositoryError> {
+        async fn create_within_quota(
+            &self,
+            to_do: &ToDo,
+            max_open_todos: usize,
+        ) -> Result<bool, RepositoryError> {
+            let open_todos = self
+                .list(to_do.get_tenant(), to_do.get_owner())
+                .await?
+                .todos
+                .iter()
+                .filter(|todo| todo.is_open())
+                .count();
+
+            Ok(open_todos < max_open_todos)
+        }
+
+        async fn get(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _todo_id: &str,
+        ) -> Result<ToDo, RepositoryError> {
             if self.should_fail {
                 return Err(RepositoryError::new("Forced failure!".to_string()));
             }
@@ -192,8 +797,10 @@ mod tests {
                 OwnerId::new("owner").unwrap(),
                 Some(self.to_do_status_to_return.to_string()),
                 Some(ToDoId::parse("id").unwrap()),
-                Some(String::from("Description")),
-                Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
+                Some(Description::new("Description").unwrap()),
+                Some(DueDate::At(
+                    DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+                )),
                 match self.to_do_status_to_return.as_str() {
                     "COMPLETE" => {
                         Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap())
@@ -203,6 +810,244 @@ mod tests {
             )
             .unwrap())
         }
+
+        async fn search(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _terms: &[String],
+        ) -> Result<Vec<SearchPosting>, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Vec::new())
+        }
+
+        async fn reserve_positions(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            count: usize,
+        ) -> Result<Vec<Position>, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Position::sequence(None, count))
+        }
+
+        async fn set_position(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _todo_id: &str,
+            _position: &Position,
+        ) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
+
+        async fn health_check(&self) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
+
+        async fn get_preferences(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+        ) -> Result<Preferences, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Preferences::default())
+        }
+
+        async fn set_preferences(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _preferences: &Preferences,
+        ) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Stops the owner's running timer once a ToDo has been read for an update, before the
+    /// update is written, as a concurrent request would.
+    struct TimerStoppedDuringUpdate {
+        inner: Arc<InMemoryToDoRepo>,
+        tracked: Duration,
+    }
+
+    #[async_trait]
+    impl ToDoRepo for TimerStoppedDuringUpdate {
+        async fn list(&self, tenant: &TenantId, user_id: &str) -> Result<ToDoList, RepositoryError> {
+            self.inner.list(tenant, user_id).await
+        }
+
+        async fn create(&self, to_do: &ToDo) -> Result<(), RepositoryError> {
+            self.inner.create(to_do).await
+        }
+
+        async fn create_within_quota(
+            &self,
+            to_do: &ToDo,
+            max_open_todos: usize,
+        ) -> Result<bool, RepositoryError> {
+            self.inner.create_within_quota(to_do, max_open_todos).await
+        }
+
+        async fn get(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            todo_id: &str,
+        ) -> Result<ToDo, RepositoryError> {
+            self.inner.get(tenant, user_id, todo_id).await
+        }
+
+        async fn get_for_update(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            todo_id: &str,
+        ) -> Result<ToDo, RepositoryError> {
+            let to_do = self.inner.get_for_update(tenant, user_id, todo_id).await?;
+
+            let entry = self.inner.running_timer(tenant, user_id).await?.unwrap();
+            let stopped_on = entry.started_on() + self.tracked;
+
+            assert!(self.inner.stop_timer(tenant, user_id, &entry.stop(stopped_on)).await?);
+
+            Ok(to_do)
+        }
+
+        async fn search(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            terms: &[String],
+        ) -> Result<Vec<SearchPosting>, RepositoryError> {
+            self.inner.search(tenant, user_id, terms).await
+        }
+
+        async fn reserve_positions(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            count: usize,
+        ) -> Result<Vec<Position>, RepositoryError> {
+            self.inner.reserve_positions(tenant, user_id, count).await
+        }
+
+        async fn set_position(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            todo_id: &str,
+            position: &Position,
+        ) -> Result<(), RepositoryError> {
+            self.inner.set_position(tenant, user_id, todo_id, position).await
+        }
+
+        async fn health_check(&self) -> Result<(), RepositoryError> {
+            self.inner.health_check().await
+        }
+
+        async fn get_preferences(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+        ) -> Result<Preferences, RepositoryError> {
+            self.inner.get_preferences(tenant, user_id).await
+        }
+
+        async fn set_preferences(
+            &self,
+            tenant: &TenantId,
+            user_id: &str,
+            preferences: &Preferences,
+        ) -> Result<(), RepositoryError> {
+            self.inner.set_preferences(tenant, user_id, preferences).await
+        }
+    }
+
+    #[tokio::test]
+    async fn create_todo_over_quota_should_return_quota_error() {
+        let shared_state = Arc::new(AppState {
+            todo_repo: Arc::new(MockRepository {
+                should_fail: false,
+                to_do_status_to_return: "INCOMPLETE".to_string(),
+            }),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: Some(1),
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        });
+
+        let to_do = commands::create_to_do(
+            "jameseastham".to_string(),
+            &TenantId::default(),
+            CreateToDoCommand {
+                title: "newtitle".to_string(),
+                description: None,
+                due_date: None,
+                priority: None,
+            },
+            shared_state.max_open_todos,
+            &shared_state.todo_repo,
+            &shared_state.message_publisher,
+        )
+        .await;
+
+        assert!(to_do.is_err());
+        assert_eq!(to_do.err().unwrap().kind(), &ServiceErrorKind::QuotaExceeded);
+    }
+
+    #[tokio::test]
+    async fn create_todo_under_quota_should_create() {
+        let shared_state = Arc::new(AppState {
+            todo_repo: Arc::new(MockRepository {
+                should_fail: false,
+                to_do_status_to_return: "COMPLETE".to_string(),
+            }),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: Some(1),
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        });
+
+        let to_do = commands::create_to_do(
+            "jameseastham".to_string(),
+            &TenantId::default(),
+            CreateToDoCommand {
+                title: "newtitle".to_string(),
+                description: None,
+                due_date: None,
+                priority: None,
+            },
+            shared_state.max_open_todos,
+            &shared_state.todo_repo,
+            &shared_state.message_publisher,
+        )
+        .await;
+
+        assert!(to_do.is_ok());
     }
 
     #[tokio::test]
@@ -213,16 +1058,22 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: false,
                 description: None,
                 due_date: None,
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -241,16 +1092,22 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: false,
                 description: Some("mydescription".to_string()),
                 due_date: None,
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -269,16 +1126,22 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: false,
                 description: Some("mydescription".to_string()),
                 due_date: Some("2023-08-13T00:00:00+00:00".to_string()),
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -297,16 +1160,22 @@ mod tests {
                 to_do_status_to_return: "COMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: true,
                 description: None,
                 due_date: None,
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -325,16 +1194,22 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: false,
                 description: None,
                 due_date: None,
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -353,16 +1228,22 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
         let to_dos = commands::update_todo(
             "jameseastham".to_string(),
+            &TenantId::default(),
             "12345".to_string(),
             UpdateToDoCommand {
                 title: "newtitle".to_string(),
                 set_as_complete: true,
                 description: None,
                 due_date: None,
+                priority: None,
             },
             &shared_state.todo_repo,
             &shared_state.message_publisher,
@@ -373,4 +1254,165 @@ mod tests {
         assert_eq!(to_dos.as_ref().unwrap().title, "title");
         assert!(to_dos.as_ref().unwrap().is_complete);
     }
+
+    #[tokio::test]
+    async fn update_todo_should_not_lose_time_tracked_after_it_was_read() {
+        let inner = Arc::new(InMemoryToDoRepo::new());
+        let to_do = ToDo::new(
+            Title::new("title").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        inner.create(&to_do).await.unwrap();
+        inner
+            .start_timer(&TenantId::default(), "owner", &TimeEntry::start(to_do.get_id()))
+            .await
+            .unwrap();
+
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(TimerStoppedDuringUpdate {
+            inner: inner.clone(),
+            tracked: Duration::minutes(5),
+        });
+        let message_publisher: Arc<dyn MessagePublisher + Send + Sync> =
+            Arc::new(InMemoryMessagePublisher::new());
+
+        let res = commands::update_todo(
+            "owner".to_string(),
+            &TenantId::default(),
+            to_do.get_id().to_string(),
+            UpdateToDoCommand {
+                title: "newtitle".to_string(),
+                set_as_complete: false,
+                description: None,
+                due_date: None,
+                priority: None,
+            },
+            &todo_repo,
+            &message_publisher,
+        )
+        .await;
+
+        let stored = inner
+            .get(&TenantId::default(), "owner", to_do.get_id())
+            .await
+            .unwrap();
+
+        assert_eq!(res.err().unwrap().kind(), &ServiceErrorKind::Conflict);
+        assert_eq!(stored.get_title(), "title");
+        assert_eq!(stored.get_tracked_seconds(), 300);
+    }
+
+    #[tokio::test]
+    async fn move_should_place_unplaced_todos_and_keep_creates_at_end() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let client: Arc<dyn ToDoRepo + Send + Sync> = repo.clone();
+        let message_publisher: Arc<dyn MessagePublisher + Send + Sync> =
+            Arc::new(InMemoryMessagePublisher::new());
+
+        // Stored before ToDos were given positions
+        let mut unplaced_ids = Vec::new();
+
+        for title in ["First", "Second"] {
+            let to_do =
+                ToDo::new(Title::new(title).unwrap(), OwnerId::new("owner").unwrap(), None, None)
+                    .unwrap();
+
+            repo.create(&to_do).await.unwrap();
+            unplaced_ids.push(to_do.get_id().to_string());
+        }
+
+        let create = |title: &str| CreateToDoCommand {
+            title: title.to_string(),
+            description: None,
+            due_date: None,
+            priority: None,
+        };
+
+        let third = commands::create_to_do(
+            "owner".to_string(),
+            &TenantId::default(),
+            create("Third"),
+            None,
+            &client,
+            &message_publisher,
+        )
+        .await
+        .unwrap();
+
+        let last_unplaced = unplaced_ids.iter().max().unwrap().clone();
+
+        commands::move_todo(
+            "owner".to_string(),
+            &TenantId::default(),
+            third.id.clone(),
+            MoveToDoCommand {
+                before: None,
+                after: Some(last_unplaced.clone()),
+            },
+            &client,
+            &message_publisher,
+        )
+        .await
+        .unwrap();
+
+        commands::create_to_do(
+            "owner".to_string(),
+            &TenantId::default(),
+            create("Fourth"),
+            None,
+            &client,
+            &message_publisher,
+        )
+        .await
+        .unwrap();
+
+        let mut todos = client.list(&TenantId::default(), "owner").await.unwrap().todos;
+        sort_by_position(&mut todos);
+
+        let titles: Vec<&str> = todos.iter().map(|todo| todo.get_title()).collect();
+
+        assert!(todos.iter().all(|todo| todo.get_position().is_some()));
+        assert_eq!(titles[2..], ["Third", "Fourth"]);
+    }
+
+    #[tokio::test]
+    async fn move_next_to_missing_todo_should_return_not_found() {
+        let client: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+        let message_publisher: Arc<dyn MessagePublisher + Send + Sync> =
+            Arc::new(InMemoryMessagePublisher::new());
+
+        let created = commands::create_to_do(
+            "owner".to_string(),
+            &TenantId::default(),
+            CreateToDoCommand {
+                title: "First".to_string(),
+                description: None,
+                due_date: None,
+                priority: None,
+            },
+            None,
+            &client,
+            &message_publisher,
+        )
+        .await
+        .unwrap();
+
+        let moved = commands::move_todo(
+            "owner".to_string(),
+            &TenantId::default(),
+            created.id,
+            MoveToDoCommand {
+                before: Some("missing".to_string()),
+                after: None,
+            },
+            &client,
+            &message_publisher,
+        )
+        .await;
+
+        assert_eq!(moved.err().unwrap().kind(), &ServiceErrorKind::NotFound);
+    }
 }
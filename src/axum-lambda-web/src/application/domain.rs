@@ -1,24 +1,39 @@
-use crate::application::helpers::check_not_empty_and_length_less_than;
+use crate::application::attachments::AttachmentStore;
+use crate::application::cache::CacheStats;
+use crate::application::helpers::{check_length_less_than, check_not_empty_and_length_less_than};
 use crate::application::messaging::MessagePublisher;
+use crate::application::search::SearchPosting;
+use crate::application::timers::TimerRepo;
+use crate::application::webhooks::WebhookRepo;
 use async_trait::async_trait;
-use chrono::{DateTime, FixedOffset, Utc};
+use chrono::{DateTime, Days, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use super::error_types::{RepositoryError, ValidationError};
+use super::error_types::{RepositoryError, RepositoryErrorKind, ValidationError};
 
 pub struct AppState {
     pub todo_repo: Arc<dyn ToDoRepo + Send + Sync>,
     pub message_publisher: Arc<dyn MessagePublisher + Send + Sync>,
+    /// The maximum number of incomplete ToDos a single owner can have, `None` for no limit.
+    pub max_open_todos: Option<usize>,
+    /// Where files attached to ToDos are kept, `None` when attachments are turned off.
+    pub attachment_store: Option<Arc<AttachmentStore>>,
+    pub webhook_repo: Arc<dyn WebhookRepo + Send + Sync>,
+    pub timer_repo: Arc<dyn TimerRepo + Send + Sync>,
 }
 
-const INCOMPLETE_STATUS: &str = "INCOMPLETE";
+pub(crate) const INCOMPLETE_STATUS: &str = "INCOMPLETE";
 const COMPLETE_STATUS: &str = "COMPLETE";
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
+const MAX_REMINDER_LEAD_TIME_MINUTES: i64 = 1440;
 
 /// Represents a ToDo list item, a ToDo can be incomplete or complete.
 #[non_exhaustive]
+#[derive(Clone)]
 pub enum ToDo {
     /// Represents an incomplete ToDo item
     Incomplete(IncompleteToDo),
@@ -32,8 +47,8 @@ impl ToDo {
     pub(crate) fn new(
         title: Title,
         owner_id: OwnerId,
-        description: Option<String>,
-        due_date: Option<DateTime<FixedOffset>>,
+        description: Option<Description>,
+        due_date: Option<DueDate>,
     ) -> Result<ToDo, Vec<ValidationError>> {
         let title_res = ToDo::check_title(&title);
         let owner_res = ToDo::check_owner_id(&owner_id);
@@ -62,6 +77,11 @@ impl ToDo {
             owner: owner_id,
             description,
             due_date,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
             has_changes: false,
         }))
     }
@@ -79,8 +99,8 @@ impl ToDo {
         owner_id: OwnerId,
         status: Option<String>,
         existing_id: Option<ToDoId>,
-        description: Option<String>,
-        due_date: Option<DateTime<FixedOffset>>,
+        description: Option<Description>,
+        due_date: Option<DueDate>,
         completed_on: Option<DateTime<FixedOffset>>,
     ) -> Result<ToDo, Vec<ValidationError>> {
         let mut errors: Vec<ValidationError> = Vec::new();
@@ -117,6 +137,11 @@ impl ToDo {
                         owner: owner_id,
                         description,
                         due_date,
+                        priority: Priority::Normal,
+                        position: None,
+                        attachments: Vec::new(),
+                        tracked_seconds: 0,
+                        version: 0,
                         has_changes: false,
                     })),
                     COMPLETE_STATUS => {
@@ -134,6 +159,11 @@ impl ToDo {
                             owner: owner_id,
                             description,
                             due_date,
+                            priority: Priority::Normal,
+                            position: None,
+                            attachments: Vec::new(),
+                            tracked_seconds: 0,
+                            version: 0,
                             completed_on: parsed_completed_on,
                             has_changes: false,
                         }))
@@ -143,6 +173,11 @@ impl ToDo {
                         title,
                         description,
                         due_date,
+                        priority: Priority::Normal,
+                        position: None,
+                        attachments: Vec::new(),
+                        tracked_seconds: 0,
+                        version: 0,
                         owner: owner_id,
                         has_changes: false,
                     })),
@@ -153,6 +188,11 @@ impl ToDo {
                 title,
                 description,
                 due_date,
+                priority: Priority::Normal,
+                position: None,
+                attachments: Vec::new(),
+                tracked_seconds: 0,
+                version: 0,
                 owner: owner_id,
                 has_changes: false,
             })),
@@ -176,23 +216,40 @@ impl ToDo {
 
         match desc {
             None => "",
-            Some(val) => val,
+            Some(val) => val.to_string(),
         }
     }
 
-    /// GET the title of the ToDo
+    /// GET the due date of the ToDo, as an RFC3339 timestamp or a date for all-day due dates
     pub(crate) fn get_due_date(&self) -> String {
-        let due_date = match &self {
+        match self.get_due() {
+            None => String::from(""),
+            Some(due_date) => due_date.to_string(),
+        }
+    }
+
+    /// GET the due date of the ToDo, if it has one
+    pub(crate) fn get_due(&self) -> Option<DueDate> {
+        match &self {
             ToDo::Incomplete(incomplete) => incomplete.due_date,
             ToDo::Complete(complete) => complete.due_date,
-        };
-
-        match due_date {
-            None => String::from(""),
-            Some(date) => date.to_rfc3339().to_string(),
         }
     }
 
+    /// GET the moment the ToDo becomes overdue for an owner in the given timezone, if it has a
+    /// due date.
+    pub(crate) fn get_deadline_in(&self, timezone: Tz) -> Option<DateTime<Utc>> {
+        self.get_due().map(|due_date| due_date.deadline_in(timezone))
+    }
+
+    /// GET the due date as a single moment, if it has one. All-day due dates are treated as due
+    /// at the end of the day in UTC, as the owner's timezone is not known here, use
+    /// `get_deadline_in` to decide when the ToDo is overdue.
+    pub(crate) fn get_due_date_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.get_due()
+            .map(|due_date| due_date.deadline_in(Tz::UTC).fixed_offset())
+    }
+
     /// GET the date the ToDo was completed. Returns an empty string if incomplete.
     pub(crate) fn get_completed_on(&self) -> String {
         match &self {
@@ -209,6 +266,14 @@ impl ToDo {
         }
     }
 
+    /// GET the tenant the ToDo's owner belongs to
+    pub(crate) fn get_tenant(&self) -> &TenantId {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.owner.tenant(),
+            ToDo::Complete(complete) => complete.owner.tenant(),
+        }
+    }
+
     /// GET the ID of the ToDo
     pub(crate) fn get_id(&self) -> &str {
         match &self {
@@ -217,6 +282,11 @@ impl ToDo {
         }
     }
 
+    /// Whether the ToDo still counts against its owner's quota of open ToDos.
+    pub(crate) fn is_open(&self) -> bool {
+        matches!(self, ToDo::Incomplete(_))
+    }
+
     /// GET the status of the ToDo
     pub(crate) fn get_status(&self) -> String {
         match &self {
@@ -235,17 +305,27 @@ impl ToDo {
             ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
                 to_do_id: incomplete.to_do_id.clone(),
                 title: new_title_value,
-                owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
+                owner: incomplete.owner.clone(),
                 description: incomplete.description.clone(),
                 due_date: incomplete.due_date,
+                priority: incomplete.priority,
+                position: incomplete.position.clone(),
+                attachments: incomplete.attachments.clone(),
+                tracked_seconds: incomplete.tracked_seconds,
+                version: incomplete.version,
                 has_changes: true,
             }),
             ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
                 to_do_id: complete.to_do_id.clone(),
                 title: Title::new(complete.title.to_string()).unwrap(),
-                owner: OwnerId::new(complete.owner.to_string()).unwrap(),
+                owner: complete.owner.clone(),
                 description: complete.description.clone(),
                 due_date: complete.due_date,
+                priority: complete.priority,
+                position: complete.position.clone(),
+                attachments: complete.attachments.clone(),
+                tracked_seconds: complete.tracked_seconds,
+                version: complete.version,
                 completed_on: complete.completed_on,
                 has_changes: self.has_changes(),
             }),
@@ -254,64 +334,315 @@ impl ToDo {
         Ok(response)
     }
 
-    pub(crate) fn update_description(self, new_description: Option<String>) -> ToDo {
+    /// Update the description of the existing ToDo.
+    /// If the ToDo is already completed then the description cannot be updated.
+    pub(crate) fn update_description(
+        self,
+        new_description: Option<String>,
+    ) -> Result<ToDo, ValidationError> {
         let response = match new_description {
             None => self,
             Some(desc) => match &self {
                 ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
                     to_do_id: incomplete.to_do_id.clone(),
                     title: incomplete.title.clone(),
-                    owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
-                    description: Some(desc),
+                    owner: incomplete.owner.clone(),
+                    description: Some(Description::new(desc.as_str())?),
                     due_date: incomplete.due_date,
+                    priority: incomplete.priority,
+                    position: incomplete.position.clone(),
+                    attachments: incomplete.attachments.clone(),
+                    tracked_seconds: incomplete.tracked_seconds,
+                    version: incomplete.version,
                     has_changes: true,
                 }),
                 ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
                     to_do_id: complete.to_do_id.clone(),
                     title: Title::new(complete.title.to_string()).unwrap(),
-                    owner: OwnerId::new(complete.owner.to_string()).unwrap(),
+                    owner: complete.owner.clone(),
                     description: complete.description.clone(),
                     due_date: complete.due_date,
+                    priority: complete.priority,
+                    position: complete.position.clone(),
+                    attachments: complete.attachments.clone(),
+                    tracked_seconds: complete.tracked_seconds,
+                    version: complete.version,
                     completed_on: complete.completed_on,
                     has_changes: self.has_changes(),
                 }),
             },
         };
 
-        response
+        Ok(response)
     }
-    pub(crate) fn update_due_date(self, new_due_date: Option<String>) -> ToDo {
-        let response = match new_due_date {
+
+    /// Update the due date of the existing ToDo, an empty due date leaves it unchanged.
+    /// If the ToDo is already completed then the due date cannot be updated.
+    pub(crate) fn update_due_date(
+        self,
+        new_due_date: Option<String>,
+    ) -> Result<ToDo, ValidationError> {
+        let response = match DueDate::parse_optional(new_due_date)? {
             None => self,
-            Some(due_date) => {
-                let parsed_date = DateTime::parse_from_rfc3339(due_date.as_str());
-
-                match parsed_date {
-                    Ok(date) => match &self {
-                        ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
-                            to_do_id: incomplete.to_do_id.clone(),
-                            title: incomplete.title.clone(),
-                            owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
-                            description: incomplete.description.clone(),
-                            due_date: Some(date),
-                            has_changes: true,
-                        }),
-                        ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
-                            to_do_id: complete.to_do_id.clone(),
-                            title: Title::new(complete.title.to_string()).unwrap(),
-                            owner: OwnerId::new(complete.owner.to_string()).unwrap(),
-                            description: complete.description.clone(),
-                            due_date: complete.due_date,
-                            completed_on: complete.completed_on,
-                            has_changes: self.has_changes(),
-                        }),
-                    },
-                    Err(_) => self,
-                }
-            }
+            Some(due_date) => match &self {
+                ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                    to_do_id: incomplete.to_do_id.clone(),
+                    title: incomplete.title.clone(),
+                    owner: incomplete.owner.clone(),
+                    description: incomplete.description.clone(),
+                    due_date: Some(due_date),
+                    priority: incomplete.priority,
+                    position: incomplete.position.clone(),
+                    attachments: incomplete.attachments.clone(),
+                    tracked_seconds: incomplete.tracked_seconds,
+                    version: incomplete.version,
+                    has_changes: true,
+                }),
+                ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                    to_do_id: complete.to_do_id.clone(),
+                    title: Title::new(complete.title.to_string()).unwrap(),
+                    owner: complete.owner.clone(),
+                    description: complete.description.clone(),
+                    due_date: complete.due_date,
+                    priority: complete.priority,
+                    position: complete.position.clone(),
+                    attachments: complete.attachments.clone(),
+                    tracked_seconds: complete.tracked_seconds,
+                    version: complete.version,
+                    completed_on: complete.completed_on,
+                    has_changes: self.has_changes(),
+                }),
+            },
         };
 
-        response
+        Ok(response)
+    }
+
+    /// GET the priority of the ToDo
+    pub(crate) fn get_priority(&self) -> Priority {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.priority,
+            ToDo::Complete(complete) => complete.priority,
+        }
+    }
+
+    /// GET the position of the ToDo in its owner's list, if it has been placed
+    pub(crate) fn get_position(&self) -> Option<&Position> {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.position.as_ref(),
+            ToDo::Complete(complete) => complete.position.as_ref(),
+        }
+    }
+
+    /// Update the priority of the existing ToDo.
+    /// If the ToDo is already completed then the priority cannot be updated.
+    pub(crate) fn update_priority(self, new_priority: Option<Priority>) -> ToDo {
+        match (new_priority, &self) {
+            (Some(priority), ToDo::Incomplete(incomplete)) if priority != incomplete.priority => {
+                ToDo::Incomplete(IncompleteToDo {
+                    to_do_id: incomplete.to_do_id.clone(),
+                    title: incomplete.title.clone(),
+                    owner: incomplete.owner.clone(),
+                    description: incomplete.description.clone(),
+                    due_date: incomplete.due_date,
+                    priority,
+                    position: incomplete.position.clone(),
+                    attachments: incomplete.attachments.clone(),
+                    tracked_seconds: incomplete.tracked_seconds,
+                    version: incomplete.version,
+                    has_changes: true,
+                })
+            }
+            _ => self,
+        }
+    }
+
+    /// Move the ToDo to a new position in its owner's list, complete ToDos can be moved too.
+    pub(crate) fn move_to(self, position: Position) -> ToDo {
+        match &self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                to_do_id: incomplete.to_do_id.clone(),
+                title: incomplete.title.clone(),
+                owner: incomplete.owner.clone(),
+                description: incomplete.description.clone(),
+                due_date: incomplete.due_date,
+                priority: incomplete.priority,
+                position: Some(position),
+                attachments: incomplete.attachments.clone(),
+                tracked_seconds: incomplete.tracked_seconds,
+                version: incomplete.version,
+                has_changes: true,
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                to_do_id: complete.to_do_id.clone(),
+                title: complete.title.clone(),
+                owner: complete.owner.clone(),
+                description: complete.description.clone(),
+                due_date: complete.due_date,
+                priority: complete.priority,
+                position: Some(position),
+                attachments: complete.attachments.clone(),
+                tracked_seconds: complete.tracked_seconds,
+                version: complete.version,
+                completed_on: complete.completed_on,
+                has_changes: true,
+            }),
+        }
+    }
+
+    /// Set the priority and position of a ToDo loaded from storage, without marking it as changed.
+    pub(crate) fn with_ordering(self, priority: Priority, position: Option<Position>) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                priority,
+                position,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                priority,
+                position,
+                ..complete
+            }),
+        }
+    }
+
+    /// GET the files attached to the ToDo, oldest first
+    pub(crate) fn get_attachments(&self) -> &[Attachment] {
+        match &self {
+            ToDo::Incomplete(incomplete) => &incomplete.attachments,
+            ToDo::Complete(complete) => &complete.attachments,
+        }
+    }
+
+    /// Attach a file to the ToDo, complete ToDos can have files attached too. Pending
+    /// attachments added before `abandoned_before` were never uploaded, they are dropped so they
+    /// do not count towards the limit.
+    pub(crate) fn add_attachment(
+        self,
+        attachment: Attachment,
+        abandoned_before: DateTime<Utc>,
+    ) -> Result<ToDo, ValidationError> {
+        let mut attachments = self.get_attachments().to_vec();
+        attachments.retain(|existing| {
+            existing.is_confirmed() || existing.added_on() >= abandoned_before
+        });
+
+        if attachments.len() >= MAX_ATTACHMENTS_PER_TODO {
+            return Err(ValidationError::new(format!(
+                "A ToDo can have at most {} attachments",
+                MAX_ATTACHMENTS_PER_TODO
+            ))
+            .with_field("attachments"));
+        }
+
+        attachments.push(attachment);
+
+        Ok(self.with_changed_attachments(attachments))
+    }
+
+    /// Mark a pending attachment as uploaded. An attachment that is already confirmed keeps the
+    /// time it was first confirmed.
+    pub(crate) fn confirm_attachment(self, attachment_id: &str, confirmed_on: DateTime<Utc>) -> ToDo {
+        let attachments = self
+            .get_attachments()
+            .iter()
+            .cloned()
+            .map(|attachment| match attachment.id() == attachment_id && !attachment.is_confirmed() {
+                true => attachment.with_confirmed_on(Some(confirmed_on)),
+                false => attachment,
+            })
+            .collect();
+
+        self.with_changed_attachments(attachments)
+    }
+
+    fn with_changed_attachments(self, attachments: Vec<Attachment>) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                attachments,
+                has_changes: true,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                attachments,
+                has_changes: true,
+                ..complete
+            }),
+        }
+    }
+
+    /// Set the attachments of a ToDo loaded from storage, without marking it as changed.
+    pub(crate) fn with_attachments(self, attachments: Vec<Attachment>) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                attachments,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                attachments,
+                ..complete
+            }),
+        }
+    }
+
+    /// GET the total time tracked against the ToDo, in seconds
+    pub(crate) fn get_tracked_seconds(&self) -> u64 {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.tracked_seconds,
+            ToDo::Complete(complete) => complete.tracked_seconds,
+        }
+    }
+
+    /// Add the time from a stopped timer to the ToDo's total.
+    pub(crate) fn add_tracked_time(self, seconds: u64) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                tracked_seconds: incomplete.tracked_seconds.saturating_add(seconds),
+                has_changes: true,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                tracked_seconds: complete.tracked_seconds.saturating_add(seconds),
+                has_changes: true,
+                ..complete
+            }),
+        }
+    }
+
+    /// Set the tracked time of a ToDo loaded from storage, without marking it as changed.
+    pub(crate) fn with_tracked_seconds(self, tracked_seconds: u64) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                tracked_seconds,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                tracked_seconds,
+                ..complete
+            }),
+        }
+    }
+
+    /// GET the version of the ToDo read from storage, 0 if it hasn't been stored yet
+    pub(crate) fn get_version(&self) -> u64 {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.version,
+            ToDo::Complete(complete) => complete.version,
+        }
+    }
+
+    /// Set the version of a ToDo loaded from storage, without marking it as changed.
+    pub(crate) fn with_version(self, version: u64) -> ToDo {
+        match self {
+            ToDo::Incomplete(incomplete) => ToDo::Incomplete(IncompleteToDo {
+                version,
+                ..incomplete
+            }),
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                version,
+                ..complete
+            }),
+        }
     }
 
     /// Set the ToDo as completed
@@ -320,28 +651,50 @@ impl ToDo {
             ToDo::Incomplete(incomplete) => ToDo::Complete(CompleteToDo {
                 to_do_id: incomplete.to_do_id.clone(),
                 title: Title::new(incomplete.title.to_string()).unwrap(),
-                owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
+                owner: incomplete.owner.clone(),
                 completed_on: DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
                 description: incomplete.description.clone(),
                 due_date: incomplete.due_date,
+                priority: incomplete.priority,
+                position: incomplete.position.clone(),
+                attachments: incomplete.attachments.clone(),
+                tracked_seconds: incomplete.tracked_seconds,
+                version: incomplete.version,
                 has_changes: true,
             }),
             ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
                 to_do_id: complete.to_do_id.clone(),
                 title: Title::new(complete.title.to_string()).unwrap(),
-                owner: OwnerId::new(complete.owner.to_string()).unwrap(),
+                owner: complete.owner.clone(),
                 description: complete.description.clone(),
                 due_date: complete.due_date,
+                priority: complete.priority,
+                position: complete.position.clone(),
+                attachments: complete.attachments.clone(),
+                tracked_seconds: complete.tracked_seconds,
+                version: complete.version,
                 completed_on: complete.completed_on,
                 has_changes: false,
             }),
         }
     }
 
+    /// Set the ToDo as completed on the given date, used when importing a ToDo that was
+    /// completed elsewhere.
+    pub(crate) fn set_completed_on(self, completed_on: DateTime<FixedOffset>) -> ToDo {
+        match self.set_completed() {
+            ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
+                completed_on,
+                ..complete
+            }),
+            incomplete => incomplete,
+        }
+    }
+
     fn check_title(input: &Title) -> Result<(), ValidationError> {
         tracing::info!("Checking title: '{}'", input.to_string());
 
-        let _ = check_not_empty_and_length_less_than(input.to_string(), 50)?;
+        check_not_empty_and_length_less_than(input.to_string(), 50)?;
 
         Ok(())
     }
@@ -350,7 +703,8 @@ impl ToDo {
         if input.to_string().is_empty() {
             return Err(ValidationError::new(
                 "Owner Id must have a length".to_string(),
-            ));
+            )
+            .with_field("owner"));
         }
 
         Ok(())
@@ -359,22 +713,40 @@ impl ToDo {
 
 /// Represents the structure of an incomplete ToDo
 #[non_exhaustive]
+#[derive(Clone)]
 pub struct IncompleteToDo {
     to_do_id: ToDoId,
     title: Title,
-    description: Option<String>,
-    due_date: Option<DateTime<FixedOffset>>,
+    description: Option<Description>,
+    due_date: Option<DueDate>,
+    priority: Priority,
+    position: Option<Position>,
+    attachments: Vec<Attachment>,
+    /// The time spent on the ToDo, the total of its stopped timers.
+    tracked_seconds: u64,
+    /// How many times the ToDo has been written, used to detect that it was changed by another
+    /// request since it was read.
+    version: u64,
     owner: OwnerId,
     has_changes: bool,
 }
 
 /// Represents the structure of a complete ToDo item
 #[non_exhaustive]
+#[derive(Clone)]
 pub struct CompleteToDo {
     to_do_id: ToDoId,
     title: Title,
-    description: Option<String>,
-    due_date: Option<DateTime<FixedOffset>>,
+    description: Option<Description>,
+    due_date: Option<DueDate>,
+    priority: Priority,
+    position: Option<Position>,
+    attachments: Vec<Attachment>,
+    /// The time spent on the ToDo, the total of its stopped timers.
+    tracked_seconds: u64,
+    /// How many times the ToDo has been written, used to detect that it was changed by another
+    /// request since it was read.
+    version: u64,
     owner: OwnerId,
     completed_on: DateTime<FixedOffset>,
     has_changes: bool,
@@ -391,7 +763,7 @@ impl ToDoId {
     }
 
     pub fn parse(existing_id: &str) -> Result<ToDoId, ValidationError> {
-        let _ = check_not_empty_and_length_less_than(existing_id, 50)?;
+        check_not_empty_and_length_less_than(existing_id, 50)?;
 
         Ok(ToDoId {
             value: existing_id.to_string(),
@@ -410,7 +782,7 @@ pub(crate) struct Title {
 
 impl Title {
     pub fn new(title: &str) -> Result<Title, ValidationError> {
-        let _ = check_not_empty_and_length_less_than(title, 50)?;
+        check_not_empty_and_length_less_than(title, 50).map_err(|e| e.with_field("title"))?;
 
         Ok(Title {
             value: title.to_string(),
@@ -422,25 +794,62 @@ impl Title {
     }
 }
 
+/// A free text description of a ToDo, written in CommonMark.
+#[derive(Clone)]
+pub(crate) struct Description {
+    value: String,
+}
+
+impl Description {
+    pub fn new(description: &str) -> Result<Description, ValidationError> {
+        check_length_less_than(description, MAX_DESCRIPTION_LENGTH).map_err(|e| e.with_field("description"))?;
+
+        Ok(Description {
+            value: description.to_string(),
+        })
+    }
+
+    pub fn to_string(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+/// The owner of a ToDo. Owner ids are only unique within a tenant, so the tenant is part of
+/// the owner's identity.
 #[derive(Clone)]
 pub(crate) struct OwnerId {
     value: String,
+    tenant: TenantId,
 }
 
 impl OwnerId {
+    /// An owner in the default tenant, use `in_tenant` to move them to another tenant.
     pub fn new(owner_id: &str) -> Result<OwnerId, ValidationError> {
-        let _ = check_not_empty_and_length_less_than(owner_id, 50)?;
+        check_not_empty_and_length_less_than(owner_id, 50).map_err(|e| e.with_field("owner"))?;
 
         Ok(OwnerId {
             value: owner_id.to_string(),
+            tenant: TenantId::default(),
         })
     }
 
+    pub fn in_tenant(self, tenant: &TenantId) -> OwnerId {
+        OwnerId {
+            tenant: tenant.clone(),
+            ..self
+        }
+    }
+
+    pub fn tenant(&self) -> &TenantId {
+        &self.tenant
+    }
+
     pub fn to_string(&self) -> &str {
         self.value.as_str()
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) enum IsComplete {
     Incomplete,
@@ -453,32 +862,698 @@ impl fmt::Display for IsComplete {
     }
 }
 
+pub(crate) const DEFAULT_TENANT: &str = "default";
+
+/// The organisation a request is made on behalf of. Every ToDo, preference and event belongs
+/// to exactly one tenant, and an owner can only see the data in their own tenant.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TenantId {
+    value: String,
+}
+
+impl TenantId {
+    /// Tenant ids become part of the table keys, so they are limited to lowercase letters,
+    /// digits and dashes. `default` is reserved, the default tenant's keys have no prefix so a
+    /// request naming it would read the data of every request that names no tenant.
+    pub fn new(tenant_id: &str) -> Result<TenantId, ValidationError> {
+        let tenant_id = tenant_id.trim().to_lowercase();
+
+        check_not_empty_and_length_less_than(&tenant_id, 50).map_err(|e| e.with_field("tenant"))?;
+
+        if tenant_id == DEFAULT_TENANT {
+            return Err(ValidationError::new(format!(
+                "'{}' is reserved and cannot be used as a tenant",
+                tenant_id
+            ))
+            .with_field("tenant"));
+        }
+
+        if !tenant_id
+            .chars()
+            .all(|val| val.is_ascii_alphanumeric() || val == '-')
+        {
+            return Err(ValidationError::new(format!(
+                "'{}' is not a valid tenant, use letters, digits and dashes",
+                tenant_id
+            ))
+            .with_field("tenant"));
+        }
+
+        Ok(TenantId { value: tenant_id })
+    }
+
+    /// Read a tenant id stored by the service, which may be the default tenant.
+    pub fn parse(tenant_id: &str) -> Result<TenantId, ValidationError> {
+        match tenant_id == DEFAULT_TENANT {
+            true => Ok(TenantId::default()),
+            false => TenantId::new(tenant_id),
+        }
+    }
+
+    /// The tenant used when a request does not name one, which is the only tenant in a
+    /// single-tenant deployment.
+    pub fn is_default(&self) -> bool {
+        self.value == DEFAULT_TENANT
+    }
+
+    pub fn to_string(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+impl Default for TenantId {
+    fn default() -> Self {
+        TenantId {
+            value: DEFAULT_TENANT.to_string(),
+        }
+    }
+}
+
+/// Put ToDos in the order their owner arranged them. ToDos that have never been placed come
+/// last, ordered by id so the order is at least stable.
+pub(crate) fn sort_by_position(todos: &mut [ToDo]) {
+    todos.sort_by(|a, b| match (a.get_position(), b.get_position()) {
+        (Some(a_position), Some(b_position)) => a_position.cmp(b_position),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.get_id().cmp(b.get_id()),
+    });
+}
+
+/// How important a ToDo is, `Normal` unless the owner chooses otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn new(priority: &str) -> Result<Priority, ValidationError> {
+        match priority.to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            _ => Err(ValidationError::new(format!(
+                "'{}' is not a valid priority, use low, normal, high or urgent",
+                priority
+            ))
+            .with_field("priority")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+const MAX_FILE_NAME_LENGTH: i64 = 255;
+
+// Attachments are recorded on the ToDo item, which DynamoDB limits to 400KB. Each one takes at
+// most a few hundred bytes, so this leaves plenty of room for the rest of the ToDo.
+pub(crate) const MAX_ATTACHMENTS_PER_TODO: usize = 50;
+
+/// A file attached to a ToDo. The file itself is kept in object storage, the ToDo records what
+/// was attached so its attachments can be listed and downloaded. An attachment is pending from
+/// when its upload URL is issued until the upload is confirmed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Attachment {
+    attachment_id: String,
+    file_name: String,
+    content_type: String,
+    size: u64,
+    added_on: DateTime<Utc>,
+    confirmed_on: Option<DateTime<Utc>>,
+}
+
+impl Attachment {
+    pub fn new(file_name: &str, content_type: &str, size: u64) -> Result<Attachment, ValidationError> {
+        Attachment::parse(
+            &Uuid::new_v4().to_string(),
+            file_name,
+            content_type,
+            size,
+            Utc::now(),
+        )
+    }
+
+    /// Parse an attachment from a set of existing values
+    pub fn parse(
+        attachment_id: &str,
+        file_name: &str,
+        content_type: &str,
+        size: u64,
+        added_on: DateTime<Utc>,
+    ) -> Result<Attachment, ValidationError> {
+        check_not_empty_and_length_less_than(file_name, MAX_FILE_NAME_LENGTH)
+            .map_err(|e| e.with_field("file_name"))?;
+
+        // The file name is used to name the download, so it must not be able to name a path.
+        if file_name.contains(['/', '\\']) || file_name.chars().any(char::is_control) {
+            return Err(ValidationError::new(format!(
+                "'{}' is not a valid file name",
+                file_name
+            ))
+            .with_field("file_name"));
+        }
+
+        if content_type.parse::<mime::Mime>().is_err() {
+            return Err(ValidationError::new(format!(
+                "'{}' is not a valid content type",
+                content_type
+            ))
+            .with_field("content_type"));
+        }
+
+        if size == 0 {
+            return Err(ValidationError::new("An attachment cannot be empty".to_string())
+                .with_field("size"));
+        }
+
+        Ok(Attachment {
+            attachment_id: attachment_id.to_string(),
+            file_name: file_name.to_string(),
+            content_type: content_type.to_lowercase(),
+            size,
+            added_on,
+            confirmed_on: None,
+        })
+    }
+
+    /// Set when the upload was confirmed, `None` while it is pending.
+    pub fn with_confirmed_on(self, confirmed_on: Option<DateTime<Utc>>) -> Attachment {
+        Attachment {
+            confirmed_on,
+            ..self
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.attachment_id.as_str()
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.file_name.as_str()
+    }
+
+    pub fn content_type(&self) -> &str {
+        self.content_type.as_str()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn added_on(&self) -> DateTime<Utc> {
+        self.added_on
+    }
+
+    pub fn confirmed_on(&self) -> Option<DateTime<Utc>> {
+        self.confirmed_on
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_on.is_some()
+    }
+}
+
+// Position keys are written in these digits, which are in ASCII order so keys can be compared
+// as plain strings.
+const POSITION_DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Where a ToDo sits in its owner's list, as a fractional index. A key is a base 62 fraction
+/// written without the leading "0.", so there is always another key between any two keys and a
+/// ToDo can be moved by rewriting only its own position.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub(crate) struct Position {
+    value: String,
+}
+
+impl Position {
+    pub fn parse(position: &str) -> Result<Position, ValidationError> {
+        // A trailing zero adds nothing to the fraction, so there would be no key between it and
+        // the same key without the zero.
+        if position.is_empty()
+            || position.ends_with('0')
+            || !position.bytes().all(|digit| POSITION_DIGITS.contains(&digit))
+        {
+            return Err(ValidationError::new(format!("'{}' is not a valid position", position))
+                .with_field("position"));
+        }
+
+        Ok(Position {
+            value: position.to_string(),
+        })
+    }
+
+    /// A position between `lower` and `upper`, where `None` is the start or end of the list.
+    /// Returns `None` if `lower` is not before `upper`.
+    pub fn between(lower: Option<&Position>, upper: Option<&Position>) -> Option<Position> {
+        let lower = lower.map(|position| position.value.as_str()).unwrap_or_default();
+        let upper = upper.map(|position| position.value.as_str());
+
+        if upper.is_some_and(|upper| lower >= upper) {
+            return None;
+        }
+
+        Some(Position {
+            value: midpoint(lower.as_bytes(), upper.map(str::as_bytes)),
+        })
+    }
+
+    /// Positions for `count` ToDos in order after `last`, or from the start of the list when it
+    /// is `None`.
+    pub fn sequence(last: Option<&Position>, count: usize) -> Vec<Position> {
+        let mut positions: Vec<Position> = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let next = Position::between(positions.last().or(last), None).unwrap();
+            positions.push(next);
+        }
+
+        positions
+    }
+
+    pub fn to_string(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+// Find a key between `lower` and `upper`, or between `lower` and the end of the list when
+// `upper` is `None`. An empty `lower` is the start of the list.
+fn midpoint(lower: &[u8], upper: Option<&[u8]>) -> String {
+    let digit = |key: &[u8], index: usize| -> usize {
+        key.get(index)
+            .and_then(|val| POSITION_DIGITS.iter().position(|digit| digit == val))
+            .unwrap_or(0)
+    };
+
+    if let Some(upper) = upper {
+        // Keep the prefix the two keys share, a missing digit in `lower` counts as a zero.
+        let shared = (0..upper.len())
+            .take_while(|index| lower.get(*index).copied().unwrap_or(POSITION_DIGITS[0]) == upper[*index])
+            .count();
+
+        if shared > 0 {
+            let prefix = String::from_utf8_lossy(&upper[..shared]).to_string();
+            let lower_rest = lower.get(shared..).unwrap_or_default();
+
+            return prefix + &midpoint(lower_rest, Some(&upper[shared..]));
+        }
+    }
+
+    let lower_digit = digit(lower, 0);
+    let upper_digit = match upper {
+        Some(upper) => digit(upper, 0),
+        None => POSITION_DIGITS.len(),
+    };
+
+    if upper_digit - lower_digit > 1 {
+        let middle = (lower_digit + upper_digit).div_ceil(2);
+
+        return (POSITION_DIGITS[middle] as char).to_string();
+    }
+
+    match upper {
+        // The first digit of `upper` on its own sorts after `lower` and before `upper`.
+        Some(upper) if upper.len() > 1 => (upper[0] as char).to_string(),
+        _ => {
+            let lower_rest = lower.get(1..).unwrap_or_default();
+
+            (POSITION_DIGITS[lower_digit] as char).to_string() + &midpoint(lower_rest, None)
+        }
+    }
+}
+
+/// When a ToDo is due. All-day due dates have no time of day, so they fall on the same date
+/// whatever timezone the owner is in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DueDate {
+    AllDay(NaiveDate),
+    At(DateTime<FixedOffset>),
+}
+
+impl DueDate {
+    /// Parse an RFC3339 timestamp, or a `YYYY-MM-DD` date for an all-day due date.
+    pub fn new(due_date: &str) -> Result<DueDate, ValidationError> {
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(due_date) {
+            return Ok(DueDate::At(date_time));
+        }
+
+        NaiveDate::parse_from_str(due_date, "%Y-%m-%d")
+            .map(DueDate::AllDay)
+            .map_err(|_| {
+                ValidationError::new(format!(
+                    "'{}' is not a valid due date, use an RFC3339 timestamp or a YYYY-MM-DD date",
+                    due_date
+                ))
+                .with_field("due_date")
+            })
+    }
+
+    /// Parse an optional due date from a request, where an empty string means no due date.
+    pub fn parse_optional(due_date: Option<String>) -> Result<Option<DueDate>, ValidationError> {
+        match due_date {
+            Some(due_date) if !due_date.trim().is_empty() => Ok(Some(DueDate::new(due_date.trim())?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The date the ToDo is due on in the given timezone.
+    pub fn date_in(&self, timezone: Tz) -> NaiveDate {
+        match self {
+            DueDate::AllDay(date) => *date,
+            DueDate::At(date_time) => date_time.with_timezone(&timezone).date_naive(),
+        }
+    }
+
+    /// The moment the ToDo becomes overdue in the given timezone, the start of the following
+    /// day for all-day due dates.
+    pub fn deadline_in(&self, timezone: Tz) -> DateTime<Utc> {
+        match self {
+            DueDate::AllDay(date) => {
+                let next_day = date
+                    .checked_add_days(Days::new(1))
+                    .unwrap_or(*date)
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap();
+
+                // Midnight can be skipped by a daylight saving change, fall back to UTC if so.
+                timezone
+                    .from_local_datetime(&next_day)
+                    .earliest()
+                    .map(|date_time| date_time.with_timezone(&Utc))
+                    .unwrap_or_else(|| next_day.and_utc())
+            }
+            DueDate::At(date_time) => date_time.with_timezone(&Utc),
+        }
+    }
+
+    /// Which view the due date falls into at `now`, for an owner in the given timezone.
+    pub fn view_at(&self, now: DateTime<Utc>, timezone: Tz) -> DueDateView {
+        if self.deadline_in(timezone) <= now {
+            DueDateView::Overdue
+        } else if self.date_in(timezone) == now.with_timezone(&timezone).date_naive() {
+            DueDateView::Today
+        } else {
+            DueDateView::Upcoming
+        }
+    }
+}
+
+impl fmt::Display for DueDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DueDate::AllDay(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            DueDate::At(date_time) => write!(f, "{}", date_time.to_rfc3339()),
+        }
+    }
+}
+
+/// Groups incomplete ToDos by when they are due, relative to the current day in the owner's
+/// timezone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DueDateView {
+    /// Due later today.
+    Today,
+    /// Past the due date and still incomplete.
+    Overdue,
+    /// Due after today.
+    Upcoming,
+}
+
+impl DueDateView {
+    pub fn new(view: &str) -> Result<DueDateView, ValidationError> {
+        match view.to_lowercase().as_str() {
+            "today" => Ok(DueDateView::Today),
+            "overdue" => Ok(DueDateView::Overdue),
+            "upcoming" => Ok(DueDateView::Upcoming),
+            _ => Err(ValidationError::new(format!(
+                "'{}' is not a valid view, use today, overdue or upcoming",
+                view
+            ))
+            .with_field("view")),
+        }
+    }
+}
+
+/// An IANA timezone name, such as `Europe/London`, that an owner's due dates are viewed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Timezone {
+    value: Tz,
+}
+
+impl Timezone {
+    pub fn new(timezone: &str) -> Result<Timezone, ValidationError> {
+        timezone
+            .parse::<Tz>()
+            .map(|value| Timezone { value })
+            .map_err(|_| {
+                ValidationError::new(format!("'{}' is not a valid IANA timezone", timezone))
+                    .with_field("timezone")
+            })
+    }
+
+    pub fn name(&self) -> &str {
+        self.value.name()
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.value
+    }
+}
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Timezone { value: Tz::UTC }
+    }
+}
+
+/// How long before a ToDo is due its owner wants a `ToDoDueSoon` reminder, up to a day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ReminderLeadTime {
+    minutes: i64,
+}
+
+impl ReminderLeadTime {
+    pub fn new(minutes: i64) -> Result<ReminderLeadTime, ValidationError> {
+        if !(1..=MAX_REMINDER_LEAD_TIME_MINUTES).contains(&minutes) {
+            return Err(ValidationError::new(format!(
+                "Reminder lead time must be between 1 and {} minutes",
+                MAX_REMINDER_LEAD_TIME_MINUTES
+            ))
+            .with_field("reminder_lead_time_minutes"));
+        }
+
+        Ok(ReminderLeadTime { minutes })
+    }
+
+    /// The longest lead time an owner can choose.
+    pub fn longest() -> Duration {
+        Duration::minutes(MAX_REMINDER_LEAD_TIME_MINUTES)
+    }
+
+    pub fn minutes(&self) -> i64 {
+        self.minutes
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::minutes(self.minutes)
+    }
+}
+
+/// Settings that apply to all of an owner's ToDos.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Preferences {
+    pub timezone: Timezone,
+    /// Replaces the scheduler's lead time for the owner's reminders when set.
+    pub reminder_lead_time: Option<ReminderLeadTime>,
+}
+
+/// The ToDos read for an owner. Items that could not be read are skipped and reported in
+/// `skipped`, so one malformed item does not hide the rest of the owner's list.
+#[derive(Clone, Default)]
+pub struct ToDoList {
+    pub todos: Vec<ToDo>,
+    pub skipped: Vec<RepositoryError>,
+}
+
+impl From<Vec<ToDo>> for ToDoList {
+    fn from(todos: Vec<ToDo>) -> Self {
+        ToDoList {
+            todos,
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// Up to a page of an owner's ToDos. `next_page` is where the following page starts, `None`
+/// once every ToDo has been read.
+#[derive(Clone, Default)]
+pub struct ToDoPage {
+    pub list: ToDoList,
+    pub next_page: Option<String>,
+}
+
+/// Stores ToDos. Every read is scoped to a tenant and an owner within it, ToDos are written to
+/// the tenant of their owner.
 #[async_trait]
 pub trait ToDoRepo {
-    async fn list(&self, user_id: &str) -> Result<Vec<ToDo>, RepositoryError>;
+    async fn list(&self, tenant: &TenantId, user_id: &str) -> Result<ToDoList, RepositoryError>;
+
+    /// Read the owner's ToDos a page at a time, in the order they are stored, starting from
+    /// the `next_page` of the previous page. Pages can be shorter than `page_size`, as items
+    /// that could not be read are reported in `skipped` instead. Stores that can't page return
+    /// every ToDo as a single page.
+    async fn list_page(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        _start: Option<&str>,
+        _page_size: usize,
+    ) -> Result<ToDoPage, RepositoryError> {
+        Ok(ToDoPage {
+            list: self.list(tenant, user_id).await?,
+            next_page: None,
+        })
+    }
 
+    /// Store a ToDo. A ToDo read from the store is only written if it is still the version that
+    /// was read, otherwise it fails with a `Conflict` error.
     async fn create(&self, to_do: &ToDo) -> Result<(), RepositoryError>;
 
-    async fn get(&self, user_id: &str, todo_id: &str) -> Result<ToDo, RepositoryError>;
+    /// Store a ToDo as `create` does, unless it would take its owner over `max_open_todos` open
+    /// ToDos. Returns whether it was stored, the check and the write are a single operation so
+    /// concurrent creates can't exceed the quota.
+    async fn create_within_quota(
+        &self,
+        to_do: &ToDo,
+        max_open_todos: usize,
+    ) -> Result<bool, RepositoryError>;
+
+    async fn get(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError>;
+
+    /// Read a ToDo that is about to be changed and stored again. Unlike `get` this always reads
+    /// the latest version, never a cached or eventually consistent copy.
+    async fn get_for_update(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+    ) -> Result<ToDo, RepositoryError> {
+        self.get(tenant, user_id, todo_id).await
+    }
+
+    /// Read several of the owner's ToDos at once, returned in no particular order. Ids that
+    /// aren't stored are left out, and items that could not be read are reported in `skipped`.
+    /// Stores that can't batch reads get each ToDo in turn.
+    async fn get_many(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> Result<ToDoList, RepositoryError> {
+        let mut list = ToDoList::default();
+
+        for todo_id in todo_ids {
+            match self.get(tenant, user_id, todo_id).await {
+                Ok(todo) => list.todos.push(todo),
+                Err(e) if e.kind() == &RepositoryErrorKind::InvalidItem => list.skipped.push(e),
+                Err(e) if e.kind() == &RepositoryErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Return the search index postings for the owner that match any of the given terms.
+    async fn search(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        terms: &[String],
+    ) -> Result<Vec<SearchPosting>, RepositoryError>;
+
+    /// Reserve `count` positions at the end of the owner's list, after every position reserved
+    /// for them before, so concurrent creates are never given the same position. Owners who
+    /// have never reserved one start after the last of their ToDos.
+    async fn reserve_positions(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        count: usize,
+    ) -> Result<Vec<Position>, RepositoryError>;
+
+    /// Move one of the owner's ToDos, rewriting only its position.
+    async fn set_position(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        todo_id: &str,
+        position: &Position,
+    ) -> Result<(), RepositoryError>;
+
+    /// Check the underlying store can be reached, used by the readiness endpoint.
+    async fn health_check(&self) -> Result<(), RepositoryError>;
+
+    /// Hit and miss counts when the repository caches reads, reported by the readiness endpoint.
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+
+    /// Drop anything cached for the owner after their ToDos were changed outside this repository.
+    fn invalidate_owner(&self, _tenant: &TenantId, _user_id: &str) {}
+
+    /// The owner's preferences, the defaults for any they have not chosen.
+    async fn get_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+    ) -> Result<Preferences, RepositoryError>;
+
+    async fn set_preferences(
+        &self,
+        tenant: &TenantId,
+        user_id: &str,
+        preferences: &Preferences,
+    ) -> Result<(), RepositoryError>;
 }
 
 /// Unit tests
 ///
 /// These tests are run using the `cargo test` command.
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
 mod tests {
     use chrono::{DateTime, Utc};
 
-    use crate::application::domain::{OwnerId, Title, ToDo};
+    use crate::application::domain::{Description, OwnerId, Title, ToDo};
 
-    use super::ToDoId;
+    use super::{
+        Attachment, DueDate, DueDateView, Position, Priority, TenantId, Timezone, ToDoId,
+        MAX_ATTACHMENTS_PER_TODO,
+    };
 
     #[test]
     fn valid_data_should_return_validated_to_do() {
         let to_do = ToDo::new(
             Title::new("my title").unwrap(),
             OwnerId::new("jameseastham").unwrap(),
-            Some(String::from("This is the description")),
+            Some(Description::new("This is the description").unwrap()),
             None,
         );
 
@@ -493,8 +1568,13 @@ mod tests {
             to_do_id: ToDoId::parse("hello").unwrap(),
             title: Title::new("hello").unwrap(),
             owner: OwnerId::new("hello").unwrap(),
-            description: Some(String::from("This is the description")),
+            description: Some(Description::new("This is the description").unwrap()),
             due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
             has_changes: false,
         });
 
@@ -514,8 +1594,13 @@ mod tests {
             to_do_id: ToDoId::parse("hello").unwrap(),
             title: Title::new("hello").unwrap(),
             owner: OwnerId::new("hello").unwrap(),
-            description: Some(String::from("This is the description")),
+            description: Some(Description::new("This is the description").unwrap()),
             due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
             completed_on: DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
             has_changes: false,
         });
@@ -536,8 +1621,13 @@ mod tests {
             to_do_id: ToDoId::parse("hello").unwrap(),
             title: Title::new("hello").unwrap(),
             owner: OwnerId::new("hello").unwrap(),
-            description: Some(String::from("This is the description")),
+            description: Some(Description::new("This is the description").unwrap()),
             due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
             has_changes: false,
         });
 
@@ -558,8 +1648,13 @@ mod tests {
             to_do_id: ToDoId::parse("hello").unwrap(),
             title: Title::new("hello").unwrap(),
             owner: OwnerId::new("hello").unwrap(),
-            description: Some(String::from("This is the description")),
+            description: Some(Description::new("This is the description").unwrap()),
             due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
             completed_on: date,
             has_changes: false,
         });
@@ -610,10 +1705,271 @@ mod tests {
         assert!(to_do.is_err());
     }
 
+    #[test]
+    fn long_description_should_return_validate_error() {
+        let description = Description::new("a".repeat(2001).as_str());
+
+        assert!(description.is_err());
+    }
+
+    #[test]
+    fn empty_description_should_be_valid() {
+        let description = Description::new("");
+
+        assert!(description.is_ok());
+    }
+
+    #[test]
+    fn update_description_for_incomplete_todo_should_change() {
+        let todo = ToDo::Incomplete(super::IncompleteToDo {
+            to_do_id: ToDoId::parse("hello").unwrap(),
+            title: Title::new("hello").unwrap(),
+            owner: OwnerId::new("hello").unwrap(),
+            description: None,
+            due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
+            has_changes: false,
+        });
+
+        let updated_todo = todo
+            .update_description(Some(String::from("**Bold** description")))
+            .unwrap();
+
+        assert_eq!(updated_todo.get_description(), "**Bold** description");
+        assert!(updated_todo.has_changes());
+    }
+
+    #[test]
+    fn update_description_too_long_should_return_validate_error() {
+        let todo = ToDo::Incomplete(super::IncompleteToDo {
+            to_do_id: ToDoId::parse("hello").unwrap(),
+            title: Title::new("hello").unwrap(),
+            owner: OwnerId::new("hello").unwrap(),
+            description: None,
+            due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
+            has_changes: false,
+        });
+
+        let updated_todo = todo.update_description(Some("a".repeat(2001)));
+
+        assert!(updated_todo.is_err());
+    }
+
     #[test]
     fn empty_owner_should_return_validate_error() {
         let owner = OwnerId::new("");
 
         assert!(owner.is_err());
     }
+
+    #[test]
+    fn due_date_should_parse_dates_and_timestamps() {
+        let all_day = DueDate::new("2023-08-12").unwrap();
+        let timestamp = DueDate::new("2023-08-12T09:30:00+02:00").unwrap();
+
+        assert_eq!(all_day.to_string(), "2023-08-12");
+        assert_eq!(timestamp.to_string(), "2023-08-12T09:30:00+02:00");
+    }
+
+    #[test]
+    fn invalid_due_date_should_return_validate_error() {
+        let due_date = DueDate::new("next tuesday");
+
+        assert_eq!(due_date.unwrap_err().field(), Some("due_date"));
+    }
+
+    #[test]
+    fn update_due_date_with_invalid_date_should_return_validate_error() {
+        let todo = ToDo::Incomplete(super::IncompleteToDo {
+            to_do_id: ToDoId::parse("hello").unwrap(),
+            title: Title::new("hello").unwrap(),
+            owner: OwnerId::new("hello").unwrap(),
+            description: None,
+            due_date: None,
+            priority: Priority::Normal,
+            position: None,
+            attachments: Vec::new(),
+            tracked_seconds: 0,
+            version: 0,
+            has_changes: false,
+        });
+
+        let updated_todo = todo.update_due_date(Some(String::from("2023-13-45")));
+
+        assert!(updated_todo.is_err());
+    }
+
+    #[test]
+    fn due_date_view_should_depend_on_timezone() {
+        let now = DateTime::parse_from_rfc3339("2023-08-12T23:30:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let utc = Timezone::default().tz();
+        let berlin = Timezone::new("Europe/Berlin").unwrap().tz();
+
+        let all_day = DueDate::new("2023-08-12").unwrap();
+        let tomorrow_morning = DueDate::new("2023-08-13T10:00:00+00:00").unwrap();
+
+        assert_eq!(all_day.view_at(now, utc), DueDateView::Today);
+        assert_eq!(all_day.view_at(now, berlin), DueDateView::Overdue);
+        assert_eq!(tomorrow_morning.view_at(now, utc), DueDateView::Upcoming);
+        assert_eq!(tomorrow_morning.view_at(now, berlin), DueDateView::Today);
+    }
+
+    #[test]
+    fn unknown_timezone_should_return_validate_error() {
+        let timezone = Timezone::new("Mars/Olympus_Mons");
+
+        assert_eq!(timezone.unwrap_err().field(), Some("timezone"));
+    }
+
+    #[test]
+    fn position_between_should_sort_between_neighbours() {
+        let cases = [
+            (None, None),
+            (None, Some("V")),
+            (Some("V"), None),
+            (Some("A"), Some("B")),
+            (Some("A"), Some("A1")),
+            (Some("Az"), Some("B")),
+            (Some("zz"), None),
+        ];
+
+        for (lower, upper) in cases {
+            let lower = lower.map(|val| Position::parse(val).unwrap());
+            let upper = upper.map(|val| Position::parse(val).unwrap());
+
+            let position = Position::between(lower.as_ref(), upper.as_ref()).unwrap();
+
+            assert!(Position::parse(position.to_string()).is_ok());
+            assert!(lower.as_ref().is_none_or(|lower| lower < &position));
+            assert!(upper.as_ref().is_none_or(|upper| &position < upper));
+        }
+    }
+
+    #[test]
+    fn position_between_out_of_order_neighbours_should_fail() {
+        let lower = Position::parse("B").unwrap();
+        let upper = Position::parse("A").unwrap();
+
+        assert!(Position::between(Some(&lower), Some(&upper)).is_none());
+        assert!(Position::between(Some(&lower), Some(&lower)).is_none());
+    }
+
+    #[test]
+    fn repeated_moves_to_the_front_should_keep_order() {
+        let mut first = Position::between(None, None).unwrap();
+
+        for _ in 0..100 {
+            let next = Position::between(None, Some(&first)).unwrap();
+
+            assert!(next < first);
+            first = next;
+        }
+    }
+
+    #[test]
+    fn position_sequence_should_be_ascending() {
+        let last = Position::between(None, None).unwrap();
+        let positions = Position::sequence(Some(&last), 5);
+
+        assert_eq!(positions.len(), 5);
+        assert!(positions[0] > last);
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn default_tenant_should_only_be_parsed_from_storage() {
+        assert!(TenantId::new("Default").is_err());
+        assert!(TenantId::parse("default").unwrap().is_default());
+        assert_eq!(TenantId::parse("acme").unwrap(), TenantId::new("acme").unwrap());
+    }
+
+    #[test]
+    fn invalid_priority_should_fail() {
+        assert_eq!(Priority::new("HIGH").unwrap(), Priority::High);
+        assert!(Priority::new("whenever").is_err());
+    }
+
+    #[test]
+    fn attachments_over_the_limit_should_be_rejected() {
+        let mut todo = ToDo::new(
+            Title::new("Fix the bug").unwrap(),
+            OwnerId::new("jameseastham").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        for _ in 0..MAX_ATTACHMENTS_PER_TODO {
+            let attachment = Attachment::new("scan.pdf", "application/pdf", 10).unwrap();
+            todo = todo
+                .add_attachment(attachment.clone(), DateTime::<Utc>::MIN_UTC)
+                .unwrap()
+                .confirm_attachment(attachment.id(), Utc::now());
+        }
+
+        let one_more = Attachment::new("scan.pdf", "application/pdf", 10).unwrap();
+        let err = todo.add_attachment(one_more, Utc::now()).err().unwrap();
+
+        assert_eq!(err.field(), Some("attachments"));
+    }
+
+    #[test]
+    fn abandoned_pending_attachments_should_not_count_towards_the_limit() {
+        let mut todo = ToDo::new(
+            Title::new("Fix the bug").unwrap(),
+            OwnerId::new("jameseastham").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        for _ in 0..MAX_ATTACHMENTS_PER_TODO {
+            let attachment = Attachment::new("scan.pdf", "application/pdf", 10).unwrap();
+            todo = todo
+                .add_attachment(attachment, DateTime::<Utc>::MIN_UTC)
+                .unwrap();
+        }
+
+        let one_more = Attachment::new("scan.pdf", "application/pdf", 10).unwrap();
+        let todo = todo
+            .add_attachment(one_more.clone(), one_more.added_on())
+            .unwrap();
+
+        assert_eq!(todo.get_attachments(), &[one_more]);
+    }
+
+    #[test]
+    fn confirming_an_attachment_should_only_confirm_it_once() {
+        let attachment = Attachment::new("scan.pdf", "application/pdf", 10).unwrap();
+        let first_confirmed = Utc::now();
+
+        let todo = ToDo::new(
+            Title::new("Fix the bug").unwrap(),
+            OwnerId::new("jameseastham").unwrap(),
+            None,
+            None,
+        )
+        .unwrap()
+        .add_attachment(attachment.clone(), DateTime::<Utc>::MIN_UTC)
+        .unwrap();
+
+        assert!(!todo.get_attachments()[0].is_confirmed());
+
+        let todo = todo
+            .confirm_attachment(attachment.id(), first_confirmed)
+            .confirm_attachment(attachment.id(), first_confirmed + chrono::Duration::minutes(5));
+
+        assert_eq!(todo.get_attachments()[0].confirmed_on(), Some(first_confirmed));
+    }
 }
@@ -1,16 +1,71 @@
 use std::{error::Error, fmt};
 
+/// Describes what caused a `RepositoryError`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepositoryErrorKind {
+    /// The store could not be reached, or rejected the request.
+    DataAccess,
+    /// An item was read from the store but could not be mapped to a ToDo.
+    InvalidItem,
+    /// The item is not stored, or belongs to another owner or tenant.
+    NotFound,
+    /// The item was changed by another request since it was read.
+    Conflict,
+}
+
 #[derive(Debug, Clone)]
 pub struct RepositoryError {
     error_message: String,
+    kind: RepositoryErrorKind,
+    item_key: Option<String>,
 }
 
 impl RepositoryError {
     pub fn new(data_access_error: String) -> RepositoryError {
         RepositoryError {
             error_message: data_access_error,
+            kind: RepositoryErrorKind::DataAccess,
+            item_key: None,
+        }
+    }
+
+    /// The item stored under `item_key` is malformed, or has a shape this version can't read.
+    pub fn invalid_item(item_key: String, message: String) -> RepositoryError {
+        RepositoryError {
+            error_message: message,
+            kind: RepositoryErrorKind::InvalidItem,
+            item_key: Some(item_key),
+        }
+    }
+
+    pub fn not_found(message: String) -> RepositoryError {
+        RepositoryError {
+            error_message: message,
+            kind: RepositoryErrorKind::NotFound,
+            item_key: None,
+        }
+    }
+
+    pub fn conflict(message: String) -> RepositoryError {
+        RepositoryError {
+            error_message: message,
+            kind: RepositoryErrorKind::Conflict,
+            item_key: None,
         }
     }
+
+    pub fn kind(&self) -> &RepositoryErrorKind {
+        &self.kind
+    }
+
+    /// The key of the item that could not be read, only set for `InvalidItem` errors.
+    pub fn item_key(&self) -> Option<&str> {
+        self.item_key.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
 }
 
 // Generation of an error is completely separate from how it is displayed.
@@ -20,7 +75,10 @@ impl RepositoryError {
 // which string failed to parse without modifying our types to carry that information.
 impl fmt::Display for RepositoryError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error persisting data {0}", self.error_message)
+        match &self.item_key {
+            Some(item_key) => write!(f, "Error reading item {0}: {1}", item_key, self.error_message),
+            None => write!(f, "Error persisting data {0}", self.error_message),
+        }
     }
 }
 
@@ -29,14 +87,32 @@ impl Error for RepositoryError {}
 #[derive(Debug, Clone)]
 pub struct ValidationError {
     error_message: String,
+    field: Option<String>,
 }
 
 impl ValidationError {
     pub fn new(message: String) -> ValidationError {
         ValidationError {
             error_message: message,
+            field: None,
+        }
+    }
+
+    /// Record which input field failed validation, so callers can report it against that field.
+    pub fn with_field(self, field: &str) -> ValidationError {
+        ValidationError {
+            field: Some(field.to_string()),
+            ..self
         }
     }
+
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
 }
 
 // Generation of an error is completely separate from how it is displayed.
@@ -52,23 +128,127 @@ impl fmt::Display for ValidationError {
 
 impl Error for ValidationError {}
 
+#[derive(Debug, Clone)]
+pub struct PublishError {
+    error_message: String,
+    undelivered_events: usize,
+    spilled_events: usize,
+}
+
+impl PublishError {
+    pub fn new(message: String, undelivered_events: usize) -> PublishError {
+        PublishError {
+            error_message: message,
+            undelivered_events,
+            spilled_events: 0,
+        }
+    }
+
+    /// Record how many of the undelivered events were written to the dead-letter sink.
+    pub fn with_spilled_events(self, spilled_events: usize) -> PublishError {
+        PublishError {
+            spilled_events,
+            ..self
+        }
+    }
+
+    /// The number of events that could not be delivered.
+    pub fn undelivered_events(&self) -> usize {
+        self.undelivered_events
+    }
+
+    /// The number of undelivered events kept in the dead-letter sink, to be redriven from there.
+    pub fn spilled_events(&self) -> usize {
+        self.spilled_events
+    }
+
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
+}
+
+// Generation of an error is completely separate from how it is displayed.
+// There's no need to be concerned about cluttering complex logic with the display style.
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Error publishing {0} event(s): {1}",
+            self.undelivered_events, self.error_message
+        )
+    }
+}
+
+impl Error for PublishError {}
+
+/// Describes what caused a `ServiceError`, so callers can decide how to report it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceErrorKind {
+    Validation,
+    Repository,
+    QuotaExceeded,
+    /// Something the request refers to does not exist.
+    NotFound,
+    /// The request clashes with the current state, such as starting a second timer.
+    Conflict,
+}
+
 #[derive(Debug, Clone)]
 pub struct ServiceError {
     error_message: String,
+    kind: ServiceErrorKind,
+    validation_errors: Vec<ValidationError>,
 }
 
 impl ServiceError {
-    pub fn new(message: String) -> ServiceError {
+    pub fn validation(message: String) -> ServiceError {
         ServiceError {
             error_message: message,
+            kind: ServiceErrorKind::Validation,
+            validation_errors: Vec::new(),
         }
     }
+
+    pub fn quota_exceeded(message: String) -> ServiceError {
+        ServiceError {
+            error_message: message,
+            kind: ServiceErrorKind::QuotaExceeded,
+            validation_errors: Vec::new(),
+        }
+    }
+
+    pub fn not_found(message: String) -> ServiceError {
+        ServiceError {
+            error_message: message,
+            kind: ServiceErrorKind::NotFound,
+            validation_errors: Vec::new(),
+        }
+    }
+
+    pub fn conflict(message: String) -> ServiceError {
+        ServiceError {
+            error_message: message,
+            kind: ServiceErrorKind::Conflict,
+            validation_errors: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> &ServiceErrorKind {
+        &self.kind
+    }
+
+    /// The individual validation failures that caused this error, empty for other kinds.
+    pub fn validation_errors(&self) -> &[ValidationError] {
+        &self.validation_errors
+    }
 }
 
 impl From<ValidationError> for ServiceError {
     fn from(value: ValidationError) -> Self {
         Self {
-            error_message: value.error_message,
+            error_message: value.error_message.clone(),
+            kind: ServiceErrorKind::Validation,
+            validation_errors: vec![value],
         }
     }
 }
@@ -76,7 +256,13 @@ impl From<ValidationError> for ServiceError {
 impl From<RepositoryError> for ServiceError {
     fn from(value: RepositoryError) -> Self {
         Self {
+            kind: match value.kind {
+                RepositoryErrorKind::NotFound => ServiceErrorKind::NotFound,
+                RepositoryErrorKind::Conflict => ServiceErrorKind::Conflict,
+                _ => ServiceErrorKind::Repository,
+            },
             error_message: value.error_message,
+            validation_errors: Vec::new(),
         }
     }
 }
@@ -85,12 +271,14 @@ impl From<Vec<ValidationError>> for ServiceError {
     fn from(value: Vec<ValidationError>) -> Self {
         let mut error_string = String::from("");
 
-        for err in value {
+        for err in &value {
             error_string = format!("{} {}", error_string, err);
         }
 
         Self {
             error_message: error_string,
+            kind: ServiceErrorKind::Validation,
+            validation_errors: value,
         }
     }
 }
@@ -1,21 +1,71 @@
+use crate::application::domain::Attachment;
+use crate::application::timers::TimeEntry;
 use serde::{Deserialize, Serialize};
 
+/// The `event_type` each message is published with, the names webhooks can filter on.
+pub(crate) const EVENT_TYPES: [&str; 8] = [
+    "ToDoCreated",
+    "ToDoUpdated",
+    "ToDoCompleted",
+    "ToDoDueSoon",
+    "ToDoOverdue",
+    "AttachmentAdded",
+    "TimerStarted",
+    "TimerStopped",
+];
+
 #[derive(Deserialize, Serialize)]
 pub(crate) enum MessageType {
     Created(ToDoCreated),
     Updated(ToDoUpdated),
     Completed(ToDoCompleted),
+    DueSoon(ToDoDueSoon),
+    Overdue(ToDoOverdue),
+    AttachmentAdded(AttachmentAdded),
+    TimerStarted(TimerStarted),
+    TimerStopped(TimerStopped),
+}
+
+impl MessageType {
+    /// The owner of the ToDo the event relates to.
+    pub(crate) fn user_id(&self) -> &str {
+        match self {
+            MessageType::Created(event) => &event.user_id,
+            MessageType::Updated(event) => &event.user_id,
+            MessageType::Completed(event) => &event.user_id,
+            MessageType::DueSoon(event) => &event.user_id,
+            MessageType::Overdue(event) => &event.user_id,
+            MessageType::AttachmentAdded(event) => &event.user_id,
+            MessageType::TimerStarted(event) => &event.user_id,
+            MessageType::TimerStopped(event) => &event.user_id,
+        }
+    }
+
+    /// The tenant of the owner of the ToDo the event relates to.
+    pub(crate) fn tenant_id(&self) -> &str {
+        match self {
+            MessageType::Created(event) => &event.tenant_id,
+            MessageType::Updated(event) => &event.tenant_id,
+            MessageType::Completed(event) => &event.tenant_id,
+            MessageType::DueSoon(event) => &event.tenant_id,
+            MessageType::Overdue(event) => &event.tenant_id,
+            MessageType::AttachmentAdded(event) => &event.tenant_id,
+            MessageType::TimerStarted(event) => &event.tenant_id,
+            MessageType::TimerStopped(event) => &event.tenant_id,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct ToDoCreated {
     to_do_id: String,
     user_id: String,
+    tenant_id: String,
 }
 
 impl ToDoCreated {
-    pub(crate) fn new(to_do_id: &str, user_id: &str) -> Self {
-        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string() }
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str) -> Self {
+        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string(), tenant_id: tenant_id.to_string() }
     }
 }
 
@@ -23,11 +73,12 @@ impl ToDoCreated {
 pub struct ToDoCompleted {
     to_do_id: String,
     user_id: String,
+    tenant_id: String,
 }
 
 impl ToDoCompleted {
-    pub(crate) fn new(to_do_id: &str, user_id: &str) -> Self {
-        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string() }
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str) -> Self {
+        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string(), tenant_id: tenant_id.to_string() }
     }
 }
 
@@ -35,10 +86,123 @@ impl ToDoCompleted {
 pub struct ToDoUpdated {
     to_do_id: String,
     user_id: String,
+    tenant_id: String,
 }
 
 impl ToDoUpdated {
-    pub(crate) fn new(to_do_id: &str, user_id: &str) -> Self {
-        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string() }
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str) -> Self {
+        Self { to_do_id: to_do_id.to_string(), user_id: user_id.to_string(), tenant_id: tenant_id.to_string() }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ToDoDueSoon {
+    to_do_id: String,
+    user_id: String,
+    tenant_id: String,
+    due_date: String,
+}
+
+impl ToDoDueSoon {
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str, due_date: &str) -> Self {
+        Self {
+            to_do_id: to_do_id.to_string(),
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            due_date: due_date.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ToDoOverdue {
+    to_do_id: String,
+    user_id: String,
+    tenant_id: String,
+    due_date: String,
+}
+
+impl ToDoOverdue {
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str, due_date: &str) -> Self {
+        Self {
+            to_do_id: to_do_id.to_string(),
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            due_date: due_date.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AttachmentAdded {
+    to_do_id: String,
+    user_id: String,
+    tenant_id: String,
+    attachment_id: String,
+    file_name: String,
+    content_type: String,
+    size: u64,
+}
+
+impl AttachmentAdded {
+    pub(crate) fn new(to_do_id: &str, user_id: &str, tenant_id: &str, attachment: &Attachment) -> Self {
+        Self {
+            to_do_id: to_do_id.to_string(),
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            attachment_id: attachment.id().to_string(),
+            file_name: attachment.file_name().to_string(),
+            content_type: attachment.content_type().to_string(),
+            size: attachment.size(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TimerStarted {
+    to_do_id: String,
+    user_id: String,
+    tenant_id: String,
+    entry_id: String,
+    started_on: String,
+}
+
+impl TimerStarted {
+    pub(crate) fn new(user_id: &str, tenant_id: &str, entry: &TimeEntry) -> Self {
+        Self {
+            to_do_id: entry.to_do_id().to_string(),
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            entry_id: entry.id().to_string(),
+            started_on: entry.started_on().to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TimerStopped {
+    to_do_id: String,
+    user_id: String,
+    tenant_id: String,
+    entry_id: String,
+    started_on: String,
+    stopped_on: String,
+    duration_seconds: u64,
+}
+
+impl TimerStopped {
+    pub(crate) fn new(user_id: &str, tenant_id: &str, entry: &TimeEntry) -> Self {
+        Self {
+            to_do_id: entry.to_do_id().to_string(),
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            entry_id: entry.id().to_string(),
+            started_on: entry.started_on().to_rfc3339(),
+            stopped_on: entry
+                .stopped_on()
+                .map(|val| val.to_rfc3339())
+                .unwrap_or_default(),
+            duration_seconds: entry.duration_seconds(),
+        }
     }
 }
@@ -0,0 +1,227 @@
+use crate::application::cache::CacheStats;
+use crate::application::domain::AppState;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT_MILLIS: u64 = 2000;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyStatus {
+    Up,
+    Down,
+}
+
+/// The result of probing a single dependency.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: DependencyStatus,
+    /// Whether the service can handle requests without this dependency.
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub dependencies: Vec<DependencyHealth>,
+    /// Hit and miss counts of the ToDo cache since the instance started, when caching is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStats>,
+}
+
+impl ReadinessReport {
+    /// The service is ready when every required dependency is up.
+    pub fn new(dependencies: Vec<DependencyHealth>) -> Self {
+        Self {
+            ready: dependencies
+                .iter()
+                .all(|dependency| !dependency.required || dependency.status == DependencyStatus::Up),
+            dependencies,
+            cache: None,
+        }
+    }
+}
+
+/// How long each dependency has to respond, from `HEALTH_CHECK_TIMEOUT_MS`.
+pub fn timeout_from_env() -> Duration {
+    Duration::from_millis(
+        env::var("HEALTH_CHECK_TIMEOUT_MS")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_MILLIS),
+    )
+}
+
+/// Probe every dependency in the application state at the same time. The ToDo repository is
+/// required, the message publisher is not as events that cannot be delivered are kept in the
+/// dead-letter sink.
+pub async fn check_readiness(state: &AppState, timeout: Duration) -> ReadinessReport {
+    let (todo_repo, message_publisher) = futures_util::join!(
+        probe("todo_repo", true, timeout, async {
+            state
+                .todo_repo
+                .health_check()
+                .await
+                .map_err(|e| e.message().to_string())
+        }),
+        probe("message_publisher", false, timeout, async {
+            state
+                .message_publisher
+                .health_check()
+                .await
+                .map_err(|e| e.message().to_string())
+        }),
+    );
+
+    let mut report = ReadinessReport::new(vec![todo_repo, message_publisher]);
+    report.cache = state.todo_repo.cache_stats();
+
+    report
+}
+
+async fn probe<F>(name: &str, required: bool, timeout: Duration, check: F) -> DependencyHealth
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let started = Instant::now();
+
+    let res = match tokio::time::timeout(timeout, check).await {
+        Ok(res) => res,
+        Err(_) => Err(format!("Timed out after {}ms", timeout.as_millis())),
+    };
+
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    if let Err(e) = &res {
+        tracing::warn!("Health check for {} failed: {}", name, e);
+    }
+
+    DependencyHealth {
+        name: name.to_string(),
+        status: match res {
+            Ok(_) => DependencyStatus::Up,
+            Err(_) => DependencyStatus::Down,
+        },
+        required,
+        latency_ms,
+        error: res.err(),
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{check_readiness, probe, DependencyStatus, ReadinessReport};
+    use crate::application::adapters::InMemoryToDoRepo;
+    use crate::application::cache::{CacheStats, CachingToDoRepo};
+    use crate::application::domain::{AppState, TenantId, ToDoRepo};
+    use crate::application::error_types::PublishError;
+    use crate::application::events::MessageType;
+    use crate::application::messaging::MessagePublisher;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct UnreachableMessagePublisher {}
+
+    #[async_trait]
+    impl MessagePublisher for UnreachableMessagePublisher {
+        async fn publish(&self, _message: MessageType) -> Result<(), PublishError> {
+            Ok(())
+        }
+
+        async fn health_check(&self) -> Result<(), PublishError> {
+            Err(PublishError::new(String::from("Event bus not found"), 0))
+        }
+    }
+
+    #[tokio::test]
+    async fn probe_should_report_slow_dependency_as_down() {
+        let health = probe("slow", true, Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+
+        assert_eq!(health.status, DependencyStatus::Down);
+        assert_eq!(health.error.unwrap(), "Timed out after 10ms");
+    }
+
+    #[tokio::test]
+    async fn probe_should_report_error() {
+        let health = probe("failing", true, Duration::from_secs(1), async {
+            Err(String::from("Table not found"))
+        })
+        .await;
+
+        assert_eq!(health.status, DependencyStatus::Down);
+        assert_eq!(health.error.unwrap(), "Table not found");
+    }
+
+    #[tokio::test]
+    async fn required_dependency_down_should_not_be_ready() {
+        let report = ReadinessReport::new(vec![
+            probe("required", true, Duration::from_secs(1), async {
+                Err(String::from("Unreachable"))
+            })
+            .await,
+            probe("optional", false, Duration::from_secs(1), async { Ok(()) }).await,
+        ]);
+
+        assert!(!report.ready);
+    }
+
+    #[tokio::test]
+    async fn optional_dependency_down_should_still_be_ready() {
+        let state = AppState {
+            todo_repo: Arc::new(InMemoryToDoRepo::new()),
+            message_publisher: Arc::new(UnreachableMessagePublisher {}),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        };
+
+        let report = check_readiness(&state, Duration::from_secs(1)).await;
+
+        assert!(report.ready);
+        assert_eq!(report.dependencies[0].status, DependencyStatus::Up);
+        assert_eq!(report.dependencies[1].status, DependencyStatus::Down);
+        assert_eq!(
+            report.dependencies[1].error.as_deref(),
+            Some("Event bus not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_should_report_cache_stats() {
+        let todo_repo = Arc::new(CachingToDoRepo::new(
+            Arc::new(InMemoryToDoRepo::new()),
+            Duration::from_secs(60),
+            10,
+        ));
+        todo_repo.list(&TenantId::default(), "owner").await.unwrap();
+        todo_repo.list(&TenantId::default(), "owner").await.unwrap();
+
+        let state = AppState {
+            todo_repo,
+            message_publisher: Arc::new(UnreachableMessagePublisher {}),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        };
+
+        let report = check_readiness(&state, Duration::from_secs(1)).await;
+
+        assert_eq!(report.cache, Some(CacheStats { hits: 1, misses: 1 }));
+    }
+}
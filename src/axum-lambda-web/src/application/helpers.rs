@@ -9,4 +9,15 @@ pub fn check_not_empty_and_length_less_than(input: &str, max_len: i64) -> Result
     else {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+pub fn check_length_less_than(input: &str, max_len: usize) -> Result<(), ValidationError> {
+    if input.chars().count() > max_len {
+        Err(ValidationError::new(
+            format!("Must be {} chars or fewer", max_len),
+        ))
+    }
+    else {
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use pulldown_cmark::{html, Parser};
+
+/// Render a CommonMark description to HTML that is safe to embed in a page. Any raw HTML in the
+/// description is passed through ammonia, which removes script tags, inline event handlers and
+/// links using unsafe URL schemes.
+pub(crate) fn render_description_html(description: &str) -> String {
+    let parser = Parser::new(description);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser);
+
+    ammonia::clean(&unsafe_html)
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::render_description_html;
+
+    #[test]
+    fn commonmark_should_render_to_html() {
+        let html = render_description_html("**Buy** milk\n\n- semi skimmed");
+
+        assert!(html.contains("<strong>Buy</strong> milk"));
+        assert!(html.contains("<li>semi skimmed</li>"));
+    }
+
+    #[test]
+    fn script_tags_should_be_removed() {
+        let html = render_description_html("Hello <script>alert('hi')</script>");
+
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert"));
+    }
+
+    #[test]
+    fn inline_event_handlers_should_be_removed() {
+        let html = render_description_html("<img src=\"x.png\" onerror=\"alert('hi')\">");
+
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn javascript_links_should_be_removed() {
+        let html = render_description_html("[click me](javascript:alert('hi'))");
+
+        assert!(!html.contains("javascript:"));
+    }
+}
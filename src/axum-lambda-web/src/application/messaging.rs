@@ -1,13 +1,39 @@
+use crate::application::aws_query::AwsQueryClient;
+use crate::application::domain::TenantId;
+use crate::application::error_types::PublishError;
 use crate::application::events::MessageType;
+use crate::application::webhooks::{is_public_address, Webhook, WebhookDelivery, WebhookRepo};
 use async_trait::async_trait;
+use aws_config::SdkConfig;
 use aws_sdk_eventbridge::types::PutEventsRequestEntry;
 use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::env;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
+// The limits EventBridge places on a single PutEvents call.
+const MAX_BATCH_ENTRIES: usize = 10;
+const MAX_BATCH_BYTES: usize = 256 * 1024;
+const EVENT_SOURCE: &str = "axum-lambda-todo";
+
+// Webhooks that fail this many deliveries in a row are disabled, unless overridden by
+// `WEBHOOK_FAILURE_THRESHOLD`.
+const DEFAULT_WEBHOOK_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_WEBHOOK_TIMEOUT_MS: u64 = 5000;
+
 #[derive(Deserialize, Serialize)]
-struct MessageWrapper<T>
+pub(crate) struct MessageWrapper<T>
 where
     T: Serialize,
 {
@@ -25,11 +51,12 @@ impl MessageWrapper<MessageType> {
 }
 
 #[derive(Deserialize, Serialize)]
-struct Metadata {
+pub(crate) struct Metadata {
     event_id: String,
     event_date: i64,
     event_type: String,
     event_version: String,
+    tenant_id: String,
 }
 
 impl Metadata {
@@ -38,6 +65,11 @@ impl Metadata {
             MessageType::Created(_) => ("ToDoCreated", "v1"),
             MessageType::Updated(_) => ("ToDoUpdated", "v1"),
             MessageType::Completed(_) => ("ToDoCompleted", "v1"),
+            MessageType::DueSoon(_) => ("ToDoDueSoon", "v1"),
+            MessageType::Overdue(_) => ("ToDoOverdue", "v1"),
+            MessageType::AttachmentAdded(_) => ("AttachmentAdded", "v1"),
+            MessageType::TimerStarted(_) => ("TimerStarted", "v1"),
+            MessageType::TimerStopped(_) => ("TimerStopped", "v1"),
         };
 
         Self {
@@ -45,13 +77,32 @@ impl Metadata {
             event_date: Utc::now().timestamp(),
             event_type: String::from(event_type),
             event_version: String::from(event_version),
+            tenant_id: message_type.tenant_id().to_string(),
         }
     }
 }
 
 #[async_trait]
 pub trait MessagePublisher {
-    async fn publish(&self, message: MessageType) -> Result<(), ()>;
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError>;
+
+    /// Send any messages the publisher is holding on to. Publishers that send every message
+    /// immediately have nothing to flush.
+    async fn flush(&self) -> Result<(), PublishError> {
+        Ok(())
+    }
+
+    /// Check the destination of the messages can be reached, used by the readiness endpoint.
+    async fn health_check(&self) -> Result<(), PublishError> {
+        Ok(())
+    }
+}
+
+/// A publisher that can deliver an already wrapped message, so the fan-out publisher can send
+/// the same event, with the same metadata, to every backend.
+#[async_trait]
+pub(crate) trait PublishTarget: MessagePublisher {
+    async fn deliver(&self, message: &MessageWrapper<MessageType>) -> Result<(), PublishError>;
 }
 
 pub struct InMemoryMessagePublisher {}
@@ -64,51 +115,2030 @@ impl InMemoryMessagePublisher {
 
 #[async_trait]
 impl MessagePublisher for InMemoryMessagePublisher {
-    async fn publish(&self, message: MessageType) -> Result<(), ()> {
-        let wrapped_message = MessageWrapper::new(message);
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        self.deliver(&MessageWrapper::new(message)).await
+    }
+}
 
-        tracing::info!("{}", serde_json::json!(wrapped_message));
+#[async_trait]
+impl PublishTarget for InMemoryMessagePublisher {
+    async fn deliver(&self, message: &MessageWrapper<MessageType>) -> Result<(), PublishError> {
+        tracing::info!("{}", serde_json::json!(message));
+
+        Ok(())
+    }
+}
+
+/// Somewhere to write events that could not be delivered, so they can be replayed later.
+pub trait DeadLetterSink {
+    fn spill(&self, events: &[String]) -> Result<(), std::io::Error>;
+}
+
+/// Appends undelivered events to a local file, one JSON document per line.
+pub struct FileDeadLetterSink {
+    path: PathBuf,
+}
+
+impl FileDeadLetterSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Write to `DEAD_LETTER_PATH`, or a file in `/tmp` as that is the only writable
+    /// location on Lambda.
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("DEAD_LETTER_PATH")
+                .unwrap_or_else(|_| String::from("/tmp/undelivered-events.jsonl")),
+        )
+    }
+}
+
+impl DeadLetterSink for FileDeadLetterSink {
+    fn spill(&self, events: &[String]) -> Result<(), std::io::Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        for event in events {
+            writeln!(file, "{}", event)?;
+        }
 
         Ok(())
     }
 }
 
+/// How failed entries are retried before they are written to the dead-letter sink.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with full jitter, a random delay between zero and the capped
+    /// exponential delay for the attempt.
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        if exponential.is_zero() {
+            return exponential;
+        }
+
+        Duration::from_millis(rand::thread_rng().gen_range(0..=exponential.as_millis() as u64))
+    }
+}
+
+/// A single event waiting to be sent in a `PutEvents` call.
+#[derive(Clone)]
+pub(crate) struct BufferedEvent {
+    detail_type: String,
+    detail: String,
+}
+
+impl BufferedEvent {
+    // EventBridge counts the source, detail type and detail towards the size of an entry.
+    fn size(&self) -> usize {
+        EVENT_SOURCE.len() + self.detail_type.len() + self.detail.len()
+    }
+}
+
+/// Sends a batch of entries to EventBridge. Returns the error code for each entry that failed,
+/// in the same order as the entries, or an error if the whole call failed.
+#[async_trait]
+pub(crate) trait PutEventsSender {
+    async fn put_events(
+        &self,
+        event_bus_name: &str,
+        events: &[BufferedEvent],
+    ) -> Result<Vec<Option<String>>, String>;
+
+    /// Check the event bus exists and can be described.
+    async fn describe_event_bus(&self, event_bus_name: &str) -> Result<(), String>;
+}
+
+#[async_trait]
+impl PutEventsSender for aws_sdk_eventbridge::Client {
+    async fn put_events(
+        &self,
+        event_bus_name: &str,
+        events: &[BufferedEvent],
+    ) -> Result<Vec<Option<String>>, String> {
+        let entries: Vec<PutEventsRequestEntry> = events
+            .iter()
+            .map(|event| {
+                PutEventsRequestEntry::builder()
+                    .event_bus_name(event_bus_name)
+                    .source(EVENT_SOURCE)
+                    .detail(&event.detail)
+                    .detail_type(&event.detail_type)
+                    .build()
+            })
+            .collect();
+
+        let res = self
+            .put_events()
+            .set_entries(Some(entries))
+            .send()
+            .await
+            .map_err(|err| err.into_service_error().to_string())?;
+
+        Ok(res
+            .entries()
+            .iter()
+            .map(|entry| entry.error_code().map(|code| code.to_string()))
+            .collect())
+    }
+
+    async fn describe_event_bus(&self, event_bus_name: &str) -> Result<(), String> {
+        self.describe_event_bus()
+            .name(event_bus_name)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|err| err.into_service_error().to_string())
+    }
+}
+
+tokio::task_local! {
+    // The EventBridge events published by the work running in `with_event_buffer`.
+    static EVENT_BUFFER: RefCell<Vec<BufferedEvent>>;
+}
+
+/// Run `work`, such as handling a single request, with its own buffer of EventBridge and
+/// webhook events.
+/// Flushing only ever sends, or dead-letters, the events `work` published, never those of
+/// another request handled at the same time. Events published outside of a buffer are sent
+/// straight away.
+pub async fn with_event_buffer<F: Future>(work: F) -> F::Output {
+    EVENT_BUFFER
+        .scope(
+            RefCell::new(Vec::new()),
+            WEBHOOK_BUFFER.scope(RefCell::new(Vec::new()), work),
+        )
+        .await
+}
+
+/// Buffers events and sends them to EventBridge in batches of up to 10 entries and 256KB.
+/// Entries that fail are retried with a jittered backoff, events that still cannot be
+/// delivered are written to the dead-letter sink.
 pub struct EventBridgeEventPublisher {
-    client: aws_sdk_eventbridge::Client,
+    sender: Arc<dyn PutEventsSender + Send + Sync>,
+    dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+    event_bus_name: String,
+    retry_policy: RetryPolicy,
 }
 
 impl EventBridgeEventPublisher {
     pub fn new(client: aws_sdk_eventbridge::Client) -> Self {
-        Self { client }
+        Self::with_sender(
+            Arc::new(client),
+            Arc::new(FileDeadLetterSink::from_env()),
+            env::var("EVENT_BUS_NAME").unwrap_or_else(|_| String::from("default")),
+            RetryPolicy::default(),
+        )
+    }
+
+    pub(crate) fn with_sender(
+        sender: Arc<dyn PutEventsSender + Send + Sync>,
+        dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+        event_bus_name: String,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            sender,
+            dead_letter_sink,
+            event_bus_name,
+            retry_policy,
+        }
+    }
+
+    /// Send a single batch, retrying only the entries that failed. Returns the events that
+    /// could not be delivered after every attempt along with the last error seen.
+    async fn send_batch(&self, batch: Vec<BufferedEvent>) -> (Vec<BufferedEvent>, String) {
+        let mut pending = batch;
+        let mut last_error = String::new();
+
+        for attempt in 0..self.retry_policy.max_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.retry_policy.delay_for(attempt)).await;
+            }
+
+            match self.sender.put_events(&self.event_bus_name, &pending).await {
+                Ok(results) => {
+                    let failed: Vec<BufferedEvent> = pending
+                        .into_iter()
+                        .zip(results.into_iter().chain(std::iter::repeat(None)))
+                        .filter_map(|(event, error_code)| {
+                            error_code.map(|code| {
+                                last_error = code;
+                                event
+                            })
+                        })
+                        .collect();
+
+                    if failed.is_empty() {
+                        return (Vec::new(), last_error);
+                    }
+
+                    tracing::warn!(
+                        "{} of the events failed on attempt {}",
+                        failed.len(),
+                        attempt + 1
+                    );
+
+                    pending = failed;
+                }
+                Err(err) => {
+                    tracing::warn!("PutEvents failed on attempt {}: {}", attempt + 1, err);
+                    last_error = err;
+                }
+            }
+        }
+
+        (pending, last_error)
+    }
+}
+
+/// Split events into batches that fit within the EventBridge limits. Events that are too large
+/// to ever be sent are returned separately.
+fn batch_events(events: Vec<BufferedEvent>) -> (Vec<Vec<BufferedEvent>>, Vec<BufferedEvent>) {
+    let mut batches: Vec<Vec<BufferedEvent>> = Vec::new();
+    let mut oversized: Vec<BufferedEvent> = Vec::new();
+    let mut current: Vec<BufferedEvent> = Vec::new();
+    let mut current_size = 0;
+
+    for event in events {
+        let size = event.size();
+
+        if size > MAX_BATCH_BYTES {
+            oversized.push(event);
+            continue;
+        }
+
+        if current.len() == MAX_BATCH_ENTRIES || current_size + size > MAX_BATCH_BYTES {
+            batches.push(std::mem::take(&mut current));
+            current_size = 0;
+        }
+
+        current_size += size;
+        current.push(event);
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    (batches, oversized)
+}
+
+#[async_trait]
+impl PublishTarget for EventBridgeEventPublisher {
+    async fn deliver(
+        &self,
+        wrapped_message: &MessageWrapper<MessageType>,
+    ) -> Result<(), PublishError> {
+        tracing::info!("{}", serde_json::json!(wrapped_message));
+
+        let event = BufferedEvent {
+            detail_type: wrapped_message.metadata.event_type.clone(),
+            detail: serde_json::json!(wrapped_message).to_string(),
+        };
+
+        let buffer_full = EVENT_BUFFER.try_with(|buffer| {
+            let mut buffer = buffer.borrow_mut();
+
+            buffer.push(event.clone());
+
+            buffer.len() >= MAX_BATCH_ENTRIES
+                || buffer.iter().map(BufferedEvent::size).sum::<usize>() >= MAX_BATCH_BYTES
+        });
+
+        match buffer_full {
+            Ok(false) => Ok(()),
+            Ok(true) => self.flush().await,
+            Err(_) => self.send_events(vec![event]).await,
+        }
     }
 }
 
 #[async_trait]
 impl MessagePublisher for EventBridgeEventPublisher {
-    async fn publish(&self, message: MessageType) -> Result<(), ()> {
-        let wrapped_message = MessageWrapper::new(message);
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        self.deliver(&MessageWrapper::new(message)).await
+    }
 
-        tracing::info!("{}", serde_json::json!(wrapped_message));
+    async fn flush(&self) -> Result<(), PublishError> {
+        let events = EVENT_BUFFER
+            .try_with(|buffer| std::mem::take(&mut *buffer.borrow_mut()))
+            .unwrap_or_default();
 
-        let publish_res = self
-            .client
-            .put_events()
-            .entries(
-                PutEventsRequestEntry::builder()
-                    .event_bus_name(env::var("EVENT_BUS_NAME").unwrap())
-                    .detail(serde_json::json!(wrapped_message).to_string())
-                    .detail_type(&wrapped_message.metadata.event_type)
-                    .build(),
+        self.send_events(events).await
+    }
+
+    async fn health_check(&self) -> Result<(), PublishError> {
+        self.sender
+            .describe_event_bus(&self.event_bus_name)
+            .await
+            .map_err(|err| PublishError::new(err, 0))
+    }
+}
+
+impl EventBridgeEventPublisher {
+    async fn send_events(&self, events: Vec<BufferedEvent>) -> Result<(), PublishError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let (batches, mut undelivered) = batch_events(events);
+        let mut last_error = match undelivered.is_empty() {
+            true => String::new(),
+            false => String::from("Event is larger than the 256KB EventBridge limit"),
+        };
+
+        for batch in batches {
+            let (failed, error) = self.send_batch(batch).await;
+
+            if !failed.is_empty() {
+                undelivered.extend(failed);
+                last_error = error;
+            }
+        }
+
+        if undelivered.is_empty() {
+            return Ok(());
+        }
+
+        let details: Vec<String> = undelivered
+            .iter()
+            .map(|event| event.detail.clone())
+            .collect();
+
+        let spilled_events = match self.dead_letter_sink.spill(&details) {
+            Ok(_) => details.len(),
+            Err(e) => {
+                tracing::error!(
+                    "Failed to write {} events to the dead-letter sink: {}",
+                    details.len(),
+                    e
+                );
+                0
+            }
+        };
+
+        Err(PublishError::new(last_error, undelivered.len()).with_spilled_events(spilled_events))
+    }
+}
+
+/// Send a single Query API call, retrying with a jittered backoff. The message is written to the
+/// dead-letter sink if every attempt fails.
+async fn call_with_retry(
+    client: &AwsQueryClient,
+    action: &str,
+    params: &[(&str, &str)],
+    retry_policy: RetryPolicy,
+    dead_letter_sink: &Arc<dyn DeadLetterSink + Send + Sync>,
+    detail: String,
+) -> Result<(), PublishError> {
+    let mut last_error = String::new();
+
+    for attempt in 0..retry_policy.max_attempts {
+        if attempt > 0 {
+            tokio::time::sleep(retry_policy.delay_for(attempt)).await;
+        }
+
+        match client.call(action, params).await {
+            Ok(_) => return Ok(()),
+            Err(err) => {
+                tracing::warn!("{} failed on attempt {}: {}", action, attempt + 1, err);
+                last_error = err;
+            }
+        }
+    }
+
+    let spilled_events = match dead_letter_sink.spill(&[detail]) {
+        Ok(_) => 1,
+        Err(e) => {
+            tracing::error!("Failed to write event to the dead-letter sink: {}", e);
+            0
+        }
+    };
+
+    Err(PublishError::new(last_error, 1).with_spilled_events(spilled_events))
+}
+
+/// Publishes each message to an SNS topic as soon as it is received. The event type is sent as
+/// the `event_type` message attribute so subscriptions can filter on it.
+pub struct SnsMessagePublisher {
+    client: AwsQueryClient,
+    topic_arn: String,
+    retry_policy: RetryPolicy,
+    dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+}
+
+impl SnsMessagePublisher {
+    pub(crate) fn new(
+        client: AwsQueryClient,
+        topic_arn: String,
+        retry_policy: RetryPolicy,
+        dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+    ) -> Self {
+        Self {
+            client,
+            topic_arn,
+            retry_policy,
+            dead_letter_sink,
+        }
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for SnsMessagePublisher {
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        self.deliver(&MessageWrapper::new(message)).await
+    }
+
+    async fn health_check(&self) -> Result<(), PublishError> {
+        self.client
+            .call("GetTopicAttributes", &[("TopicArn", self.topic_arn.as_str())])
+            .await
+            .map_err(|err| PublishError::new(err, 0))
+    }
+}
+
+#[async_trait]
+impl PublishTarget for SnsMessagePublisher {
+    async fn deliver(&self, message: &MessageWrapper<MessageType>) -> Result<(), PublishError> {
+        let body = serde_json::json!(message).to_string();
+
+        call_with_retry(
+            &self.client,
+            "Publish",
+            &[
+                ("TopicArn", self.topic_arn.as_str()),
+                ("Message", body.as_str()),
+                ("MessageAttributes.entry.1.Name", "event_type"),
+                ("MessageAttributes.entry.1.Value.DataType", "String"),
+                (
+                    "MessageAttributes.entry.1.Value.StringValue",
+                    message.metadata.event_type.as_str(),
+                ),
+            ],
+            self.retry_policy,
+            &self.dead_letter_sink,
+            body.clone(),
+        )
+        .await
+    }
+}
+
+/// Sends each message to an SQS FIFO queue as soon as it is received. Messages are grouped by
+/// owner, so each owner's events are delivered in order, and deduplicated on the event id.
+pub struct SqsFifoMessagePublisher {
+    client: AwsQueryClient,
+    queue_url: String,
+    retry_policy: RetryPolicy,
+    dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+}
+
+impl SqsFifoMessagePublisher {
+    pub(crate) fn new(
+        client: AwsQueryClient,
+        queue_url: String,
+        retry_policy: RetryPolicy,
+        dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+    ) -> Self {
+        Self {
+            client,
+            queue_url,
+            retry_policy,
+            dead_letter_sink,
+        }
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for SqsFifoMessagePublisher {
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        self.deliver(&MessageWrapper::new(message)).await
+    }
+
+    async fn health_check(&self) -> Result<(), PublishError> {
+        self.client
+            .call(
+                "GetQueueAttributes",
+                &[
+                    ("QueueUrl", self.queue_url.as_str()),
+                    ("AttributeName.1", "QueueArn"),
+                ],
             )
+            .await
+            .map_err(|err| PublishError::new(err, 0))
+    }
+}
+
+#[async_trait]
+impl PublishTarget for SqsFifoMessagePublisher {
+    async fn deliver(&self, message: &MessageWrapper<MessageType>) -> Result<(), PublishError> {
+        let body = serde_json::json!(message).to_string();
+
+        call_with_retry(
+            &self.client,
+            "SendMessage",
+            &[
+                ("QueueUrl", self.queue_url.as_str()),
+                ("MessageBody", body.as_str()),
+                ("MessageGroupId", message.data.user_id()),
+                ("MessageDeduplicationId", message.metadata.event_id.as_str()),
+            ],
+            self.retry_policy,
+            &self.dead_letter_sink,
+            body.clone(),
+        )
+        .await
+    }
+}
+
+/// Sends a single webhook request, returning the status code of the response or an error if no
+/// response was received.
+#[async_trait]
+pub(crate) trait WebhookSender {
+    async fn send(&self, url: &str, headers: &[(String, String)], body: &str) -> Result<u16, String>;
+}
+
+/// Resolves webhook hosts, refusing any that resolve to a loopback, private or link-local
+/// address. URLs are checked when a webhook is registered, but the addresses a name resolves
+/// to can change after that, so they are checked again when each request is sent.
+struct PublicAddressResolver;
+
+impl reqwest::dns::Resolve for PublicAddressResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let addresses = public_addresses(&host).await?;
+            let addresses: reqwest::dns::Addrs = Box::new(addresses.into_iter());
+
+            Ok(addresses)
+        })
+    }
+}
+
+async fn public_addresses(
+    host: &str,
+) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await?
+        .filter(|address| is_public_address(&address.ip()))
+        .collect();
+
+    match addresses.is_empty() {
+        true => Err(format!("'{}' does not resolve to a public address", host).into()),
+        false => Ok(addresses),
+    }
+}
+
+pub(crate) struct HttpWebhookSender {
+    client: reqwest::Client,
+}
+
+impl HttpWebhookSender {
+    /// Requests time out after `WEBHOOK_TIMEOUT_MS`, 5 seconds if not set. Redirects are not
+    /// followed, so a webhook cannot be bounced to an address its URL would not be allowed, and
+    /// hosts are only connected to at public addresses.
+    pub fn from_env() -> Self {
+        let timeout_ms = env::var("WEBHOOK_TIMEOUT_MS")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_MS);
+
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_millis(timeout_ms))
+                .redirect(reqwest::redirect::Policy::none())
+                .dns_resolver(Arc::new(PublicAddressResolver))
+                .build()
+                .expect("the webhook HTTP client can be built"),
+        }
+    }
+}
+
+#[async_trait]
+impl WebhookSender for HttpWebhookSender {
+    async fn send(&self, url: &str, headers: &[(String, String)], body: &str) -> Result<u16, String> {
+        // Addresses in the URL itself are never passed to the resolver.
+        let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+        let host = parsed.host_str().unwrap_or_default();
+        let host_address = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+
+        if let Ok(address) = host_address {
+            if !is_public_address(&address) {
+                return Err(format!("'{}' is not a public address", host));
+            }
+        }
+
+        let mut request = self.client.post(parsed).body(body.to_string());
+
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        request
             .send()
-            .await;
+            .await
+            .map(|res| res.status().as_u16())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The signature sent with a webhook delivery, an HMAC-SHA256 of the timestamp and body joined
+/// by a `.`, keyed with the webhook's secret.
+pub(crate) fn sign_webhook(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// An event waiting to be sent to its owner's webhooks, as it is written to the delivery queue.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct WebhookEvent {
+    tenant_id: String,
+    owner: String,
+    event_id: String,
+    event_type: String,
+    body: String,
+}
 
-        match publish_res {
-            Ok(res) => {
-                tracing::info!("{} failed events", res.failed_entry_count);
-                Ok(())
+impl From<&MessageWrapper<MessageType>> for WebhookEvent {
+    fn from(message: &MessageWrapper<MessageType>) -> Self {
+        WebhookEvent {
+            tenant_id: message.metadata.tenant_id.clone(),
+            owner: message.data.user_id().to_string(),
+            event_id: message.metadata.event_id.clone(),
+            event_type: message.metadata.event_type.clone(),
+            body: serde_json::json!(message).to_string(),
+        }
+    }
+}
+
+tokio::task_local! {
+    // The webhook events published by the work running in `with_event_buffer`.
+    static WEBHOOK_BUFFER: RefCell<Vec<WebhookEvent>>;
+}
+
+/// Where webhook events wait to be delivered, so no request waits on an owner's webhooks.
+#[async_trait]
+pub(crate) trait WebhookQueue {
+    async fn enqueue(&self, events: Vec<WebhookEvent>) -> Result<(), PublishError>;
+}
+
+/// Queues each webhook event as a message on an SQS queue, for the delivery function to send.
+/// Messages the queue will not take are retried with a jittered backoff, then written to the
+/// dead-letter sink.
+pub(crate) struct SqsWebhookQueue {
+    client: AwsQueryClient,
+    queue_url: String,
+    retry_policy: RetryPolicy,
+    dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+}
+
+impl SqsWebhookQueue {
+    pub(crate) fn new(
+        client: AwsQueryClient,
+        queue_url: String,
+        retry_policy: RetryPolicy,
+        dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync>,
+    ) -> Self {
+        Self {
+            client,
+            queue_url,
+            retry_policy,
+            dead_letter_sink,
+        }
+    }
+}
+
+#[async_trait]
+impl WebhookQueue for SqsWebhookQueue {
+    async fn enqueue(&self, events: Vec<WebhookEvent>) -> Result<(), PublishError> {
+        let mut errors: Vec<PublishError> = Vec::new();
+
+        for event in events {
+            let body = serde_json::json!(event).to_string();
+
+            if let Err(e) = call_with_retry(
+                &self.client,
+                "SendMessage",
+                &[
+                    ("QueueUrl", self.queue_url.as_str()),
+                    ("MessageBody", body.as_str()),
+                ],
+                self.retry_policy,
+                &self.dead_letter_sink,
+                body.clone(),
+            )
+            .await
+            {
+                errors.push(e);
             }
-            Err(err) => panic!("{}", err.into_service_error()),
         }
+
+        combine_publish_errors(errors)
+    }
+}
+
+/// Delivers webhook events on a background task of this process, used when running locally
+/// where there is no queue.
+pub(crate) struct InProcessWebhookQueue {
+    dispatcher: Arc<WebhookDispatcher>,
+}
+
+impl InProcessWebhookQueue {
+    pub(crate) fn new(dispatcher: Arc<WebhookDispatcher>) -> Self {
+        Self { dispatcher }
+    }
+}
+
+#[async_trait]
+impl WebhookQueue for InProcessWebhookQueue {
+    async fn enqueue(&self, events: Vec<WebhookEvent>) -> Result<(), PublishError> {
+        let dispatcher = self.dispatcher.clone();
+
+        tokio::spawn(async move { dispatcher.dispatch(&events).await });
+
+        Ok(())
+    }
+}
+
+/// Queues every event for its owner's webhooks. Events published in `with_event_buffer` are
+/// queued when that work flushes the publisher, any others straight away.
+pub struct WebhookPublisher {
+    queue: Arc<dyn WebhookQueue + Send + Sync>,
+}
+
+impl WebhookPublisher {
+    pub(crate) fn new(queue: Arc<dyn WebhookQueue + Send + Sync>) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl PublishTarget for WebhookPublisher {
+    async fn deliver(&self, message: &MessageWrapper<MessageType>) -> Result<(), PublishError> {
+        let event = WebhookEvent::from(message);
+
+        let buffered = WEBHOOK_BUFFER.try_with(|buffer| buffer.borrow_mut().push(event.clone()));
+
+        match buffered {
+            Ok(_) => Ok(()),
+            Err(_) => self.queue.enqueue(vec![event]).await,
+        }
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for WebhookPublisher {
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        self.deliver(&MessageWrapper::new(message)).await
+    }
+
+    async fn flush(&self) -> Result<(), PublishError> {
+        let events = WEBHOOK_BUFFER
+            .try_with(|buffer| std::mem::take(&mut *buffer.borrow_mut()))
+            .unwrap_or_default();
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        self.queue.enqueue(events).await
+    }
+}
+
+/// Sends queued events to the webhooks their owner has subscribed to them with. Each delivery
+/// is retried with a jittered backoff and its outcome is added to the webhook's delivery log.
+/// Webhooks that fail too many deliveries in a row are disabled.
+///
+/// A failing webhook is a problem with the receiver rather than the event, so failed deliveries
+/// are recorded in the log instead of being returned as errors. Only events that could not be
+/// processed at all, such as when the webhooks could not be loaded, are returned to be queued
+/// again.
+pub struct WebhookDispatcher {
+    repo: Arc<dyn WebhookRepo + Send + Sync>,
+    sender: Arc<dyn WebhookSender + Send + Sync>,
+    retry_policy: RetryPolicy,
+    failure_threshold: u32,
+}
+
+impl WebhookDispatcher {
+    pub fn from_env(repo: Arc<dyn WebhookRepo + Send + Sync>) -> Self {
+        Self::with_sender(
+            repo,
+            Arc::new(HttpWebhookSender::from_env()),
+            RetryPolicy::default(),
+            env::var("WEBHOOK_FAILURE_THRESHOLD")
+                .ok()
+                .and_then(|val| val.parse().ok())
+                .unwrap_or(DEFAULT_WEBHOOK_FAILURE_THRESHOLD),
+        )
+    }
+
+    pub(crate) fn with_sender(
+        repo: Arc<dyn WebhookRepo + Send + Sync>,
+        sender: Arc<dyn WebhookSender + Send + Sync>,
+        retry_policy: RetryPolicy,
+        failure_threshold: u32,
+    ) -> Self {
+        Self {
+            repo,
+            sender,
+            retry_policy,
+            failure_threshold,
+        }
+    }
+
+    /// Send an event to a single webhook. Server errors, rate limiting and requests that get no
+    /// response are retried, any other response is final.
+    async fn send_event(&self, webhook: &Webhook, event: &WebhookEvent) -> WebhookDelivery {
+        let mut delivery = WebhookDelivery {
+            delivery_id: Uuid::new_v4().to_string(),
+            webhook_id: webhook.id().to_string(),
+            event_id: event.event_id.clone(),
+            event_type: event.event_type.clone(),
+            delivered_on: Utc::now(),
+            attempts: 0,
+            status_code: None,
+            error: None,
+        };
+
+        for attempt in 0..self.retry_policy.max_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.retry_policy.delay_for(attempt)).await;
+            }
+
+            // Signed on every attempt, so the timestamp is always the time the request was sent.
+            let timestamp = Utc::now().timestamp();
+            let headers = vec![
+                (String::from("Content-Type"), String::from("application/json")),
+                (String::from("X-Todo-Event-Id"), event.event_id.clone()),
+                (String::from("X-Todo-Event-Type"), event.event_type.clone()),
+                (String::from("X-Todo-Timestamp"), timestamp.to_string()),
+                (
+                    String::from("X-Todo-Signature"),
+                    sign_webhook(webhook.secret(), timestamp, &event.body),
+                ),
+            ];
+
+            delivery.attempts = attempt + 1;
+
+            let retry = match self.sender.send(webhook.url(), &headers, &event.body).await {
+                Ok(status_code) => {
+                    delivery.status_code = Some(status_code);
+                    delivery.error = match status_code {
+                        200..=299 => None,
+                        _ => Some(format!("The webhook responded with {}", status_code)),
+                    };
+
+                    status_code == 429 || status_code >= 500
+                }
+                Err(err) => {
+                    delivery.status_code = None;
+                    delivery.error = Some(err);
+
+                    true
+                }
+            };
+
+            if delivery.succeeded() || !retry {
+                break;
+            }
+
+            tracing::warn!(
+                "Webhook {} delivery failed on attempt {}",
+                webhook.id(),
+                attempt + 1
+            );
+        }
+
+        delivery
+    }
+
+    /// Send every event to one owner's webhooks, recording each delivery and its result.
+    async fn send_to_owner(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        events: &[WebhookEvent],
+    ) -> Result<(), String> {
+        let mut webhooks = self
+            .repo
+            .list_webhooks(tenant, owner)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for event in events {
+            let mut current_webhooks = Vec::with_capacity(webhooks.len());
+
+            for webhook in webhooks {
+                if !webhook.subscribes_to(&event.event_type) {
+                    current_webhooks.push(webhook);
+                    continue;
+                }
+
+                let delivery = self.send_event(&webhook, event).await;
+
+                let updated = self
+                    .repo
+                    .record_delivery_result(&webhook, delivery.succeeded(), self.failure_threshold)
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                // The webhook was deleted while the event was being sent.
+                let updated = match updated {
+                    Some(updated) => updated,
+                    None => continue,
+                };
+
+                if webhook.is_enabled() && !updated.is_enabled() {
+                    tracing::warn!(
+                        "Webhook {} disabled after {} failed deliveries",
+                        updated.id(),
+                        updated.consecutive_failures()
+                    );
+                }
+
+                self.repo
+                    .record_delivery(&updated, &delivery)
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                current_webhooks.push(updated);
+            }
+
+            webhooks = current_webhooks;
+        }
+
+        Ok(())
+    }
+
+    /// Send each event to its owner's webhooks. Returns the ids of the events that could not be
+    /// processed, some of their webhooks may already have been sent them, so receivers should
+    /// use the event id to ignore repeats.
+    pub async fn dispatch(&self, events: &[WebhookEvent]) -> Vec<String> {
+        // Events are grouped by owner, so each owner's webhooks are only loaded once.
+        let mut by_owner: Vec<((&str, &str), Vec<WebhookEvent>)> = Vec::new();
+
+        for event in events {
+            let key = (event.tenant_id.as_str(), event.owner.as_str());
+
+            match by_owner.iter_mut().find(|(owner, _)| owner == &key) {
+                Some((_, owner_events)) => owner_events.push(event.clone()),
+                None => by_owner.push((key, vec![event.clone()])),
+            }
+        }
+
+        let mut failed: Vec<String> = Vec::new();
+
+        for ((tenant_id, owner), owner_events) in by_owner {
+            let res = match TenantId::parse(tenant_id) {
+                Ok(tenant) => self.send_to_owner(&tenant, owner, &owner_events).await,
+                Err(e) => Err(e.to_string()),
+            };
+
+            if let Err(e) = res {
+                tracing::error!(
+                    "Failed to send {} events to the webhooks of {}: {}",
+                    owner_events.len(),
+                    owner,
+                    e
+                );
+
+                failed.extend(owner_events.into_iter().map(|event| event.event_id));
+            }
+        }
+
+        failed
+    }
+
+    /// Deliver a batch of messages received from the webhook queue. Messages that could not be
+    /// processed are reported as failed, so the queue makes them visible again to be retried
+    /// and eventually moves them to its dead-letter queue.
+    pub async fn deliver_queued(&self, batch: QueuedWebhookBatch) -> QueuedWebhookBatchResponse {
+        let mut failed_messages: Vec<String> = Vec::new();
+        let mut queued: Vec<(String, WebhookEvent)> = Vec::new();
+
+        for record in batch.records {
+            match serde_json::from_str::<WebhookEvent>(&record.body) {
+                Ok(event) => queued.push((record.message_id, event)),
+                Err(e) => {
+                    tracing::error!("Message {} is not a webhook event: {}", record.message_id, e);
+                    failed_messages.push(record.message_id);
+                }
+            }
+        }
+
+        let events: Vec<WebhookEvent> = queued.iter().map(|(_, event)| event.clone()).collect();
+        let failed_events = self.dispatch(&events).await;
+
+        failed_messages.extend(
+            queued
+                .into_iter()
+                .filter(|(_, event)| failed_events.contains(&event.event_id))
+                .map(|(message_id, _)| message_id),
+        );
+
+        QueuedWebhookBatchResponse {
+            batch_item_failures: failed_messages
+                .into_iter()
+                .map(|item_identifier| QueuedWebhookFailure { item_identifier })
+                .collect(),
+        }
+    }
+}
+
+/// The messages an SQS event source mapping hands the delivery function.
+#[derive(Deserialize)]
+pub struct QueuedWebhookBatch {
+    #[serde(rename = "Records")]
+    records: Vec<QueuedWebhookMessage>,
+}
+
+#[derive(Deserialize)]
+struct QueuedWebhookMessage {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// The messages of a batch that failed, only these are returned to the queue.
+#[derive(Debug, Serialize)]
+pub struct QueuedWebhookBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<QueuedWebhookFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueuedWebhookFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
+}
+
+/// Publishes every message to each of its targets. A failure in one target does not stop the
+/// message being sent to the others.
+pub struct FanOutMessagePublisher {
+    targets: Vec<Arc<dyn PublishTarget + Send + Sync>>,
+}
+
+impl FanOutMessagePublisher {
+    pub(crate) fn new(targets: Vec<Arc<dyn PublishTarget + Send + Sync>>) -> Self {
+        Self { targets }
+    }
+}
+
+fn combine_publish_errors(errors: Vec<PublishError>) -> Result<(), PublishError> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    Err(PublishError::new(
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join("; "),
+        errors.iter().map(PublishError::undelivered_events).sum(),
+    )
+    .with_spilled_events(errors.iter().map(PublishError::spilled_events).sum()))
+}
+
+#[async_trait]
+impl MessagePublisher for FanOutMessagePublisher {
+    async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+        let wrapped_message = MessageWrapper::new(message);
+        let mut errors: Vec<PublishError> = Vec::new();
+
+        for target in &self.targets {
+            if let Err(e) = target.deliver(&wrapped_message).await {
+                errors.push(e);
+            }
+        }
+
+        combine_publish_errors(errors)
+    }
+
+    async fn flush(&self) -> Result<(), PublishError> {
+        let mut errors: Vec<PublishError> = Vec::new();
+
+        for target in &self.targets {
+            if let Err(e) = target.flush().await {
+                errors.push(e);
+            }
+        }
+
+        combine_publish_errors(errors)
+    }
+
+    async fn health_check(&self) -> Result<(), PublishError> {
+        let mut errors: Vec<PublishError> = Vec::new();
+
+        for target in &self.targets {
+            if let Err(e) = target.health_check().await {
+                errors.push(e);
+            }
+        }
+
+        combine_publish_errors(errors)
+    }
+}
+
+/// The backends events can be published to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PublisherBackend {
+    EventBridge,
+    Sns,
+    Sqs,
+}
+
+impl PublisherBackend {
+    /// Parse a comma separated list of backends, such as `eventbridge,sqs`.
+    pub fn parse_list(backends: &str) -> Result<Vec<PublisherBackend>, String> {
+        backends
+            .split(',')
+            .map(|backend| backend.trim().to_lowercase())
+            .filter(|backend| !backend.is_empty())
+            .map(|backend| match backend.as_str() {
+                "eventbridge" => Ok(PublisherBackend::EventBridge),
+                "sns" => Ok(PublisherBackend::Sns),
+                "sqs" => Ok(PublisherBackend::Sqs),
+                _ => Err(format!(
+                    "'{}' is not a supported publisher, use eventbridge, sns or sqs",
+                    backend
+                )),
+            })
+            .collect()
+    }
+}
+
+/// Build the publisher selected by `MESSAGE_PUBLISHERS`, a comma separated list of backends
+/// that defaults to `eventbridge`. When more than one backend is selected every event is sent
+/// to each of them. `SNS_ENDPOINT` and `SQS_ENDPOINT` override the service endpoints, for
+/// example to point at a local stand-in. Every event is also queued on `WEBHOOK_QUEUE_URL` for
+/// its owner's webhooks. Returns an error if the list is invalid or a selected backend, or the
+/// webhook queue, is missing its configuration.
+pub fn message_publisher_from_env(
+    config: &SdkConfig,
+) -> Result<Arc<dyn MessagePublisher + Send + Sync>, String> {
+    let backends = PublisherBackend::parse_list(
+        &env::var("MESSAGE_PUBLISHERS").unwrap_or_else(|_| String::from("eventbridge")),
+    )?;
+
+    let dead_letter_sink: Arc<dyn DeadLetterSink + Send + Sync> =
+        Arc::new(FileDeadLetterSink::from_env());
+
+    let mut targets: Vec<Arc<dyn PublishTarget + Send + Sync>> = backends
+        .iter()
+        .map(|backend| -> Result<Arc<dyn PublishTarget + Send + Sync>, String> {
+            Ok(match backend {
+                PublisherBackend::EventBridge => Arc::new(EventBridgeEventPublisher::new(
+                    aws_sdk_eventbridge::Client::new(config),
+                )),
+                PublisherBackend::Sns => Arc::new(SnsMessagePublisher::new(
+                    AwsQueryClient::from_config(config, "sns", env::var("SNS_ENDPOINT").ok())?,
+                    required_env("SNS_TOPIC_ARN")?,
+                    RetryPolicy::default(),
+                    dead_letter_sink.clone(),
+                )),
+                PublisherBackend::Sqs => Arc::new(SqsFifoMessagePublisher::new(
+                    AwsQueryClient::from_config(config, "sqs", env::var("SQS_ENDPOINT").ok())?,
+                    required_env("SQS_QUEUE_URL")?,
+                    RetryPolicy::default(),
+                    dead_letter_sink.clone(),
+                )),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    targets.push(Arc::new(WebhookPublisher::new(Arc::new(SqsWebhookQueue::new(
+        AwsQueryClient::from_config(config, "sqs", env::var("SQS_ENDPOINT").ok())?,
+        required_env("WEBHOOK_QUEUE_URL")?,
+        RetryPolicy::default(),
+        dead_letter_sink,
+    )))));
+
+    Ok(Arc::new(FanOutMessagePublisher::new(targets)))
+}
+
+fn required_env(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("{} must be set", name))
+}
+
+/// Log every event and send it to its owner's webhooks in the background, used when running
+/// locally.
+pub fn local_message_publisher(
+    webhook_repo: Arc<dyn WebhookRepo + Send + Sync>,
+) -> Arc<dyn MessagePublisher + Send + Sync> {
+    Arc::new(FanOutMessagePublisher::new(vec![
+        Arc::new(InMemoryMessagePublisher::new()),
+        Arc::new(WebhookPublisher::new(Arc::new(InProcessWebhookQueue::new(
+            Arc::new(WebhookDispatcher::from_env(webhook_repo)),
+        )))),
+    ]))
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{
+        public_addresses, sign_webhook, BufferedEvent, DeadLetterSink, EventBridgeEventPublisher,
+        FanOutMessagePublisher, HttpWebhookSender, MessagePublisher, MessageWrapper,
+        PublishError, PublisherBackend, PutEventsSender, QueuedWebhookBatch, RetryPolicy,
+        SnsMessagePublisher, SqsFifoMessagePublisher, WebhookDispatcher, WebhookEvent,
+        WebhookPublisher, WebhookQueue, WebhookSender, with_event_buffer, MAX_BATCH_ENTRIES,
+    };
+    use crate::application::adapters::InMemoryToDoRepo;
+    use crate::application::aws_query::AwsQueryClient;
+    use crate::application::domain::TenantId;
+    use crate::application::events::{MessageType, ToDoCompleted, ToDoCreated};
+    use crate::application::webhooks::{Webhook, WebhookRepo};
+    use async_trait::async_trait;
+    use aws_credential_types::provider::SharedCredentialsProvider;
+    use aws_credential_types::Credentials;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Fails the entries at the given positions for a number of calls, then succeeds.
+    struct FakeSender {
+        failing_positions: Vec<usize>,
+        failing_calls: usize,
+        service_error: bool,
+        calls: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl PutEventsSender for FakeSender {
+        async fn put_events(
+            &self,
+            _event_bus_name: &str,
+            events: &[BufferedEvent],
+        ) -> Result<Vec<Option<String>>, String> {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push(events.len());
+
+            if self.service_error {
+                return Err(String::from("Service unavailable"));
+            }
+
+            let failing = calls.len() <= self.failing_calls;
+
+            Ok((0..events.len())
+                .map(
+                    |position| match failing && self.failing_positions.contains(&position) {
+                        true => Some(String::from("ThrottlingException")),
+                        false => None,
+                    },
+                )
+                .collect())
+        }
+
+        async fn describe_event_bus(&self, _event_bus_name: &str) -> Result<(), String> {
+            match self.service_error {
+                true => Err(String::from("Service unavailable")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    struct InMemoryDeadLetterSink {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl DeadLetterSink for InMemoryDeadLetterSink {
+        fn spill(&self, events: &[String]) -> Result<(), std::io::Error> {
+            self.events.lock().unwrap().extend_from_slice(events);
+            Ok(())
+        }
+    }
+
+    fn no_delay() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    fn publisher(
+        sender: Arc<FakeSender>,
+        dead_letter_sink: Arc<InMemoryDeadLetterSink>,
+    ) -> EventBridgeEventPublisher {
+        EventBridgeEventPublisher::with_sender(
+            sender,
+            dead_letter_sink,
+            String::from("default"),
+            no_delay(),
+        )
+    }
+
+    fn fake_sender(failing_positions: Vec<usize>, failing_calls: usize) -> Arc<FakeSender> {
+        Arc::new(FakeSender {
+            failing_positions,
+            failing_calls,
+            service_error: false,
+            calls: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn dead_letter_sink() -> Arc<InMemoryDeadLetterSink> {
+        Arc::new(InMemoryDeadLetterSink {
+            events: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn created() -> MessageType {
+        MessageType::Created(ToDoCreated::new("id", "owner", "acme"))
+    }
+
+    #[test]
+    fn metadata_should_include_tenant() {
+        let message = serde_json::json!(MessageWrapper::new(created()));
+
+        assert_eq!(message["metadata"]["tenant_id"], "acme");
+        assert_eq!(message["metadata"]["event_type"], "ToDoCreated");
+    }
+
+    #[tokio::test]
+    async fn publish_should_buffer_until_flushed() {
+        with_event_buffer(async {
+            let sender = fake_sender(vec![], 0);
+            let publisher = publisher(sender.clone(), dead_letter_sink());
+
+            publisher.publish(created()).await.unwrap();
+            publisher.publish(created()).await.unwrap();
+
+            assert!(sender.calls.lock().unwrap().is_empty());
+
+            publisher.flush().await.unwrap();
+
+            assert_eq!(*sender.calls.lock().unwrap(), vec![2]);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn publish_should_send_when_batch_is_full() {
+        with_event_buffer(async {
+            let sender = fake_sender(vec![], 0);
+            let publisher = publisher(sender.clone(), dead_letter_sink());
+
+            for _ in 0..MAX_BATCH_ENTRIES + 1 {
+                publisher.publish(created()).await.unwrap();
+            }
+
+            assert_eq!(*sender.calls.lock().unwrap(), vec![MAX_BATCH_ENTRIES]);
+
+            publisher.flush().await.unwrap();
+
+            assert_eq!(*sender.calls.lock().unwrap(), vec![MAX_BATCH_ENTRIES, 1]);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn flush_should_only_retry_failed_entries() {
+        with_event_buffer(async {
+            let sender = fake_sender(vec![1, 3], 1);
+            let sink = dead_letter_sink();
+            let publisher = publisher(sender.clone(), sink.clone());
+
+            for _ in 0..5 {
+                publisher.publish(created()).await.unwrap();
+            }
+
+            publisher.flush().await.unwrap();
+
+            assert_eq!(*sender.calls.lock().unwrap(), vec![5, 2]);
+            assert!(sink.events.lock().unwrap().is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn flush_should_spill_undeliverable_events_to_dead_letter_sink() {
+        with_event_buffer(async {
+            let sender = fake_sender(vec![0], 10);
+            let sink = dead_letter_sink();
+            let publisher = publisher(sender.clone(), sink.clone());
+
+            publisher.publish(created()).await.unwrap();
+            publisher.publish(created()).await.unwrap();
+
+            let res = publisher.flush().await;
+
+            assert_eq!(res.unwrap_err().undelivered_events(), 1);
+            assert_eq!(*sender.calls.lock().unwrap(), vec![2, 1, 1]);
+            assert_eq!(sink.events.lock().unwrap().len(), 1);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn service_error_should_return_error_instead_of_panicking() {
+        with_event_buffer(async {
+            let sender = Arc::new(FakeSender {
+                failing_positions: vec![],
+                failing_calls: 0,
+                service_error: true,
+                calls: Mutex::new(Vec::new()),
+            });
+            let sink = dead_letter_sink();
+            let publisher = publisher(sender.clone(), sink.clone());
+
+            publisher.publish(created()).await.unwrap();
+
+            let res = publisher.flush().await;
+
+            assert!(res.is_err());
+            assert_eq!(sender.calls.lock().unwrap().len(), 3);
+            assert_eq!(sink.events.lock().unwrap().len(), 1);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn flush_should_only_send_events_of_its_own_buffer() {
+        let sender = fake_sender(vec![], 0);
+        let publisher = Arc::new(publisher(sender.clone(), dead_letter_sink()));
+
+        let (first, second) = tokio::join!(
+            with_event_buffer({
+                let publisher = publisher.clone();
+
+                async move {
+                    publisher.publish(created()).await.unwrap();
+                    tokio::task::yield_now().await;
+                    publisher.flush().await
+                }
+            }),
+            with_event_buffer({
+                let publisher = publisher.clone();
+
+                async move {
+                    publisher.publish(created()).await.unwrap();
+                    publisher.publish(created()).await.unwrap();
+                    tokio::task::yield_now().await;
+                    publisher.flush().await
+                }
+            })
+        );
+
+        assert!(first.is_ok() && second.is_ok());
+
+        let mut calls = sender.calls.lock().unwrap().clone();
+        calls.sort();
+
+        assert_eq!(calls, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn publish_without_buffer_should_send_straight_away() {
+        let sender = fake_sender(vec![], 0);
+        let publisher = publisher(sender.clone(), dead_letter_sink());
+
+        publisher.publish(created()).await.unwrap();
+
+        assert_eq!(*sender.calls.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn query_client_without_credentials_should_return_error() {
+        let config = aws_config::SdkConfig::builder().build();
+
+        assert!(AwsQueryClient::from_config(&config, "sns", None).is_err());
+    }
+
+    #[test]
+    fn oversized_events_should_not_be_batched() {
+        let (batches, oversized) = super::batch_events(vec![
+            BufferedEvent {
+                detail_type: String::from("ToDoCreated"),
+                detail: "a".repeat(300 * 1024),
+            },
+            BufferedEvent {
+                detail_type: String::from("ToDoCreated"),
+                detail: "a".repeat(200 * 1024),
+            },
+            BufferedEvent {
+                detail_type: String::from("ToDoCreated"),
+                detail: "a".repeat(100 * 1024),
+            },
+        ]);
+
+        assert_eq!(oversized.len(), 1);
+        assert_eq!(batches.len(), 2);
+    }
+
+    // A local stand-in for the SNS and SQS endpoints, responding to every call with `status`.
+    async fn stand_in_endpoint(status: u16) -> MockServer {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    fn query_client(server: &MockServer, service: &'static str) -> AwsQueryClient {
+        AwsQueryClient::new(
+            SharedCredentialsProvider::new(Credentials::new("test", "test", None, None, "test")),
+            "us-east-1",
+            service,
+            &server.uri(),
+        )
+    }
+
+    async fn received_forms(server: &MockServer) -> Vec<HashMap<String, String>> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_urlencoded::from_bytes(&request.body).unwrap())
+            .collect()
+    }
+
+    fn event_id(body: &str) -> String {
+        let message: serde_json::Value = serde_json::from_str(body).unwrap();
+
+        message["metadata"]["event_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn sqs_publisher(
+        server: &MockServer,
+        sink: Arc<InMemoryDeadLetterSink>,
+    ) -> SqsFifoMessagePublisher {
+        SqsFifoMessagePublisher::new(
+            query_client(server, "sqs"),
+            String::from("https://sqs.us-east-1.amazonaws.com/123456789012/todo.fifo"),
+            no_delay(),
+            sink,
+        )
+    }
+
+    fn sns_publisher(
+        server: &MockServer,
+        sink: Arc<InMemoryDeadLetterSink>,
+    ) -> SnsMessagePublisher {
+        SnsMessagePublisher::new(
+            query_client(server, "sns"),
+            String::from("arn:aws:sns:us-east-1:123456789012:todo"),
+            no_delay(),
+            sink,
+        )
+    }
+
+    #[tokio::test]
+    async fn sqs_publisher_should_group_by_owner_and_deduplicate_on_event_id() {
+        let server = stand_in_endpoint(200).await;
+        let publisher = sqs_publisher(&server, dead_letter_sink());
+
+        publisher.publish(created()).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let forms = received_forms(&server).await;
+
+        assert_eq!(forms.len(), 1);
+        assert_eq!(forms[0]["Action"], "SendMessage");
+        assert_eq!(forms[0]["MessageGroupId"], "owner");
+        assert_eq!(
+            forms[0]["MessageDeduplicationId"],
+            event_id(&forms[0]["MessageBody"])
+        );
+        assert!(requests[0].headers["authorization"]
+            .to_str()
+            .unwrap()
+            .starts_with("AWS4-HMAC-SHA256"));
+    }
+
+    #[tokio::test]
+    async fn sns_publisher_should_publish_to_topic_with_event_type() {
+        let server = stand_in_endpoint(200).await;
+        let publisher = sns_publisher(&server, dead_letter_sink());
+
+        publisher.publish(created()).await.unwrap();
+
+        let forms = received_forms(&server).await;
+
+        assert_eq!(forms[0]["Action"], "Publish");
+        assert_eq!(
+            forms[0]["TopicArn"],
+            "arn:aws:sns:us-east-1:123456789012:todo"
+        );
+        assert_eq!(
+            forms[0]["MessageAttributes.entry.1.Value.StringValue"],
+            "ToDoCreated"
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_endpoint_should_spill_to_dead_letter_sink() {
+        let server = stand_in_endpoint(500).await;
+        let sink = dead_letter_sink();
+        let publisher = sqs_publisher(&server, sink.clone());
+
+        let res = publisher.publish(created()).await;
+
+        assert_eq!(res.unwrap_err().undelivered_events(), 1);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+        assert_eq!(sink.events.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fan_out_should_send_the_same_event_to_every_backend() {
+        let sns_server = stand_in_endpoint(200).await;
+        let sqs_server = stand_in_endpoint(200).await;
+
+        let publisher = FanOutMessagePublisher::new(vec![
+            Arc::new(sns_publisher(&sns_server, dead_letter_sink())),
+            Arc::new(sqs_publisher(&sqs_server, dead_letter_sink())),
+        ]);
+
+        publisher.publish(created()).await.unwrap();
+        publisher.flush().await.unwrap();
+
+        let sns_forms = received_forms(&sns_server).await;
+        let sqs_forms = received_forms(&sqs_server).await;
+
+        assert_eq!(
+            event_id(&sns_forms[0]["Message"]),
+            event_id(&sqs_forms[0]["MessageBody"])
+        );
+    }
+
+    #[tokio::test]
+    async fn fan_out_should_continue_when_a_backend_fails() {
+        let sns_server = stand_in_endpoint(500).await;
+        let sqs_server = stand_in_endpoint(200).await;
+
+        let publisher = FanOutMessagePublisher::new(vec![
+            Arc::new(sns_publisher(&sns_server, dead_letter_sink())),
+            Arc::new(sqs_publisher(&sqs_server, dead_letter_sink())),
+        ]);
+
+        let res = publisher.publish(created()).await;
+
+        assert_eq!(res.unwrap_err().undelivered_events(), 1);
+        assert_eq!(received_forms(&sqs_server).await.len(), 1);
+    }
+
+    #[test]
+    fn parse_backends_should_accept_supported_backends() {
+        assert_eq!(
+            PublisherBackend::parse_list("EventBridge, sqs").unwrap(),
+            vec![PublisherBackend::EventBridge, PublisherBackend::Sqs]
+        );
+        assert!(PublisherBackend::parse_list("kafka").is_err());
+    }
+
+    // The headers and body of a webhook request.
+    type WebhookRequest = (Vec<(String, String)>, String);
+
+    /// Responds to each webhook request with the next status in `statuses`, repeating the last.
+    struct FakeWebhookSender {
+        statuses: Vec<u16>,
+        requests: Mutex<Vec<WebhookRequest>>,
+    }
+
+    #[async_trait]
+    impl WebhookSender for FakeWebhookSender {
+        async fn send(
+            &self,
+            _url: &str,
+            headers: &[(String, String)],
+            body: &str,
+        ) -> Result<u16, String> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push((headers.to_vec(), body.to_string()));
+
+            Ok(self.statuses[(requests.len() - 1).min(self.statuses.len() - 1)])
+        }
+    }
+
+    fn webhook_sender(statuses: Vec<u16>) -> Arc<FakeWebhookSender> {
+        Arc::new(FakeWebhookSender {
+            statuses,
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    async fn registered_webhook(repo: &InMemoryToDoRepo, event_types: &[&str]) -> Webhook {
+        let webhook = Webhook::new(
+            &TenantId::new("acme").unwrap(),
+            "owner",
+            "https://example.com/hooks/todo",
+            event_types.iter().map(|val| val.to_string()).collect(),
+        )
+        .unwrap();
+
+        repo.save_webhook(&webhook).await.unwrap();
+
+        webhook
+    }
+
+    fn webhook_event(message: MessageType) -> WebhookEvent {
+        WebhookEvent::from(&MessageWrapper::new(message))
+    }
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn webhook_delivery_should_be_signed_and_logged() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let webhook = registered_webhook(&repo, &[]).await;
+        let sender = webhook_sender(vec![204]);
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender.clone(), no_delay(), 5);
+
+        let failed = dispatcher.dispatch(&[webhook_event(created())]).await;
+
+        assert!(failed.is_empty());
+
+        let requests = sender.requests.lock().unwrap().clone();
+        let (headers, body) = &requests[0];
+        let timestamp: i64 = header(headers, "X-Todo-Timestamp").parse().unwrap();
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            header(headers, "X-Todo-Signature"),
+            sign_webhook(webhook.secret(), timestamp, body)
+        );
+        assert_eq!(header(headers, "X-Todo-Event-Id"), event_id(body));
+        assert_eq!(header(headers, "X-Todo-Event-Type"), "ToDoCreated");
+
+        let deliveries = repo
+            .list_deliveries(webhook.tenant(), "owner", webhook.id())
+            .await
+            .unwrap();
+
+        assert_eq!(deliveries.len(), 1);
+        assert!(deliveries[0].succeeded());
+        assert_eq!(deliveries[0].status_code, Some(204));
+    }
+
+    #[test]
+    fn signature_should_match_known_value() {
+        assert_eq!(
+            sign_webhook("whsec_test", 1700000000, "{}"),
+            "sha256=35495024f4ef3f94e5a93e22221544c4b75e9a42300cd965ab81cb85cd994e91"
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_webhook_should_be_retried_then_disabled() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let webhook = registered_webhook(&repo, &[]).await;
+        let sender = webhook_sender(vec![503]);
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender.clone(), no_delay(), 2);
+
+        let events: Vec<WebhookEvent> = (0..3).map(|_| webhook_event(created())).collect();
+        dispatcher.dispatch(&events).await;
+
+        // Two deliveries of three attempts each, the third event is not sent once the webhook
+        // has been disabled.
+        assert_eq!(sender.requests.lock().unwrap().len(), 6);
+
+        let stored = repo
+            .get_webhook(webhook.tenant(), "owner", webhook.id())
+            .await
+            .unwrap()
+            .unwrap();
+        let deliveries = repo
+            .list_deliveries(webhook.tenant(), "owner", webhook.id())
+            .await
+            .unwrap();
+
+        assert!(!stored.is_enabled());
+        assert_eq!(stored.consecutive_failures(), 2);
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries[0].attempts, 3);
+        assert_eq!(deliveries[0].status_code, Some(503));
+    }
+
+    #[tokio::test]
+    async fn client_error_should_not_be_retried() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let webhook = registered_webhook(&repo, &[]).await;
+        let sender = webhook_sender(vec![410]);
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender.clone(), no_delay(), 5);
+
+        dispatcher.dispatch(&[webhook_event(created())]).await;
+
+        let deliveries = repo
+            .list_deliveries(webhook.tenant(), "owner", webhook.id())
+            .await
+            .unwrap();
+
+        assert_eq!(sender.requests.lock().unwrap().len(), 1);
+        assert!(!deliveries[0].succeeded());
+    }
+
+    #[tokio::test]
+    async fn webhook_should_only_receive_subscribed_events() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        registered_webhook(&repo, &["ToDoCompleted"]).await;
+        let sender = webhook_sender(vec![200]);
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender.clone(), no_delay(), 5);
+
+        dispatcher
+            .dispatch(&[
+                webhook_event(created()),
+                webhook_event(MessageType::Completed(ToDoCompleted::new("id", "owner", "acme"))),
+                webhook_event(MessageType::Completed(ToDoCompleted::new("id", "owner", "globex"))),
+            ])
+            .await;
+
+        let requests = sender.requests.lock().unwrap();
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(header(&requests[0].0, "X-Todo-Event-Type"), "ToDoCompleted");
+    }
+
+    /// Records every event it is asked to queue.
+    struct FakeWebhookQueue {
+        queued: Mutex<Vec<Vec<WebhookEvent>>>,
+    }
+
+    #[async_trait]
+    impl WebhookQueue for FakeWebhookQueue {
+        async fn enqueue(&self, events: Vec<WebhookEvent>) -> Result<(), PublishError> {
+            self.queued.lock().unwrap().push(events);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn webhook_events_should_only_be_queued_by_their_own_request() {
+        let queue = Arc::new(FakeWebhookQueue {
+            queued: Mutex::new(Vec::new()),
+        });
+        let publisher = Arc::new(WebhookPublisher::new(queue.clone()));
+
+        let (first, second) = tokio::join!(
+            with_event_buffer({
+                let publisher = publisher.clone();
+
+                async move {
+                    publisher.publish(created()).await.unwrap();
+                    tokio::task::yield_now().await;
+                    publisher.flush().await
+                }
+            }),
+            with_event_buffer({
+                let publisher = publisher.clone();
+
+                async move {
+                    publisher.publish(created()).await.unwrap();
+                    publisher.publish(created()).await.unwrap();
+                    tokio::task::yield_now().await;
+                    publisher.flush().await
+                }
+            })
+        );
+
+        assert!(first.is_ok() && second.is_ok());
+
+        let mut queued: Vec<usize> = queue.queued.lock().unwrap().iter().map(Vec::len).collect();
+        queued.sort();
+
+        assert_eq!(queued, vec![1, 2]);
+
+        publisher.publish(created()).await.unwrap();
+
+        assert_eq!(queue.queued.lock().unwrap().len(), 3);
+    }
+
+    /// Changes the webhook the way a concurrent `PUT /webhooks/:id` would, while a delivery to
+    /// it is failing.
+    struct ConcurrentlyUpdatedSender {
+        repo: Arc<InMemoryToDoRepo>,
+        updated: Webhook,
+    }
+
+    #[async_trait]
+    impl WebhookSender for ConcurrentlyUpdatedSender {
+        async fn send(
+            &self,
+            _url: &str,
+            _headers: &[(String, String)],
+            _body: &str,
+        ) -> Result<u16, String> {
+            self.repo.save_webhook(&self.updated).await.unwrap();
+
+            Ok(500)
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_should_keep_concurrent_webhook_changes() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let webhook = registered_webhook(&repo, &[]).await;
+        let updated = webhook
+            .clone()
+            .update_event_types(vec![String::from("ToDoCompleted")])
+            .unwrap();
+        let sender = Arc::new(ConcurrentlyUpdatedSender {
+            repo: repo.clone(),
+            updated,
+        });
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender, no_delay(), 5);
+
+        dispatcher.dispatch(&[webhook_event(created())]).await;
+
+        let stored = repo
+            .get_webhook(webhook.tenant(), "owner", webhook.id())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(stored.event_types(), &[String::from("ToDoCompleted")]);
+        assert_eq!(stored.consecutive_failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_messages_that_cannot_be_read_should_be_reported_as_failed() {
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        registered_webhook(&repo, &[]).await;
+        let sender = webhook_sender(vec![200]);
+        let dispatcher = WebhookDispatcher::with_sender(repo.clone(), sender.clone(), no_delay(), 5);
+
+        let batch: QueuedWebhookBatch = serde_json::from_value(serde_json::json!({
+            "Records": [
+                {
+                    "messageId": "readable",
+                    "body": serde_json::json!(webhook_event(created())).to_string(),
+                },
+                { "messageId": "unreadable", "body": "not an event" },
+            ]
+        }))
+        .unwrap();
+
+        let response = serde_json::json!(dispatcher.deliver_queued(batch).await);
+
+        assert_eq!(
+            response,
+            serde_json::json!({ "batchItemFailures": [{ "itemIdentifier": "unreadable" }] })
+        );
+        assert_eq!(sender.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn webhook_hosts_should_only_resolve_to_public_addresses() {
+        assert!(public_addresses("localhost").await.is_err());
+        assert!(public_addresses("127.0.0.1").await.is_err());
+        assert!(public_addresses("10.0.0.8").await.is_err());
+        assert!(public_addresses("93.184.215.14").await.is_ok());
+
+        let sender = HttpWebhookSender::from_env();
+
+        assert!(sender
+            .send("https://169.254.169.254/latest/meta-data", &[], "{}")
+            .await
+            .unwrap_err()
+            .contains("is not a public address"));
     }
 }
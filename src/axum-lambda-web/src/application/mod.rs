@@ -1,9 +1,19 @@
 pub mod adapters;
+pub mod attachments;
+mod aws_query;
+pub mod cache;
 pub mod commands;
 pub mod domain;
 pub mod error_types;
 mod events;
+pub mod health;
 mod helpers;
+pub(crate) mod markdown;
 pub mod messaging;
 pub mod public_types;
 pub mod queries;
+pub mod reminders;
+mod search;
+pub mod timers;
+pub mod transfer;
+pub mod webhooks;
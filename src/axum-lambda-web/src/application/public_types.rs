@@ -1,5 +1,9 @@
-use crate::application::domain::ToDo;
+use crate::application::domain::{Attachment, Preferences, ToDo};
+use crate::application::markdown::render_description_html;
+use crate::application::timers::TimeEntry;
+use crate::application::webhooks::{Webhook, WebhookDelivery};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct ToDoItem {
@@ -9,6 +13,30 @@ pub struct ToDoItem {
     pub completed_on: String,
     pub description: String,
     pub due_date: String,
+    #[serde(default)]
+    pub priority: String,
+    /// The ToDo's place in its owner's list, empty until the ToDo has been placed.
+    #[serde(default)]
+    pub position: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentItem>,
+    /// The total of the ToDo's stopped timers, in seconds.
+    #[serde(default)]
+    pub tracked_seconds: u64,
+}
+
+impl ToDoItem {
+    /// Include the description rendered from CommonMark to sanitised HTML.
+    pub fn with_description_html(self) -> Self {
+        let description_html = render_description_html(&self.description);
+
+        Self {
+            description_html: Some(description_html),
+            ..self
+        }
+    }
 }
 
 impl From<ToDo> for ToDoItem {
@@ -22,16 +50,38 @@ impl From<ToDo> for ToDoItem {
             title: value.get_title().to_string(),
             description: value.get_description().to_string(),
             due_date: value.get_due_date(),
+            priority: value.get_priority().as_str().to_string(),
+            position: value
+                .get_position()
+                .map(|position| position.to_string().into())
+                .unwrap_or_default(),
             completed_on: value.get_completed_on(),
+            description_html: None,
+            attachments: value
+                .get_attachments()
+                .iter()
+                .map(AttachmentItem::from)
+                .collect(),
+            tracked_seconds: value.get_tracked_seconds(),
         }
     }
 }
 
+/// The ToDos listed for an owner. `skipped` counts the stored ToDos that could not be read and
+/// were left out, so a short list can be told apart from an empty one.
+#[derive(Deserialize, Serialize, Default)]
+pub struct ToDoListing {
+    pub items: Vec<ToDoItem>,
+    pub skipped: usize,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct CreateToDoCommand {
     pub title: String,
     pub description: Option<String>,
     pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -40,4 +90,207 @@ pub struct UpdateToDoCommand {
     pub set_as_complete: bool,
     pub description: Option<String>,
     pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+/// Place a ToDo directly before or directly after another ToDo, only one can be set.
+#[derive(Deserialize, Serialize)]
+pub struct MoveToDoCommand {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Attach a file to a ToDo. The size is the exact size of the file in bytes, the upload must
+/// match it and the content type.
+#[derive(Deserialize, Serialize)]
+pub struct AddAttachmentCommand {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct AttachmentItem {
+    pub id: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+    pub added_on: String,
+    /// `None` until the upload is confirmed.
+    pub confirmed_on: Option<String>,
+}
+
+impl From<&Attachment> for AttachmentItem {
+    fn from(value: &Attachment) -> Self {
+        AttachmentItem {
+            id: value.id().to_string(),
+            file_name: value.file_name().to_string(),
+            content_type: value.content_type().to_string(),
+            size: value.size(),
+            added_on: value.added_on().to_rfc3339(),
+            confirmed_on: value
+                .confirmed_on()
+                .map(|confirmed_on| confirmed_on.to_rfc3339()),
+        }
+    }
+}
+
+/// Where to upload a new attachment. The upload is a `PUT` of the file to `upload_url`, sending
+/// every header in `headers` unchanged.
+#[derive(Deserialize, Serialize, Default)]
+pub struct AttachmentUpload {
+    pub attachment: AttachmentItem,
+    pub upload_url: String,
+    pub headers: BTreeMap<String, String>,
+    pub expires_on: String,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct AttachmentDownload {
+    pub attachment: AttachmentItem,
+    pub download_url: String,
+    pub expires_on: String,
+}
+
+/// A timer started on a ToDo, `stopped_on` is empty while the timer is running.
+#[derive(Deserialize, Serialize, Default)]
+pub struct TimeEntryItem {
+    pub id: String,
+    pub to_do_id: String,
+    pub started_on: String,
+    pub stopped_on: String,
+    pub duration_seconds: u64,
+    pub is_running: bool,
+}
+
+impl From<&TimeEntry> for TimeEntryItem {
+    fn from(value: &TimeEntry) -> Self {
+        TimeEntryItem {
+            id: value.id().to_string(),
+            to_do_id: value.to_do_id().to_string(),
+            started_on: value.started_on().to_rfc3339(),
+            stopped_on: value
+                .stopped_on()
+                .map(|val| val.to_rfc3339())
+                .unwrap_or_default(),
+            duration_seconds: value.duration_seconds(),
+            is_running: value.is_running(),
+        }
+    }
+}
+
+/// Subscribe an HTTPS endpoint to events about the owner's ToDos. Leave `event_types` empty to
+/// receive every event.
+#[derive(Deserialize, Serialize)]
+pub struct RegisterWebhookCommand {
+    pub url: String,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+/// Change a webhook, only the fields that are set are changed. Enabling a webhook that was
+/// disabled after failing clears its failure count.
+#[derive(Deserialize, Serialize)]
+pub struct UpdateWebhookCommand {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct WebhookItem {
+    pub id: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+    pub consecutive_failures: u32,
+    pub created_on: String,
+    /// The secret deliveries are signed with, only returned when the webhook is registered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl WebhookItem {
+    pub fn with_secret(self, webhook: &Webhook) -> Self {
+        Self {
+            secret: Some(webhook.secret().to_string()),
+            ..self
+        }
+    }
+}
+
+impl From<&Webhook> for WebhookItem {
+    fn from(value: &Webhook) -> Self {
+        WebhookItem {
+            id: value.id().to_string(),
+            url: value.url().to_string(),
+            event_types: value.event_types().to_vec(),
+            enabled: value.is_enabled(),
+            consecutive_failures: value.consecutive_failures(),
+            created_on: value.created_on().to_rfc3339(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct WebhookDeliveryItem {
+    pub id: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub delivered_on: String,
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub succeeded: bool,
+}
+
+impl From<&WebhookDelivery> for WebhookDeliveryItem {
+    fn from(value: &WebhookDelivery) -> Self {
+        WebhookDeliveryItem {
+            id: value.delivery_id.clone(),
+            event_id: value.event_id.clone(),
+            event_type: value.event_type.clone(),
+            delivered_on: value.delivered_on.to_rfc3339(),
+            attempts: value.attempts,
+            status_code: value.status_code,
+            error: value.error.clone(),
+            succeeded: value.succeeded(),
+        }
+    }
+}
+
+/// Settings that apply to all of an owner's ToDos.
+#[derive(Deserialize, Serialize, Default)]
+pub struct UserPreferences {
+    /// The IANA timezone, such as `Europe/London`, used to decide which day it is for the owner.
+    pub timezone: String,
+    /// How many minutes before a ToDo is due to be reminded about it, up to a day. The
+    /// scheduler's lead time is used when this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_lead_time_minutes: Option<i64>,
+}
+
+impl From<Preferences> for UserPreferences {
+    fn from(preferences: Preferences) -> Self {
+        UserPreferences {
+            timezone: preferences.timezone.name().to_string(),
+            reminder_lead_time_minutes: preferences
+                .reminder_lead_time
+                .map(|lead_time| lead_time.minutes()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ImportReport {
+    pub imported: Vec<ToDoItem>,
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub message: String,
 }
@@ -1,44 +1,331 @@
-use crate::application::domain::ToDoRepo;
-use crate::application::public_types::ToDoItem;
+use crate::application::attachments::AttachmentStore;
+use crate::application::domain::{
+    sort_by_position, DueDateView, TenantId, ToDo, ToDoList, ToDoPage, ToDoRepo,
+};
+use crate::application::error_types::{RepositoryError, ServiceError};
+use crate::application::public_types::{
+    AttachmentDownload, AttachmentItem, ToDoItem, ToDoListing, UserPreferences,
+    WebhookDeliveryItem, WebhookItem,
+};
+use crate::application::search::{query_terms, rank};
+use crate::application::transfer::{
+    render_export_footer, render_export_header, render_export_item, TransferFormat,
+};
+use crate::application::webhooks::WebhookRepo;
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+// How many ToDos are read from the store for every chunk of an export.
+const EXPORT_PAGE_SIZE: usize = 100;
+
+// How many matches a search returns when no limit is given, and the most it will return.
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const MAX_SEARCH_LIMIT: usize = 100;
+
 pub async fn list_todos(
     owner: &str,
+    tenant: &TenantId,
     client: &Arc<dyn ToDoRepo + Send + Sync>,
-) -> Result<Vec<ToDoItem>, ()> {
-    let query_res = client.list(owner).await;
+) -> Result<ToDoListing, ()> {
+    let query_res = client.list(tenant, owner).await;
 
     match query_res {
-        Ok(todos) => {
+        Ok(list) => {
+            let (mut todos, skipped) = report_skipped(list);
+            sort_by_position(&mut todos);
+
             let mut to_do_items: Vec<ToDoItem> = Vec::new();
 
             for todo in todos {
                 to_do_items.push(ToDoItem::from(todo));
             }
 
-            Ok(to_do_items)
+            Ok(ToDoListing {
+                items: to_do_items,
+                skipped,
+            })
         }
         Err(_) => Err(()),
     }
 }
 
+/// List the owner's incomplete ToDos that fall into the view at `now`, soonest due first. The
+/// owner's timezone decides which day it is.
+pub async fn list_todos_in_view(
+    owner: &str,
+    tenant: &TenantId,
+    view: DueDateView,
+    now: DateTime<Utc>,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<ToDoListing, ()> {
+    let timezone = match client.get_preferences(tenant, owner).await {
+        Ok(preferences) => preferences.timezone.tz(),
+        Err(_) => return Err(()),
+    };
+
+    let (todos, skipped) = match client.list(tenant, owner).await {
+        Ok(list) => report_skipped(list),
+        Err(_) => return Err(()),
+    };
+
+    let mut in_view: Vec<ToDo> = todos
+        .into_iter()
+        .filter(|todo| matches!(todo, ToDo::Incomplete(_)))
+        .filter(|todo| {
+            todo.get_due()
+                .is_some_and(|due_date| due_date.view_at(now, timezone) == view)
+        })
+        .collect();
+
+    in_view.sort_by_key(|todo| todo.get_due().map(|due_date| due_date.deadline_in(timezone)));
+
+    Ok(ToDoListing {
+        items: in_view.into_iter().map(ToDoItem::from).collect(),
+        skipped,
+    })
+}
+
+// Items that could not be read are left out of the results rather than failing the whole
+// list, they are logged so they can be found and repaired and counted for the caller.
+fn report_skipped(list: ToDoList) -> (Vec<ToDo>, usize) {
+    for e in &list.skipped {
+        tracing::warn!(item_key = e.item_key(), "Skipping unreadable ToDo: {}", e.message());
+    }
+
+    (list.todos, list.skipped.len())
+}
+
+pub async fn get_preferences(
+    owner: &str,
+    tenant: &TenantId,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<UserPreferences, ()> {
+    match client.get_preferences(tenant, owner).await {
+        Ok(preferences) => Ok(UserPreferences::from(preferences)),
+        Err(_) => Err(()),
+    }
+}
+
 pub async fn get_todos(
     owner: &str,
+    tenant: &TenantId,
     to_do_id: &str,
     client: &Arc<dyn ToDoRepo + Send + Sync>,
-) -> Result<ToDoItem, ()> {
-    let query_res = client.get(owner, to_do_id).await;
+) -> Result<ToDoItem, RepositoryError> {
+    let todo = client.get(tenant, owner, to_do_id).await?;
 
-    match query_res {
-        Ok(todo) => Ok(ToDoItem::from(todo)),
-        Err(_) => Err(()),
+    Ok(ToDoItem::from(todo))
+}
+
+/// The files attached to a ToDo, each with a presigned URL it can be downloaded from.
+pub async fn list_attachments(
+    owner: &str,
+    tenant: &TenantId,
+    to_do_id: &str,
+    attachment_store: &AttachmentStore,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<Vec<AttachmentDownload>, ServiceError> {
+    let to_do = client.get(tenant, owner, to_do_id).await?;
+
+    let mut downloads: Vec<AttachmentDownload> = Vec::new();
+
+    // Pending attachments may never have been uploaded, so there may be nothing to download.
+    for attachment in to_do
+        .get_attachments()
+        .iter()
+        .filter(|attachment| attachment.is_confirmed())
+    {
+        let download = attachment_store
+            .presign_download(tenant, to_do.get_owner(), to_do.get_id(), attachment)
+            .await?;
+
+        downloads.push(AttachmentDownload {
+            attachment: AttachmentItem::from(attachment),
+            download_url: download.url,
+            expires_on: download.expires_on.to_rfc3339(),
+        });
     }
+
+    Ok(downloads)
+}
+
+pub async fn list_webhooks(
+    owner: &str,
+    tenant: &TenantId,
+    client: &Arc<dyn WebhookRepo + Send + Sync>,
+) -> Result<Vec<WebhookItem>, ()> {
+    match client.list_webhooks(tenant, owner).await {
+        Ok(webhooks) => Ok(webhooks.iter().map(WebhookItem::from).collect()),
+        Err(e) => {
+            tracing::error!("Failed to list webhooks: {}", e);
+            Err(())
+        }
+    }
+}
+
+/// The most recent deliveries to one of the owner's webhooks, newest first. Returns an error if
+/// the owner has no such webhook.
+pub async fn list_webhook_deliveries(
+    owner: &str,
+    tenant: &TenantId,
+    webhook_id: &str,
+    client: &Arc<dyn WebhookRepo + Send + Sync>,
+) -> Result<Vec<WebhookDeliveryItem>, ()> {
+    match client.get_webhook(tenant, owner, webhook_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(()),
+        Err(e) => {
+            tracing::error!("Failed to load webhook: {}", e);
+            return Err(());
+        }
+    }
+
+    match client.list_deliveries(tenant, owner, webhook_id).await {
+        Ok(deliveries) => Ok(deliveries.iter().map(WebhookDeliveryItem::from).collect()),
+        Err(e) => {
+            tracing::error!("Failed to list webhook deliveries: {}", e);
+            Err(())
+        }
+    }
+}
+
+/// Search the owner's ToDos, returning up to `limit` of the best matches, best first. Without
+/// a `limit` the top `DEFAULT_SEARCH_LIMIT` are returned, and never more than
+/// `MAX_SEARCH_LIMIT`.
+pub async fn search_todos(
+    owner: &str,
+    tenant: &TenantId,
+    query: &str,
+    limit: Option<usize>,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<Vec<ToDoItem>, ()> {
+    let terms = query_terms(query);
+
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let postings = match client.search(tenant, owner, &terms).await {
+        Ok(postings) => postings,
+        Err(_) => return Err(()),
+    };
+
+    let ranked: Vec<String> = rank(postings)
+        .into_iter()
+        .take(limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT))
+        .collect();
+
+    // Skipped, the same as they would be when listing the owner's ToDos.
+    let (todos, _) = match client.get_many(tenant, owner, &ranked).await {
+        Ok(list) => report_skipped(list),
+        Err(_) => return Err(()),
+    };
+
+    // The ToDos are read in no particular order, put them back in the order they ranked.
+    let mut by_id: HashMap<String, ToDo> = todos
+        .into_iter()
+        .map(|todo| (todo.get_id().to_uppercase(), todo))
+        .collect();
+
+    Ok(ranked
+        .iter()
+        .filter_map(|to_do_id| by_id.remove(&to_do_id.to_uppercase()))
+        .map(ToDoItem::from)
+        .collect())
+}
+
+struct ExportProgress {
+    client: Arc<dyn ToDoRepo + Send + Sync>,
+    owner: String,
+    tenant: TenantId,
+    format: TransferFormat,
+    page: Option<ToDoPage>,
+    next_page: Option<String>,
+    started: bool,
+    exported_any: bool,
+}
+
+/// Export the owner's ToDos in the order they are stored, reading them a page at a time and
+/// yielding a chunk for every page as it is read. The first page is read up front, so a store
+/// that can't be reached is an error here rather than an empty export. A page that fails after
+/// that ends the stream with the error.
+pub async fn export_todos(
+    owner: &str,
+    tenant: &TenantId,
+    format: TransferFormat,
+    client: &Arc<dyn ToDoRepo + Send + Sync>,
+) -> Result<impl Stream<Item = Result<String, RepositoryError>> + Send + 'static, RepositoryError> {
+    let first_page = client.list_page(tenant, owner, None, EXPORT_PAGE_SIZE).await?;
+
+    let progress = ExportProgress {
+        client: client.clone(),
+        owner: owner.to_string(),
+        tenant: tenant.clone(),
+        format,
+        page: Some(first_page),
+        next_page: None,
+        started: false,
+        exported_any: false,
+    };
+
+    Ok(stream::unfold(Some(progress), |progress| async move {
+        let mut progress = progress?;
+
+        let page = match progress.page.take() {
+            Some(page) => page,
+            None => match progress
+                .client
+                .list_page(
+                    &progress.tenant,
+                    &progress.owner,
+                    progress.next_page.as_deref(),
+                    EXPORT_PAGE_SIZE,
+                )
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => return Some((Err(e), None)),
+            },
+        };
+
+        let mut chunk = String::new();
+
+        if !progress.started {
+            chunk.extend(render_export_header(progress.format));
+            progress.started = true;
+        }
+
+        let (todos, _) = report_skipped(page.list);
+
+        for todo in todos {
+            chunk.push_str(&render_export_item(
+                progress.format,
+                &ToDoItem::from(todo),
+                !progress.exported_any,
+            ));
+            progress.exported_any = true;
+        }
+
+        match page.next_page {
+            Some(next_page) => {
+                progress.next_page = Some(next_page);
+                Some((Ok(chunk), Some(progress)))
+            }
+            None => {
+                chunk.extend(render_export_footer(progress.format));
+                Some((Ok(chunk), None))
+            }
+        }
+    }))
 }
 
 /// Unit tests
 ///
 /// These tests are run using the `cargo test` command.
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
 mod tests {
     use async_trait::async_trait;
     use chrono::{DateTime, Utc};
@@ -46,9 +333,20 @@ mod tests {
 
     use crate::application::domain::AppState;
     use crate::application::messaging::InMemoryMessagePublisher;
-    use crate::application::queries::{get_todos, list_todos};
+    use crate::application::adapters::InMemoryToDoRepo;
+    use crate::application::public_types::ToDoItem;
+    use crate::application::queries::{
+        export_todos, get_todos, list_todos, report_skipped, search_todos, DEFAULT_SEARCH_LIMIT,
+        EXPORT_PAGE_SIZE, MAX_SEARCH_LIMIT,
+    };
+    use crate::application::transfer::TransferFormat;
+    use futures_util::StreamExt;
+    use crate::application::search::SearchPosting;
     use crate::application::{
-        domain::{OwnerId, Title, ToDo, ToDoId, ToDoRepo},
+        domain::{
+            Description, DueDate, OwnerId, Position, Preferences, TenantId, Title, ToDo, ToDoId,
+            ToDoList, ToDoRepo,
+        },
         error_types::RepositoryError,
     };
 
@@ -59,7 +357,11 @@ mod tests {
 
     #[async_trait]
     impl ToDoRepo for MockRepository {
-        async fn list(&self, _user_id: &str) -> Result<Vec<ToDo>, RepositoryError> {
+        async fn list(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+        ) -> Result<ToDoList, RepositoryError> {
             if self.should_fail {
                 return Err(RepositoryError::new("Forced failure!".to_string()));
             }
@@ -72,8 +374,10 @@ mod tests {
                     OwnerId::new("owner").unwrap(),
                     Some(self.to_do_status_to_return.to_string()),
                     Some(ToDoId::parse("id").unwrap()),
-                    Some(String::from("Description")),
-                    Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
+                    Some(Description::new("Description").unwrap()),
+                    Some(DueDate::At(
+                        DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+                    )),
                     match self.to_do_status_to_return.as_str() {
                         "COMPLETE" => {
                             Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap())
@@ -84,7 +388,7 @@ mod tests {
                 .unwrap(),
             );
 
-            Ok(todos)
+            Ok(ToDoList::from(todos))
         }
 
         async fn create(&self, _to_do: &ToDo) -> Result<(), RepositoryError> {
@@ -95,7 +399,24 @@ mod tests {
             }
         }
 
-        async fn get(&self, _user_id: &str, _todo_id: &str) -> Result<ToDo, RepositoryError> {
+        async fn create_within_quota(
+            &self,
+            _to_do: &ToDo,
+            _max_open_todos: usize,
+        ) -> Result<bool, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            } else {
+                Ok(true)
+            }
+        }
+
+        async fn get(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _todo_id: &str,
+        ) -> Result<ToDo, RepositoryError> {
             if self.should_fail {
                 return Err(RepositoryError::new("Forced failure!".to_string()));
             }
@@ -105,8 +426,10 @@ mod tests {
                 OwnerId::new("owner").unwrap(),
                 Some(self.to_do_status_to_return.to_string()),
                 Some(ToDoId::parse("id").unwrap()),
-                Some(String::from("Description")),
-                Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
+                Some(Description::new("Description").unwrap()),
+                Some(DueDate::At(
+                    DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+                )),
                 match self.to_do_status_to_return.as_str() {
                     "COMPLETE" => {
                         Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap())
@@ -116,6 +439,79 @@ mod tests {
             )
             .unwrap())
         }
+
+        async fn search(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _terms: &[String],
+        ) -> Result<Vec<SearchPosting>, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Vec::new())
+        }
+
+        async fn reserve_positions(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            count: usize,
+        ) -> Result<Vec<Position>, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Position::sequence(None, count))
+        }
+
+        async fn set_position(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _todo_id: &str,
+            _position: &Position,
+        ) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
+
+        async fn health_check(&self) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
+
+        async fn get_preferences(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+        ) -> Result<Preferences, RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(Preferences::default())
+        }
+
+        async fn set_preferences(
+            &self,
+            _tenant: &TenantId,
+            _user_id: &str,
+            _preferences: &Preferences,
+        ) -> Result<(), RepositoryError> {
+            if self.should_fail {
+                return Err(RepositoryError::new("Forced failure!".to_string()));
+            }
+
+            Ok(())
+        }
     }
 
     #[tokio::test]
@@ -126,12 +522,21 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
-        let to_dos = list_todos(&String::from("owner"), &shared_state.todo_repo).await;
+        let to_dos = list_todos(
+            &String::from("owner"),
+            &TenantId::default(),
+            &shared_state.todo_repo,
+        )
+        .await;
 
         assert!(!to_dos.is_err());
-        assert_eq!(to_dos.unwrap().len(), 1);
+        assert_eq!(to_dos.unwrap().items.len(), 1);
     }
 
     #[tokio::test]
@@ -142,14 +547,37 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
-        let to_dos = get_todos(&String::from("owner"), "the id", &shared_state.todo_repo).await;
+        let to_dos = get_todos(
+            &String::from("owner"),
+            &TenantId::default(),
+            "the id", &shared_state.todo_repo).await;
 
         assert!(!to_dos.is_err());
         assert_eq!(to_dos.unwrap().title, "title");
     }
 
+    #[test]
+    fn unreadable_items_should_be_counted_as_skipped() {
+        let list = ToDoList {
+            todos: Vec::new(),
+            skipped: vec![RepositoryError::invalid_item(
+                "USER#OWNER/TODO#ID".to_string(),
+                "'title' is missing".to_string(),
+            )],
+        };
+
+        let (todos, skipped) = report_skipped(list);
+
+        assert!(todos.is_empty());
+        assert_eq!(skipped, 1);
+    }
+
     #[tokio::test]
     async fn list_todos_on_error_should_return_error() {
         let shared_state = Arc::new(AppState {
@@ -158,10 +586,246 @@ mod tests {
                 to_do_status_to_return: "INCOMPLETE".to_string(),
             }),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        });
+
+        let to_dos = list_todos(
+            &String::from("owner"),
+            &TenantId::default(),
+            &shared_state.todo_repo,
+        )
+        .await;
+
+        assert!(to_dos.is_err());
+    }
+
+    #[tokio::test]
+    async fn search_todos_should_rank_by_term_frequency() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+
+        let mentioned_once = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        let mentioned_twice = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            Some(Description::new("Oat milk").unwrap()),
+            None,
+        )
+        .unwrap();
+        let unrelated = ToDo::new(
+            Title::new("Walk the dog").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        todo_repo.create(&mentioned_once).await.unwrap();
+        todo_repo.create(&mentioned_twice).await.unwrap();
+        todo_repo.create(&unrelated).await.unwrap();
+
+        let to_dos = search_todos("owner", &TenantId::default(), "MILK", None, &todo_repo)
+            .await
+            .unwrap();
+
+        assert_eq!(to_dos.len(), 2);
+        assert_eq!(to_dos[0].id, mentioned_twice.get_id());
+        assert_eq!(to_dos[1].id, mentioned_once.get_id());
+    }
+
+    #[tokio::test]
+    async fn search_todos_should_return_at_most_limit_matches() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+
+        for _ in 0..MAX_SEARCH_LIMIT + 1 {
+            let to_do = ToDo::new(
+                Title::new("Buy milk").unwrap(),
+                OwnerId::new("owner").unwrap(),
+                None,
+                None,
+            )
+            .unwrap();
+
+            todo_repo.create(&to_do).await.unwrap();
+        }
+
+        let limited = search_todos("owner", &TenantId::default(), "milk", Some(5), &todo_repo)
+            .await
+            .unwrap();
+        let default = search_todos("owner", &TenantId::default(), "milk", None, &todo_repo)
+            .await
+            .unwrap();
+        let capped = search_todos("owner", &TenantId::default(), "milk", Some(1000), &todo_repo)
+            .await
+            .unwrap();
+
+        assert_eq!(limited.len(), 5);
+        assert_eq!(default.len(), DEFAULT_SEARCH_LIMIT);
+        assert_eq!(capped.len(), MAX_SEARCH_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn search_todos_should_not_match_removed_terms() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+
+        let to_do = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        todo_repo.create(&to_do).await.unwrap();
+
+        let stored = todo_repo
+            .get(&TenantId::default(), "owner", to_do.get_id())
+            .await
+            .unwrap();
+
+        todo_repo
+            .create(&stored.update_title("Buy bread").unwrap())
+            .await
+            .unwrap();
+
+        let milk = search_todos("owner", &TenantId::default(), "milk", None, &todo_repo)
+            .await
+            .unwrap();
+        let bread = search_todos("owner", &TenantId::default(), "bread", None, &todo_repo)
+            .await
+            .unwrap();
+
+        assert!(milk.is_empty());
+        assert_eq!(bread.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_todos_should_only_return_owners_todos() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+
+        let to_do = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("owner").unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        todo_repo.create(&to_do).await.unwrap();
+
+        let to_dos = search_todos("someone-else", &TenantId::default(), "milk", None, &todo_repo)
+            .await
+            .unwrap();
+
+        assert!(to_dos.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_todos_on_error_should_return_error() {
+        let shared_state = Arc::new(AppState {
+            todo_repo: Arc::new(MockRepository {
+                should_fail: true,
+                to_do_status_to_return: "INCOMPLETE".to_string(),
+            }),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         });
 
-        let to_dos = list_todos(&String::from("owner"), &shared_state.todo_repo).await;
+        let to_dos = search_todos(
+            "owner",
+            &TenantId::default(),
+            "milk",
+            None,
+            &shared_state.todo_repo,
+        )
+        .await;
 
         assert!(to_dos.is_err());
     }
+
+    #[tokio::test]
+    async fn export_todos_should_yield_a_chunk_for_every_page() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+
+        for index in 0..EXPORT_PAGE_SIZE + 1 {
+            let to_do = ToDo::new(
+                Title::new(&format!("ToDo {}", index)).unwrap(),
+                OwnerId::new("owner").unwrap(),
+                None,
+                None,
+            )
+            .unwrap();
+
+            todo_repo.create(&to_do).await.unwrap();
+        }
+
+        let chunks: Vec<String> =
+            export_todos("owner", &TenantId::default(), TransferFormat::Json, &todo_repo)
+                .await
+                .unwrap()
+                .map(|chunk| chunk.unwrap())
+                .collect()
+                .await;
+
+        let exported: Vec<ToDoItem> = serde_json::from_str(&chunks.concat()).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(exported.len(), EXPORT_PAGE_SIZE + 1);
+    }
+
+    #[tokio::test]
+    async fn export_todos_on_error_should_return_error() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(MockRepository {
+            should_fail: true,
+            to_do_status_to_return: "INCOMPLETE".to_string(),
+        });
+
+        let chunks =
+            export_todos("owner", &TenantId::default(), TransferFormat::Json, &todo_repo).await;
+
+        assert!(chunks.is_err());
+    }
+
+    #[tokio::test]
+    async fn list_todos_should_only_return_tenants_todos() {
+        let todo_repo: Arc<dyn ToDoRepo + Send + Sync> = Arc::new(InMemoryToDoRepo::new());
+        let acme = TenantId::new("acme").unwrap();
+        let globex = TenantId::new("globex").unwrap();
+
+        let to_do = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("owner").unwrap().in_tenant(&acme),
+            None,
+            None,
+        )
+        .unwrap();
+
+        todo_repo.create(&to_do).await.unwrap();
+
+        let acme_to_dos = list_todos("owner", &acme, &todo_repo).await.unwrap();
+        let globex_to_dos = list_todos("owner", &globex, &todo_repo).await.unwrap();
+        let default_to_dos = list_todos("owner", &TenantId::default(), &todo_repo)
+            .await
+            .unwrap();
+
+        assert_eq!(acme_to_dos.items.len(), 1);
+        assert!(globex_to_dos.items.is_empty());
+        assert!(default_to_dos.items.is_empty());
+        assert!(get_todos("owner", &globex, to_do.get_id(), &todo_repo).await.is_err());
+        assert!(search_todos("owner", &globex, "milk", None, &todo_repo)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
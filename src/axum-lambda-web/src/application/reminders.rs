@@ -0,0 +1,609 @@
+use crate::application::domain::{Preferences, ReminderLeadTime, TenantId, ToDo};
+use crate::application::error_types::{RepositoryError, ServiceError};
+use crate::application::events::{MessageType, ToDoDueSoon, ToDoOverdue};
+use crate::application::messaging::MessagePublisher;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+const DEFAULT_LEAD_TIME_MINUTES: i64 = 60;
+
+/// A reminder claimed this long ago that was never confirmed as sent, because the run sending it
+/// stopped part way through, can be claimed again.
+pub(crate) const CLAIM_TIMEOUT_MINUTES: i64 = 15;
+
+/// All-day due dates are stored as due at the end of the day in UTC. An owner in a timezone up
+/// to this far ahead of UTC reaches the end of the day sooner, so candidates are looked up this
+/// much further ahead.
+const MAX_UTC_OFFSET_HOURS: i64 = 14;
+
+/// The reminders that can be sent for a single due date.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReminderKind {
+    DueSoon,
+    Overdue,
+}
+
+impl ReminderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderKind::DueSoon => "DUE_SOON",
+            ReminderKind::Overdue => "OVERDUE",
+        }
+    }
+}
+
+/// Persists which reminders have already been sent, so running the scheduler again never
+/// sends the same reminder twice.
+#[async_trait]
+pub trait ReminderStore {
+    /// Return the incomplete ToDos of every owner that are due on or before `due_before`.
+    async fn list_due_before(&self, due_before: DateTime<Utc>) -> Result<Vec<ToDo>, RepositoryError>;
+
+    /// The owner's preferences, their timezone decides when all-day due dates end and their
+    /// lead time, if they have chosen one, overrides the configured lead times.
+    async fn owner_preferences(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Preferences, RepositoryError>;
+
+    /// Record that a reminder is being sent for the ToDo's current due date. Returns false if
+    /// the reminder was already sent, or claimed by another run less than
+    /// `CLAIM_TIMEOUT_MINUTES` before `now`.
+    async fn claim_reminder(
+        &self,
+        to_do: &ToDo,
+        kind: ReminderKind,
+        now: DateTime<Utc>,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Record that a claimed reminder was sent, so it is never claimed again.
+    async fn confirm_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError>;
+
+    /// Remove a reminder recorded by `claim_reminder`, used when the reminder could not be sent.
+    async fn release_reminder(&self, to_do: &ToDo, kind: ReminderKind) -> Result<(), RepositoryError>;
+
+    /// Add the incomplete ToDos stored before the due date index was sharded to it, returning
+    /// how many were added. Stores without an index have nothing to add.
+    async fn index_due_dates(&self) -> Result<usize, RepositoryError> {
+        Ok(0)
+    }
+}
+
+/// How long before a ToDo is due a `ToDoDueSoon` reminder is sent, with optional per owner
+/// overrides. An owner's own preference takes precedence over both.
+pub struct LeadTimes {
+    default_lead_time: Duration,
+    owner_lead_times: HashMap<String, Duration>,
+}
+
+impl LeadTimes {
+    pub fn new(default_lead_time: Duration) -> Self {
+        Self {
+            default_lead_time,
+            owner_lead_times: HashMap::new(),
+        }
+    }
+
+    pub fn with_owner(mut self, owner: &str, lead_time: Duration) -> Self {
+        self.owner_lead_times.insert(owner.to_uppercase(), lead_time);
+        self
+    }
+
+    /// Configure the lead times from `REMINDER_LEAD_TIME_MINUTES` and
+    /// `REMINDER_OWNER_LEAD_TIMES`, a comma separated list of `owner=minutes` pairs.
+    pub fn from_env() -> Self {
+        let default_minutes = env::var("REMINDER_LEAD_TIME_MINUTES")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_LEAD_TIME_MINUTES);
+
+        let mut lead_times = LeadTimes::new(Duration::minutes(default_minutes));
+
+        if let Ok(owner_lead_times) = env::var("REMINDER_OWNER_LEAD_TIMES") {
+            for pair in owner_lead_times.split(',') {
+                match pair.split_once('=') {
+                    Some((owner, minutes)) => match minutes.trim().parse::<i64>() {
+                        Ok(minutes) => {
+                            lead_times = lead_times.with_owner(owner.trim(), Duration::minutes(minutes))
+                        }
+                        Err(_) => tracing::warn!("Ignoring invalid reminder lead time '{}'", pair),
+                    },
+                    None => tracing::warn!("Ignoring invalid reminder lead time '{}'", pair),
+                }
+            }
+        }
+
+        lead_times
+    }
+
+    fn for_owner(&self, owner: &str) -> Duration {
+        *self
+            .owner_lead_times
+            .get(&owner.to_uppercase())
+            .unwrap_or(&self.default_lead_time)
+    }
+
+    fn longest(&self) -> Duration {
+        self.owner_lead_times
+            .values()
+            .fold(self.default_lead_time, |longest, lead_time| longest.max(*lead_time))
+    }
+}
+
+/// The outcome of a single scheduler run.
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct ReminderRunSummary {
+    pub due_soon_sent: usize,
+    pub overdue_sent: usize,
+    pub already_sent: usize,
+    pub failed: usize,
+}
+
+/// Sends `ToDoDueSoon` and `ToDoOverdue` events for ToDos with a due date, once per due date.
+pub struct ReminderScheduler {
+    store: Arc<dyn ReminderStore + Send + Sync>,
+    message_publisher: Arc<dyn MessagePublisher + Send + Sync>,
+    lead_times: LeadTimes,
+}
+
+impl ReminderScheduler {
+    pub fn new(
+        store: Arc<dyn ReminderStore + Send + Sync>,
+        message_publisher: Arc<dyn MessagePublisher + Send + Sync>,
+        lead_times: LeadTimes,
+    ) -> Self {
+        Self {
+            store,
+            message_publisher,
+            lead_times,
+        }
+    }
+
+    /// Add ToDos stored before the due date index was sharded to it, so they are found by
+    /// `run`. Only needs to be run once, after deploying the sharded index.
+    pub async fn index_existing(&self) -> Result<usize, ServiceError> {
+        Ok(self.store.index_due_dates().await?)
+    }
+
+    pub async fn run(&self, now: DateTime<Utc>) -> Result<ReminderRunSummary, ServiceError> {
+        let mut summary = ReminderRunSummary::default();
+
+        let candidates = self
+            .store
+            .list_due_before(
+                now + self.lead_times.longest().max(ReminderLeadTime::longest())
+                    + Duration::hours(MAX_UTC_OFFSET_HOURS),
+            )
+            .await?;
+
+        let mut owner_preferences: HashMap<String, Preferences> = HashMap::new();
+
+        for to_do in candidates {
+            if !matches!(to_do, ToDo::Incomplete(_)) {
+                continue;
+            }
+
+            let preferences = self.preferences(&to_do, &mut owner_preferences).await?;
+
+            let due_date = match to_do.get_deadline_in(preferences.timezone.tz()) {
+                Some(due_date) => due_date,
+                None => continue,
+            };
+
+            let lead_time = match preferences.reminder_lead_time {
+                Some(lead_time) => lead_time.duration(),
+                None => self.lead_times.for_owner(to_do.get_owner()),
+            };
+
+            let kind = if due_date <= now {
+                ReminderKind::Overdue
+            } else if due_date <= now + lead_time {
+                ReminderKind::DueSoon
+            } else {
+                continue;
+            };
+
+            if !self.store.claim_reminder(&to_do, kind, now).await? {
+                summary.already_sent += 1;
+                continue;
+            }
+
+            let message = match kind {
+                ReminderKind::DueSoon => MessageType::DueSoon(ToDoDueSoon::new(
+                    to_do.get_id(),
+                    to_do.get_owner(),
+                    to_do.get_tenant().to_string(),
+                    &to_do.get_due_date(),
+                )),
+                ReminderKind::Overdue => MessageType::Overdue(ToDoOverdue::new(
+                    to_do.get_id(),
+                    to_do.get_owner(),
+                    to_do.get_tenant().to_string(),
+                    &to_do.get_due_date(),
+                )),
+            };
+
+            // Flush straight away so a failed delivery is known before the reminder is counted.
+            let published = match self.message_publisher.publish(message).await {
+                Ok(_) => self.message_publisher.flush().await,
+                Err(e) => Err(e),
+            };
+
+            match published {
+                Ok(_) => {
+                    self.store.confirm_reminder(&to_do, kind).await?;
+
+                    match kind {
+                        ReminderKind::DueSoon => summary.due_soon_sent += 1,
+                        ReminderKind::Overdue => summary.overdue_sent += 1,
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to send reminder for {}: {}", to_do.get_id(), e);
+
+                    // A reminder kept in the dead-letter sink is redriven from there, releasing
+                    // it as well would send it twice. Otherwise the next run tries again.
+                    match e.spilled_events() {
+                        0 => self.store.release_reminder(&to_do, kind).await?,
+                        _ => self.store.confirm_reminder(&to_do, kind).await?,
+                    }
+
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    // Each owner's preferences are read once per run, however many of their ToDos are due.
+    async fn preferences(
+        &self,
+        to_do: &ToDo,
+        owner_preferences: &mut HashMap<String, Preferences>,
+    ) -> Result<Preferences, ServiceError> {
+        let owner_key = format!(
+            "{0}#{1}",
+            to_do.get_tenant().to_string(),
+            to_do.get_owner().to_uppercase()
+        );
+
+        if let Some(preferences) = owner_preferences.get(&owner_key) {
+            return Ok(*preferences);
+        }
+
+        let preferences = self
+            .store
+            .owner_preferences(to_do.get_tenant(), to_do.get_owner())
+            .await?;
+
+        owner_preferences.insert(owner_key, preferences);
+
+        Ok(preferences)
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{LeadTimes, ReminderKind, ReminderScheduler, ReminderStore};
+    use crate::application::adapters::InMemoryToDoRepo;
+    use crate::application::domain::{
+        DueDate, OwnerId, Preferences, ReminderLeadTime, TenantId, Timezone, Title, ToDo,
+        ToDoRepo,
+    };
+    use crate::application::error_types::PublishError;
+    use crate::application::events::MessageType;
+    use crate::application::messaging::MessagePublisher;
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, NaiveDate, Utc};
+    use std::sync::{Arc, Mutex};
+
+    struct RecordingMessagePublisher {
+        should_fail: bool,
+        should_spill: bool,
+        published: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl MessagePublisher for RecordingMessagePublisher {
+        async fn publish(&self, message: MessageType) -> Result<(), PublishError> {
+            if self.should_fail {
+                return Err(PublishError::new(String::from("Publishing failed"), 1)
+                    .with_spilled_events(self.should_spill as usize));
+            }
+
+            let event_type = match message {
+                MessageType::DueSoon(_) => "ToDoDueSoon",
+                MessageType::Overdue(_) => "ToDoOverdue",
+                _ => "Other",
+            };
+
+            self.published.lock().unwrap().push(event_type.to_string());
+
+            Ok(())
+        }
+    }
+
+    fn recording_publisher(should_fail: bool) -> Arc<RecordingMessagePublisher> {
+        Arc::new(RecordingMessagePublisher {
+            should_fail,
+            should_spill: false,
+            published: Mutex::new(Vec::new()),
+        })
+    }
+
+    async fn create_due(repo: &InMemoryToDoRepo, owner: &str, due_date: DateTime<Utc>) -> ToDo {
+        let to_do = ToDo::new(
+            Title::new("Due").unwrap(),
+            OwnerId::new(owner).unwrap(),
+            None,
+            Some(DueDate::At(due_date.fixed_offset())),
+        )
+        .unwrap();
+
+        repo.create(&to_do).await.unwrap();
+
+        repo.get(to_do.get_tenant(), owner, to_do.get_id()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_should_send_due_soon_and_overdue_once() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        create_due(&repo, "owner", now + Duration::minutes(30)).await;
+        create_due(&repo, "owner", now - Duration::minutes(30)).await;
+        create_due(&repo, "owner", now + Duration::days(2)).await;
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let first_run = scheduler.run(now).await.unwrap();
+        let second_run = scheduler.run(now).await.unwrap();
+
+        assert_eq!(first_run.due_soon_sent, 1);
+        assert_eq!(first_run.overdue_sent, 1);
+        assert_eq!(second_run.due_soon_sent, 0);
+        assert_eq!(second_run.overdue_sent, 0);
+        assert_eq!(second_run.already_sent, 2);
+        assert_eq!(publisher.published.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn due_soon_todo_should_also_be_sent_overdue_once_passed() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        create_due(&repo, "owner", now + Duration::minutes(30)).await;
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        scheduler.run(now).await.unwrap();
+        scheduler.run(now + Duration::minutes(31)).await.unwrap();
+
+        assert_eq!(
+            *publisher.published.lock().unwrap(),
+            vec!["ToDoDueSoon", "ToDoOverdue"]
+        );
+    }
+
+    #[tokio::test]
+    async fn owner_lead_time_should_override_default() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        create_due(&repo, "early-bird", now + Duration::hours(20)).await;
+        create_due(&repo, "owner", now + Duration::hours(20)).await;
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)).with_owner("early-bird", Duration::days(1)),
+        );
+
+        let summary = scheduler.run(now).await.unwrap();
+
+        assert_eq!(summary.due_soon_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn owner_preference_should_override_lead_times() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+
+        create_due(&repo, "early-bird", now + Duration::hours(20)).await;
+
+        repo.set_preferences(
+            &TenantId::default(),
+            "early-bird",
+            &Preferences {
+                reminder_lead_time: Some(ReminderLeadTime::new(1440).unwrap()),
+                ..Preferences::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            recording_publisher(false),
+            LeadTimes::new(Duration::minutes(60)).with_owner("early-bird", Duration::minutes(5)),
+        );
+
+        let summary = scheduler.run(now).await.unwrap();
+
+        assert_eq!(summary.due_soon_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn all_day_due_date_should_end_in_owner_timezone() {
+        // Half an hour before midnight in Auckland, twelve and a half hours before it in UTC
+        let now = DateTime::parse_from_rfc3339("2023-08-12T11:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        for owner in ["auckland", "owner"] {
+            let to_do = ToDo::new(
+                Title::new("Due").unwrap(),
+                OwnerId::new(owner).unwrap(),
+                None,
+                Some(DueDate::AllDay(NaiveDate::from_ymd_opt(2023, 8, 12).unwrap())),
+            )
+            .unwrap();
+
+            repo.create(&to_do).await.unwrap();
+        }
+
+        repo.set_preferences(
+            &TenantId::default(),
+            "auckland",
+            &Preferences {
+                timezone: Timezone::new("Pacific/Auckland").unwrap(),
+                ..Preferences::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let summary = scheduler.run(now).await.unwrap();
+
+        assert_eq!(summary.due_soon_sent, 1);
+        assert_eq!(summary.overdue_sent, 0);
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_claim_should_be_retried_once_it_times_out() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        let to_do = create_due(&repo, "owner", now - Duration::minutes(30)).await;
+
+        // A run that stopped after claiming the reminder, before it was sent
+        assert!(repo
+            .claim_reminder(&to_do, ReminderKind::Overdue, now)
+            .await
+            .unwrap());
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let claimed_run = scheduler.run(now + Duration::minutes(1)).await.unwrap();
+        let timed_out_run = scheduler.run(now + Duration::minutes(16)).await.unwrap();
+        let sent_run = scheduler.run(now + Duration::minutes(60)).await.unwrap();
+
+        assert_eq!(claimed_run.already_sent, 1);
+        assert_eq!(timed_out_run.overdue_sent, 1);
+        assert_eq!(sent_run.already_sent, 1);
+        assert_eq!(publisher.published.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_publish_should_be_retried_on_next_run() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+
+        create_due(&repo, "owner", now - Duration::minutes(30)).await;
+
+        let failing_scheduler = ReminderScheduler::new(
+            repo.clone(),
+            recording_publisher(true),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let failed_run = failing_scheduler.run(now).await.unwrap();
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            recording_publisher(false),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let retried_run = scheduler.run(now).await.unwrap();
+
+        assert_eq!(failed_run.failed, 1);
+        assert_eq!(retried_run.overdue_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn dead_lettered_reminder_should_not_be_sent_again() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+
+        create_due(&repo, "owner", now - Duration::minutes(30)).await;
+
+        let spilling_scheduler = ReminderScheduler::new(
+            repo.clone(),
+            Arc::new(RecordingMessagePublisher {
+                should_fail: true,
+                should_spill: true,
+                published: Mutex::new(Vec::new()),
+            }),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let failed_run = spilling_scheduler.run(now).await.unwrap();
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            recording_publisher(false),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let next_run = scheduler.run(now + Duration::minutes(30)).await.unwrap();
+
+        assert_eq!(failed_run.failed, 1);
+        assert_eq!(next_run.overdue_sent, 0);
+        assert_eq!(next_run.already_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn completed_todo_should_not_be_reminded() {
+        let now = Utc::now();
+        let repo = Arc::new(InMemoryToDoRepo::new());
+        let publisher = recording_publisher(false);
+
+        let to_do = create_due(&repo, "owner", now - Duration::minutes(30)).await;
+        repo.create(&to_do.set_completed()).await.unwrap();
+
+        let scheduler = ReminderScheduler::new(
+            repo.clone(),
+            publisher.clone(),
+            LeadTimes::new(Duration::minutes(60)),
+        );
+
+        let summary = scheduler.run(now).await.unwrap();
+
+        assert_eq!(summary.overdue_sent, 0);
+        assert!(publisher.published.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::application::domain::ToDo;
+use std::collections::HashMap;
+
+/// The most terms indexed for a single ToDo. The ToDo and its index entries are written in one
+/// transaction, which DynamoDB limits to 100 items: the ToDo, and at worst an entry removed for
+/// every term it used to contain and an entry written for every term it now contains.
+pub(crate) const MAX_INDEXED_TERMS: usize = 49;
+
+/// A single entry in the per-owner inverted index, linking a term to a ToDo that contains it.
+#[derive(Clone, Debug)]
+pub struct SearchPosting {
+    pub to_do_id: String,
+    pub term: String,
+    pub frequency: u32,
+    pub indexed_on: i64,
+}
+
+/// Split free text into lowercase alphanumeric terms, keeping duplicates.
+pub(crate) fn tokenise(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Tokenise a search query, removing duplicate terms while keeping their order.
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+
+    for token in tokenise(query) {
+        if !terms.contains(&token) {
+            terms.push(token);
+        }
+    }
+
+    terms
+}
+
+/// Count how often each term appears across the title and description of a ToDo.
+pub(crate) fn term_frequencies(to_do: &ToDo) -> HashMap<String, u32> {
+    let mut frequencies: HashMap<String, u32> = HashMap::new();
+
+    let title_terms = tokenise(to_do.get_title());
+    let description_terms = tokenise(to_do.get_description());
+
+    for term in title_terms.into_iter().chain(description_terms) {
+        *frequencies.entry(term).or_insert(0) += 1;
+    }
+
+    frequencies
+}
+
+/// The terms to index for a ToDo, at most `MAX_INDEXED_TERMS` of them. Terms in the title are
+/// always kept, the remaining space goes to the most frequent terms in the description.
+pub(crate) fn indexed_terms(to_do: &ToDo) -> HashMap<String, u32> {
+    let frequencies = term_frequencies(to_do);
+
+    if frequencies.len() <= MAX_INDEXED_TERMS {
+        return frequencies;
+    }
+
+    let title_terms = tokenise(to_do.get_title());
+    let mut ordered: Vec<(String, u32)> = frequencies.into_iter().collect();
+
+    ordered.sort_by(|(left_term, left), (right_term, right)| {
+        title_terms
+            .contains(right_term)
+            .cmp(&title_terms.contains(left_term))
+            .then(right.cmp(left))
+            .then(left_term.cmp(right_term))
+    });
+    ordered.truncate(MAX_INDEXED_TERMS);
+
+    ordered.into_iter().collect()
+}
+
+/// Rank the ToDo ids found in a set of postings. ToDos matching more of the query terms come
+/// first, then those with the highest combined term frequency, then the most recently indexed.
+pub(crate) fn rank(postings: Vec<SearchPosting>) -> Vec<String> {
+    let mut scores: HashMap<String, (u32, u32, i64)> = HashMap::new();
+
+    for posting in postings {
+        let score = scores.entry(posting.to_do_id).or_insert((0, 0, 0));
+        score.0 += 1;
+        score.1 += posting.frequency;
+        score.2 = score.2.max(posting.indexed_on);
+    }
+
+    let mut ranked: Vec<(String, (u32, u32, i64))> = scores.into_iter().collect();
+
+    ranked.sort_by(|(left_id, left), (right_id, right)| {
+        right
+            .0
+            .cmp(&left.0)
+            .then(right.1.cmp(&left.1))
+            .then(right.2.cmp(&left.2))
+            .then(left_id.cmp(right_id))
+    });
+
+    ranked.into_iter().map(|(id, _)| id).collect()
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{
+        indexed_terms, query_terms, rank, term_frequencies, tokenise, SearchPosting,
+        MAX_INDEXED_TERMS,
+    };
+    use crate::application::domain::{Description, OwnerId, Title, ToDo};
+
+    fn posting(to_do_id: &str, term: &str, frequency: u32, indexed_on: i64) -> SearchPosting {
+        SearchPosting {
+            to_do_id: to_do_id.to_string(),
+            term: term.to_string(),
+            frequency,
+            indexed_on,
+        }
+    }
+
+    #[test]
+    fn tokenise_should_lowercase_and_split_on_punctuation() {
+        let tokens = tokenise("Buy milk, eggs & BREAD!");
+
+        assert_eq!(tokens, vec!["buy", "milk", "eggs", "bread"]);
+    }
+
+    #[test]
+    fn query_terms_should_remove_duplicates() {
+        let terms = query_terms("milk Milk bread");
+
+        assert_eq!(terms, vec!["milk", "bread"]);
+    }
+
+    #[test]
+    fn term_frequencies_should_count_title_and_description() {
+        let to_do = ToDo::new(
+            Title::new("Buy milk").unwrap(),
+            OwnerId::new("jameseastham").unwrap(),
+            Some(Description::new("Semi skimmed milk").unwrap()),
+            None,
+        )
+        .unwrap();
+
+        let frequencies = term_frequencies(&to_do);
+
+        assert_eq!(frequencies.get("milk"), Some(&2));
+        assert_eq!(frequencies.get("buy"), Some(&1));
+        assert_eq!(frequencies.get("skimmed"), Some(&1));
+    }
+
+    #[test]
+    fn indexed_terms_should_keep_title_terms_and_most_frequent_description_terms() {
+        let description: Vec<String> = (0..100).map(|i| format!("word{0}", i)).collect();
+        let to_do = ToDo::new(
+            Title::new("Zebra crossing").unwrap(),
+            OwnerId::new("jameseastham").unwrap(),
+            Some(Description::new(&format!("{0} often often", description.join(" "))).unwrap()),
+            None,
+        )
+        .unwrap();
+
+        let terms = indexed_terms(&to_do);
+
+        assert_eq!(terms.len(), MAX_INDEXED_TERMS);
+        assert!(terms.contains_key("zebra"));
+        assert!(terms.contains_key("crossing"));
+        assert_eq!(terms.get("often"), Some(&2));
+    }
+
+    #[test]
+    fn rank_should_prefer_matched_terms_then_frequency_then_recency() {
+        let ranked = rank(vec![
+            posting("frequent", "milk", 3, 1),
+            posting("both-terms", "milk", 1, 1),
+            posting("both-terms", "bread", 1, 1),
+            posting("recent", "milk", 3, 2),
+        ]);
+
+        assert_eq!(ranked, vec!["both-terms", "recent", "frequent"]);
+    }
+}
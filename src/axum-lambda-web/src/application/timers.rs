@@ -0,0 +1,155 @@
+use crate::application::domain::TenantId;
+use crate::application::error_types::RepositoryError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A period of time spent on a ToDo, recorded by starting and stopping a timer. An owner can
+/// only have one timer running at a time, across all of their ToDos.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TimeEntry {
+    entry_id: String,
+    to_do_id: String,
+    started_on: DateTime<Utc>,
+    stopped_on: Option<DateTime<Utc>>,
+}
+
+impl TimeEntry {
+    /// Start a timer on a ToDo now.
+    pub fn start(to_do_id: &str) -> TimeEntry {
+        TimeEntry {
+            entry_id: Uuid::new_v4().to_string(),
+            to_do_id: to_do_id.to_string(),
+            started_on: Utc::now(),
+            stopped_on: None,
+        }
+    }
+
+    /// Parse a time entry from a set of existing values
+    pub fn parse(
+        entry_id: &str,
+        to_do_id: &str,
+        started_on: DateTime<Utc>,
+        stopped_on: Option<DateTime<Utc>>,
+    ) -> TimeEntry {
+        TimeEntry {
+            entry_id: entry_id.to_string(),
+            to_do_id: to_do_id.to_string(),
+            started_on,
+            stopped_on,
+        }
+    }
+
+    /// Stop the timer at `stopped_on`. A clock that has gone backwards records no time rather
+    /// than a negative duration.
+    pub fn stop(self, stopped_on: DateTime<Utc>) -> TimeEntry {
+        TimeEntry {
+            stopped_on: Some(stopped_on.max(self.started_on)),
+            ..self
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stopped_on.is_none()
+    }
+
+    /// Whether the timer was started on the ToDo, ToDo ids are compared ignoring case.
+    pub fn is_for(&self, to_do_id: &str) -> bool {
+        self.to_do_id.eq_ignore_ascii_case(to_do_id)
+    }
+
+    /// The whole seconds the timer ran for, zero while it is still running.
+    pub fn duration_seconds(&self) -> u64 {
+        self.stopped_on
+            .map(|stopped_on| (stopped_on - self.started_on).num_seconds().max(0) as u64)
+            .unwrap_or_default()
+    }
+
+    pub fn id(&self) -> &str {
+        self.entry_id.as_str()
+    }
+
+    pub fn to_do_id(&self) -> &str {
+        self.to_do_id.as_str()
+    }
+
+    pub fn started_on(&self) -> DateTime<Utc> {
+        self.started_on
+    }
+
+    pub fn stopped_on(&self) -> Option<DateTime<Utc>> {
+        self.stopped_on
+    }
+}
+
+/// Stores time entries alongside the ToDos they were recorded against, and the timer each owner
+/// has running.
+#[async_trait]
+pub trait TimerRepo {
+    /// The owner's running timer, if they have one.
+    async fn running_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Option<TimeEntry>, RepositoryError>;
+
+    /// Record a newly started timer. Returns `false`, without recording it, if the owner
+    /// already has a timer running.
+    async fn start_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Record a stopped timer, clear the owner's running timer and add its time to the ToDo, all
+    /// or nothing. Returns `false` if the timer is no longer the owner's running timer, for
+    /// example because it was already stopped.
+    async fn stop_timer(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        entry: &TimeEntry,
+    ) -> Result<bool, RepositoryError>;
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::TimeEntry;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn stopped_timer_should_record_whole_seconds() {
+        let started_on = Utc::now();
+        let entry = TimeEntry::parse("entry", "abc", started_on, None);
+
+        assert!(entry.is_running());
+        assert_eq!(entry.duration_seconds(), 0);
+
+        let stopped = entry.stop(started_on + Duration::milliseconds(90_500));
+
+        assert!(!stopped.is_running());
+        assert_eq!(stopped.duration_seconds(), 90);
+    }
+
+    #[test]
+    fn timer_stopped_before_it_started_should_record_no_time() {
+        let started_on = Utc::now();
+        let entry = TimeEntry::parse("entry", "abc", started_on, None)
+            .stop(started_on - Duration::seconds(30));
+
+        assert_eq!(entry.duration_seconds(), 0);
+        assert_eq!(entry.stopped_on(), Some(started_on));
+    }
+
+    #[test]
+    fn timer_should_match_to_do_ignoring_case() {
+        let entry = TimeEntry::start("abc-def");
+
+        assert!(entry.is_for("ABC-DEF"));
+        assert!(!entry.is_for("abc"));
+    }
+}
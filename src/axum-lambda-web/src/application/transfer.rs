@@ -0,0 +1,401 @@
+use crate::application::error_types::ValidationError;
+use crate::application::public_types::ToDoItem;
+use serde::{Deserialize, Serialize};
+
+const CSV_COLUMNS: [&str; 6] = [
+    "id",
+    "title",
+    "is_complete",
+    "completed_on",
+    "description",
+    "due_date",
+];
+const MARKDOWN_INCOMPLETE: &str = "- [ ] ";
+const MARKDOWN_COMPLETE: &str = "- [x] ";
+const MARKDOWN_DESCRIPTION_INDENT: &str = "  ";
+const MARKDOWN_DUE_PREFIX: &str = " @due(";
+const MARKDOWN_DONE_PREFIX: &str = " @done(";
+
+/// The formats ToDos can be exported to and imported from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl TransferFormat {
+    pub fn parse(format: &str) -> Result<TransferFormat, ValidationError> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(TransferFormat::Json),
+            "csv" => Ok(TransferFormat::Csv),
+            "md" | "markdown" => Ok(TransferFormat::Markdown),
+            _ => Err(ValidationError::new(format!(
+                "'{}' is not a supported format, use json, csv or md",
+                format
+            ))),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "application/json",
+            TransferFormat::Csv => "text/csv",
+            TransferFormat::Markdown => "text/markdown",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "json",
+            TransferFormat::Csv => "csv",
+            TransferFormat::Markdown => "md",
+        }
+    }
+}
+
+/// A single ToDo read from an import file, before it has been validated.
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct ImportRow {
+    pub title: String,
+    pub description: Option<String>,
+    pub due_date: Option<String>,
+    pub is_complete: bool,
+    pub completed_on: Option<String>,
+}
+
+/// The chunk that opens an export, if the format has one.
+pub(crate) fn render_export_header(format: TransferFormat) -> Option<String> {
+    match format {
+        TransferFormat::Json => Some(String::from("[")),
+        TransferFormat::Csv => Some(render_csv_record(&CSV_COLUMNS)),
+        TransferFormat::Markdown => None,
+    }
+}
+
+/// The chunk for a single ToDo. `is_first` is set for the first ToDo in the export, as JSON
+/// separates the rest from the one before.
+pub(crate) fn render_export_item(format: TransferFormat, item: &ToDoItem, is_first: bool) -> String {
+    match format {
+        TransferFormat::Json => {
+            let separator = if is_first { "" } else { "," };
+            format!("{}{}", separator, serde_json::json!(item))
+        }
+        TransferFormat::Csv => {
+            let is_complete = item.is_complete.to_string();
+
+            render_csv_record(&[
+                item.id.as_str(),
+                item.title.as_str(),
+                is_complete.as_str(),
+                item.completed_on.as_str(),
+                item.description.as_str(),
+                item.due_date.as_str(),
+            ])
+        }
+        TransferFormat::Markdown => render_markdown_item(item),
+    }
+}
+
+/// The chunk that closes an export, if the format has one.
+pub(crate) fn render_export_footer(format: TransferFormat) -> Option<String> {
+    match format {
+        TransferFormat::Json => Some(String::from("]")),
+        TransferFormat::Csv | TransferFormat::Markdown => None,
+    }
+}
+
+/// Parse an import file. The outer error is returned when the file as a whole cannot be read,
+/// each inner error describes a single row that could not be read.
+pub(crate) fn parse_import(
+    format: TransferFormat,
+    body: &str,
+) -> Result<Vec<Result<ImportRow, String>>, ValidationError> {
+    match format {
+        TransferFormat::Json => {
+            let values: Vec<serde_json::Value> = serde_json::from_str(body).map_err(|e| {
+                ValidationError::new(format!("Import must be a JSON array of ToDos: {}", e))
+            })?;
+
+            Ok(values
+                .into_iter()
+                .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+                .map(|row| row.map(normalise_row))
+                .collect())
+        }
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(body.as_bytes());
+
+            Ok(reader
+                .deserialize::<ImportRow>()
+                .map(|row| row.map(normalise_row).map_err(|e| e.to_string()))
+                .collect())
+        }
+        TransferFormat::Markdown => Ok(parse_markdown(body).into_iter().map(Ok).collect()),
+    }
+}
+
+fn render_csv_record(fields: &[&str]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    // Writing to an in-memory buffer cannot fail
+    writer.write_record(fields).unwrap();
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+fn render_markdown_item(item: &ToDoItem) -> String {
+    let mut rendered = String::from(match item.is_complete {
+        true => MARKDOWN_COMPLETE,
+        false => MARKDOWN_INCOMPLETE,
+    });
+
+    rendered.push_str(&item.title);
+
+    if !item.due_date.is_empty() {
+        rendered.push_str(&format!("{}{})", MARKDOWN_DUE_PREFIX, item.due_date));
+    }
+
+    if !item.completed_on.is_empty() {
+        rendered.push_str(&format!("{}{})", MARKDOWN_DONE_PREFIX, item.completed_on));
+    }
+
+    rendered.push('\n');
+
+    for line in item.description.lines() {
+        rendered.push_str(&format!("{}{}\n", MARKDOWN_DESCRIPTION_INDENT, line));
+    }
+
+    rendered
+}
+
+fn parse_markdown(body: &str) -> Vec<ImportRow> {
+    let mut rows: Vec<ImportRow> = Vec::new();
+
+    for line in body.lines() {
+        // Only list items starting in the first column are ToDos, indented ones belong to the
+        // description of the ToDo above them
+        let is_complete = if line.starts_with(MARKDOWN_INCOMPLETE) {
+            Some(false)
+        } else if line.starts_with(MARKDOWN_COMPLETE) || line.starts_with("- [X] ") {
+            Some(true)
+        } else {
+            None
+        };
+
+        match is_complete {
+            Some(is_complete) => {
+                let text = &line[MARKDOWN_INCOMPLETE.len()..];
+
+                // Tags are rendered as `@due(..) @done(..)`, so they are removed in reverse
+                let (text, completed_on) = strip_markdown_tag(text, MARKDOWN_DONE_PREFIX);
+                let (title, due_date) = strip_markdown_tag(text, MARKDOWN_DUE_PREFIX);
+
+                rows.push(ImportRow {
+                    title: title.trim().to_string(),
+                    description: None,
+                    due_date,
+                    is_complete,
+                    completed_on,
+                });
+            }
+            None => {
+                // Indented lines following a checklist item make up its description, anything
+                // else (headings, blank lines) is not part of a ToDo.
+                if let (Some(row), Some(description_line)) = (
+                    rows.last_mut(),
+                    line.strip_prefix(MARKDOWN_DESCRIPTION_INDENT),
+                ) {
+                    row.description = Some(match &row.description {
+                        None => description_line.to_string(),
+                        Some(existing) => format!("{}\n{}", existing, description_line),
+                    });
+                }
+            }
+        }
+    }
+
+    rows
+}
+
+// Split a trailing `@tag(value)` from the text of a checklist item.
+fn strip_markdown_tag<'a>(text: &'a str, prefix: &str) -> (&'a str, Option<String>) {
+    match text.rfind(prefix) {
+        Some(index) if text.ends_with(')') => (
+            &text[..index],
+            Some(text[index + prefix.len()..text.len() - 1].to_string()),
+        ),
+        _ => (text, None),
+    }
+}
+
+// Exports write empty strings for missing values, treat those the same as a missing value so
+// an export can be imported again unchanged.
+fn normalise_row(row: ImportRow) -> ImportRow {
+    ImportRow {
+        description: row.description.filter(|val| !val.is_empty()),
+        due_date: row.due_date.filter(|val| !val.is_empty()),
+        completed_on: row.completed_on.filter(|val| !val.is_empty()),
+        ..row
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_import, render_export_footer, render_export_header, render_export_item, ImportRow,
+        TransferFormat,
+    };
+    use crate::application::public_types::ToDoItem;
+
+    // The whole export, as the stream would send it.
+    fn render_export(format: TransferFormat, items: &[ToDoItem]) -> Vec<String> {
+        let mut chunks: Vec<String> = Vec::new();
+
+        chunks.extend(render_export_header(format));
+
+        for (index, item) in items.iter().enumerate() {
+            chunks.push(render_export_item(format, item, index == 0));
+        }
+
+        chunks.extend(render_export_footer(format));
+
+        chunks
+    }
+
+    fn test_items() -> Vec<ToDoItem> {
+        vec![
+            ToDoItem {
+                id: String::from("1"),
+                title: String::from("Buy milk, eggs"),
+                is_complete: false,
+                completed_on: String::from(""),
+                description: String::from("Semi skimmed\nFree range"),
+                due_date: String::from("2023-08-12T00:00:00+00:00"),
+                priority: String::from("normal"),
+                position: String::new(),
+                description_html: None,
+                attachments: Vec::new(),
+                tracked_seconds: 0,
+            },
+            ToDoItem {
+                id: String::from("2"),
+                title: String::from("Walk the dog"),
+                is_complete: true,
+                completed_on: String::from("2023-08-11T00:00:00+00:00"),
+                description: String::from(""),
+                due_date: String::from(""),
+                priority: String::from("normal"),
+                position: String::new(),
+                description_html: None,
+                attachments: Vec::new(),
+                tracked_seconds: 0,
+            },
+        ]
+    }
+
+    fn expected_rows() -> Vec<ImportRow> {
+        vec![
+            ImportRow {
+                title: String::from("Buy milk, eggs"),
+                description: Some(String::from("Semi skimmed\nFree range")),
+                due_date: Some(String::from("2023-08-12T00:00:00+00:00")),
+                is_complete: false,
+                completed_on: None,
+            },
+            ImportRow {
+                title: String::from("Walk the dog"),
+                description: None,
+                due_date: None,
+                is_complete: true,
+                completed_on: Some(String::from("2023-08-11T00:00:00+00:00")),
+            },
+        ]
+    }
+
+    fn round_trip(format: TransferFormat) -> Vec<ImportRow> {
+        let exported = render_export(format, &test_items()).concat();
+
+        parse_import(format, &exported)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parse_format_should_accept_supported_formats() {
+        assert_eq!(TransferFormat::parse("JSON").unwrap(), TransferFormat::Json);
+        assert_eq!(TransferFormat::parse("csv").unwrap(), TransferFormat::Csv);
+        assert_eq!(TransferFormat::parse("md").unwrap(), TransferFormat::Markdown);
+        assert!(TransferFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn json_export_should_round_trip() {
+        assert_eq!(round_trip(TransferFormat::Json), expected_rows());
+    }
+
+    #[test]
+    fn csv_export_should_round_trip() {
+        assert_eq!(round_trip(TransferFormat::Csv), expected_rows());
+    }
+
+    #[test]
+    fn markdown_export_should_round_trip() {
+        assert_eq!(round_trip(TransferFormat::Markdown), expected_rows());
+    }
+
+    #[test]
+    fn markdown_export_should_render_checklist() {
+        let exported = render_export(TransferFormat::Markdown, &test_items()).concat();
+
+        assert_eq!(
+            exported,
+            "- [ ] Buy milk, eggs @due(2023-08-12T00:00:00+00:00)\n  Semi skimmed\n  Free range\n- [x] Walk the dog @done(2023-08-11T00:00:00+00:00)\n"
+        );
+    }
+
+    #[test]
+    fn indented_checklist_in_description_should_round_trip() {
+        let mut items = test_items();
+        items[0].description = String::from("Shops:\n- [ ] Corner shop\n  - [x] Market");
+
+        let exported = render_export(TransferFormat::Markdown, &items).concat();
+        let rows: Vec<ImportRow> = parse_import(TransferFormat::Markdown, &exported)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.unwrap())
+            .collect();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].description,
+            Some(String::from("Shops:\n- [ ] Corner shop\n  - [x] Market"))
+        );
+    }
+
+    #[test]
+    fn invalid_json_row_should_be_reported_without_failing_import() {
+        let rows = parse_import(
+            TransferFormat::Json,
+            "[{\"title\": \"Valid\"}, {\"title\": 12}]",
+        )
+        .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+    }
+
+    #[test]
+    fn invalid_json_document_should_fail_import() {
+        let rows = parse_import(TransferFormat::Json, "{\"title\": \"Not an array\"}");
+
+        assert!(rows.is_err());
+    }
+}
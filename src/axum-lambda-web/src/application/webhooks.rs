@@ -0,0 +1,408 @@
+use crate::application::domain::TenantId;
+use crate::application::error_types::{RepositoryError, ValidationError};
+use crate::application::events::EVENT_TYPES;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+const MAX_URL_LENGTH: usize = 2048;
+const SECRET_PREFIX: &str = "whsec_";
+
+/// The number of deliveries kept in each webhook's delivery log.
+pub(crate) const DELIVERY_LOG_LIMIT: usize = 50;
+
+/// Every event is sent to each of its owner's webhooks, so the number an owner can register is
+/// capped.
+pub(crate) const MAX_WEBHOOKS_PER_OWNER: usize = 10;
+
+/// An HTTPS endpoint an owner has subscribed to events about their ToDos. Every delivery is
+/// signed with the webhook's secret, so the receiver can check it came from this service.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Webhook {
+    webhook_id: String,
+    tenant: TenantId,
+    owner: String,
+    url: String,
+    event_types: Vec<String>,
+    secret: String,
+    enabled: bool,
+    consecutive_failures: u32,
+    created_on: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Register a new webhook, an empty list of event types subscribes to every event.
+    pub fn new(
+        tenant: &TenantId,
+        owner: &str,
+        url: &str,
+        event_types: Vec<String>,
+    ) -> Result<Webhook, ValidationError> {
+        let secret: [u8; 32] = rand::thread_rng().gen();
+
+        Webhook {
+            webhook_id: Uuid::new_v4().to_string(),
+            tenant: tenant.clone(),
+            owner: owner.to_string(),
+            url: String::new(),
+            event_types: Vec::new(),
+            secret: format!("{}{}", SECRET_PREFIX, hex::encode(secret)),
+            enabled: true,
+            consecutive_failures: 0,
+            created_on: Utc::now(),
+        }
+        .update_url(url)?
+        .update_event_types(event_types)
+    }
+
+    /// Parse a webhook from a set of existing values
+    #[allow(clippy::too_many_arguments)]
+    pub fn parse(
+        webhook_id: &str,
+        tenant: &TenantId,
+        owner: &str,
+        url: &str,
+        event_types: Vec<String>,
+        secret: &str,
+        enabled: bool,
+        consecutive_failures: u32,
+        created_on: DateTime<Utc>,
+    ) -> Webhook {
+        Webhook {
+            webhook_id: webhook_id.to_string(),
+            tenant: tenant.clone(),
+            owner: owner.to_string(),
+            url: url.to_string(),
+            event_types,
+            secret: secret.to_string(),
+            enabled,
+            consecutive_failures,
+            created_on,
+        }
+    }
+
+    /// Deliveries are only sent over HTTPS, and never to a loopback, private or link-local
+    /// address, so a webhook cannot be used to reach services inside the network.
+    pub fn update_url(self, url: &str) -> Result<Webhook, ValidationError> {
+        let invalid_url = |reason: &str| {
+            ValidationError::new(format!("'{}' is not a valid webhook URL, {}", url, reason))
+                .with_field("url")
+        };
+
+        if url.len() > MAX_URL_LENGTH {
+            return Err(invalid_url("it is too long"));
+        }
+
+        let parsed = reqwest::Url::parse(url).map_err(|_| invalid_url("it cannot be parsed"))?;
+
+        if parsed.scheme() != "https" {
+            return Err(invalid_url("it must use https"));
+        }
+
+        let host = parsed.host_str().unwrap_or_default();
+        let host_address = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+
+        if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+            return Err(invalid_url("it must name a public host"));
+        }
+
+        if let Ok(address) = host_address {
+            if !is_public_address(&address) {
+                return Err(invalid_url("it must name a public host"));
+            }
+        }
+
+        Ok(Webhook {
+            url: parsed.to_string(),
+            ..self
+        })
+    }
+
+    pub fn update_event_types(self, event_types: Vec<String>) -> Result<Webhook, ValidationError> {
+        for event_type in &event_types {
+            if !EVENT_TYPES.contains(&event_type.as_str()) {
+                return Err(ValidationError::new(format!(
+                    "'{}' is not an event type, use one of {}",
+                    event_type,
+                    EVENT_TYPES.join(", ")
+                ))
+                .with_field("event_types"));
+            }
+        }
+
+        Ok(Webhook {
+            event_types,
+            ..self
+        })
+    }
+
+    /// Turn the webhook on again, for example once its endpoint has been fixed after it was
+    /// disabled for failing.
+    pub fn enable(self) -> Webhook {
+        Webhook {
+            enabled: true,
+            consecutive_failures: 0,
+            ..self
+        }
+    }
+
+    pub fn disable(self) -> Webhook {
+        Webhook {
+            enabled: false,
+            ..self
+        }
+    }
+
+    pub fn record_success(self) -> Webhook {
+        Webhook {
+            consecutive_failures: 0,
+            ..self
+        }
+    }
+
+    /// Count a failed delivery, disabling the webhook once `failure_threshold` deliveries in a
+    /// row have failed.
+    pub fn record_failure(self, failure_threshold: u32) -> Webhook {
+        let consecutive_failures = self.consecutive_failures.saturating_add(1);
+
+        Webhook {
+            enabled: self.enabled && consecutive_failures < failure_threshold,
+            consecutive_failures,
+            ..self
+        }
+    }
+
+    /// Whether events of `event_type` should be sent to the webhook.
+    pub fn subscribes_to(&self, event_type: &str) -> bool {
+        self.enabled
+            && (self.event_types.is_empty() || self.event_types.iter().any(|val| val == event_type))
+    }
+
+    pub fn id(&self) -> &str {
+        self.webhook_id.as_str()
+    }
+
+    pub fn tenant(&self) -> &TenantId {
+        &self.tenant
+    }
+
+    pub fn owner(&self) -> &str {
+        self.owner.as_str()
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn event_types(&self) -> &[String] {
+        &self.event_types
+    }
+
+    pub fn secret(&self) -> &str {
+        self.secret.as_str()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn created_on(&self) -> DateTime<Utc> {
+        self.created_on
+    }
+}
+
+/// Whether deliveries may be sent to `address`, loopback, private and link-local addresses are
+/// refused.
+pub(crate) fn is_public_address(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            !(address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast())
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(mapped) => is_public_address(&IpAddr::V4(mapped)),
+            // Unique local (fc00::/7) and link-local (fe80::/10) addresses are not public.
+            None => {
+                !(address.is_loopback()
+                    || address.is_unspecified()
+                    || (address.segments()[0] & 0xfe00) == 0xfc00
+                    || (address.segments()[0] & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// One attempt to deliver an event to a webhook, after any retries.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct WebhookDelivery {
+    pub delivery_id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub delivered_on: DateTime<Utc>,
+    pub attempts: u32,
+    /// The status code of the last response, `None` if no response was received.
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl WebhookDelivery {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Stores webhooks and the log of their deliveries.
+#[async_trait]
+pub trait WebhookRepo {
+    async fn list_webhooks(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+    ) -> Result<Vec<Webhook>, RepositoryError>;
+
+    /// Returns `None` if the owner has no such webhook.
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Option<Webhook>, RepositoryError>;
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), RepositoryError>;
+
+    /// Returns `false` if the owner has no such webhook.
+    async fn delete_webhook(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Count a delivery towards the webhook's consecutive failures, disabling it once
+    /// `failure_threshold` deliveries in a row have failed. Only the failure count and whether
+    /// the webhook is enabled are written, so changes the owner made since `webhook` was loaded
+    /// are kept. Returns the webhook as it now is, or `None` if it has been deleted.
+    async fn record_delivery_result(
+        &self,
+        webhook: &Webhook,
+        succeeded: bool,
+        failure_threshold: u32,
+    ) -> Result<Option<Webhook>, RepositoryError>;
+
+    /// Add a delivery to the webhook's log. Only the most recent deliveries are kept.
+    async fn record_delivery(
+        &self,
+        webhook: &Webhook,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), RepositoryError>;
+
+    /// The webhook's most recent deliveries, newest first.
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        owner: &str,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError>;
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::Webhook;
+    use crate::application::domain::TenantId;
+
+    fn webhook(url: &str, event_types: &[&str]) -> Result<Webhook, String> {
+        Webhook::new(
+            &TenantId::default(),
+            "james",
+            url,
+            event_types.iter().map(|val| val.to_string()).collect(),
+        )
+        .map_err(|e| e.field().unwrap_or_default().to_string())
+    }
+
+    #[test]
+    fn webhook_should_only_accept_public_https_urls() {
+        assert!(webhook("https://example.com/hooks/todo", &[]).is_ok());
+
+        for url in [
+            "http://example.com/hooks/todo",
+            "not a url",
+            "https://localhost/hooks",
+            "https://127.0.0.1/hooks",
+            "https://10.0.0.8/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/hooks",
+            "https://[::ffff:192.168.0.1]/hooks",
+        ] {
+            assert_eq!(webhook(url, &[]).err(), Some("url".to_string()), "{}", url);
+        }
+    }
+
+    #[test]
+    fn webhook_should_reject_unknown_event_types() {
+        let res = webhook("https://example.com/hooks/todo", &["ToDoDeleted"]);
+
+        assert_eq!(res.err(), Some("event_types".to_string()));
+    }
+
+    #[test]
+    fn webhook_should_only_subscribe_to_filtered_event_types() {
+        let filtered = webhook("https://example.com/hooks/todo", &["ToDoCompleted"]).unwrap();
+        let unfiltered = webhook("https://example.com/hooks/todo", &[]).unwrap();
+
+        assert!(filtered.subscribes_to("ToDoCompleted"));
+        assert!(!filtered.subscribes_to("ToDoCreated"));
+        assert!(unfiltered.subscribes_to("ToDoCreated"));
+        assert!(unfiltered.secret().starts_with("whsec_"));
+    }
+
+    #[test]
+    fn webhook_should_be_disabled_after_repeated_failures() {
+        let mut webhook = webhook("https://example.com/hooks/todo", &[]).unwrap();
+
+        for _ in 0..2 {
+            webhook = webhook.record_failure(3);
+        }
+
+        assert!(webhook.is_enabled());
+
+        webhook = webhook.record_failure(3);
+
+        assert!(!webhook.is_enabled());
+        assert!(!webhook.subscribes_to("ToDoCreated"));
+
+        webhook = webhook.enable();
+
+        assert!(webhook.is_enabled());
+        assert_eq!(webhook.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn success_should_reset_failure_count() {
+        let webhook = webhook("https://example.com/hooks/todo", &[])
+            .unwrap()
+            .record_failure(3)
+            .record_failure(3)
+            .record_success()
+            .record_failure(3);
+
+        assert!(webhook.is_enabled());
+        assert_eq!(webhook.consecutive_failures(), 1);
+    }
+}
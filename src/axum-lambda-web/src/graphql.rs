@@ -0,0 +1,298 @@
+use crate::application::commands::{create_to_do, update_todo};
+use crate::application::domain::{AppState, TenantId};
+use crate::application::error_types::{ServiceError, ServiceErrorKind};
+use crate::application::markdown::render_description_html;
+use crate::application::public_types::{CreateToDoCommand, ToDoItem, UpdateToDoCommand};
+use crate::application::queries::{get_todos, list_todos};
+use async_graphql::{
+    Context, EmptySubscription, Error, ErrorExtensions, InputObject, Object, PathSegment, Schema,
+    ServerError,
+};
+use std::sync::Arc;
+
+pub type ToDoSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+pub fn build_schema() -> ToDoSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+}
+
+/// The owner of the request, taken from the `user-id` header by the HTTP handler.
+pub struct Owner(pub String);
+
+struct ToDoObject(ToDoItem);
+
+#[Object(name = "ToDo")]
+impl ToDoObject {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn is_complete(&self) -> bool {
+        self.0.is_complete
+    }
+
+    async fn completed_on(&self) -> &str {
+        &self.0.completed_on
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    /// The description rendered from CommonMark to sanitised HTML, only rendered when requested.
+    async fn description_html(&self) -> String {
+        render_description_html(&self.0.description)
+    }
+
+    async fn due_date(&self) -> &str {
+        &self.0.due_date
+    }
+
+    async fn priority(&self) -> &str {
+        &self.0.priority
+    }
+
+    /// The ToDo's place in the owner's list, ToDos are listed in ascending order of position.
+    async fn position(&self) -> &str {
+        &self.0.position
+    }
+
+    /// The total of the ToDo's stopped timers, in seconds.
+    async fn tracked_seconds(&self) -> u64 {
+        self.0.tracked_seconds
+    }
+}
+
+#[derive(InputObject)]
+struct CreateTodoInput {
+    title: String,
+    description: Option<String>,
+    due_date: Option<String>,
+    priority: Option<String>,
+}
+
+#[derive(InputObject)]
+struct UpdateTodoInput {
+    title: String,
+    description: Option<String>,
+    due_date: Option<String>,
+    priority: Option<String>,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn todos(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ToDoObject>> {
+        let (state, owner, tenant) = request_data(ctx)?;
+
+        let listing = list_todos(owner, tenant, &state.todo_repo)
+            .await
+            .map_err(|_| Error::new("Failed to load ToDos"))?;
+
+        // The readable ToDos are still returned, alongside an error counting the rest.
+        if listing.skipped > 0 {
+            let mut server_error = ServerError::new(
+                format!("{} ToDos could not be read and were left out", listing.skipped),
+                Some(ctx.item.pos),
+            );
+            server_error.path = ctx
+                .path_node
+                .map(|node| node.to_string_vec())
+                .unwrap_or_default()
+                .into_iter()
+                .map(PathSegment::Field)
+                .collect();
+
+            let extensions = server_error.extensions.get_or_insert_with(Default::default);
+            extensions.set("code", "SKIPPED_ITEMS");
+            extensions.set("skipped", listing.skipped);
+
+            ctx.add_error(server_error);
+        }
+
+        Ok(listing.items.into_iter().map(ToDoObject).collect())
+    }
+
+    async fn todo(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<ToDoObject> {
+        let (state, owner, tenant) = request_data(ctx)?;
+
+        let item = get_todos(owner, tenant, &id, &state.todo_repo)
+            .await
+            .map_err(|_| Error::new(format!("ToDo '{}' not found", id)))?;
+
+        Ok(ToDoObject(item))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Returns null, along with an error for each invalid field, when the input is invalid.
+    async fn create_todo(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateTodoInput,
+    ) -> async_graphql::Result<Option<ToDoObject>> {
+        let (state, owner, tenant) = request_data(ctx)?;
+
+        let res = create_to_do(
+            owner.to_string(),
+            tenant,
+            CreateToDoCommand {
+                title: input.title,
+                description: input.description,
+                due_date: input.due_date,
+                priority: input.priority,
+            },
+            state.max_open_todos,
+            &state.todo_repo,
+            &state.message_publisher,
+        )
+        .await;
+
+        resolve_command(ctx, res)
+    }
+
+    /// Returns null, along with an error for each invalid field, when the input is invalid.
+    async fn update_todo(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        input: UpdateTodoInput,
+    ) -> async_graphql::Result<Option<ToDoObject>> {
+        let (state, owner, tenant) = request_data(ctx)?;
+
+        let res = update_todo(
+            owner.to_string(),
+            tenant,
+            id,
+            UpdateToDoCommand {
+                title: input.title,
+                set_as_complete: false,
+                description: input.description,
+                due_date: input.due_date,
+                priority: input.priority,
+            },
+            &state.todo_repo,
+            &state.message_publisher,
+        )
+        .await;
+
+        resolve_command(ctx, res)
+    }
+
+    async fn complete_todo(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Option<ToDoObject>> {
+        let (state, owner, tenant) = request_data(ctx)?;
+
+        let existing = get_todos(owner, tenant, &id, &state.todo_repo)
+            .await
+            .map_err(|_| Error::new(format!("ToDo '{}' not found", id)))?;
+
+        let res = update_todo(
+            owner.to_string(),
+            tenant,
+            id,
+            UpdateToDoCommand {
+                title: existing.title,
+                set_as_complete: true,
+                description: None,
+                due_date: None,
+                priority: None,
+            },
+            &state.todo_repo,
+            &state.message_publisher,
+        )
+        .await;
+
+        resolve_command(ctx, res)
+    }
+}
+
+// The tenant is added to the request by the HTTP handler, alongside the owner.
+fn request_data<'a>(
+    ctx: &Context<'a>,
+) -> async_graphql::Result<(&'a Arc<AppState>, &'a str, &'a TenantId)> {
+    let state = ctx.data::<Arc<AppState>>()?;
+    let owner = ctx.data::<Owner>()?;
+    let tenant = ctx.data::<TenantId>()?;
+
+    Ok((state, owner.0.as_str(), tenant))
+}
+
+/// Validation failures are reported as one GraphQL error per invalid field, with the path
+/// pointing at that field of the mutation input, e.g. `["createTodo", "input", "title"]`.
+fn resolve_command(
+    ctx: &Context<'_>,
+    res: Result<ToDoItem, ServiceError>,
+) -> async_graphql::Result<Option<ToDoObject>> {
+    let err = match res {
+        Ok(item) => return Ok(Some(ToDoObject(item))),
+        Err(err) => err,
+    };
+
+    if err.kind() != &ServiceErrorKind::Validation || err.validation_errors().is_empty() {
+        return Err(Error::new(err.to_string()).extend_with(|_, extensions| {
+            extensions.set("code", error_code(err.kind()))
+        }));
+    }
+
+    let resolver_path: Vec<PathSegment> = ctx
+        .path_node
+        .map(|node| node.to_string_vec())
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathSegment::Field)
+        .collect();
+
+    for validation_error in err.validation_errors() {
+        let mut path = resolver_path.clone();
+
+        if let Some(field) = validation_error.field().and_then(input_field_name) {
+            path.push(PathSegment::Field(String::from("input")));
+            path.push(PathSegment::Field(field.to_string()));
+        }
+
+        let mut server_error = ServerError::new(validation_error.message(), Some(ctx.item.pos));
+        server_error.path = path;
+        server_error
+            .extensions
+            .get_or_insert_with(Default::default)
+            .set("code", error_code(&ServiceErrorKind::Validation));
+
+        ctx.add_error(server_error);
+    }
+
+    Ok(None)
+}
+
+fn error_code(kind: &ServiceErrorKind) -> &'static str {
+    match kind {
+        ServiceErrorKind::Validation => "VALIDATION_ERROR",
+        ServiceErrorKind::QuotaExceeded => "QUOTA_EXCEEDED",
+        ServiceErrorKind::NotFound => "NOT_FOUND",
+        ServiceErrorKind::Conflict => "CONFLICT",
+        ServiceErrorKind::Repository => "INTERNAL_ERROR",
+    }
+}
+
+// Validation errors name fields as they appear in the REST API. Errors for anything that is
+// not part of the mutation input, such as the owner, are reported against the mutation.
+fn input_field_name(field: &str) -> Option<&'static str> {
+    match field {
+        "title" => Some("title"),
+        "description" => Some("description"),
+        "due_date" => Some("dueDate"),
+        "priority" => Some("priority"),
+        _ => None,
+    }
+}
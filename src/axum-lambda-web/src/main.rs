@@ -1,38 +1,184 @@
 mod application;
+mod graphql;
+mod negotiation;
+mod rate_limit;
+mod shutdown;
+mod tenancy;
 
 use std::env;
 
-use crate::application::adapters::DynamoDbToDoRepo;
-use crate::application::commands::{create_to_do, update_todo};
-use crate::application::domain::AppState;
-use crate::application::messaging::{EventBridgeEventPublisher, InMemoryMessagePublisher};
-use crate::application::public_types::{CreateToDoCommand, ToDoItem, UpdateToDoCommand};
-use crate::application::queries::{get_todos, list_todos};
+use crate::application::adapters::{DynamoDbToDoRepo, InMemoryToDoRepo};
+use crate::application::attachments::AttachmentStore;
+use crate::application::cache::CachingToDoRepo;
+use crate::application::commands::{
+    add_attachment, confirm_attachment, create_to_do, delete_webhook, import_todos, move_todo,
+    register_webhook, start_timer, stop_timer, update_preferences, update_todo, update_webhook,
+};
+use crate::application::domain::{AppState, DueDateView, TenantId};
+use crate::application::messaging::{
+    local_message_publisher, message_publisher_from_env, with_event_buffer, MessagePublisher,
+    QueuedWebhookBatch, QueuedWebhookBatchResponse, WebhookDispatcher,
+};
+use crate::application::public_types::{
+    AddAttachmentCommand, AttachmentDownload, AttachmentItem, AttachmentUpload, CreateToDoCommand,
+    ImportReport, MoveToDoCommand, RegisterWebhookCommand, TimeEntryItem, ToDoItem,
+    UpdateToDoCommand, UpdateWebhookCommand, UserPreferences, WebhookDeliveryItem, WebhookItem,
+};
+use crate::application::queries::{
+    export_todos, get_preferences, get_todos, list_attachments, list_todos, list_todos_in_view,
+    list_webhook_deliveries, list_webhooks, search_todos,
+};
+use crate::application::error_types::{RepositoryErrorKind, ServiceErrorKind};
+use crate::application::health::{self, check_readiness};
+use crate::application::reminders::{
+    LeadTimes, ReminderRunSummary, ReminderScheduler, ReminderStore,
+};
+use crate::application::timers::TimerRepo;
+use crate::application::transfer::TransferFormat;
+use crate::application::webhooks::WebhookRepo;
+use crate::graphql::{build_schema, Owner, ToDoSchema};
+use crate::negotiation::{Encoding, JSON_CONTENT_TYPE};
+use crate::rate_limit::RateLimiter;
+use crate::shutdown::{
+    drain_deadline_from_env, serve_until_drained, shutdown_signal, track_in_flight,
+    InFlightRequests,
+};
+use crate::tenancy::TenantResolver;
 use aws_config::{BehaviorVersion, Region, SdkConfig};
 use aws_sdk_dynamodb::Client;
-use axum::response::IntoResponse;
-use axum::{extract::Path, extract::State, response::Json, routing::get, Router};
-use http::{HeaderMap, StatusCode};
+use axum::body::Body;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{post, put};
+use axum::{extract::Path, extract::Query, extract::State, response::Json, routing::get, Extension, Router};
+use http::header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER, VARY};
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
 use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use lambda_runtime::{service_fn, LambdaEvent};
+use std::io::Write;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+// The largest payload a Lambda function can receive or return.
+const MAX_BODY_BYTES: usize = 6 * 1024 * 1024;
+
 #[derive(Serialize, Deserialize)]
 struct ApiResponse<T> {
     data: T,
     message: String,
 }
 
+/// The response to listing ToDos, `skipped` counts the owner's stored ToDos that could not be
+/// read and were left out of `data`.
+#[derive(Serialize, Deserialize)]
+struct ListResponse<T> {
+    data: T,
+    message: String,
+    skipped: usize,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    view: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RenderParams {
+    render: Option<String>,
+}
+
+impl RenderParams {
+    fn render_item(&self, item: ToDoItem) -> ToDoItem {
+        match self.render.as_deref() {
+            Some("html") => item.with_description_html(),
+            _ => item,
+        }
+    }
+
+    fn render_items(&self, items: Vec<ToDoItem>) -> Vec<ToDoItem> {
+        items.into_iter().map(|item| self.render_item(item)).collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct TransferParams {
+    format: Option<String>,
+}
+
+#[cfg(test)]
 fn app(app_state: Arc<AppState>) -> Router {
+    app_with_rate_limiter(app_state, Arc::new(RateLimiter::default()))
+}
+
+fn app_with_rate_limiter(app_state: Arc<AppState>, rate_limiter: Arc<RateLimiter>) -> Router {
+    app_with(
+        app_state,
+        rate_limiter,
+        Arc::new(TenantResolver::from_env()),
+    )
+}
+
+fn app_with(
+    app_state: Arc<AppState>,
+    rate_limiter: Arc<RateLimiter>,
+    tenant_resolver: Arc<TenantResolver>,
+) -> Router {
     Router::new()
-        .route("/health", get(health))
         .route("/todo", get(list_todo_endpoint).post(post_todo_endpoint))
+        .route("/todo/search", get(search_todo_endpoint))
+        .route("/todo/export", get(export_todo_endpoint))
+        .route("/todo/import", post(import_todo_endpoint))
         .route(
             "/todo/:id",
             get(get_todo_endpoint).put(update_todo_endpoint),
         )
+        .route("/todo/:id/move", post(move_todo_endpoint))
+        .route("/todo/:id/timer/start", post(start_timer_endpoint))
+        .route("/todo/:id/timer/stop", post(stop_timer_endpoint))
+        .route(
+            "/todo/:id/attachments",
+            get(list_attachments_endpoint).post(post_attachment_endpoint),
+        )
+        .route(
+            "/todo/:id/attachments/:attachment_id/confirm",
+            post(confirm_attachment_endpoint),
+        )
+        .route(
+            "/preferences",
+            get(get_preferences_endpoint).put(put_preferences_endpoint),
+        )
+        .route(
+            "/webhooks",
+            get(list_webhooks_endpoint).post(post_webhook_endpoint),
+        )
+        .route(
+            "/webhooks/:id",
+            put(update_webhook_endpoint).delete(delete_webhook_endpoint),
+        )
+        .route("/webhooks/:id/deliveries", get(list_webhook_deliveries_endpoint))
+        .route("/graphql", post(graphql_endpoint))
+        .route_layer(middleware::from_fn_with_state(rate_limiter, rate_limit))
+        .route_layer(middleware::from_fn_with_state(tenant_resolver, resolve_tenant))
+        .route("/health", get(health))
+        .route("/health/live", get(health))
+        .route("/health/ready", get(ready))
+        .layer(Extension(build_schema()))
+        .layer(middleware::from_fn_with_state(
+            app_state.message_publisher.clone(),
+            buffer_events,
+        ))
         .with_state(app_state)
         .fallback(handler_404)
+        .layer(middleware::from_fn(negotiate_encoding))
 }
 
 #[tokio::main]
@@ -50,10 +196,21 @@ async fn main() {
     let config: SdkConfig = aws_config::load_defaults(BehaviorVersion::latest()).await;
 
     let mut dynamodb_client: Client = Client::new(&config);
-    let eventbridge_client: aws_sdk_eventbridge::Client = aws_sdk_eventbridge::Client::new(&config);
 
     let mut table_name = env::var("TABLE_NAME").expect("TABLE_NAME must be set");
 
+    let max_open_todos: Option<usize> = env::var("MAX_OPEN_TODOS_PER_OWNER")
+        .ok()
+        .and_then(|val| val.parse().ok());
+
+    let attachment_store = match AttachmentStore::from_env(&config) {
+        Ok(attachment_store) => attachment_store.map(Arc::new),
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     if use_local.is_ok() {
         let dynamodb_local_config = aws_sdk_dynamodb::config::Builder::from(&config)
             .endpoint_url(
@@ -66,31 +223,392 @@ async fn main() {
         table_name = String::from("TODO");
     }
 
+    let dynamodb_repo = Arc::new(DynamoDbToDoRepo::new(
+        dynamodb_client.clone(),
+        table_name.clone(),
+    ));
+    let mut reminder_store: Arc<dyn ReminderStore + Send + Sync> = dynamodb_repo.clone();
+    let webhook_repo: Arc<dyn WebhookRepo + Send + Sync> = dynamodb_repo.clone();
+    let timer_repo: Arc<dyn TimerRepo + Send + Sync> = dynamodb_repo.clone();
+    let todo_repo = CachingToDoRepo::wrap_from_env(dynamodb_repo);
+
+    if let Ok(delivery_mode) = env::var("WEBHOOK_DELIVERY_MODE") {
+        let dispatcher = Arc::new(WebhookDispatcher::from_env(webhook_repo));
+
+        if let Err(e) = run_webhook_delivery(delivery_mode.as_str(), dispatcher).await {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let message_publisher = match message_publisher_from_env(&config) {
+        Ok(message_publisher) => message_publisher,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     let mut shared_state = Arc::new(AppState {
-        todo_repo: Arc::new(DynamoDbToDoRepo::new(
-            dynamodb_client.clone(),
-            table_name.clone(),
-        )),
-        message_publisher: Arc::new(EventBridgeEventPublisher::new(eventbridge_client)),
+        todo_repo: todo_repo.clone(),
+        message_publisher,
+        max_open_todos,
+        attachment_store: attachment_store.clone(),
+        webhook_repo: webhook_repo.clone(),
+        timer_repo: timer_repo.clone(),
     });
 
-    if use_local.is_ok() {
+    // Running locally events are only logged and sent to webhooks, unless publishers have been
+    // chosen explicitly, such as SNS or SQS pointed at a local stand-in endpoint.
+    if use_local.is_ok() && env::var("MESSAGE_PUBLISHERS").is_err() {
         shared_state = Arc::new(AppState {
-            todo_repo: Arc::new(DynamoDbToDoRepo::new(
-                dynamodb_client.clone(),
-                table_name.clone(),
-            )),
-            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            todo_repo,
+            message_publisher: local_message_publisher(webhook_repo.clone()),
+            max_open_todos,
+            attachment_store: attachment_store.clone(),
+            webhook_repo,
+            timer_repo,
         });
     }
 
-    let app = app(shared_state);
+    if env::var("USE_IN_MEMORY").is_ok() {
+        let in_memory_repo = Arc::new(InMemoryToDoRepo::new());
+        reminder_store = in_memory_repo.clone();
+
+        shared_state = Arc::new(AppState {
+            todo_repo: in_memory_repo.clone(),
+            message_publisher: local_message_publisher(in_memory_repo.clone()),
+            max_open_todos,
+            attachment_store: attachment_store.clone(),
+            webhook_repo: in_memory_repo.clone(),
+            timer_repo: in_memory_repo,
+        });
+    }
+
+    if let Ok(reminder_mode) = env::var("REMINDER_MODE") {
+        let scheduler = Arc::new(ReminderScheduler::new(
+            reminder_store,
+            shared_state.message_publisher.clone(),
+            LeadTimes::from_env(),
+        ));
+
+        if let Err(e) = run_reminder_scheduler(reminder_mode.as_str(), scheduler).await {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let in_flight = Arc::new(InFlightRequests::new());
+    let message_publisher = shared_state.message_publisher.clone();
+
+    let rate_limiter = match RateLimiter::from_env() {
+        Ok(rate_limiter) => Arc::new(rate_limiter),
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let app = app_with_rate_limiter(shared_state, rate_limiter)
+        .layer(middleware::from_fn_with_state(in_flight.clone(), track_in_flight));
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
         .await
         .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    let (signal_sender, signal_receiver) = tokio::sync::oneshot::channel();
+
+    // Once a signal arrives the server stops accepting connections and waits for the
+    // requests it is already handling.
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        let _ = signal_sender.send(shutdown_signal().await);
+    });
+
+    let summary = serve_until_drained(
+        async move { server.await.unwrap() },
+        signal_receiver,
+        &in_flight,
+        drain_deadline_from_env(),
+    )
+    .await;
+
+    let mut summary = match summary {
+        Some(summary) => summary,
+        None => return,
+    };
+
+    summary.undelivered_events = match message_publisher.flush().await {
+        Ok(_) => 0,
+        Err(e) => {
+            tracing::error!("{}", e);
+            e.undelivered_events()
+        }
+    };
+
+    summary.log();
+
+    // Traces are written to stdout, make sure nothing is left buffered when the process exits.
+    let _ = std::io::stdout().flush();
+}
+
+/// Run the reminder scheduler instead of the API. In `lambda` mode each invocation, normally
+/// triggered by an EventBridge schedule, is a single run. In `local` mode the scheduler runs in
+/// a loop every `REMINDER_INTERVAL_SECONDS`. `backfill` adds the ToDos stored before the due date
+/// index was sharded to it, once, and exits.
+async fn run_reminder_scheduler(
+    reminder_mode: &str,
+    scheduler: Arc<ReminderScheduler>,
+) -> Result<(), String> {
+    match reminder_mode {
+        "lambda" => {
+            lambda_runtime::run(service_fn(|_event: LambdaEvent<serde_json::Value>| {
+                let scheduler = scheduler.clone();
+
+                async move {
+                    let summary = scheduler.run(Utc::now()).await?;
+
+                    tracing::info!("{}", serde_json::json!(summary));
+
+                    Ok::<ReminderRunSummary, lambda_runtime::Error>(summary)
+                }
+            }))
+            .await
+            .map_err(|e| e.to_string())
+        }
+        "local" => {
+            let interval_seconds = env::var("REMINDER_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|val| val.parse().ok())
+                .unwrap_or(60);
+
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
+
+            loop {
+                interval.tick().await;
+
+                match scheduler.run(Utc::now()).await {
+                    Ok(summary) => tracing::info!("{}", serde_json::json!(summary)),
+                    Err(e) => tracing::error!("Reminder run failed: {}", e),
+                }
+            }
+        }
+        "backfill" => {
+            let indexed = scheduler.index_existing().await.map_err(|e| e.to_string())?;
+
+            tracing::info!("Added {} ToDos to the due date index", indexed);
+
+            Ok(())
+        }
+        _ => Err(format!(
+            "REMINDER_MODE must be 'lambda', 'local' or 'backfill', not '{}'",
+            reminder_mode
+        )),
+    }
+}
+
+async fn run_webhook_delivery(
+    delivery_mode: &str,
+    dispatcher: Arc<WebhookDispatcher>,
+) -> Result<(), String> {
+    match delivery_mode {
+        "lambda" => {
+            lambda_runtime::run(service_fn(|event: LambdaEvent<QueuedWebhookBatch>| {
+                let dispatcher = dispatcher.clone();
+
+                async move {
+                    Ok::<QueuedWebhookBatchResponse, lambda_runtime::Error>(
+                        dispatcher.deliver_queued(event.payload).await,
+                    )
+                }
+            }))
+            .await
+            .map_err(|e| e.to_string())
+        }
+        // Running locally webhook events are delivered by the process that published them.
+        _ => Err(format!(
+            "WEBHOOK_DELIVERY_MODE must be 'lambda', not '{}'",
+            delivery_mode
+        )),
+    }
+}
+
+/// Give each request its own buffer of events, so a flush only sends, or dead-letters, the
+/// events of the request that made it. Anything the handler left unflushed is sent once the
+/// response is ready.
+async fn buffer_events(
+    State(message_publisher): State<Arc<dyn MessagePublisher + Send + Sync>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    with_event_buffer(async move {
+        let response = next.run(request).await;
+
+        if let Err(e) = message_publisher.flush().await {
+            tracing::error!(undelivered_events = e.undelivered_events(), "{}", e);
+        }
+
+        response
+    })
+    .await
+}
+
+/// Reject requests from an owner that has used up their rate limit with a 429 and a
+/// `Retry-After` header. Requests without an owner are left for the handler to reject. Runs
+/// inside `resolve_tenant`, so the owner's bucket is the one in the request's tenant.
+async fn rate_limit(
+    State(rate_limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let owner = request
+        .headers()
+        .get("user-id")
+        .and_then(|val| val.to_str().ok())
+        .map(|val| val.to_string());
+    let tenant = request
+        .extensions()
+        .get::<TenantId>()
+        .cloned()
+        .unwrap_or_default();
+
+    if let Some(owner) = owner {
+        if let Err(retry_after) = rate_limiter.try_acquire(&tenant, &owner) {
+            let retry_after_seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after_seconds.to_string())],
+                Json(ApiResponse {
+                    data: "",
+                    message: format!(
+                        "Rate limit exceeded, retry after {} seconds",
+                        retry_after_seconds
+                    ),
+                }),
+            )
+                .into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Work out the tenant of the request and add it to the request extensions for the handlers.
+/// Requests with an unreadable tenant are rejected with a 400, and requests whose token and
+/// host name different tenants with a 403.
+async fn resolve_tenant(
+    State(tenant_resolver): State<Arc<TenantResolver>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match tenant_resolver.resolve(request.headers()) {
+        Ok(tenant) => {
+            request.extensions_mut().insert(tenant);
+
+            next.run(request).await
+        }
+        Err(e) => (
+            e.status(),
+            Json(ApiResponse {
+                data: "",
+                message: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Let clients send and receive MessagePack or CBOR instead of JSON. Request bodies are
+/// converted to JSON before they reach the handlers, and JSON responses are converted to the
+/// encoding preferred in the `Accept` header.
+async fn negotiate_encoding(request: Request, next: Next) -> Response {
+    let response_encoding = Encoding::from_accept(request.headers().get(ACCEPT));
+    let request_encoding = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .and_then(Encoding::from_content_type);
+    let has_body = matches!(*request.method(), Method::POST | Method::PUT | Method::PATCH);
+
+    let response = match request_encoding {
+        Some(encoding) if encoding != Encoding::Json && has_body => {
+            let (mut parts, body) = request.into_parts();
+
+            let transcoded = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+                Ok(bytes) => Encoding::Json.transcode(encoding, &bytes),
+                Err(e) => Err(e.to_string()),
+            };
+
+            match transcoded {
+                Ok(json) => {
+                    parts
+                        .headers
+                        .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+                    parts.headers.remove(CONTENT_LENGTH);
+
+                    next.run(Request::from_parts(parts, Body::from(json))).await
+                }
+                Err(e) => (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiResponse {
+                        data: "",
+                        message: format!("Request body is not valid {}: {}", encoding.content_type(), e),
+                    }),
+                )
+                    .into_response(),
+            }
+        }
+        _ => next.run(request).await,
+    };
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .and_then(Encoding::from_content_type)
+        == Some(Encoding::Json);
+
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts.headers.append(VARY, HeaderValue::from_static("accept"));
+
+    if response_encoding == Encoding::Json {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read response body: {}", e);
+
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match response_encoding.transcode(Encoding::Json, &bytes) {
+        Ok(encoded) => {
+            parts.headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(response_encoding.content_type()),
+            );
+            parts.headers.remove(CONTENT_LENGTH);
+
+            Response::from_parts(parts, Body::from(encoded))
+        }
+        // Fall back to the JSON the handler wrote rather than failing the request.
+        Err(e) => {
+            tracing::warn!("Failed to encode response as {}: {}", response_encoding.content_type(), e);
+
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
 }
 
 async fn health() -> impl IntoResponse {
@@ -103,6 +621,25 @@ async fn health() -> impl IntoResponse {
     )
 }
 
+/// Report whether every required dependency can be reached, with a 503 when one cannot so
+/// the load balancer stops sending traffic.
+async fn ready(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let report = check_readiness(&state, health::timeout_from_env()).await;
+
+    let (status, message) = match report.ready {
+        true => (StatusCode::OK, "Ready"),
+        false => (StatusCode::SERVICE_UNAVAILABLE, "Not ready"),
+    };
+
+    (
+        status,
+        Json(ApiResponse {
+            data: report,
+            message: message.to_string(),
+        }),
+    )
+}
+
 async fn handler_404() -> impl IntoResponse {
     (
         StatusCode::NOT_FOUND,
@@ -115,47 +652,264 @@ async fn handler_404() -> impl IntoResponse {
 
 async fn list_todo_endpoint(
     headers: HeaderMap,
+    Query(render): Query<RenderParams>,
+    Query(params): Query<ListParams>,
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
 ) -> impl IntoResponse {
     match check_user_header(headers) {
         Ok(user_id) => {
-            let items = list_todos(&user_id, &state.todo_repo).await.unwrap();
+            let listing = match params.view.as_deref().map(DueDateView::new) {
+                None => list_todos(&user_id, &tenant, &state.todo_repo).await,
+                Some(Ok(view)) => {
+                    list_todos_in_view(&user_id, &tenant, view, Utc::now(), &state.todo_repo).await
+                }
+                Some(Err(e)) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(ListResponse {
+                            data: Vec::new(),
+                            message: e.to_string(),
+                            skipped: 0,
+                        }),
+                    )
+                }
+            };
 
-            let response = ApiResponse {
-                data: items,
+            let listing = match listing {
+                Ok(listing) => listing,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ListResponse {
+                            data: Vec::new(),
+                            message: "Failed to load ToDos".to_string(),
+                            skipped: 0,
+                        }),
+                    )
+                }
+            };
+
+            let response = ListResponse {
+                data: render.render_items(listing.items),
                 message: "Success".to_string(),
+                skipped: listing.skipped,
             };
 
             (StatusCode::OK, Json(response))
         }
         Err(_) => (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse {
+            Json(ListResponse {
                 data: Vec::new(),
                 message: "Please set the 'user-id".to_string(),
+                skipped: 0,
             }),
         ),
     }
 }
 
-async fn get_todo_endpoint(
-    Path(id): Path<String>,
+async fn search_todo_endpoint(
     headers: HeaderMap,
+    Query(params): Query<SearchParams>,
+    Query(render): Query<RenderParams>,
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
 ) -> impl IntoResponse {
     match check_user_header(headers) {
         Ok(user_id) => {
-            let todo = get_todos(&user_id, id.as_str(), &state.todo_repo)
-                .await
-                .unwrap();
+            let items = match search_todos(
+                &user_id,
+                &tenant,
+                &params.q,
+                params.limit,
+                &state.todo_repo,
+            )
+            .await
+            {
+                Ok(items) => items,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse {
+                            data: Vec::new(),
+                            message: "Failed to search ToDos".to_string(),
+                        }),
+                    )
+                }
+            };
 
             let response = ApiResponse {
-                data: todo,
+                data: render.render_items(items),
                 message: "Success".to_string(),
             };
 
             (StatusCode::OK, Json(response))
         }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: Vec::new(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn export_todo_endpoint(
+    headers: HeaderMap,
+    Query(params): Query<TransferParams>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> Response {
+    let user_id = match check_user_header(headers) {
+        Ok(user_id) => user_id,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse {
+                    data: "",
+                    message: "Please set the 'user-id".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let format = match TransferFormat::parse(params.format.as_deref().unwrap_or("json")) {
+        Ok(format) => format,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse {
+                    data: "",
+                    message: e.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let chunks = match export_todos(&user_id, &tenant, format, &state.todo_repo).await {
+        Ok(chunks) => chunks,
+        Err(e) => {
+            tracing::error!("Failed to export ToDos: {}", e);
+
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse {
+                    data: "",
+                    message: "Failed to export ToDos".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"todos.{}\"", format.file_extension()),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+async fn import_todo_endpoint(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Query(params): Query<TransferParams>,
+    body: String,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let report = match TransferFormat::parse(params.format.as_deref().unwrap_or("json")) {
+                Ok(format) => {
+                    import_todos(
+                        user_id,
+                        &tenant,
+                        format,
+                        body.as_str(),
+                        state.max_open_todos,
+                        &state.todo_repo,
+                        &state.message_publisher,
+                    )
+                    .await
+                }
+                Err(e) => Err(e.into()),
+            };
+
+            match report {
+                Ok(report) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: report,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiResponse {
+                        data: ImportReport::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: ImportReport::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn get_todo_endpoint(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Query(render): Query<RenderParams>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            // ToDos that belong to another owner or tenant are reported as not found.
+            match get_todos(&user_id, &tenant, id.as_str(), &state.todo_repo).await {
+                Ok(todo) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: render.render_item(todo),
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) if e.kind() == &RepositoryErrorKind::NotFound => (
+                    StatusCode::NOT_FOUND,
+                    Json(ApiResponse {
+                        data: ToDoItem::default(),
+                        message: format!("ToDo '{}' not found", id),
+                    }),
+                ),
+                Err(e) => {
+                    tracing::error!("Failed to load ToDo {}: {}", id, e);
+
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse {
+                            data: ToDoItem::default(),
+                            message: "Failed to load ToDo".to_string(),
+                        }),
+                    )
+                }
+            }
+        }
         Err(_) => (
             StatusCode::BAD_REQUEST,
             Json(ApiResponse {
@@ -168,21 +922,38 @@ async fn get_todo_endpoint(
 
 async fn post_todo_endpoint(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
     headers: HeaderMap,
     Json(input): Json<CreateToDoCommand>,
 ) -> impl IntoResponse {
     match check_user_header(headers) {
         Ok(user_id) => {
-            let todo = create_to_do(user_id, input, &state.todo_repo, &state.message_publisher)
-                .await
-                .unwrap();
-
-            let response = ApiResponse {
-                data: todo,
-                message: "Success".to_string(),
-            };
+            let todo = create_to_do(
+                user_id,
+                &tenant,
+                input,
+                state.max_open_todos,
+                &state.todo_repo,
+                &state.message_publisher,
+            )
+            .await;
 
-            (StatusCode::OK, Json(response))
+            match todo {
+                Ok(todo) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: todo,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: ToDoItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
         }
         Err(_) => (
             StatusCode::BAD_REQUEST,
@@ -197,6 +968,7 @@ async fn post_todo_endpoint(
 async fn update_todo_endpoint(
     Path(id): Path<String>,
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
     headers: HeaderMap,
     Json(input): Json<UpdateToDoCommand>,
 ) -> impl IntoResponse {
@@ -204,20 +976,30 @@ async fn update_todo_endpoint(
         Ok(user_id) => {
             let todo = update_todo(
                 user_id,
+                &tenant,
                 id,
                 input,
                 &state.todo_repo,
                 &state.message_publisher,
             )
-            .await
-            .unwrap();
-
-            let response = ApiResponse {
-                data: todo,
-                message: "Success".to_string(),
-            };
+            .await;
 
-            (StatusCode::OK, Json(response))
+            match todo {
+                Ok(todo) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: todo,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: ToDoItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
         }
         Err(_) => (
             StatusCode::BAD_REQUEST,
@@ -229,136 +1011,2139 @@ async fn update_todo_endpoint(
     }
 }
 
-fn check_user_header(headers: HeaderMap) -> Result<String, ()> {
-    return if let Some(user_id) = headers.get("user-id") {
-        Ok(user_id.to_str().unwrap().to_string())
-    } else {
-        Err(())
-    };
-}
+async fn move_todo_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Json(input): Json<MoveToDoCommand>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let todo = move_todo(
+                user_id,
+                &tenant,
+                id,
+                input,
+                &state.todo_repo,
+                &state.message_publisher,
+            )
+            .await;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use axum::response::Response;
-    use axum::{
-        body::Body,
-        http::{self, Request, StatusCode},
+            match todo {
+                Ok(todo) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: todo,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: ToDoItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: ToDoItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn start_timer_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let entry = start_timer(
+                user_id,
+                &tenant,
+                id,
+                &state.todo_repo,
+                &state.timer_repo,
+                &state.message_publisher,
+            )
+            .await;
+
+            match entry {
+                Ok(entry) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: entry,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: TimeEntryItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: TimeEntryItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn stop_timer_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let entry = stop_timer(
+                user_id,
+                &tenant,
+                id,
+                &state.todo_repo,
+                &state.timer_repo,
+                &state.message_publisher,
+            )
+            .await;
+
+            match entry {
+                Ok(entry) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: entry,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: TimeEntryItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: TimeEntryItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn post_attachment_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Json(input): Json<AddAttachmentCommand>,
+) -> impl IntoResponse {
+    let attachment_store = match &state.attachment_store {
+        Some(attachment_store) => attachment_store,
+        None => return attachments_disabled(AttachmentUpload::default()),
     };
-    use http::Method;
-    use http_body_util::BodyExt;
-    use tower::ServiceExt;
 
-    struct ApiDriver {
-        router: Box<Router>,
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let upload = add_attachment(
+                user_id,
+                &tenant,
+                id,
+                input,
+                attachment_store,
+                &state.todo_repo,
+            )
+            .await;
+
+            match upload {
+                Ok(upload) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: upload,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: AttachmentUpload::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: AttachmentUpload::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn confirm_attachment_endpoint(
+    Path((id, attachment_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let attachment_store = match &state.attachment_store {
+        Some(attachment_store) => attachment_store,
+        None => return attachments_disabled(AttachmentItem::default()),
+    };
+
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let confirmed = confirm_attachment(
+                user_id,
+                &tenant,
+                id,
+                attachment_id,
+                attachment_store,
+                &state.todo_repo,
+                &state.message_publisher,
+            )
+            .await;
+
+            match confirmed {
+                Ok(attachment) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: attachment,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: AttachmentItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: AttachmentItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn list_attachments_endpoint(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> impl IntoResponse {
+    let attachment_store = match &state.attachment_store {
+        Some(attachment_store) => attachment_store,
+        None => return attachments_disabled(Vec::new()),
+    };
+
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            match list_attachments(&user_id, &tenant, id.as_str(), attachment_store, &state.todo_repo)
+                .await
+            {
+                Ok(downloads) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: downloads,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: Vec::<AttachmentDownload>::new(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: Vec::new(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+fn attachments_disabled<T>(data: T) -> (StatusCode, Json<ApiResponse<T>>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ApiResponse {
+            data,
+            message: "Attachments are not enabled".to_string(),
+        }),
+    )
+}
+
+async fn list_webhooks_endpoint(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => match list_webhooks(&user_id, &tenant, &state.webhook_repo).await {
+            Ok(webhooks) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    data: webhooks,
+                    message: "Success".to_string(),
+                }),
+            ),
+            Err(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse {
+                    data: Vec::<WebhookItem>::new(),
+                    message: "Failed to load webhooks".to_string(),
+                }),
+            ),
+        },
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: Vec::new(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn post_webhook_endpoint(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Json(input): Json<RegisterWebhookCommand>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => match register_webhook(user_id, &tenant, input, &state.webhook_repo).await {
+            Ok(webhook) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    data: webhook,
+                    message: "Success".to_string(),
+                }),
+            ),
+            Err(e) => (
+                status_for(e.kind()),
+                Json(ApiResponse {
+                    data: WebhookItem::default(),
+                    message: e.to_string(),
+                }),
+            ),
+        },
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: WebhookItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn update_webhook_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Json(input): Json<UpdateWebhookCommand>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            match update_webhook(user_id, &tenant, id, input, &state.webhook_repo).await {
+                Ok(webhook) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: webhook,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(e) => (
+                    status_for(e.kind()),
+                    Json(ApiResponse {
+                        data: WebhookItem::default(),
+                        message: e.to_string(),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: WebhookItem::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn delete_webhook_endpoint(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => match delete_webhook(user_id, &tenant, id, &state.webhook_repo).await {
+            Ok(_) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    data: (),
+                    message: "Success".to_string(),
+                }),
+            ),
+            Err(e) => (
+                status_for(e.kind()),
+                Json(ApiResponse {
+                    data: (),
+                    message: e.to_string(),
+                }),
+            ),
+        },
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: (),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn list_webhook_deliveries_endpoint(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            match list_webhook_deliveries(&user_id, &tenant, id.as_str(), &state.webhook_repo).await
+            {
+                Ok(deliveries) => (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        data: deliveries,
+                        message: "Success".to_string(),
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::NOT_FOUND,
+                    Json(ApiResponse {
+                        data: Vec::<WebhookDeliveryItem>::new(),
+                        message: format!("Webhook '{}' not found", id),
+                    }),
+                ),
+            }
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: Vec::new(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn get_preferences_endpoint(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => match get_preferences(&user_id, &tenant, &state.todo_repo).await {
+            Ok(preferences) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    data: preferences,
+                    message: "Success".to_string(),
+                }),
+            ),
+            Err(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse {
+                    data: UserPreferences::default(),
+                    message: "Failed to load preferences".to_string(),
+                }),
+            ),
+        },
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: UserPreferences::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn put_preferences_endpoint(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    headers: HeaderMap,
+    Json(input): Json<UserPreferences>,
+) -> impl IntoResponse {
+    match check_user_header(headers) {
+        Ok(user_id) => match update_preferences(user_id, &tenant, input, &state.todo_repo).await {
+            Ok(preferences) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    data: preferences,
+                    message: "Success".to_string(),
+                }),
+            ),
+            Err(e) => (
+                status_for(e.kind()),
+                Json(ApiResponse {
+                    data: UserPreferences::default(),
+                    message: e.to_string(),
+                }),
+            ),
+        },
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: UserPreferences::default(),
+                message: "Please set the 'user-id".to_string(),
+            }),
+        ),
+    }
+}
+
+async fn graphql_endpoint(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<TenantId>,
+    Extension(schema): Extension<ToDoSchema>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Response {
+    match check_user_header(headers) {
+        Ok(user_id) => {
+            let response = schema
+                .execute(request.data(state).data(Owner(user_id)).data(tenant))
+                .await;
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                data: "",
+                message: "Please set the 'user-id".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// The status a failed command is reported with.
+fn status_for(kind: &ServiceErrorKind) -> StatusCode {
+    match kind {
+        ServiceErrorKind::QuotaExceeded => StatusCode::FORBIDDEN,
+        ServiceErrorKind::Conflict => StatusCode::CONFLICT,
+        ServiceErrorKind::Validation => StatusCode::BAD_REQUEST,
+        ServiceErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ServiceErrorKind::Repository => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn check_user_header(headers: HeaderMap) -> Result<String, ()> {
+    if let Some(user_id) = headers.get("user-id") {
+        Ok(user_id.to_str().unwrap().to_string())
+    } else {
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::health::ReadinessReport;
+    use crate::application::messaging::InMemoryMessagePublisher;
+    use aws_credential_types::provider::SharedCredentialsProvider;
+    use aws_credential_types::Credentials;
+    use axum::response::Response;
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+    };
+    use http::Method;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    struct ApiDriver {
+        router: Box<Router>,
+        headers: Vec<(http::header::HeaderName, String)>,
+    }
+
+    impl ApiDriver {
+        fn new(router: Box<Router>) -> Self {
+            Self {
+                router,
+                headers: Vec::new(),
+            }
+        }
+
+        /// Send the header with every request the driver makes.
+        fn with_header(mut self, name: http::header::HeaderName, value: &str) -> Self {
+            self.headers.push((name, value.to_string()));
+            self
+        }
+
+        fn request(&self) -> http::request::Builder {
+            self.headers.iter().fold(
+                Request::builder().header("user-id", "jameseastham"),
+                |builder, (name, value)| builder.header(name, value),
+            )
+        }
+
+        async fn list(&self) -> Response {
+            self.list_with_query("").await
+        }
+
+        async fn list_with_query(&self, query: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo{0}", query))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn create(&self, text: &str, description: &str, due_date: &str) -> Response {
+            let body = format!(
+                "{{\"title\":\"{0}\", \"description\":\"{1}\", \"due_date\":\"{2}\"}}",
+                text, description, due_date
+            );
+
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri("/todo")
+                        .method(Method::POST)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn update(
+            &self,
+            text: &str,
+            todo_id: &str,
+            set_as_complete: &bool,
+            description: &str,
+            due_date: &str,
+        ) -> Response {
+            let body = format!(
+                "{{\"title\":\"{0}\", \"set_as_complete\":{1}, \"description\":\"{2}\", \"due_date\":\"{3}\"}}",
+                text, set_as_complete, description, due_date
+            );
+
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}", todo_id))
+                        .method(Method::PUT)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn search(&self, query: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/search?q={0}", query))
+                        .method(Method::GET)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn export(&self, format: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/export?format={0}", format))
+                        .method(Method::GET)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn import(&self, format: &str, body: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/import?format={0}", format))
+                        .method(Method::POST)
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn move_todo(&self, todo_id: &str, body: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}/move", todo_id))
+                        .method(Method::POST)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn add_attachment(&self, todo_id: &str, body: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}/attachments", todo_id))
+                        .method(Method::POST)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn confirm_attachment(&self, todo_id: &str, attachment_id: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!(
+                            "/todo/{0}/attachments/{1}/confirm",
+                            todo_id, attachment_id
+                        ))
+                        .method(Method::POST)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn list_attachments(&self, todo_id: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}/attachments", todo_id))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn timer(&self, todo_id: &str, action: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}/timer/{1}", todo_id, action))
+                        .method(Method::POST)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn send_to_webhooks(&self, method: Method, path: &str, body: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/webhooks{0}", path))
+                        .method(method)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn set_timezone(&self, timezone: &str) -> Response {
+            self.set_preferences(&format!("{{\"timezone\":\"{0}\"}}", timezone))
+                .await
+        }
+
+        async fn set_preferences(&self, body: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri("/preferences")
+                        .method(Method::PUT)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn send_encoded(
+            &self,
+            method: Method,
+            uri: &str,
+            encoding: Encoding,
+            body: Vec<u8>,
+        ) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(uri)
+                        .method(method)
+                        .header(CONTENT_TYPE, encoding.content_type())
+                        .header(ACCEPT, encoding.content_type())
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn get(&self, id: &str) -> Response {
+            self.get_with_query(id, "").await
+        }
+
+        async fn graphql(&self, query: &str, variables: serde_json::Value) -> serde_json::Value {
+            let body = serde_json::json!({ "query": query, "variables": variables });
+
+            let response = self
+                .router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri("/graphql")
+                        .method(Method::POST)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        async fn get_with_query(&self, id: &str, query: &str) -> Response {
+            self.router
+                .clone()
+                .oneshot(
+                    self.request()
+                        .uri(format!("/todo/{0}{1}", id, query))
+                        .method(Method::GET)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+    }
+
+    async fn load_test_state() -> Arc<AppState> {
+        let config: SdkConfig = aws_config::load_defaults(BehaviorVersion::latest()).await;
+
+        let dynamodb_local_config = aws_sdk_dynamodb::config::Builder::from(&config)
+            .endpoint_url(
+                // 8000 is the default dynamodb port
+                "http://localhost:8000",
+            )
+            .region(Region::from_static("us-east-1"))
+            .build();
+        let dynamodb_client = Client::from_conf(dynamodb_local_config);
+        let table_name = String::from("TODO");
+
+        Arc::new(AppState {
+            todo_repo: Arc::new(DynamoDbToDoRepo::new(
+                dynamodb_client.clone(),
+                table_name.clone(),
+            )),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        })
+    }
+
+    // One repository plays every part, as the table does, so stopping a timer updates the ToDo.
+    fn load_in_memory_test_state() -> Arc<AppState> {
+        let in_memory_repo = Arc::new(InMemoryToDoRepo::new());
+
+        Arc::new(AppState {
+            todo_repo: in_memory_repo.clone(),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: in_memory_repo.clone(),
+            timer_repo: in_memory_repo,
+        })
+    }
+
+    // Presigning needs no network access, so the store can point at a local stand-in that is
+    // not running.
+    fn load_attachment_test_state(s3_endpoint: &str) -> Arc<AppState> {
+        let attachment_store = AttachmentStore::new(
+            SharedCredentialsProvider::new(Credentials::new("AKIDEXAMPLE", "secret", None, None, "test")),
+            "us-east-1",
+            "todo-attachments",
+            Some(s3_endpoint),
+        )
+        .with_limits(1024 * 1024, "image/png,application/pdf");
+
+        let in_memory_repo = Arc::new(InMemoryToDoRepo::new());
+
+        Arc::new(AppState {
+            todo_repo: in_memory_repo.clone(),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: Some(Arc::new(attachment_store)),
+            webhook_repo: in_memory_repo.clone(),
+            timer_repo: in_memory_repo,
+        })
+    }
+
+    async fn search_finds_updated_todo(shared_state: Arc<AppState>) {
+        let app = app(shared_state);
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let unique_term = uuid::Uuid::new_v4().simple().to_string();
+
+        let response = driver.create("Search me", unique_term.as_str(), "").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let search_response = driver.search(unique_term.as_str()).await;
+
+        assert_eq!(search_response.status(), StatusCode::OK);
+        let search_body = search_response.into_body().collect().await.unwrap().to_bytes();
+        let found: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&search_body).unwrap();
+
+        assert_eq!(found.data.len(), 1);
+        assert_eq!(found.data[0].id, created_todo.data.id);
+
+        let _update_response = driver
+            .update("Search me", &created_todo.data.id, &false, "replaced", "")
+            .await;
+
+        let search_response = driver.search(unique_term.as_str()).await;
+        let search_body = search_response.into_body().collect().await.unwrap().to_bytes();
+        let found: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&search_body).unwrap();
+
+        assert!(found.data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_todo_in_memory() {
+        search_finds_updated_todo(load_in_memory_test_state()).await;
+    }
+
+    #[tokio::test]
+    async fn search_todo() {
+        search_finds_updated_todo(load_test_state().await).await;
+    }
+
+    #[tokio::test]
+    async fn import_then_export_markdown_checklist() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let import_response = driver
+            .import("md", "# Shopping\n- [ ] Buy milk\n  Semi skimmed\n- [x] Buy bread\n")
+            .await;
+
+        assert_eq!(import_response.status(), StatusCode::OK);
+        let import_body = import_response.into_body().collect().await.unwrap().to_bytes();
+        let report: ApiResponse<ImportReport> = serde_json::from_slice(&import_body).unwrap();
+
+        assert_eq!(report.data.imported.len(), 2);
+        assert!(report.data.errors.is_empty());
+
+        let export_response = driver.export("md").await;
+
+        assert_eq!(export_response.status(), StatusCode::OK);
+        assert_eq!(
+            export_response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/markdown"
+        );
+        let export_body = export_response.into_body().collect().await.unwrap().to_bytes();
+        let exported = String::from_utf8(export_body.to_vec()).unwrap();
+
+        assert!(exported.contains("- [ ] Buy milk\n  Semi skimmed\n"));
+        assert!(exported.contains("- [x] Buy bread @done("));
+    }
+
+    #[tokio::test]
+    async fn import_should_keep_completed_on_date() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let import_response = driver
+            .import(
+                "md",
+                "- [x] Buy bread @due(2023-08-12T00:00:00+00:00) @done(2023-08-11T09:30:00+00:00)\n",
+            )
+            .await;
+
+        assert_eq!(import_response.status(), StatusCode::OK);
+        let import_body = import_response.into_body().collect().await.unwrap().to_bytes();
+        let report: ApiResponse<ImportReport> = serde_json::from_slice(&import_body).unwrap();
+
+        assert_eq!(report.data.imported.len(), 1);
+        assert_eq!(report.data.imported[0].title, "Buy bread");
+        assert_eq!(report.data.imported[0].due_date, "2023-08-12T00:00:00+00:00");
+        assert_eq!(report.data.imported[0].completed_on, "2023-08-11T09:30:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn import_reports_invalid_rows_without_aborting() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let csv = "title,description,due_date,is_complete\n\
+                   Valid,,,false\n\
+                   ,Missing title,,false\n\
+                   Bad date,,not-a-date,false\n";
+
+        let import_response = driver.import("csv", csv).await;
+
+        assert_eq!(import_response.status(), StatusCode::OK);
+        let import_body = import_response.into_body().collect().await.unwrap().to_bytes();
+        let report: ApiResponse<ImportReport> = serde_json::from_slice(&import_body).unwrap();
+
+        assert_eq!(report.data.imported.len(), 1);
+        assert_eq!(report.data.errors.len(), 2);
+        assert_eq!(report.data.errors[0].row, 2);
+        assert_eq!(report.data.errors[1].row, 3);
+
+        let export_response = driver.export("json").await;
+        let export_body = export_response.into_body().collect().await.unwrap().to_bytes();
+        let exported: Vec<ToDoItem> = serde_json::from_slice(&export_body).unwrap();
+
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].title, "Valid");
+    }
+
+    #[tokio::test]
+    async fn export_unsupported_format_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let export_response = driver.export("xml").await;
+
+        assert_eq!(export_response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn requests_over_rate_limit_should_return_too_many_requests() {
+        let app = app_with_rate_limiter(
+            load_in_memory_test_state(),
+            Arc::new(RateLimiter::new(1, 0.5)),
+        );
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let first_response = driver.create("My todo", "", "").await;
+
+        assert_eq!(first_response.status(), StatusCode::OK);
+
+        let second_response = driver.create("My todo", "", "").await;
+
+        assert_eq!(second_response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(second_response.headers().get(RETRY_AFTER).unwrap(), "2");
+    }
+
+    #[tokio::test]
+    async fn owners_in_different_tenants_should_not_share_a_rate_limit() {
+        let app = app_with_rate_limiter(
+            load_in_memory_test_state(),
+            Arc::new(RateLimiter::new(1, 0.5)),
+        );
+
+        let acme = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::AUTHORIZATION, &bearer_token("acme"));
+        let globex = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::AUTHORIZATION, &bearer_token("globex"));
+
+        assert_eq!(acme.create("My todo", "", "").await.status(), StatusCode::OK);
+        assert_eq!(globex.create("My todo", "", "").await.status(), StatusCode::OK);
+        assert_eq!(
+            acme.create("My todo", "", "").await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn create_over_quota_should_return_quota_error() {
+        let app = app(Arc::new(AppState {
+            todo_repo: Arc::new(InMemoryToDoRepo::new()),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: Some(1),
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        }));
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let first_response = driver.create("My todo", "", "").await;
+
+        assert_eq!(first_response.status(), StatusCode::OK);
+
+        let second_response = driver.create("My todo", "", "").await;
+
+        assert_eq!(second_response.status(), StatusCode::FORBIDDEN);
+        let body = second_response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+        assert!(error.message.contains("Quota exceeded"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_creates_should_not_exceed_quota() {
+        let app = app(Arc::new(AppState {
+            todo_repo: Arc::new(InMemoryToDoRepo::new()),
+            message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: Some(1),
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
+        }));
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let (first_response, second_response) = tokio::join!(
+            driver.create("My todo", "", ""),
+            driver.create("My other todo", "", "")
+        );
+
+        let mut statuses = vec![first_response.status(), second_response.status()];
+        statuses.sort();
+
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::FORBIDDEN]);
+    }
+
+    #[tokio::test]
+    async fn move_should_change_list_order() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let mut ids = Vec::new();
+
+        for title in ["First", "Second", "Third"] {
+            let response = driver.create(title, "", "").await;
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+            ids.push(created.data.id);
+        }
+
+        let move_response = driver
+            .move_todo(&ids[2], &format!("{{\"before\":\"{0}\"}}", ids[0]))
+            .await;
+
+        assert_eq!(move_response.status(), StatusCode::OK);
+
+        let move_response = driver
+            .move_todo(&ids[0], &format!("{{\"after\":\"{0}\"}}", ids[1]))
+            .await;
+
+        assert_eq!(move_response.status(), StatusCode::OK);
+
+        let list_response = driver.list().await;
+        let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+        let listed: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&list_body).unwrap();
+
+        let titles: Vec<&str> = listed.data.iter().map(|todo| todo.title.as_str()).collect();
+
+        assert_eq!(titles, vec!["Third", "Second", "First"]);
+    }
+
+    #[tokio::test]
+    async fn move_without_target_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("My todo", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let move_response = driver.move_todo(&created.data.id, "{}").await;
+
+        assert_eq!(move_response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_with_invalid_priority_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .send_encoded(
+                Method::POST,
+                "/todo",
+                Encoding::Json,
+                br#"{"title":"My todo","priority":"whenever"}"#.to_vec(),
+            )
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+        assert!(error.message.contains("priority"));
+    }
+
+    fn bearer_token(tenant_id: &str) -> String {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        format!(
+            "Bearer {}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(serde_json::json!({ "tenant_id": tenant_id }).to_string())
+        )
+    }
+
+    // Stands in for S3, every object exists when `uploaded` is set and none do otherwise.
+    async fn s3_stand_in(uploaded: bool) -> String {
+        let s3 = Router::new().fallback(move || async move {
+            match uploaded {
+                true => StatusCode::OK,
+                false => StatusCode::NOT_FOUND,
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, s3).await });
+
+        endpoint
+    }
+
+    #[tokio::test]
+    async fn add_attachment_should_return_upload_url_and_record_pending_attachment() {
+        let s3_endpoint = s3_stand_in(true).await;
+        let app = app(load_attachment_test_state(&s3_endpoint));
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("Fix the bug", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let attachment_response = driver
+            .add_attachment(
+                &created.data.id,
+                r#"{"file_name":"screenshot.png","content_type":"image/png","size":2048}"#,
+            )
+            .await;
+
+        assert_eq!(attachment_response.status(), StatusCode::OK);
+        let attachment_body = attachment_response.into_body().collect().await.unwrap().to_bytes();
+        let upload: ApiResponse<AttachmentUpload> =
+            serde_json::from_slice(&attachment_body).unwrap();
+
+        assert!(upload.data.upload_url.starts_with(&format!(
+            "{0}/todo-attachments/default/jameseastham/{1}/{2}?",
+            s3_endpoint, created.data.id, upload.data.attachment.id
+        )));
+        assert_eq!(upload.data.headers.get("content-length").unwrap(), "2048");
+        assert_eq!(upload.data.headers.get("content-type").unwrap(), "image/png");
+
+        let get_response = driver.get(&created.data.id).await;
+        let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
+        let retrieved: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body).unwrap();
+
+        assert_eq!(retrieved.data.attachments.len(), 1);
+        assert_eq!(retrieved.data.attachments[0].file_name, "screenshot.png");
+        assert!(retrieved.data.attachments[0].confirmed_on.is_none());
+
+        let list_response = driver.list_attachments(&created.data.id).await;
+        let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+        let downloads: ApiResponse<Vec<AttachmentDownload>> =
+            serde_json::from_slice(&list_body).unwrap();
+
+        assert!(downloads.data.is_empty());
+
+        let confirm_response = driver
+            .confirm_attachment(&created.data.id, &upload.data.attachment.id)
+            .await;
+
+        assert_eq!(confirm_response.status(), StatusCode::OK);
+        let confirm_body = confirm_response.into_body().collect().await.unwrap().to_bytes();
+        let confirmed: ApiResponse<AttachmentItem> =
+            serde_json::from_slice(&confirm_body).unwrap();
+
+        assert!(confirmed.data.confirmed_on.is_some());
+
+        let list_response = driver.list_attachments(&created.data.id).await;
+
+        assert_eq!(list_response.status(), StatusCode::OK);
+        let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+        let downloads: ApiResponse<Vec<AttachmentDownload>> =
+            serde_json::from_slice(&list_body).unwrap();
+
+        assert_eq!(downloads.data.len(), 1);
+        assert_eq!(downloads.data[0].attachment.id, upload.data.attachment.id);
+        assert!(downloads.data[0].download_url.contains("X-Amz-Signature="));
+    }
+
+    #[tokio::test]
+    async fn confirming_an_attachment_that_was_not_uploaded_should_conflict() {
+        let s3_endpoint = s3_stand_in(false).await;
+        let app = app(load_attachment_test_state(&s3_endpoint));
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("Fix the bug", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let attachment_response = driver
+            .add_attachment(
+                &created.data.id,
+                r#"{"file_name":"screenshot.png","content_type":"image/png","size":2048}"#,
+            )
+            .await;
+        let attachment_body = attachment_response.into_body().collect().await.unwrap().to_bytes();
+        let upload: ApiResponse<AttachmentUpload> =
+            serde_json::from_slice(&attachment_body).unwrap();
+
+        let confirm_response = driver
+            .confirm_attachment(&created.data.id, &upload.data.attachment.id)
+            .await;
+
+        assert_eq!(confirm_response.status(), StatusCode::CONFLICT);
+
+        let unknown_response = driver.confirm_attachment(&created.data.id, "unknown").await;
+
+        assert_eq!(unknown_response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn listing_attachments_should_report_why_it_failed() {
+        let attachment_state = load_attachment_test_state("http://localhost:4566");
+
+        let driver = ApiDriver::new(Box::new(app(attachment_state.clone())));
+
+        assert_eq!(
+            driver.list_attachments("01HZXJ7V3M8Q0000000000TODO").await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let unreachable_state = load_unreachable_table_state();
+        let driver = ApiDriver::new(Box::new(app(Arc::new(AppState {
+            todo_repo: unreachable_state.todo_repo.clone(),
+            message_publisher: attachment_state.message_publisher.clone(),
+            max_open_todos: None,
+            attachment_store: attachment_state.attachment_store.clone(),
+            webhook_repo: attachment_state.webhook_repo.clone(),
+            timer_repo: attachment_state.timer_repo.clone(),
+        }))));
+
+        assert_eq!(
+            driver.list_attachments("01HZXJ7V3M8Q0000000000TODO").await.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn attachment_outside_limits_should_be_rejected() {
+        let app = app(load_attachment_test_state("http://localhost:4566"));
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("Fix the bug", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        for attachment in [
+            r#"{"file_name":"huge.pdf","content_type":"application/pdf","size":10485760}"#,
+            r#"{"file_name":"script.sh","content_type":"text/x-shellscript","size":20}"#,
+            r#"{"file_name":"../passwd.png","content_type":"image/png","size":20}"#,
+        ] {
+            let attachment_response = driver.add_attachment(&created.data.id, attachment).await;
+
+            assert_eq!(attachment_response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let list_response = driver.list_attachments(&created.data.id).await;
+        let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+        let downloads: ApiResponse<Vec<AttachmentDownload>> =
+            serde_json::from_slice(&list_body).unwrap();
+
+        assert!(downloads.data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn attachments_should_be_unavailable_without_a_store() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("Fix the bug", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let attachment_response = driver
+            .add_attachment(
+                &created.data.id,
+                r#"{"file_name":"screenshot.png","content_type":"image/png","size":2048}"#,
+            )
+            .await;
+
+        assert_eq!(attachment_response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn registered_webhook_should_only_return_secret_once() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .send_to_webhooks(
+                Method::POST,
+                "",
+                r#"{"url":"https://example.com/hooks/todo","event_types":["ToDoCompleted"]}"#,
+            )
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let registered: ApiResponse<WebhookItem> = serde_json::from_slice(&body).unwrap();
+
+        assert!(registered.data.secret.unwrap().starts_with("whsec_"));
+
+        let list_response = driver.send_to_webhooks(Method::GET, "", "").await;
+        let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+        let webhooks: ApiResponse<Vec<WebhookItem>> = serde_json::from_slice(&list_body).unwrap();
+
+        assert_eq!(webhooks.data.len(), 1);
+        assert_eq!(webhooks.data[0].event_types, vec!["ToDoCompleted"]);
+        assert!(webhooks.data[0].secret.is_none());
+
+        let update_response = driver
+            .send_to_webhooks(
+                Method::PUT,
+                &format!("/{0}", registered.data.id),
+                r#"{"enabled":false}"#,
+            )
+            .await;
+        let update_body = update_response.into_body().collect().await.unwrap().to_bytes();
+        let updated: ApiResponse<WebhookItem> = serde_json::from_slice(&update_body).unwrap();
+
+        assert!(!updated.data.enabled);
+
+        let deliveries_response = driver
+            .send_to_webhooks(Method::GET, &format!("/{0}/deliveries", registered.data.id), "")
+            .await;
+
+        assert_eq!(deliveries_response.status(), StatusCode::OK);
+
+        let delete_response = driver
+            .send_to_webhooks(Method::DELETE, &format!("/{0}", registered.data.id), "")
+            .await;
+
+        assert_eq!(delete_response.status(), StatusCode::OK);
+
+        let deleted_deliveries_response = driver
+            .send_to_webhooks(Method::GET, &format!("/{0}/deliveries", registered.data.id), "")
+            .await;
+
+        assert_eq!(deleted_deliveries_response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn unknown_webhook_should_return_not_found() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let update_response = driver
+            .send_to_webhooks(Method::PUT, "/unknown", r#"{"enabled":false}"#)
+            .await;
+
+        assert_eq!(update_response.status(), StatusCode::NOT_FOUND);
+
+        let delete_response = driver.send_to_webhooks(Method::DELETE, "/unknown", "").await;
+
+        assert_eq!(delete_response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn webhook_with_internal_url_should_be_rejected() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        for body in [
+            r#"{"url":"http://example.com/hooks/todo"}"#,
+            r#"{"url":"https://169.254.169.254/latest/meta-data"}"#,
+            r#"{"url":"https://example.com/hooks/todo","event_types":["ToDoDeleted"]}"#,
+        ] {
+            let response = driver.send_to_webhooks(Method::POST, "", body).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn stopped_timer_should_add_to_tracked_time() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("Write the report", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let start_response = driver.timer(&created.data.id, "start").await;
+
+        assert_eq!(start_response.status(), StatusCode::OK);
+        let start_body = start_response.into_body().collect().await.unwrap().to_bytes();
+        let started: ApiResponse<TimeEntryItem> = serde_json::from_slice(&start_body).unwrap();
+
+        assert!(started.data.is_running);
+        assert_eq!(started.data.to_do_id, created.data.id);
+
+        let stop_response = driver.timer(&created.data.id, "stop").await;
+
+        assert_eq!(stop_response.status(), StatusCode::OK);
+        let stop_body = stop_response.into_body().collect().await.unwrap().to_bytes();
+        let stopped: ApiResponse<TimeEntryItem> = serde_json::from_slice(&stop_body).unwrap();
+
+        assert!(!stopped.data.is_running);
+        assert_eq!(stopped.data.id, started.data.id);
+        assert!(!stopped.data.stopped_on.is_empty());
+
+        let get_response = driver.get(&created.data.id).await;
+        let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
+        let todo: serde_json::Value = serde_json::from_slice(&get_body).unwrap();
+
+        assert_eq!(
+            todo["data"]["tracked_seconds"].as_u64(),
+            Some(stopped.data.duration_seconds)
+        );
+    }
+
+    #[tokio::test]
+    async fn only_one_timer_should_run_at_a_time() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let mut ids = Vec::new();
+
+        for title in ["Write the report", "Review the report"] {
+            let response = driver.create(title, "", "").await;
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+            ids.push(created.data.id);
+        }
+
+        assert_eq!(driver.timer(&ids[0], "start").await.status(), StatusCode::OK);
+        assert_eq!(driver.timer(&ids[1], "start").await.status(), StatusCode::CONFLICT);
+        assert_eq!(driver.timer(&ids[0], "start").await.status(), StatusCode::CONFLICT);
+        assert_eq!(driver.timer(&ids[1], "stop").await.status(), StatusCode::CONFLICT);
+        assert_eq!(driver.timer(&ids[0], "stop").await.status(), StatusCode::OK);
+        assert_eq!(driver.timer(&ids[0], "stop").await.status(), StatusCode::CONFLICT);
+        assert_eq!(driver.timer(&ids[1], "start").await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn tenants_should_not_read_or_change_each_others_todos() {
+        let app = app(load_in_memory_test_state());
+
+        let acme = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::AUTHORIZATION, &bearer_token("acme"));
+        let globex = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::AUTHORIZATION, &bearer_token("globex"));
+        let default_tenant = ApiDriver::new(Box::new(app));
+
+        let response = acme.create("Acme launch plan", "confidential", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let response = globex.create("Globex plan", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let globex_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        // The same owner id in another tenant, guessing the id of the other tenant's ToDo.
+        for driver in [&globex, &default_tenant] {
+            let list_response = driver.list().await;
+            let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+            let listed: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&list_body).unwrap();
+
+            assert!(listed.data.iter().all(|todo| todo.id != created.data.id));
+
+            let search_response = driver.search("confidential").await;
+            let search_body = search_response.into_body().collect().await.unwrap().to_bytes();
+            let found: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&search_body).unwrap();
+
+            assert!(found.data.is_empty());
+
+            let get_response = driver.get(&created.data.id).await;
+
+            assert_eq!(get_response.status(), StatusCode::NOT_FOUND);
+
+            let update_response = driver
+                .update("Stolen plan", &created.data.id, &true, "", "")
+                .await;
+
+            assert_ne!(update_response.status(), StatusCode::OK);
+        }
+
+        let move_response = globex
+            .move_todo(
+                &created.data.id,
+                &format!("{{\"before\":\"{0}\"}}", globex_todo.data.id),
+            )
+            .await;
+
+        assert_ne!(move_response.status(), StatusCode::OK);
+
+        let get_response = acme.get(&created.data.id).await;
+
+        assert_eq!(get_response.status(), StatusCode::OK);
+        let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
+        let retrieved: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body).unwrap();
+
+        assert_eq!(retrieved.data.title, "Acme launch plan");
+        assert!(!retrieved.data.is_complete);
+    }
+
+    #[tokio::test]
+    async fn host_subdomain_should_select_tenant() {
+        let app = app_with(
+            load_in_memory_test_state(),
+            Arc::new(RateLimiter::default()),
+            Arc::new(TenantResolver::new("tenant_id", Some("todo.example.com"))),
+        );
+
+        let acme_host = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::HOST, "acme.todo.example.com");
+        let acme_token = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::AUTHORIZATION, &bearer_token("acme"));
+        let globex_host = ApiDriver::new(Box::new(app.clone()))
+            .with_header(http::header::HOST, "globex.todo.example.com");
+        let mismatched = ApiDriver::new(Box::new(app))
+            .with_header(http::header::HOST, "globex.todo.example.com")
+            .with_header(http::header::AUTHORIZATION, &bearer_token("acme"));
+
+        let create_response = acme_host.create("My todo", "", "").await;
+
+        assert_eq!(create_response.status(), StatusCode::OK);
+
+        for (driver, expected) in [(&acme_token, 1), (&globex_host, 0)] {
+            let list_response = driver.list().await;
+            let list_body = list_response.into_body().collect().await.unwrap().to_bytes();
+            let listed: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&list_body).unwrap();
+
+            assert_eq!(listed.data.len(), expected);
+        }
+
+        let mismatched_response = mismatched.list().await;
+
+        assert_eq!(mismatched_response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn get_todo_with_render_html_should_include_sanitised_description() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .create("My todo", "**Bold** <script>alert('hi')</script>", "")
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        assert!(created_todo.data.description_html.is_none());
+
+        let get_response = driver
+            .get_with_query(&created_todo.data.id, "?render=html")
+            .await;
+
+        assert_eq!(get_response.status(), StatusCode::OK);
+        let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
+        let get_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body).unwrap();
+
+        let description_html = get_todo.data.description_html.unwrap();
+        assert!(description_html.contains("<strong>Bold</strong>"));
+        assert!(!description_html.contains("<script"));
     }
 
-    impl ApiDriver {
-        fn new(router: Box<Router>) -> Self {
-            Self { router }
-        }
+    #[tokio::test]
+    async fn create_with_description_too_long_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
 
-        async fn list(&self) -> Response {
-            self.router
-                .clone()
-                .oneshot(
-                    Request::builder()
-                        .uri("/todo")
-                        .header("user-id", "jameseastham")
-                        .body(Body::empty())
-                        .unwrap(),
-                )
-                .await
-                .unwrap()
-        }
+        let driver = ApiDriver::new(Box::new(app));
 
-        async fn create(&self, text: &str, description: &str, due_date: &str) -> Response {
-            let body = format!(
-                "{{\"title\":\"{0}\", \"description\":\"{1}\", \"due_date\":\"{2}\"}}",
-                text, description, due_date
-            );
+        let response = driver
+            .create("My todo", "a".repeat(2001).as_str(), "")
+            .await;
 
-            self.router
-                .clone()
-                .oneshot(
-                    Request::builder()
-                        .uri("/todo")
-                        .method(Method::POST)
-                        .header("user-id", "jameseastham")
-                        .header("Content-Type", "application/json")
-                        .body(Body::from(body))
-                        .unwrap(),
-                )
-                .await
-                .unwrap()
-        }
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 
-        async fn update(
-            &self,
-            text: &str,
-            todo_id: &str,
-            set_as_complete: &bool,
-            description: &str,
-            due_date: &str,
-        ) -> Response {
-            let body = format!(
-                "{{\"title\":\"{0}\", \"set_as_complete\":{1}, \"description\":\"{2}\", \"due_date\":\"{3}\"}}",
-                text, set_as_complete, description, due_date
-            );
+    #[tokio::test]
+    async fn create_with_invalid_due_date_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
 
-            self.router
-                .clone()
-                .oneshot(
-                    Request::builder()
-                        .uri(format!("/todo/{0}", todo_id))
-                        .method(Method::PUT)
-                        .header("user-id", "jameseastham")
-                        .header("Content-Type", "application/json")
-                        .body(Body::from(body))
-                        .unwrap(),
-                )
-                .await
-                .unwrap()
-        }
+        let driver = ApiDriver::new(Box::new(app));
 
-        async fn get(&self, id: &str) -> Response {
-            self.router
-                .clone()
-                .oneshot(
-                    Request::builder()
-                        .uri(format!("/todo/{0}", id))
-                        .method(Method::GET)
-                        .header("user-id", "jameseastham")
-                        .body(Body::empty())
-                        .unwrap(),
-                )
-                .await
-                .unwrap()
-        }
+        let response = driver.create("My todo", "", "2023-02-30").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+        assert!(error.message.contains("not a valid due date"));
     }
 
-    async fn load_test_state() -> Arc<AppState> {
-        let config: SdkConfig = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    #[tokio::test]
+    async fn list_todo_views_should_group_by_due_date() {
+        let app = app(load_in_memory_test_state());
 
-        let dynamodb_local_config = aws_sdk_dynamodb::config::Builder::from(&config)
-            .endpoint_url(
-                // 8000 is the default dynamodb port
-                "http://localhost:8000",
+        let driver = ApiDriver::new(Box::new(app));
+
+        let timezone_response = driver.set_timezone("Asia/Tokyo").await;
+
+        assert_eq!(timezone_response.status(), StatusCode::OK);
+
+        let overdue = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        let upcoming = (Utc::now() + chrono::Duration::days(3))
+            .format("%Y-%m-%d")
+            .to_string();
+
+        let _overdue_response = driver.create("Overdue", "", &overdue).await;
+        let _upcoming_response = driver.create("Upcoming", "", &upcoming).await;
+        let _undated_response = driver.create("No due date", "", "").await;
+
+        let response = driver.list_with_query("?view=overdue").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let overdue_todos: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(overdue_todos.data.len(), 1);
+        assert_eq!(overdue_todos.data[0].title, "Overdue");
+
+        let response = driver.list_with_query("?view=upcoming").await;
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let upcoming_todos: ApiResponse<Vec<ToDoItem>> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(upcoming_todos.data.len(), 1);
+        assert_eq!(upcoming_todos.data[0].due_date, upcoming);
+
+        let response = driver.list_with_query("?view=someday").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn set_unknown_timezone_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.set_timezone("Mars/Olympus_Mons").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn set_reminder_lead_time_should_be_returned_with_preferences() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .set_preferences("{\"timezone\":\"Europe/London\",\"reminder_lead_time_minutes\":30}")
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let preferences: ApiResponse<UserPreferences> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(preferences.data.reminder_lead_time_minutes, Some(30));
+
+        let response = driver
+            .set_preferences("{\"timezone\":\"Europe/London\",\"reminder_lead_time_minutes\":5000}")
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_with_message_pack_should_respond_with_message_pack() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let command = CreateToDoCommand {
+            title: String::from("My todo"),
+            description: None,
+            due_date: Some(String::from("2023-08-12")),
+            priority: None,
+        };
+
+        let response = driver
+            .send_encoded(
+                Method::POST,
+                "/todo",
+                Encoding::MessagePack,
+                Encoding::MessagePack.encode(&command).unwrap(),
+            )
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "application/msgpack");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created_todo: ApiResponse<ToDoItem> = Encoding::MessagePack.decode(&body).unwrap();
+        assert_eq!(created_todo.data.title, "My todo");
+        assert_eq!(created_todo.data.due_date, "2023-08-12");
+    }
+
+    #[tokio::test]
+    async fn update_with_cbor_should_respond_with_cbor() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.create("My todo", "", "").await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
+
+        let command = UpdateToDoCommand {
+            title: String::from("My updated todo"),
+            set_as_complete: false,
+            description: Some(String::from("Updated over CBOR")),
+            due_date: None,
+            priority: None,
+        };
+
+        let response = driver
+            .send_encoded(
+                Method::PUT,
+                &format!("/todo/{0}", created_todo.data.id),
+                Encoding::Cbor,
+                Encoding::Cbor.encode(&command).unwrap(),
+            )
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "application/cbor");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let updated_todo: ApiResponse<ToDoItem> = Encoding::Cbor.decode(&body).unwrap();
+        assert_eq!(updated_todo.data.title, "My updated todo");
+        assert_eq!(updated_todo.data.description, "Updated over CBOR");
+    }
+
+    #[tokio::test]
+    async fn invalid_message_pack_body_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .send_encoded(Method::POST, "/todo", Encoding::MessagePack, vec![0xc1])
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiResponse<String> = Encoding::MessagePack.decode(&body).unwrap();
+        assert!(error.message.contains("application/msgpack"));
+    }
+
+    #[tokio::test]
+    async fn list_without_accept_should_respond_with_json() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver.list().await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept");
+    }
+
+    #[tokio::test]
+    async fn graphql_create_then_query_should_return_requested_fields() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let created = driver
+            .graphql(
+                "mutation Create($input: CreateTodoInput!) { createTodo(input: $input) { id title } }",
+                serde_json::json!({ "input": { "title": "My todo", "description": "**Bold**" } }),
+            )
+            .await;
+
+        let id = created["data"]["createTodo"]["id"].as_str().unwrap().to_string();
+        assert_eq!(created["data"]["createTodo"]["title"], "My todo");
+
+        let fetched = driver
+            .graphql(
+                "query Get($id: String!) { todo(id: $id) { title descriptionHtml } }",
+                serde_json::json!({ "id": id }),
+            )
+            .await;
+
+        assert_eq!(
+            fetched["data"]["todo"],
+            serde_json::json!({ "title": "My todo", "descriptionHtml": "<p><strong>Bold</strong></p>\n" })
+        );
+
+        let listed = driver.graphql("{ todos { id isComplete } }", serde_json::json!({})).await;
+
+        assert_eq!(
+            listed["data"]["todos"],
+            serde_json::json!([{ "id": id, "isComplete": false }])
+        );
+    }
+
+    #[tokio::test]
+    async fn graphql_complete_todo_should_mark_todo_complete() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let created = driver
+            .graphql(
+                "mutation { createTodo(input: { title: \"My todo\" }) { id } }",
+                serde_json::json!({}),
+            )
+            .await;
+        let id = created["data"]["createTodo"]["id"].as_str().unwrap().to_string();
+
+        let completed = driver
+            .graphql(
+                "mutation Complete($id: String!) { completeTodo(id: $id) { title isComplete } }",
+                serde_json::json!({ "id": id }),
+            )
+            .await;
+
+        assert_eq!(
+            completed["data"]["completeTodo"],
+            serde_json::json!({ "title": "My todo", "isComplete": true })
+        );
+    }
+
+    #[tokio::test]
+    async fn graphql_validation_errors_should_include_field_paths() {
+        let app = app(load_in_memory_test_state());
+
+        let driver = ApiDriver::new(Box::new(app));
+
+        let response = driver
+            .graphql(
+                "mutation Create($input: CreateTodoInput!) { createTodo(input: $input) { id } }",
+                serde_json::json!({ "input": { "title": "", "description": "a".repeat(2001) } }),
+            )
+            .await;
+
+        assert_eq!(response["data"]["createTodo"], serde_json::Value::Null);
+
+        let paths: Vec<serde_json::Value> = response["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["path"].clone())
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                serde_json::json!(["createTodo", "input", "title"]),
+                serde_json::json!(["createTodo", "input", "description"]),
+            ]
+        );
+        assert_eq!(response["errors"][0]["extensions"]["code"], "VALIDATION_ERROR");
+    }
+
+    #[tokio::test]
+    async fn graphql_without_user_header_should_return_bad_request() {
+        let app = app(load_in_memory_test_state());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/graphql")
+                    .method(Method::POST)
+                    .header("Content-Type", "application/json")
+                    .body(Body::from("{\"query\": \"{ todos { id } }\"}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn ready_with_in_memory_dependencies_should_return_ok() {
+        let app = app(load_in_memory_test_state());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health/ready")
+                    .body(Body::empty())
+                    .unwrap(),
             )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let report: ApiResponse<ReadinessReport> = serde_json::from_slice(&body).unwrap();
+
+        assert!(report.data.ready);
+        assert_eq!(report.data.dependencies.len(), 2);
+    }
+
+    // Nothing listens on port 1, so every request to the table fails to connect.
+    fn load_unreachable_table_state() -> Arc<AppState> {
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .endpoint_url("http://127.0.0.1:1")
             .region(Region::from_static("us-east-1"))
+            .credentials_provider(aws_sdk_dynamodb::config::Credentials::new(
+                "test", "test", None, None, "test",
+            ))
             .build();
-        let dynamodb_client = Client::from_conf(dynamodb_local_config);
-        let table_name = String::from("TODO");
 
         Arc::new(AppState {
             todo_repo: Arc::new(DynamoDbToDoRepo::new(
-                dynamodb_client.clone(),
-                table_name.clone(),
+                Client::from_conf(config),
+                String::from("TODO"),
             )),
             message_publisher: Arc::new(InMemoryMessagePublisher::new()),
+            max_open_todos: None,
+            attachment_store: None,
+            webhook_repo: Arc::new(InMemoryToDoRepo::new()),
+            timer_repo: Arc::new(InMemoryToDoRepo::new()),
         })
     }
 
+    #[tokio::test]
+    async fn ready_with_unreachable_table_should_return_service_unavailable() {
+        let app = app(load_unreachable_table_state());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health/ready")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn get_with_unreachable_table_should_return_server_error() {
+        let driver = ApiDriver::new(Box::new(app(load_unreachable_table_state())));
+
+        let response = driver.get("01HZXJ7V3M8Q0000000000TODO").await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn export_with_unreachable_table_should_return_server_error() {
+        let driver = ApiDriver::new(Box::new(app(load_unreachable_table_state())));
+
+        let response = driver.export("json").await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn get_unknown_todo_should_return_not_found() {
+        let driver = ApiDriver::new(Box::new(app(load_in_memory_test_state())));
+
+        let response = driver.get("01HZXJ7V3M8Q0000000000TODO").await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn list_todo() {
         let shared_state = load_test_state().await;
@@ -389,7 +3174,7 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
         assert!(!body.is_empty());
-        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body.to_vec()).unwrap();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(created_todo.data.title, test_text);
 
@@ -416,7 +3201,7 @@ mod tests {
         let body = response.into_body().collect().await.unwrap().to_bytes();
         assert!(!body.is_empty());
 
-        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body.to_vec()).unwrap();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
 
         let _update_response = driver
             .update(
@@ -434,7 +3219,7 @@ mod tests {
         let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
         assert!(!get_body.is_empty());
 
-        let get_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body.to_vec()).unwrap();
+        let get_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body).unwrap();
 
         assert_eq!(get_todo.data.title, "My todo");
         assert_eq!(get_todo.data.description, "");
@@ -457,7 +3242,7 @@ mod tests {
         let body = response.into_body().collect().await.unwrap().to_bytes();
         assert!(!body.is_empty());
 
-        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body.to_vec()).unwrap();
+        let created_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&body).unwrap();
 
         let update_response = driver
             .update(
@@ -476,7 +3261,7 @@ mod tests {
         let get_body = get_response.into_body().collect().await.unwrap().to_bytes();
         assert!(!get_body.is_empty());
 
-        let get_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body.to_vec()).unwrap();
+        let get_todo: ApiResponse<ToDoItem> = serde_json::from_slice(&get_body).unwrap();
 
         assert_eq!(get_todo.data.title, "Updated todo");
         assert_eq!(get_todo.data.description, "updated description");
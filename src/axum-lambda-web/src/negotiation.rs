@@ -0,0 +1,224 @@
+use http::HeaderValue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const MESSAGE_PACK_CONTENT_TYPE: &str = "application/msgpack";
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// The encodings the API can read request bodies from and write responses in. Handlers always
+/// work with JSON, other encodings are converted to and from JSON at the edge of the router.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Encoding {
+    /// The encoding for a `Content-Type` header, ignoring any parameters such as the charset.
+    pub fn from_content_type(content_type: &str) -> Option<Encoding> {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        match media_type.as_str() {
+            JSON_CONTENT_TYPE => Some(Encoding::Json),
+            MESSAGE_PACK_CONTENT_TYPE | "application/x-msgpack" => Some(Encoding::MessagePack),
+            CBOR_CONTENT_TYPE => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+
+    /// The encoding the client prefers from an `Accept` header. Media types are tried in order
+    /// of their quality value, falling back to JSON when none of them are supported.
+    pub fn from_accept(accept: Option<&HeaderValue>) -> Encoding {
+        let accept = match accept.and_then(|val| val.to_str().ok()) {
+            Some(accept) => accept,
+            None => return Encoding::Json,
+        };
+
+        let mut media_types: Vec<(&str, f32)> = accept
+            .split(',')
+            .map(|media_range| {
+                let mut parts = media_range.split(';');
+                let media_type = parts.next().unwrap_or_default().trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|val| val.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                (media_type, quality)
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+
+        // A stable sort keeps the client's order for media types with the same quality.
+        media_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        media_types
+            .into_iter()
+            .find_map(|(media_type, _)| Encoding::from_content_type(media_type))
+            .unwrap_or(Encoding::Json)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Encoding::Json => JSON_CONTENT_TYPE,
+            Encoding::MessagePack => MESSAGE_PACK_CONTENT_TYPE,
+            Encoding::Cbor => CBOR_CONTENT_TYPE,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            // Structs are written as maps so clients can read fields by name.
+            Encoding::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            Encoding::Cbor => {
+                let mut bytes: Vec<u8> = Vec::new();
+
+                ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Encoding::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Encoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            Encoding::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Re-encode a body written in `from` as this encoding.
+    pub fn transcode(&self, from: Encoding, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        if *self == from {
+            return Ok(bytes.to_vec());
+        }
+
+        let value: serde_json::Value = from.decode(bytes)?;
+
+        self.encode(&value)
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+    use crate::application::public_types::{CreateToDoCommand, ToDoItem, UpdateToDoCommand};
+    use http::HeaderValue;
+
+    const ENCODINGS: [Encoding; 3] = [Encoding::Json, Encoding::MessagePack, Encoding::Cbor];
+
+    #[test]
+    fn accept_should_prefer_highest_quality_supported_type() {
+        let accept = HeaderValue::from_static(
+            "text/html, application/json;q=0.5, application/cbor;q=0.9, application/msgpack;q=0.9",
+        );
+
+        assert_eq!(Encoding::from_accept(Some(&accept)), Encoding::Cbor);
+    }
+
+    #[test]
+    fn unsupported_or_missing_accept_should_fall_back_to_json() {
+        let accept = HeaderValue::from_static("application/xml, */*");
+
+        assert_eq!(Encoding::from_accept(Some(&accept)), Encoding::Json);
+        assert_eq!(Encoding::from_accept(None), Encoding::Json);
+    }
+
+    #[test]
+    fn to_do_item_should_round_trip() {
+        for encoding in ENCODINGS {
+            let item = ToDoItem {
+                id: String::from("id"),
+                title: String::from("Buy milk"),
+                is_complete: true,
+                completed_on: String::from("2023-08-12T00:00:00+00:00"),
+                description: String::from("**Oat** milk"),
+                due_date: String::from("2023-08-12"),
+                priority: String::from("urgent"),
+                position: String::from("V"),
+                description_html: Some(String::from("<p><strong>Oat</strong> milk</p>")),
+                attachments: Vec::new(),
+                tracked_seconds: 0,
+            };
+
+            let bytes = encoding.encode(&item).unwrap();
+            let decoded: ToDoItem = encoding.decode(&bytes).unwrap();
+
+            assert_eq!(decoded.id, item.id, "{:?}", encoding);
+            assert_eq!(decoded.title, item.title, "{:?}", encoding);
+            assert_eq!(decoded.is_complete, item.is_complete, "{:?}", encoding);
+            assert_eq!(decoded.completed_on, item.completed_on, "{:?}", encoding);
+            assert_eq!(decoded.description, item.description, "{:?}", encoding);
+            assert_eq!(decoded.due_date, item.due_date, "{:?}", encoding);
+            assert_eq!(decoded.priority, item.priority, "{:?}", encoding);
+            assert_eq!(decoded.position, item.position, "{:?}", encoding);
+            assert_eq!(decoded.description_html, item.description_html, "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn create_command_should_round_trip() {
+        for encoding in ENCODINGS {
+            let command = CreateToDoCommand {
+                title: String::from("Buy milk"),
+                description: None,
+                due_date: Some(String::from("2023-08-12T09:30:00+02:00")),
+                priority: Some(String::from("high")),
+            };
+
+            let bytes = encoding.encode(&command).unwrap();
+            let decoded: CreateToDoCommand = encoding.decode(&bytes).unwrap();
+
+            assert_eq!(decoded.title, command.title, "{:?}", encoding);
+            assert_eq!(decoded.description, command.description, "{:?}", encoding);
+            assert_eq!(decoded.due_date, command.due_date, "{:?}", encoding);
+            assert_eq!(decoded.priority, command.priority, "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn update_command_should_round_trip() {
+        for encoding in ENCODINGS {
+            let command = UpdateToDoCommand {
+                title: String::from("Buy oat milk"),
+                set_as_complete: true,
+                description: Some(String::from("From the corner shop")),
+                due_date: None,
+                priority: None,
+            };
+
+            let bytes = encoding.encode(&command).unwrap();
+            let decoded: UpdateToDoCommand = encoding.decode(&bytes).unwrap();
+
+            assert_eq!(decoded.title, command.title, "{:?}", encoding);
+            assert_eq!(decoded.set_as_complete, command.set_as_complete, "{:?}", encoding);
+            assert_eq!(decoded.description, command.description, "{:?}", encoding);
+            assert_eq!(decoded.due_date, command.due_date, "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn transcode_should_convert_json_to_each_encoding() {
+        let json = br#"{"data":{"title":"Buy milk"},"message":"Success"}"#;
+
+        for encoding in ENCODINGS {
+            let bytes = encoding.transcode(Encoding::Json, json).unwrap();
+            let value: serde_json::Value = encoding.decode(&bytes).unwrap();
+
+            assert_eq!(value["data"]["title"], "Buy milk", "{:?}", encoding);
+            assert_eq!(value["message"], "Success", "{:?}", encoding);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::application::domain::TenantId;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BURST: u32 = 20;
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+// Once this many owners are tracked, buckets that have refilled completely are dropped.
+const MAX_TRACKED_OWNERS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A token bucket rate limiter with a separate bucket for every owner. Owner ids are only unique
+/// within a tenant, so the bucket is keyed on both. Each bucket holds up to `burst` tokens and
+/// refills at `requests_per_second`, every request takes a single token.
+pub struct RateLimiter {
+    burst: f64,
+    requests_per_second: f64,
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+impl RateLimiter {
+    /// `burst` must be at least one and `requests_per_second` positive and finite, `from_env`
+    /// checks both when they are configured.
+    pub fn new(burst: u32, requests_per_second: f64) -> Self {
+        Self {
+            burst: burst as f64,
+            requests_per_second,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Configure the limiter from the `RATE_LIMIT_BURST` and `RATE_LIMIT_PER_SECOND`
+    /// environment variables, falling back to the defaults when they are not set. A burst that
+    /// isn't a positive integer is an error, as an empty bucket would reject every request, and
+    /// so is a refill rate that isn't a positive number, as the wait for a token can't be
+    /// calculated.
+    pub fn from_env() -> Result<Self, String> {
+        let burst = match env::var("RATE_LIMIT_BURST") {
+            Ok(val) => parse_burst(&val)?,
+            Err(_) => DEFAULT_BURST,
+        };
+        let requests_per_second = match env::var("RATE_LIMIT_PER_SECOND") {
+            Ok(val) => parse_requests_per_second(&val)?,
+            Err(_) => DEFAULT_REQUESTS_PER_SECOND,
+        };
+
+        Ok(Self::new(burst, requests_per_second))
+    }
+
+    /// Take a token from the owner's bucket. When the bucket is empty the error contains how
+    /// long the owner needs to wait before a token will be available.
+    pub fn try_acquire(&self, tenant: &TenantId, owner: &str) -> Result<(), Duration> {
+        self.try_acquire_at(tenant, owner, Instant::now())
+    }
+
+    fn try_acquire_at(&self, tenant: &TenantId, owner: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_OWNERS {
+            buckets.retain(|_, bucket| self.refilled_tokens(bucket, now) < self.burst);
+        }
+
+        let key = (tenant.to_string().into(), owner.to_uppercase());
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            last_refill: now,
+        });
+
+        bucket.tokens = self.refilled_tokens(bucket, now);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.requests_per_second,
+            ))
+        }
+    }
+
+    fn refilled_tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+
+        (bucket.tokens + elapsed * self.requests_per_second).min(self.burst)
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_BURST, DEFAULT_REQUESTS_PER_SECOND)
+    }
+}
+
+fn parse_burst(val: &str) -> Result<u32, String> {
+    match val.parse::<u32>() {
+        Ok(burst) if burst > 0 => Ok(burst),
+        _ => Err(format!(
+            "RATE_LIMIT_BURST must be a positive integer, '{}' is not valid",
+            val
+        )),
+    }
+}
+
+fn parse_requests_per_second(val: &str) -> Result<f64, String> {
+    match val.parse::<f64>() {
+        Ok(requests_per_second) if requests_per_second.is_finite() && requests_per_second > 0.0 => {
+            Ok(requests_per_second)
+        }
+        _ => Err(format!(
+            "RATE_LIMIT_PER_SECOND must be a positive number, '{}' is not valid",
+            val
+        )),
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{parse_burst, parse_requests_per_second, RateLimiter};
+    use crate::application::domain::TenantId;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn requests_within_burst_should_be_allowed() {
+        let limiter = RateLimiter::new(3, 1.0);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+    }
+
+    #[test]
+    fn requests_over_burst_should_be_rejected_with_retry_after() {
+        let limiter = RateLimiter::new(1, 2.0);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+
+        let retry_after = limiter.try_acquire_at(&TenantId::default(), "owner", now).unwrap_err();
+
+        assert_eq!(retry_after, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn bucket_should_refill_over_time() {
+        let limiter = RateLimiter::new(1, 1.0);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_err());
+        assert!(limiter
+            .try_acquire_at(&TenantId::default(), "owner", now + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn owners_should_have_separate_buckets() {
+        let limiter = RateLimiter::new(1, 1.0);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(&TenantId::default(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::default(), "someone-else", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::default(), "OWNER", now).is_err());
+    }
+
+    #[test]
+    fn owners_in_different_tenants_should_have_separate_buckets() {
+        let limiter = RateLimiter::new(1, 1.0);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(&TenantId::new("acme").unwrap(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::new("globex").unwrap(), "owner", now).is_ok());
+        assert!(limiter.try_acquire_at(&TenantId::new("acme").unwrap(), "owner", now).is_err());
+    }
+
+    #[test]
+    fn burst_should_be_a_positive_integer() {
+        assert_eq!(parse_burst("20"), Ok(20));
+
+        for val in ["0", "-1", "2.5", "lots"] {
+            assert!(parse_burst(val).is_err(), "{} was accepted", val);
+        }
+    }
+
+    #[test]
+    fn requests_per_second_should_be_positive_and_finite() {
+        assert_eq!(parse_requests_per_second("2.5"), Ok(2.5));
+
+        for val in ["0", "-1", "NaN", "inf", "fast"] {
+            assert!(parse_requests_per_second(val).is_err(), "{} was accepted", val);
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::env;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const DEFAULT_DRAIN_SECONDS: u64 = 25;
+
+/// How long in-flight requests have to finish after a shutdown signal, from
+/// `SHUTDOWN_DRAIN_SECONDS`. Keep this below the platform's grace period, ECS and Kubernetes
+/// send SIGKILL 30 seconds after SIGTERM by default.
+pub fn drain_deadline_from_env() -> Duration {
+    Duration::from_secs(
+        env::var("SHUTDOWN_DRAIN_SECONDS")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(DEFAULT_DRAIN_SECONDS),
+    )
+}
+
+/// Resolves with the name of the signal once the process receives SIGTERM or SIGINT.
+pub async fn shutdown_signal() -> &'static str {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+/// Counts the requests the server is handling, so shutdown can report how many were still
+/// running when the signal arrived and how many did not finish before the deadline.
+#[derive(Default)]
+pub struct InFlightRequests {
+    active: AtomicUsize,
+    completed: AtomicU64,
+    cancelled: AtomicU64,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the start of a request. It is counted as completed if the guard is finished
+    /// before it is dropped, and as cancelled otherwise.
+    pub fn start(self: &Arc<Self>) -> InFlightGuard {
+        self.active.fetch_add(1, Ordering::SeqCst);
+
+        InFlightGuard {
+            requests: self.clone(),
+            finished: false,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::SeqCst)
+    }
+
+    /// Requests dropped before a response was produced, because the client went away or the
+    /// server was aborted.
+    pub fn cancelled(&self) -> u64 {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+pub struct InFlightGuard {
+    requests: Arc<InFlightRequests>,
+    finished: bool,
+}
+
+impl InFlightGuard {
+    /// Record that the request produced a response.
+    pub fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        match self.finished {
+            true => self.requests.completed.fetch_add(1, Ordering::SeqCst),
+            false => self.requests.cancelled.fetch_add(1, Ordering::SeqCst),
+        };
+        self.requests.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub async fn track_in_flight(
+    State(requests): State<Arc<InFlightRequests>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let guard = requests.start();
+
+    let response = next.run(request).await;
+    guard.finish();
+
+    response
+}
+
+/// Run `server` until it stops. If `signal` fires first the server is given until `deadline`
+/// to finish its in-flight requests, then aborted. Returns `None` when the server stopped
+/// without a signal, otherwise a summary of the drain with no undelivered events counted yet.
+pub async fn serve_until_drained<F>(
+    server: F,
+    signal: oneshot::Receiver<&'static str>,
+    in_flight: &InFlightRequests,
+    deadline: Duration,
+) -> Option<ShutdownSummary>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let mut server = tokio::spawn(server);
+
+    let signal = tokio::select! {
+        res = &mut server => {
+            res.unwrap();
+            return None;
+        }
+        Ok(signal) = signal => signal,
+    };
+
+    let in_flight_at_signal = in_flight.active();
+    let completed_at_signal = in_flight.completed();
+    let cancelled_at_signal = in_flight.cancelled();
+
+    tracing::info!(
+        "Received {}, draining {} in-flight requests for up to {:?}",
+        signal,
+        in_flight_at_signal,
+        deadline
+    );
+
+    let abandoned_requests = match tokio::time::timeout(deadline, &mut server).await {
+        Ok(_) => 0,
+        Err(_) => {
+            let abandoned_requests = in_flight.active();
+
+            // Wait for the aborted server to be dropped, so its requests are counted as cancelled.
+            server.abort();
+            let _ = server.await;
+
+            abandoned_requests
+        }
+    };
+
+    Some(ShutdownSummary {
+        signal,
+        requests_completed: in_flight.completed() - completed_at_signal,
+        requests_cancelled: in_flight.cancelled() - cancelled_at_signal,
+        in_flight_at_signal,
+        abandoned_requests,
+        undelivered_events: 0,
+    })
+}
+
+/// What happened while the server shut down, logged just before the process exits.
+#[derive(Debug, Default, PartialEq)]
+pub struct ShutdownSummary {
+    pub signal: &'static str,
+    /// Requests that completed between the signal and the server stopping.
+    pub requests_completed: u64,
+    /// Requests dropped between the signal and the server stopping, including abandoned ones.
+    pub requests_cancelled: u64,
+    pub in_flight_at_signal: usize,
+    pub abandoned_requests: usize,
+    pub undelivered_events: usize,
+}
+
+impl ShutdownSummary {
+    pub fn log(&self) {
+        if self.abandoned_requests > 0 || self.undelivered_events > 0 {
+            tracing::warn!(
+                signal = self.signal,
+                requests_completed = self.requests_completed,
+                requests_cancelled = self.requests_cancelled,
+                in_flight_at_signal = self.in_flight_at_signal,
+                abandoned_requests = self.abandoned_requests,
+                undelivered_events = self.undelivered_events,
+                "Shut down before all work completed"
+            );
+        } else {
+            tracing::info!(
+                signal = self.signal,
+                requests_completed = self.requests_completed,
+                requests_cancelled = self.requests_cancelled,
+                in_flight_at_signal = self.in_flight_at_signal,
+                "Shut down cleanly"
+            );
+        }
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{serve_until_drained, track_in_flight, InFlightRequests, ShutdownSummary};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::middleware;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use tower::ServiceExt;
+
+    #[test]
+    fn finished_guard_should_complete_request() {
+        let requests = Arc::new(InFlightRequests::new());
+        let guard = requests.start();
+
+        assert_eq!(requests.active(), 1);
+
+        guard.finish();
+
+        assert_eq!(requests.active(), 0);
+        assert_eq!(requests.completed(), 1);
+        assert_eq!(requests.cancelled(), 0);
+    }
+
+    #[test]
+    fn dropped_guard_should_cancel_request() {
+        let requests = Arc::new(InFlightRequests::new());
+
+        drop(requests.start());
+
+        assert_eq!(requests.active(), 0);
+        assert_eq!(requests.completed(), 0);
+        assert_eq!(requests.cancelled(), 1);
+    }
+
+    #[tokio::test]
+    async fn track_in_flight_should_count_completed_requests() {
+        let requests = Arc::new(InFlightRequests::new());
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(requests.clone(), track_in_flight));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.active(), 0);
+        assert_eq!(requests.completed(), 1);
+    }
+
+    #[tokio::test]
+    async fn server_stopping_without_signal_should_not_summarise() {
+        let requests = Arc::new(InFlightRequests::new());
+        let (_signal_sender, signal) = oneshot::channel();
+
+        let summary =
+            serve_until_drained(async {}, signal, &requests, Duration::from_secs(1)).await;
+
+        assert!(summary.is_none());
+    }
+
+    #[tokio::test]
+    async fn drain_should_only_count_requests_after_signal() {
+        let requests = Arc::new(InFlightRequests::new());
+        let (signal_sender, signal) = oneshot::channel();
+
+        requests.start().finish();
+        let guard = requests.start();
+
+        let server = async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            guard.finish();
+        };
+
+        signal_sender.send("SIGTERM").unwrap();
+
+        let summary = serve_until_drained(server, signal, &requests, Duration::from_secs(5)).await;
+
+        assert_eq!(
+            summary,
+            Some(ShutdownSummary {
+                signal: "SIGTERM",
+                requests_completed: 1,
+                in_flight_at_signal: 1,
+                ..ShutdownSummary::default()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn drain_past_deadline_should_abandon_requests() {
+        let requests = Arc::new(InFlightRequests::new());
+        let (signal_sender, signal) = oneshot::channel();
+
+        let guard = requests.start();
+
+        let server = async move {
+            let _guard = guard;
+            std::future::pending::<()>().await;
+        };
+
+        signal_sender.send("SIGINT").unwrap();
+
+        let summary =
+            serve_until_drained(server, signal, &requests, Duration::from_millis(10)).await;
+
+        assert_eq!(
+            summary,
+            Some(ShutdownSummary {
+                signal: "SIGINT",
+                requests_cancelled: 1,
+                in_flight_at_signal: 1,
+                abandoned_requests: 1,
+                ..ShutdownSummary::default()
+            })
+        );
+        assert_eq!(requests.active(), 0);
+    }
+}
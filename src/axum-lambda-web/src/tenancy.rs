@@ -0,0 +1,231 @@
+use crate::application::domain::TenantId;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use http::header::{AUTHORIZATION, HOST};
+use http::{HeaderMap, StatusCode};
+use std::env;
+use std::fmt;
+
+const DEFAULT_TENANT_CLAIM: &str = "tenant_id";
+
+#[derive(Debug, PartialEq)]
+pub enum TenantError {
+    /// The token or host names a tenant that can't be read.
+    Invalid(String),
+    /// The token and the host name different tenants.
+    Mismatch(String),
+}
+
+impl TenantError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            TenantError::Invalid(_) => StatusCode::BAD_REQUEST,
+            TenantError::Mismatch(_) => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+impl fmt::Display for TenantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TenantError::Invalid(message) | TenantError::Mismatch(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TenantError {}
+
+/// Works out which tenant a request belongs to. The tenant is read from a claim in the bearer
+/// token, or from the subdomain of `host_suffix` the request was sent to. Requests that name
+/// neither belong to the default tenant.
+///
+/// The token's signature is not checked here. The service must only be reachable through an
+/// API with a JWT authorizer, as `template.yaml` configures, which rejects requests with a
+/// missing or unverified token before they reach the service.
+pub struct TenantResolver {
+    claim: String,
+    host_suffix: Option<String>,
+}
+
+impl TenantResolver {
+    pub fn new(claim: &str, host_suffix: Option<&str>) -> Self {
+        Self {
+            claim: claim.to_string(),
+            host_suffix: host_suffix.map(|suffix| suffix.trim_start_matches('.').to_lowercase()),
+        }
+    }
+
+    /// Configure the resolver from `TENANT_CLAIM`, `tenant_id` if not set, and
+    /// `TENANT_HOST_SUFFIX`, such as `todo.example.com`.
+    pub fn from_env() -> Self {
+        let claim = env::var("TENANT_CLAIM").unwrap_or_else(|_| DEFAULT_TENANT_CLAIM.to_string());
+        let host_suffix = env::var("TENANT_HOST_SUFFIX").ok();
+
+        Self::new(&claim, host_suffix.as_deref())
+    }
+
+    pub fn resolve(&self, headers: &HeaderMap) -> Result<TenantId, TenantError> {
+        let from_token = self.tenant_from_token(headers)?;
+        let from_host = self.tenant_from_host(headers)?;
+
+        match (from_token, from_host) {
+            (Some(token_tenant), Some(host_tenant)) if token_tenant != host_tenant => {
+                Err(TenantError::Mismatch(format!(
+                    "The token is for tenant '{}' but the request was sent to tenant '{}'",
+                    token_tenant.to_string(),
+                    host_tenant.to_string()
+                )))
+            }
+            (Some(tenant), _) | (None, Some(tenant)) => Ok(tenant),
+            (None, None) => Ok(TenantId::default()),
+        }
+    }
+
+    fn tenant_from_token(&self, headers: &HeaderMap) -> Result<Option<TenantId>, TenantError> {
+        let token = match headers
+            .get(AUTHORIZATION)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.strip_prefix("Bearer "))
+        {
+            Some(token) => token.trim(),
+            None => return Ok(None),
+        };
+
+        let invalid_token = || TenantError::Invalid("The bearer token could not be read".to_string());
+
+        let payload = token.split('.').nth(1).ok_or_else(invalid_token)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .map_err(|_| invalid_token())?;
+        let claims: serde_json::Value =
+            serde_json::from_slice(&payload).map_err(|_| invalid_token())?;
+
+        match claims.get(&self.claim) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::String(tenant)) => TenantId::new(tenant)
+                .map(Some)
+                .map_err(|e| TenantError::Invalid(e.to_string())),
+            Some(_) => Err(TenantError::Invalid(format!(
+                "The '{}' claim must be a string",
+                self.claim
+            ))),
+        }
+    }
+
+    fn tenant_from_host(&self, headers: &HeaderMap) -> Result<Option<TenantId>, TenantError> {
+        let host_suffix = match &self.host_suffix {
+            Some(host_suffix) => host_suffix,
+            None => return Ok(None),
+        };
+
+        let host = match headers.get(HOST).and_then(|val| val.to_str().ok()) {
+            Some(host) => host.split(':').next().unwrap_or_default().to_lowercase(),
+            None => return Ok(None),
+        };
+
+        match host
+            .strip_suffix(host_suffix.as_str())
+            .and_then(|subdomain| subdomain.strip_suffix('.'))
+        {
+            Some(subdomain) if !subdomain.contains('.') => TenantId::new(subdomain)
+                .map(Some)
+                .map_err(|e| TenantError::Invalid(e.to_string())),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{TenantError, TenantResolver};
+    use crate::application::domain::TenantId;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use http::header::{AUTHORIZATION, HOST};
+    use http::{HeaderMap, HeaderValue};
+
+    fn bearer_token(claims: serde_json::Value) -> String {
+        format!(
+            "Bearer {}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    fn headers(authorization: Option<String>, host: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&authorization).unwrap());
+        }
+
+        if let Some(host) = host {
+            headers.insert(HOST, HeaderValue::from_str(host).unwrap());
+        }
+
+        headers
+    }
+
+    fn resolver() -> TenantResolver {
+        TenantResolver::new("tenant_id", Some("todo.example.com"))
+    }
+
+    #[test]
+    fn tenant_should_be_read_from_token_claim() {
+        let token = bearer_token(serde_json::json!({"sub": "james", "tenant_id": "Acme"}));
+
+        let tenant = resolver().resolve(&headers(Some(token), None)).unwrap();
+
+        assert_eq!(tenant, TenantId::new("acme").unwrap());
+    }
+
+    #[test]
+    fn tenant_should_be_read_from_host_subdomain() {
+        let tenant = resolver()
+            .resolve(&headers(None, Some("acme.todo.example.com:443")))
+            .unwrap();
+
+        assert_eq!(tenant, TenantId::new("acme").unwrap());
+    }
+
+    #[test]
+    fn request_without_tenant_should_use_default_tenant() {
+        let token = bearer_token(serde_json::json!({"sub": "james"}));
+
+        let tenant = resolver()
+            .resolve(&headers(Some(token), Some("todo.example.com")))
+            .unwrap();
+
+        assert!(tenant.is_default());
+    }
+
+    #[test]
+    fn token_for_another_tenants_host_should_be_forbidden() {
+        let token = bearer_token(serde_json::json!({"tenant_id": "acme"}));
+
+        let res = resolver().resolve(&headers(Some(token), Some("globex.todo.example.com")));
+
+        assert!(matches!(res, Err(TenantError::Mismatch(_))));
+    }
+
+    #[test]
+    fn unreadable_token_or_tenant_should_be_invalid() {
+        let invalid_tenant = bearer_token(serde_json::json!({"tenant_id": "acme#globex"}));
+        let reserved_tenant = bearer_token(serde_json::json!({"tenant_id": "default"}));
+
+        for authorization in [
+            String::from("Bearer not-a-token"),
+            invalid_tenant,
+            reserved_tenant,
+        ] {
+            let res = resolver().resolve(&headers(Some(authorization), None));
+
+            assert!(matches!(res, Err(TenantError::Invalid(_))));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use aws_lambda_events::apigw::ApiGatewayV2httpRequest;
+
+use crate::domain::entities::OwnerId;
+
+/// The JWT claim that identifies the owner of a request.
+const OWNER_CLAIM: &str = "sub";
+
+/// The owner making the request, taken from the claims validated by the API Gateway JWT
+/// authorizer. Returns `None` if the request has no token carrying the claim.
+///
+/// With the `local-dev` feature the `Owner` header is used when there are no claims, so the
+/// handlers can be invoked locally without an authorizer. Any caller can set the header, so the
+/// feature must never be enabled in a deployed build.
+pub fn request_owner(request: &ApiGatewayV2httpRequest) -> Option<OwnerId> {
+    let claim = request
+        .request_context
+        .authorizer
+        .as_ref()
+        .and_then(|authorizer| authorizer.jwt.as_ref())
+        .and_then(|jwt| jwt.claims.get(OWNER_CLAIM))
+        .cloned();
+
+    #[cfg(feature = "local-dev")]
+    let claim = claim.or_else(|| {
+        request
+            .headers
+            .get("Owner")
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_string())
+    });
+
+    claim.and_then(|owner| OwnerId::new(owner).ok())
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::application::auth::request_owner;
+    use aws_lambda_events::apigw::{
+        ApiGatewayV2httpRequest, ApiGatewayV2httpRequestContext,
+        ApiGatewayV2httpRequestContextAuthorizerDescription,
+        ApiGatewayV2httpRequestContextAuthorizerJwtDescription,
+    };
+    use http::HeaderValue;
+    use std::collections::HashMap;
+
+    fn request_with_claims(claims: HashMap<String, String>) -> ApiGatewayV2httpRequest {
+        ApiGatewayV2httpRequest {
+            request_context: ApiGatewayV2httpRequestContext {
+                authorizer: Some(ApiGatewayV2httpRequestContextAuthorizerDescription {
+                    jwt: Some(ApiGatewayV2httpRequestContextAuthorizerJwtDescription {
+                        claims: claims,
+                        scopes: Option::None,
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn owner_should_be_read_from_sub_claim() {
+        let mut claims = HashMap::new();
+        claims.insert("sub".to_string(), "jameseastham".to_string());
+
+        let owner = request_owner(&request_with_claims(claims));
+
+        assert_eq!(owner.unwrap().to_string(), "jameseastham");
+    }
+
+    #[test]
+    fn request_without_sub_claim_should_have_no_owner() {
+        let mut claims = HashMap::new();
+        claims.insert("email".to_string(), "james@example.com".to_string());
+
+        assert_eq!(request_owner(&request_with_claims(claims)).is_none(), true);
+        assert_eq!(request_owner(&ApiGatewayV2httpRequest::default()).is_none(), true);
+    }
+
+    #[cfg(not(feature = "local-dev"))]
+    #[test]
+    fn owner_header_should_be_ignored_outside_local_dev() {
+        let mut request = ApiGatewayV2httpRequest::default();
+        request
+            .headers
+            .insert("Owner", HeaderValue::from_static("jameseastham"));
+
+        assert_eq!(request_owner(&request).is_none(), true);
+    }
+
+    #[cfg(feature = "local-dev")]
+    #[test]
+    fn owner_header_should_be_used_in_local_dev() {
+        let mut request = ApiGatewayV2httpRequest::default();
+        request
+            .headers
+            .insert("Owner", HeaderValue::from_static("jameseastham"));
+
+        assert_eq!(request_owner(&request).unwrap().to_string(), "jameseastham");
+    }
+}
@@ -5,7 +5,14 @@ use lambda_http::{
 };
 use lambda_runtime::LambdaEvent;
 
-use crate::domain::entities::Repository;
+use crate::application::{
+    auth::request_owner,
+    problem::{bad_request, service_failed, unauthorized, validation_failed},
+};
+use crate::domain::{
+    entities::{Repository, ToDoId},
+    todo_service,
+};
 
 pub async fn get_todo_handler<T: Repository>(
     client: &T,
@@ -13,6 +20,15 @@ pub async fn get_todo_handler<T: Repository>(
 ) -> Result<ApiGatewayV2httpResponse, Error> {
     tracing::info!("Received request from API Gateway");
 
+    let owner = match request_owner(&request.payload) {
+        Some(owner) => owner,
+        None => {
+            tracing::error!("Owner not found");
+
+            return Ok(unauthorized());
+        }
+    };
+
     let path_parameters = request.payload.path_parameters;
 
     tracing::info!(
@@ -25,63 +41,35 @@ pub async fn get_todo_handler<T: Repository>(
         None => {
             tracing::error!("Id not found");
 
-            return Ok(ApiGatewayV2httpResponse {
-                body: Some(Body::Text(format_error_response("Id required".to_string()))),
-                status_code: 400,
-                headers: default_headers(),
-                ..Default::default()
-            });
+            return Ok(bad_request("Id required"));
         }
     };
 
     if id == "" {
         tracing::error!("Id not found");
 
-        return Ok(ApiGatewayV2httpResponse {
-            body: Some(Body::Text(format_error_response("Id required".to_string()))),
-            status_code: 400,
-            headers: default_headers(),
-            ..Default::default()
-        });
+        return Ok(bad_request("Id required"));
     }
 
-    // Temporarily pull owner from header before implementing authentication/authorization
-    let owner = match request.payload.headers.get("Owner") {
-        None => "".to_string(),
-        Some(val) => val.to_str().unwrap().to_string(),
+    let to_do_id = match ToDoId::parse(id.to_string()) {
+        Ok(to_do_id) => to_do_id,
+        Err(err) => return Ok(validation_failed(&err)),
     };
 
-    let res = client.get_todo(&owner, id).await;
+    let res = todo_service::get_todos(owner, to_do_id, client).await;
 
     // Return a response to the end-user
-    match res {
-        Ok(_) => Ok(ApiGatewayV2httpResponse {
-            body: Some(Body::Text(
-                serde_json::to_string(&res.unwrap().into_dto()).unwrap(),
-            )),
+    match &res {
+        Ok(val) => Ok(ApiGatewayV2httpResponse {
+            body: Some(Body::Text(serde_json::to_string(val).unwrap())),
             status_code: 200,
             headers: default_headers(),
             ..Default::default()
         }),
-        Err(err) => Ok({
-            tracing::error!("{}", err.to_string());
-
-            ApiGatewayV2httpResponse {
-                body: Some(Body::Text(format_error_response(
-                    "Internal server error".to_string(),
-                ))),
-                status_code: 500,
-                headers: default_headers(),
-                ..Default::default()
-            }
-        }),
+        Err(err) => Ok(service_failed(err)),
     }
 }
 
-fn format_error_response(err: String) -> String {
-    format!("{{\"message\": {}}}", err.to_string())
-}
-
 fn default_headers() -> HeaderMap {
     let mut headers = HeaderMap::new();
     let header_value = HeaderValue::from_str("application/json");
@@ -90,3 +78,204 @@ fn default_headers() -> HeaderMap {
 
     headers
 }
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::application::get_todo_handler::get_todo_handler;
+    use crate::domain::entities::{Repository, ToDo};
+    use crate::domain::error_types::RepositoryError;
+    use async_trait::async_trait;
+    use aws_lambda_events::apigw::{
+        ApiGatewayV2httpRequest, ApiGatewayV2httpRequestContext,
+        ApiGatewayV2httpRequestContextAuthorizerDescription,
+        ApiGatewayV2httpRequestContextAuthorizerJwtDescription,
+    };
+    use http::{HeaderMap, HeaderValue};
+    use lambda_http::Body;
+    use lambda_runtime::{Context, LambdaEvent};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    struct MockRepository {}
+
+    #[async_trait]
+    impl Repository for MockRepository {
+        async fn store_todo(&self, _: &ToDo) -> Result<(), RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+
+        async fn get_todo(&self, _: &String, _: &String) -> Result<ToDo, RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+
+        async fn list_todos(&self, _: &String) -> Result<Vec<ToDo>, RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+    }
+
+    struct MissingRepository {}
+
+    #[async_trait]
+    impl Repository for MissingRepository {
+        async fn store_todo(&self, _: &ToDo) -> Result<(), RepositoryError> {
+            Ok(())
+        }
+
+        async fn get_todo(&self, _: &String, id: &String) -> Result<ToDo, RepositoryError> {
+            Err(RepositoryError::not_found(format!("ToDo {} not found", id)))
+        }
+
+        async fn list_todos(&self, _: &String) -> Result<Vec<ToDo>, RepositoryError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repository_error_should_return_500() {
+        let client = MockRepository {};
+
+        let request = build_request(Some("test1"));
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = get_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 500);
+        let body = problem_body(response.body);
+        assert_eq!(body["status"], 500);
+        assert_eq!(body["detail"].as_str().unwrap().contains("Forced failure"), false);
+    }
+
+    #[tokio::test]
+    async fn test_missing_todo_should_return_404() {
+        let client = MissingRepository {};
+
+        let request = build_request(Some("test1"));
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = get_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 404);
+        assert_eq!(problem_body(response.body)["detail"], "ToDo test1 not found");
+    }
+
+    #[tokio::test]
+    async fn test_missing_id_should_return_400() {
+        let client = MockRepository {};
+
+        let request = build_request(Option::None);
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = get_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 400);
+        assert_eq!(problem_body(response.body)["detail"], "Id required");
+    }
+
+    #[tokio::test]
+    async fn test_request_without_owner_should_return_401() {
+        let client = MockRepository {};
+
+        let mut request = build_request(Some("test1"));
+        request.request_context = ApiGatewayV2httpRequestContext::default();
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = get_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 401);
+        assert_eq!(problem_body(response.body)["status"], 401);
+    }
+
+    fn build_request(id: Option<&str>) -> ApiGatewayV2httpRequest {
+        let mut path_parameters = HashMap::new();
+
+        if let Some(id) = id {
+            path_parameters.insert("id".to_string(), id.to_string());
+        }
+
+        ApiGatewayV2httpRequest {
+            path_parameters: path_parameters,
+            request_context: build_request_context("jameseastham"),
+            ..Default::default()
+        }
+    }
+
+    fn build_request_context(owner: &str) -> ApiGatewayV2httpRequestContext {
+        let mut claims = HashMap::new();
+        claims.insert("sub".to_string(), owner.to_string());
+
+        ApiGatewayV2httpRequestContext {
+            authorizer: Some(ApiGatewayV2httpRequestContextAuthorizerDescription {
+                jwt: Some(ApiGatewayV2httpRequestContextAuthorizerJwtDescription {
+                    claims: claims,
+                    scopes: Option::None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn problem_body(body: Option<Body>) -> Value {
+        match body {
+            Some(Body::Text(val)) => serde_json::from_str(&val).expect("Body is not JSON"),
+            _ => panic!("Response has no text body"),
+        }
+    }
+
+    fn build_test_context() -> Context {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "lambda-runtime-aws-request-id",
+            HeaderValue::from_static("my-id"),
+        );
+        headers.insert(
+            "lambda-runtime-deadline-ms",
+            HeaderValue::from_static("123"),
+        );
+        headers.insert(
+            "lambda-runtime-invoked-function-arn",
+            HeaderValue::from_static("arn::myarn"),
+        );
+        headers.insert(
+            "lambda-runtime-trace-id",
+            HeaderValue::from_static("arn::myarn"),
+        );
+
+        Context::try_from(headers).expect("Failure parsing context")
+    }
+}
@@ -5,10 +5,11 @@ use lambda_http::{
 };
 use lambda_runtime::LambdaEvent;
 
-use crate::domain::{
-    entities::{OwnerId, Repository},
-    todo_service,
+use crate::application::{
+    auth::request_owner,
+    problem::{internal_server_error, unauthorized},
 };
+use crate::domain::{entities::Repository, todo_service};
 
 pub async fn list_todo_handler<T: Repository>(
     client: &T,
@@ -16,13 +17,16 @@ pub async fn list_todo_handler<T: Repository>(
 ) -> Result<ApiGatewayV2httpResponse, Error> {
     tracing::info!("Received request from API Gateway");
 
-    // Temporarily pull owner from header before implementing authentication/authorization
-    let owner = match request.payload.headers.get("Owner") {
-        None => "".to_string(),
-        Some(val) => val.to_str().unwrap().to_string(),
+    let owner = match request_owner(&request.payload) {
+        Some(owner) => owner,
+        None => {
+            tracing::error!("Owner not found");
+
+            return Ok(unauthorized());
+        }
     };
 
-    let res = todo_service::list_todos(OwnerId::new(owner).unwrap(), client).await;
+    let res = todo_service::list_todos(owner, client).await;
 
     // Return a response to the end-user
     match res {
@@ -32,23 +36,10 @@ pub async fn list_todo_handler<T: Repository>(
             headers: default_headers(),
             ..Default::default()
         }),
-        Err(_) => Ok({
-            ApiGatewayV2httpResponse {
-                body: Some(Body::Text(format_error_response(
-                    "Internal server error".to_string(),
-                ))),
-                status_code: 500,
-                headers: default_headers(),
-                ..Default::default()
-            }
-        }),
+        Err(_) => Ok(internal_server_error()),
     }
 }
 
-fn format_error_response(err: String) -> String {
-    format!("{{\"message\": {}}}", err.to_string())
-}
-
 fn default_headers() -> HeaderMap {
     let mut headers = HeaderMap::new();
     let header_value = HeaderValue::from_str("application/json");
@@ -57,3 +48,136 @@ fn default_headers() -> HeaderMap {
 
     headers
 }
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::application::list_todo_handler::list_todo_handler;
+    use crate::domain::entities::{Repository, ToDo};
+    use crate::domain::error_types::RepositoryError;
+    use async_trait::async_trait;
+    use aws_lambda_events::apigw::{
+        ApiGatewayV2httpRequest, ApiGatewayV2httpRequestContext,
+        ApiGatewayV2httpRequestContextAuthorizerDescription,
+        ApiGatewayV2httpRequestContextAuthorizerJwtDescription,
+    };
+    use http::{HeaderMap, HeaderValue};
+    use lambda_http::Body;
+    use lambda_runtime::{Context, LambdaEvent};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    struct MockRepository {}
+
+    #[async_trait]
+    impl Repository for MockRepository {
+        async fn store_todo(&self, _: &ToDo) -> Result<(), RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+
+        async fn get_todo(&self, _: &String, _: &String) -> Result<ToDo, RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+
+        async fn list_todos(&self, _: &String) -> Result<Vec<ToDo>, RepositoryError> {
+            return Err(RepositoryError::new("Forced failure!".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repository_error_should_return_500() {
+        let client = MockRepository {};
+
+        let request = build_request();
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = list_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 500);
+        assert_eq!(problem_body(response.body)["status"], 500);
+    }
+
+    #[tokio::test]
+    async fn test_request_without_owner_should_return_401() {
+        let client = MockRepository {};
+
+        let mut request = build_request();
+        request.request_context = ApiGatewayV2httpRequestContext::default();
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = list_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 401);
+        assert_eq!(problem_body(response.body)["status"], 401);
+    }
+
+    fn build_request() -> ApiGatewayV2httpRequest {
+        ApiGatewayV2httpRequest {
+            request_context: build_request_context("jameseastham"),
+            ..Default::default()
+        }
+    }
+
+    fn build_request_context(owner: &str) -> ApiGatewayV2httpRequestContext {
+        let mut claims = HashMap::new();
+        claims.insert("sub".to_string(), owner.to_string());
+
+        ApiGatewayV2httpRequestContext {
+            authorizer: Some(ApiGatewayV2httpRequestContextAuthorizerDescription {
+                jwt: Some(ApiGatewayV2httpRequestContextAuthorizerJwtDescription {
+                    claims: claims,
+                    scopes: Option::None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn problem_body(body: Option<Body>) -> Value {
+        match body {
+            Some(Body::Text(val)) => serde_json::from_str(&val).expect("Body is not JSON"),
+            _ => panic!("Response has no text body"),
+        }
+    }
+
+    fn build_test_context() -> Context {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "lambda-runtime-aws-request-id",
+            HeaderValue::from_static("my-id"),
+        );
+        headers.insert(
+            "lambda-runtime-deadline-ms",
+            HeaderValue::from_static("123"),
+        );
+        headers.insert(
+            "lambda-runtime-invoked-function-arn",
+            HeaderValue::from_static("arn::myarn"),
+        );
+        headers.insert(
+            "lambda-runtime-trace-id",
+            HeaderValue::from_static("arn::myarn"),
+        );
+
+        Context::try_from(headers).expect("Failure parsing context")
+    }
+}
@@ -1,4 +1,8 @@
+pub mod auth;
 pub mod create_todo_handler;
 pub mod get_todo_handler;
 pub mod list_todo_handler;
+pub mod problem;
+pub mod router;
+pub mod stream_handler;
 pub mod update_todo_handler;
@@ -0,0 +1,199 @@
+use aws_lambda_events::apigw::ApiGatewayV2httpResponse;
+use lambda_http::{
+    http::{HeaderMap, HeaderValue, StatusCode},
+    Body,
+};
+use serde::Serialize;
+
+use crate::domain::error_types::{FieldError, ServiceError, ServiceErrorKind, ValidationError};
+
+/// The body of every error response, an RFC 7807 problem details object.
+///
+/// The `type` is always `about:blank`, so the `title` is the HTTP status' reason phrase and the
+/// `detail` explains this occurrence. Requests that fail validation list each invalid field in
+/// `errors`.
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, detail: &str) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: detail.to_string(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_errors(self, errors: Vec<FieldError>) -> ProblemDetails {
+        ProblemDetails {
+            errors: errors,
+            ..self
+        }
+    }
+
+    pub fn into_response(self) -> ApiGatewayV2httpResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Content-Type",
+            HeaderValue::from_static("application/problem+json"),
+        );
+
+        ApiGatewayV2httpResponse {
+            body: Some(Body::Text(serde_json::to_string(&self).unwrap())),
+            status_code: self.status.into(),
+            headers: headers,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn bad_request(detail: &str) -> ApiGatewayV2httpResponse {
+    ProblemDetails::new(StatusCode::BAD_REQUEST, detail).into_response()
+}
+
+/// A 400 for a request with invalid values, listing the fields that are invalid.
+pub fn validation_failed(err: &ValidationError) -> ApiGatewayV2httpResponse {
+    ProblemDetails::new(StatusCode::BAD_REQUEST, &err.to_string())
+        .with_errors(err.field_errors().clone())
+        .into_response()
+}
+
+/// The response for a request the service couldn't complete: a 400 listing the invalid fields,
+/// a 404 for a missing ToDo, or a 500 that doesn't include the underlying error.
+pub fn service_failed(err: &ServiceError) -> ApiGatewayV2httpResponse {
+    match err.kind() {
+        ServiceErrorKind::Validation => {
+            ProblemDetails::new(StatusCode::BAD_REQUEST, &err.to_string())
+                .with_errors(err.field_errors().clone())
+                .into_response()
+        }
+        ServiceErrorKind::NotFound => not_found(&err.to_string()),
+        ServiceErrorKind::Repository => internal_server_error(),
+    }
+}
+
+/// The response for a request that doesn't identify its owner.
+pub fn unauthorized() -> ApiGatewayV2httpResponse {
+    ProblemDetails::new(
+        StatusCode::UNAUTHORIZED,
+        "The request must carry a token identifying its owner",
+    )
+    .into_response()
+}
+
+pub fn not_found(detail: &str) -> ApiGatewayV2httpResponse {
+    ProblemDetails::new(StatusCode::NOT_FOUND, detail).into_response()
+}
+
+/// A 500, which never includes the underlying error as it may disclose how the data is stored.
+pub fn internal_server_error() -> ApiGatewayV2httpResponse {
+    ProblemDetails::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "The request could not be completed",
+    )
+    .into_response()
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::application::problem::{
+        bad_request, internal_server_error, not_found, service_failed, unauthorized,
+        validation_failed,
+    };
+    use crate::domain::error_types::{RepositoryError, ServiceError, ValidationError};
+    use aws_lambda_events::apigw::ApiGatewayV2httpResponse;
+    use lambda_http::Body;
+    use serde_json::Value;
+
+    fn parse_body(response: &ApiGatewayV2httpResponse) -> Value {
+        match &response.body {
+            Some(Body::Text(body)) => serde_json::from_str(body).expect("Body is not JSON"),
+            _ => panic!("Response has no text body"),
+        }
+    }
+
+    #[test]
+    fn every_problem_should_be_problem_json() {
+        for (response, status) in [
+            (bad_request("Body cannot be empty"), 400),
+            (unauthorized(), 401),
+            (not_found("Not found"), 404),
+            (internal_server_error(), 500),
+        ] {
+            let body = parse_body(&response);
+
+            assert_eq!(response.status_code, status);
+            assert_eq!(
+                response.headers.get("Content-Type").unwrap(),
+                "application/problem+json"
+            );
+            assert_eq!(body["type"], "about:blank");
+            assert_eq!(body["status"], status);
+            assert_eq!(body["title"].as_str().unwrap().is_empty(), false);
+            assert_eq!(body["detail"].as_str().unwrap().is_empty(), false);
+            assert_eq!(body.get("errors").is_none(), true);
+        }
+    }
+
+    #[test]
+    fn detail_with_quotes_should_be_escaped() {
+        let body = parse_body(&bad_request("Due date \"tomorrow\" is invalid"));
+
+        assert_eq!(body["detail"], "Due date \"tomorrow\" is invalid");
+    }
+
+    #[test]
+    fn validation_failure_should_list_field_errors() {
+        let err = ValidationError::combine(vec![
+            ValidationError::for_field("title", "Must be between 1 and 50 chars".to_string()),
+            ValidationError::for_field("due_date", "Must be a date".to_string()),
+        ]);
+
+        let response = validation_failed(&err);
+        let body = parse_body(&response);
+
+        assert_eq!(response.status_code, 400);
+        assert_eq!(body["title"], "Bad Request");
+        assert_eq!(body["errors"][0]["field"], "title");
+        assert_eq!(body["errors"][0]["message"], "Must be between 1 and 50 chars");
+        assert_eq!(body["errors"][1]["field"], "due_date");
+    }
+
+    #[test]
+    fn service_failure_should_map_to_status() {
+        for (err, status) in [
+            (
+                ServiceError::from(ValidationError::for_field("title", "Too long".to_string())),
+                400,
+            ),
+            (
+                ServiceError::from(RepositoryError::not_found("ToDo 1234 not found".to_string())),
+                404,
+            ),
+            (
+                ServiceError::from(RepositoryError::new("Table TODO is throttled".to_string())),
+                500,
+            ),
+        ] {
+            let response = service_failed(&err);
+            let body = parse_body(&response);
+
+            assert_eq!(response.status_code, status);
+            assert_eq!(body["status"], status);
+            assert_eq!(body["detail"].as_str().unwrap().contains("TODO"), false);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use aws_lambda_events::apigw::{ApiGatewayV2httpRequest, ApiGatewayV2httpResponse};
+use lambda_http::Error;
+use lambda_runtime::LambdaEvent;
+
+use crate::application::{
+    create_todo_handler::create_todo_handler, get_todo_handler::get_todo_handler,
+    list_todo_handler::list_todo_handler, problem::not_found,
+    update_todo_handler::update_todo_handler,
+};
+use crate::domain::entities::Repository;
+
+/// The operations the API supports, each handled by its own handler.
+#[derive(Debug, PartialEq)]
+enum Route {
+    ListToDos,
+    CreateToDo,
+    GetToDo(String),
+    UpdateToDo(String),
+}
+
+/// Send a request to the handler for its route, so all four operations can be deployed as a
+/// single function. Requests that match no route are answered with a 404.
+pub async fn route_request<T: Repository>(
+    client: &T,
+    mut request: LambdaEvent<ApiGatewayV2httpRequest>,
+) -> Result<ApiGatewayV2httpResponse, Error> {
+    let route = match_route(&request.payload);
+
+    tracing::info!("Routing request to {:?}", route);
+
+    match route {
+        Some(Route::ListToDos) => list_todo_handler(client, request).await,
+        Some(Route::CreateToDo) => create_todo_handler(client, request).await,
+        Some(Route::GetToDo(id)) => {
+            // A catch-all route has no path parameters, so the id is taken from the path
+            request.payload.path_parameters.insert("id".to_string(), id);
+
+            get_todo_handler(client, request).await
+        }
+        Some(Route::UpdateToDo(_)) => update_todo_handler(client, request).await,
+        None => Ok(not_found("No route matches the request")),
+    }
+}
+
+/// Match on the route key API Gateway matched the request to, such as `GET /{id}`. Requests sent
+/// to the `$default` catch-all route are matched on their method and path instead.
+fn match_route(request: &ApiGatewayV2httpRequest) -> Option<Route> {
+    let (method, path) = match request.route_key.as_deref() {
+        Some(route_key) if route_key != "$default" => {
+            let (method, path) = route_key.split_once(' ')?;
+
+            (method.to_string(), path.to_string())
+        }
+        _ => (
+            request.request_context.http.method.as_str().to_string(),
+            request.raw_path.clone().unwrap_or_default(),
+        ),
+    };
+
+    let id = match path.trim_matches('/') {
+        "" => Option::None,
+        "{id}" => Some(request.path_parameters.get("id").cloned().unwrap_or_default()),
+        val if val.contains('/') => return Option::None,
+        val => Some(val.to_string()),
+    };
+
+    match (method.as_str(), id) {
+        ("GET", Option::None) => Some(Route::ListToDos),
+        ("POST", Option::None) => Some(Route::CreateToDo),
+        ("GET", Some(id)) => Some(Route::GetToDo(id)),
+        ("PUT", Some(id)) => Some(Route::UpdateToDo(id)),
+        _ => Option::None,
+    }
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{match_route, Route};
+    use aws_lambda_events::apigw::ApiGatewayV2httpRequest;
+    use http::Method;
+    use std::collections::HashMap;
+
+    fn request_for_route_key(route_key: &str, id: Option<&str>) -> ApiGatewayV2httpRequest {
+        let mut path_parameters = HashMap::new();
+
+        if let Some(id) = id {
+            path_parameters.insert("id".to_string(), id.to_string());
+        }
+
+        ApiGatewayV2httpRequest {
+            route_key: Some(route_key.to_string()),
+            path_parameters: path_parameters,
+            ..Default::default()
+        }
+    }
+
+    fn request_for_path(method: Method, path: &str) -> ApiGatewayV2httpRequest {
+        let mut request = ApiGatewayV2httpRequest {
+            route_key: Some("$default".to_string()),
+            raw_path: Some(path.to_string()),
+            ..Default::default()
+        };
+        request.request_context.http.method = method;
+
+        request
+    }
+
+    #[test]
+    fn route_key_should_match_route() {
+        assert_eq!(
+            match_route(&request_for_route_key("GET /", Option::None)),
+            Some(Route::ListToDos)
+        );
+        assert_eq!(
+            match_route(&request_for_route_key("POST /", Option::None)),
+            Some(Route::CreateToDo)
+        );
+        assert_eq!(
+            match_route(&request_for_route_key("GET /{id}", Some("abc"))),
+            Some(Route::GetToDo("abc".to_string()))
+        );
+        assert_eq!(
+            match_route(&request_for_route_key("PUT /{id}", Some("abc"))),
+            Some(Route::UpdateToDo("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn default_route_should_match_method_and_path() {
+        assert_eq!(
+            match_route(&request_for_path(Method::GET, "/")),
+            Some(Route::ListToDos)
+        );
+        assert_eq!(
+            match_route(&request_for_path(Method::GET, "/abc")),
+            Some(Route::GetToDo("abc".to_string()))
+        );
+        assert_eq!(
+            match_route(&request_for_path(Method::PUT, "/abc/")),
+            Some(Route::UpdateToDo("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn unknown_route_should_not_match() {
+        assert_eq!(match_route(&request_for_path(Method::DELETE, "/abc")), Option::None);
+        assert_eq!(match_route(&request_for_path(Method::PUT, "/")), Option::None);
+        assert_eq!(match_route(&request_for_path(Method::GET, "/abc/def")), Option::None);
+    }
+}
@@ -0,0 +1,230 @@
+use lambda_runtime::{Error, LambdaEvent};
+
+use crate::domain::events::{EventPublisher, ToDoEvent};
+use crate::infrastructure::stream_records::{
+    StreamBatchItemFailure, StreamBatchResponse, StreamEvent, StreamRecord,
+};
+
+/// Publish the events for each change in a batch of records from the table's stream.
+///
+/// Records are handled in order, and the first record whose events can't all be published is
+/// reported as a batch item failure. Lambda retries the batch from that record, so the records
+/// after it aren't handled until then, and events it published before failing are published
+/// again. Records that can't be parsed as a ToDo are logged and skipped, retrying them can't
+/// succeed.
+pub async fn stream_handler<T: EventPublisher>(
+    publisher: &T,
+    request: LambdaEvent<StreamEvent>,
+) -> Result<StreamBatchResponse, Error> {
+    tracing::info!(
+        "Received {} record(s) from DynamoDB Streams",
+        request.payload.records.len()
+    );
+
+    let mut response = StreamBatchResponse::default();
+
+    for record in &request.payload.records {
+        if let Err(err) = publish_record(publisher, record).await {
+            tracing::error!("Failed to publish {}: {}", record.event_id, err);
+
+            response.batch_item_failures.push(StreamBatchItemFailure {
+                item_identifier: record.dynamodb.sequence_number.clone(),
+            });
+
+            break;
+        }
+    }
+
+    Ok(response)
+}
+
+async fn publish_record<T: EventPublisher>(publisher: &T, record: &StreamRecord) -> Result<(), String> {
+    if record.is_migration() {
+        tracing::info!("Skipping {}, written by the key migration", record.event_id);
+
+        return Ok(());
+    }
+
+    let (old, new) = match (record.old_todo(), record.new_todo()) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(err), _) | (_, Err(err)) => {
+            tracing::error!("Skipping {}, not a valid ToDo: {}", record.event_id, err);
+
+            return Ok(());
+        }
+    };
+
+    for event in ToDoEvent::from_change(old.as_ref(), new.as_ref()) {
+        publisher.publish(&event).await.map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::application::stream_handler::stream_handler;
+    use crate::domain::error_types::PublishError;
+    use crate::domain::events::{EventPublisher, ToDoEvent};
+    use crate::infrastructure::stream_records::StreamEvent;
+    use async_trait::async_trait;
+    use http::{HeaderMap, HeaderValue};
+    use lambda_runtime::{Context, LambdaEvent};
+    use std::sync::Mutex;
+
+    /// Records the type of each event published, failing the call numbered `fail_on` and every
+    /// call after it.
+    struct MockPublisher {
+        fail_on: Option<usize>,
+        published: Mutex<Vec<String>>,
+    }
+
+    impl MockPublisher {
+        fn new(fail_on: Option<usize>) -> MockPublisher {
+            MockPublisher {
+                fail_on: fail_on,
+                published: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn published(&self) -> Vec<String> {
+            self.published.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl EventPublisher for MockPublisher {
+        async fn publish(&self, event: &ToDoEvent) -> Result<(), PublishError> {
+            let mut published = self.published.lock().unwrap();
+
+            if let Some(fail_on) = self.fail_on {
+                if published.len() + 1 >= fail_on {
+                    return Err(PublishError::new("Forced failure!".to_string()));
+                }
+            }
+
+            published.push(event.event_type().to_string());
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn inserted_todo_should_publish_created_event() {
+        let publisher = MockPublisher::new(Option::None);
+
+        let res = stream_handler(
+            &publisher,
+            build_event(include_str!("../../tests/fixtures/stream_insert.json")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.batch_item_failures.len(), 0);
+        assert_eq!(publisher.published(), vec!["ToDoCreated"]);
+    }
+
+    #[tokio::test]
+    async fn batch_should_publish_events_in_order() {
+        let publisher = MockPublisher::new(Option::None);
+
+        let res = stream_handler(
+            &publisher,
+            build_event(include_str!("../../tests/fixtures/stream_batch.json")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.batch_item_failures.len(), 0);
+        assert_eq!(
+            publisher.published(),
+            vec!["ToDoCreated", "ToDoTitleChanged", "ToDoCompleted"]
+        );
+    }
+
+    #[tokio::test]
+    async fn migrated_removed_and_invalid_records_should_be_skipped() {
+        let publisher = MockPublisher::new(Option::None);
+
+        let res = stream_handler(
+            &publisher,
+            build_event(include_str!("../../tests/fixtures/stream_skipped.json")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.batch_item_failures.len(), 0);
+        assert_eq!(publisher.published().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn failed_publish_should_report_record_and_stop() {
+        // The second record's first event fails, so its third record is never handled
+        let publisher = MockPublisher::new(Some(2));
+
+        let res = stream_handler(
+            &publisher,
+            build_event(include_str!("../../tests/fixtures/stream_batch.json")),
+        )
+        .await
+        .unwrap();
+
+        let failures: Vec<&str> = res
+            .batch_item_failures
+            .iter()
+            .map(|val| val.item_identifier.as_str())
+            .collect();
+
+        assert_eq!(failures, vec!["200000000000000000002"]);
+        assert_eq!(publisher.published(), vec!["ToDoCreated"]);
+    }
+
+    #[tokio::test]
+    async fn batch_response_should_serialize_for_lambda() {
+        let publisher = MockPublisher::new(Some(1));
+
+        let res = stream_handler(
+            &publisher,
+            build_event(include_str!("../../tests/fixtures/stream_insert.json")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&res).unwrap(),
+            "{\"batchItemFailures\":[{\"itemIdentifier\":\"100000000000000000001\"}]}"
+        );
+    }
+
+    fn build_event(fixture: &str) -> LambdaEvent<StreamEvent> {
+        LambdaEvent {
+            context: build_test_context(),
+            payload: serde_json::from_str(fixture).expect("Failure parsing fixture"),
+        }
+    }
+
+    fn build_test_context() -> Context {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "lambda-runtime-aws-request-id",
+            HeaderValue::from_static("my-id"),
+        );
+        headers.insert(
+            "lambda-runtime-deadline-ms",
+            HeaderValue::from_static("123"),
+        );
+        headers.insert(
+            "lambda-runtime-invoked-function-arn",
+            HeaderValue::from_static("arn::myarn"),
+        );
+        headers.insert(
+            "lambda-runtime-trace-id",
+            HeaderValue::from_static("arn::myarn"),
+        );
+
+        Context::try_from(headers).expect("Failure parsing context")
+    }
+}
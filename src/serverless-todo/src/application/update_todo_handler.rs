@@ -5,6 +5,10 @@ use lambda_http::{
 };
 use lambda_runtime::LambdaEvent;
 
+use crate::application::{
+    auth::request_owner,
+    problem::{bad_request, service_failed, unauthorized},
+};
 use crate::domain::{entities::Repository, public_types::UpdateToDoCommand, todo_service};
 
 pub async fn update_todo_handler<T: Repository>(
@@ -13,46 +17,51 @@ pub async fn update_todo_handler<T: Repository>(
 ) -> Result<ApiGatewayV2httpResponse, Error> {
     tracing::info!("Received request from API Gateway");
 
+    let owner = match request_owner(&request.payload) {
+        Some(owner) => owner,
+        None => {
+            tracing::error!("Owner not found");
+
+            return Ok(unauthorized());
+        }
+    };
+
     // Start with a string
     let body = extract_request_body(request);
 
     if body == Option::None {
-        return Ok(ApiGatewayV2httpResponse {
-            body: Some(Body::Text(format_error_response(
-                "Body cannot be empty".to_string(),
-            ))),
-            status_code: 400,
-            headers: default_headers(),
-            ..Default::default()
-        });
+        return Ok(bad_request("Body cannot be empty"));
     }
 
     // Deserialize the input command
-    let to_do_item = serde_json::from_str::<UpdateToDoCommand>(&body.unwrap()).unwrap();
+    // The owner always comes from the token, never from the body
+    let command = match serde_json::from_str::<UpdateToDoCommand>(&body.unwrap()) {
+        Ok(command) => command,
+        Err(e) => {
+            tracing::error!("Invalid body: {}", e);
+
+            return Ok(bad_request(&format!("Body is not a valid ToDo: {}", e)));
+        }
+    };
+    let to_do_item = UpdateToDoCommand {
+        owner_id: owner.to_string(),
+        ..command
+    };
 
     // Use the service to create a new todo
     // From here we are in pure domain language
     let updated_todo = todo_service::update_todo(to_do_item, client).await;
 
     // Convert the domain response back to a valid HTTP response
-    Ok(ApiGatewayV2httpResponse {
-        body: match &updated_todo {
-            Ok(val) => Some(Body::Text(serde_json::to_string_pretty(val).unwrap())),
-            Err(e) => Some(Body::Text(format_error_response(
-                e.to_string(),
-            ))),
-        },
-        status_code: match &updated_todo {
-            Ok(_) => 200,
-            Err(_) => 400,
-        },
-        headers: default_headers(),
-        ..Default::default()
-    })
-}
-
-fn format_error_response(err: String) -> String {
-    format!("{{\"message\": {}}}", err.to_string())
+    match &updated_todo {
+        Ok(val) => Ok(ApiGatewayV2httpResponse {
+            body: Some(Body::Text(serde_json::to_string_pretty(val).unwrap())),
+            status_code: 200,
+            headers: default_headers(),
+            ..Default::default()
+        }),
+        Err(err) => Ok(service_failed(err)),
+    }
 }
 
 fn default_headers() -> HeaderMap {
@@ -89,14 +98,20 @@ fn extract_request_body(request: LambdaEvent<ApiGatewayV2httpRequest>) -> Option
 #[cfg(test)]
 mod tests {
     use crate::application::update_todo_handler::update_todo_handler;
-    use crate::domain::entities::{Repository, ToDo, Title, OwnerId, ToDoId};
+    use crate::domain::entities::{Description, Repository, ToDo, Title, OwnerId, ToDoId};
     use crate::domain::error_types::RepositoryError;
     use async_trait::async_trait;
-    use aws_lambda_events::apigw::ApiGatewayV2httpRequest;
+    use aws_lambda_events::apigw::{
+        ApiGatewayV2httpRequest, ApiGatewayV2httpRequestContext,
+        ApiGatewayV2httpRequestContextAuthorizerDescription,
+        ApiGatewayV2httpRequestContextAuthorizerJwtDescription,
+    };
     use chrono::{Utc, DateTime};
     use http::{HeaderMap, HeaderValue};
     use lambda_http::Body;
     use lambda_runtime::{Context, LambdaEvent};
+    use serde_json::Value;
+    use std::collections::HashMap;
 
     struct MockRepository {
         should_fail: bool,
@@ -126,7 +141,9 @@ mod tests {
                 match self.to_do_status_to_return.as_str() {
                     "COMPLETE" => Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
                     _ => Option::None
-                }
+                },
+                Description::default(),
+                Option::None,
             )
             .unwrap())
         }
@@ -147,7 +164,9 @@ mod tests {
                     match self.to_do_status_to_return.as_str() {
                         "COMPLETE" => Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
                         _ => Option::None
-                    }
+                    },
+                    Description::default(),
+                    Option::None,
                 )
                 .unwrap(),
             );
@@ -156,6 +175,23 @@ mod tests {
         }
     }
 
+    struct MissingRepository {}
+
+    #[async_trait]
+    impl Repository for MissingRepository {
+        async fn store_todo(&self, _: &ToDo) -> Result<(), RepositoryError> {
+            Ok(())
+        }
+
+        async fn get_todo(&self, _: &String, id: &String) -> Result<ToDo, RepositoryError> {
+            Err(RepositoryError::not_found(format!("ToDo {} not found", id)))
+        }
+
+        async fn list_todos(&self, _: &String) -> Result<Vec<ToDo>, RepositoryError> {
+            Ok(Vec::new())
+        }
+    }
+
     #[tokio::test]
     async fn test_valid_request_should_return_success() {
         let client = MockRepository { should_fail: false, to_do_status_to_return: String::from("INCOMPLETE") };
@@ -177,7 +213,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_repository_error_should_return_error() {
+    async fn test_repository_error_should_return_500() {
         let client = MockRepository { should_fail: true, to_do_status_to_return: String::from("INCOMPLETE") };
 
         let request = build_request("test1".to_string(), Some("hello".to_string()));
@@ -192,12 +228,75 @@ mod tests {
         // Send mock request to Lambda handler function
         let response = update_todo_handler(&client, lambda_event).await.unwrap();
 
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 500);
+        let body = problem_body(response.body);
+        assert_eq!(body["detail"].as_str().unwrap().contains("Forced failure"), false);
+    }
+
+    #[tokio::test]
+    async fn test_missing_todo_should_return_404() {
+        let client = MissingRepository {};
+
+        let request = build_request("test1".to_string(), Some("the title".to_string()));
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = update_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 404);
+        assert_eq!(problem_body(response.body)["detail"], "ToDo test1 not found");
+    }
+
+    #[tokio::test]
+    async fn test_request_without_owner_should_return_401() {
+        let client = MockRepository { should_fail: false, to_do_status_to_return: String::from("INCOMPLETE") };
+
+        let mut request = build_request("test1".to_string(), Some("the title".to_string()));
+        request.request_context = ApiGatewayV2httpRequestContext::default();
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = update_todo_handler(&client, lambda_event).await.unwrap();
+
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 401);
+        assert_eq!(problem_body(response.body)["status"], 401);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_due_date_should_return_400() {
+        let client = MockRepository { should_fail: false, to_do_status_to_return: String::from("INCOMPLETE") };
+
+        let mut request = build_request("test1".to_string(), Some("the title".to_string()));
+        request.body = Some("{\"to_do_id\": \"test1\", \"title\": \"the title\", \"set_as_complete\": false, \"due_date\": \"12/08/2023\"}".to_string());
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = update_todo_handler(&client, lambda_event).await.unwrap();
+
         // Assert that the response is correct
         assert_eq!(response.status_code, 400);
-        assert_eq!(
-            response.body.unwrap(),
-            Body::Text("{\"message\": Record not found}".to_string())
-        );
+        assert_eq!(problem_body(response.body)["errors"][0]["field"], "due_date");
     }
 
     #[tokio::test]
@@ -216,12 +315,36 @@ mod tests {
         // Send mock request to Lambda handler function
         let response = update_todo_handler(&client, lambda_event).await.unwrap();
 
+        // Assert that the response is correct
+        assert_eq!(response.status_code, 400);
+        let body = problem_body(response.body);
+        assert_eq!(body["detail"], "Body cannot be empty");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_body_should_return_400() {
+        let client = MockRepository { should_fail: false, to_do_status_to_return: String::from("INCOMPLETE") };
+
+        let mut request = build_request("test1".to_string(), Some("the title".to_string()));
+        request.body = Some("{\"title\": ".to_string());
+
+        let test_context = build_test_context();
+
+        let lambda_event = LambdaEvent {
+            context: test_context,
+            payload: request,
+        };
+
+        // Send mock request to Lambda handler function
+        let response = update_todo_handler(&client, lambda_event).await.unwrap();
+
         // Assert that the response is correct
         assert_eq!(response.status_code, 400);
         assert_eq!(
-            response.body.unwrap(),
-            Body::Text("{\"message\": Body cannot be empty}".to_string())
+            response.headers.get("Content-Type").unwrap(),
+            "application/problem+json"
         );
+        assert_eq!(problem_body(response.body)["status"], 400);
     }
 
     #[tokio::test]
@@ -242,10 +365,9 @@ mod tests {
 
         // Assert that the response is correct
         assert_eq!(response.status_code, 400);
-        assert_eq!(
-            response.body.unwrap(),
-            Body::Text("{\"message\": Must be between 1 and 50 chars}".to_string())
-        );
+        let body = problem_body(response.body);
+        assert_eq!(body["detail"], "Must be between 1 and 50 chars");
+        assert_eq!(body["errors"][0]["field"], "title");
     }
 
     #[tokio::test]
@@ -266,10 +388,9 @@ mod tests {
 
         // Assert that the response is correct
         assert_eq!(response.status_code, 400);
-        assert_eq!(
-            response.body.unwrap(),
-            Body::Text("{\"message\": Must be between 1 and 50 chars}".to_string())
-        );
+        let body = problem_body(response.body);
+        assert_eq!(body["detail"], "Must be between 1 and 50 chars");
+        assert_eq!(body["errors"][0]["field"], "title");
     }
 
     fn build_request(id: String, title: Option<String>) -> ApiGatewayV2httpRequest {
@@ -289,10 +410,34 @@ mod tests {
 
         ApiGatewayV2httpRequest {
             body: Some(body),
+            request_context: build_request_context("jameseastham"),
             ..Default::default()
         }
     }
 
+    fn build_request_context(owner: &str) -> ApiGatewayV2httpRequestContext {
+        let mut claims = HashMap::new();
+        claims.insert("sub".to_string(), owner.to_string());
+
+        ApiGatewayV2httpRequestContext {
+            authorizer: Some(ApiGatewayV2httpRequestContextAuthorizerDescription {
+                jwt: Some(ApiGatewayV2httpRequestContextAuthorizerJwtDescription {
+                    claims: claims,
+                    scopes: Option::None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn problem_body(body: Option<Body>) -> Value {
+        match body {
+            Some(Body::Text(val)) => serde_json::from_str(&val).expect("Body is not JSON"),
+            _ => panic!("Response has no text body"),
+        }
+    }
+
     fn build_test_context() -> Context {
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -0,0 +1,30 @@
+use aws_sdk_eventbridge::Client;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use std::env;
+use todo::{
+    application::stream_handler::stream_handler,
+    infrastructure::{event_bridge::EventBridgePublisher, stream_records::StreamEvent},
+};
+
+/// Main function, publishing events for the changes on the table's stream
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Initialize the AWS SDK for Rust
+    let config = aws_config::load_from_env().await;
+    let event_bus_name = env::var("EVENT_BUS_NAME").expect("EVENT_BUS_NAME must be set");
+    let eventbridge_client = Client::new(&config);
+    let publisher = EventBridgePublisher::new(eventbridge_client, event_bus_name);
+
+    let _res = run(service_fn(
+        |request: LambdaEvent<StreamEvent>| stream_handler(&publisher, request),
+    ))
+    .await;
+
+    Ok(())
+}
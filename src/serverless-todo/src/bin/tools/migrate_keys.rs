@@ -0,0 +1,37 @@
+use aws_sdk_dynamodb::Client;
+use std::{env, process};
+use todo::infrastructure::migration::migrate_keys;
+
+/// Rewrite items stored with the old `PK = owner`, `SK = id` keys to the prefixed key format.
+///
+/// Runs as a dry run, reporting what would be rewritten, unless `--apply` is passed. The table is
+/// read from `TABLE_NAME`.
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .without_time()
+        .init();
+
+    let apply = env::args().any(|arg| arg == "--apply");
+    let table_name = env::var("TABLE_NAME").expect("TABLE_NAME must be set");
+
+    let config = aws_config::load_from_env().await;
+    let dynamodb_client = Client::new(&config);
+
+    match migrate_keys(&dynamodb_client, &table_name, !apply).await {
+        Ok(report) => {
+            print!("{}", report);
+
+            if !apply {
+                println!("Dry run, nothing was written. Run again with --apply to migrate.");
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+
+            process::exit(1);
+        }
+    }
+}
@@ -11,6 +11,7 @@ use super::{
 
 const INCOMPLETE_STATUS: &str = "INCOMPLETE";
 const COMPLETE_STATUS: &str = "COMPLETE";
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
 
 /// Represents a ToDo list item, a ToDo can be incomplete or complete.
 #[non_exhaustive]
@@ -50,6 +51,8 @@ impl ToDo {
             to_do_id: id,
             title: title,
             owner: owner_id,
+            description: Description::default(),
+            due_date: Option::None,
         }))
     }
 
@@ -60,6 +63,8 @@ impl ToDo {
         status: Option<String>,
         existing_id: Option<ToDoId>,
         completed_on: Option<DateTime<FixedOffset>>,
+        description: Description,
+        due_date: Option<DueDate>,
     ) -> Result<ToDo, Vec<ValidationError>> {
         let mut errors: Vec<ValidationError> = Vec::new();
 
@@ -94,6 +99,8 @@ impl ToDo {
                         to_do_id: existing_id.unwrap(),
                         title: title,
                         owner: owner_id,
+                        description: description,
+                        due_date: due_date,
                     })),
                     COMPLETE_STATUS => {
                         let parsed_completed_on = match completed_on {
@@ -109,12 +116,16 @@ impl ToDo {
                             title: title,
                             owner: owner_id,
                             completed_on: parsed_completed_on,
+                            description: description,
+                            due_date: due_date,
                         }))
                     }
                     _ => Ok(ToDo::Incomplete(IncompleteToDo {
                         to_do_id: id,
                         title: title,
                         owner: owner_id,
+                        description: description,
+                        due_date: due_date,
                     })),
                 }
             }
@@ -122,6 +133,8 @@ impl ToDo {
                 to_do_id: id,
                 title: title,
                 owner: owner_id,
+                description: description,
+                due_date: due_date,
             })),
         }
     }
@@ -158,6 +171,27 @@ impl ToDo {
         }
     }
 
+    /// GET the description of the ToDo. Returns an empty string if there is no description.
+    pub fn get_description(&self) -> String {
+        match &self {
+            ToDo::Incomplete(incomplete) => incomplete.description.to_string(),
+            ToDo::Complete(complete) => complete.description.to_string(),
+        }
+    }
+
+    /// GET the date the ToDo is due. Returns an empty string if it has no due date.
+    pub fn get_due_date(&self) -> String {
+        let due_date = match &self {
+            ToDo::Incomplete(incomplete) => &incomplete.due_date,
+            ToDo::Complete(complete) => &complete.due_date,
+        };
+
+        match due_date {
+            Option::None => "".to_string(),
+            Some(val) => val.to_string(),
+        }
+    }
+
     /// GET the status of the ToDo
     pub fn get_status(&self) -> String {
         match &self {
@@ -181,18 +215,59 @@ impl ToDo {
                 to_do_id: incomplete.to_do_id.clone(),
                 title: new_title_value.unwrap(),
                 owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
+                description: incomplete.description.clone(),
+                due_date: incomplete.due_date.clone(),
             }),
             ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
                 to_do_id: complete.to_do_id.clone(),
                 title: Title::new(complete.title.to_string()).unwrap(),
                 owner: OwnerId::new(complete.owner.to_string()).unwrap(),
                 completed_on: complete.completed_on,
+                description: complete.description.clone(),
+                due_date: complete.due_date.clone(),
             }),
         };
 
         Ok(response)
     }
 
+    /// Update the description of the existing ToDo, an empty description removes it.
+    /// If the ToDo is already completed, or no description is passed, it is not updated.
+    /// Returns a new ToDo
+    pub fn update_description(self, new_description: Option<String>) -> Result<ToDo, ValidationError> {
+        let new_description_value = match new_description {
+            Option::None => return Ok(self),
+            Some(val) => Description::new(val)?,
+        };
+
+        match self {
+            ToDo::Incomplete(incomplete) => Ok(ToDo::Incomplete(IncompleteToDo {
+                description: new_description_value,
+                ..incomplete
+            })),
+            complete @ ToDo::Complete(_) => Ok(complete),
+        }
+    }
+
+    /// Update the due date of the existing ToDo, an empty due date removes it.
+    /// If the ToDo is already completed, or no due date is passed, it is not updated.
+    /// Returns a new ToDo
+    pub fn update_due_date(self, new_due_date: Option<String>) -> Result<ToDo, ValidationError> {
+        let new_due_date_value = match new_due_date {
+            Option::None => return Ok(self),
+            Some(val) if val.is_empty() => Option::None,
+            Some(val) => Some(DueDate::parse(val)?),
+        };
+
+        match self {
+            ToDo::Incomplete(incomplete) => Ok(ToDo::Incomplete(IncompleteToDo {
+                due_date: new_due_date_value,
+                ..incomplete
+            })),
+            complete @ ToDo::Complete(_) => Ok(complete),
+        }
+    }
+
     /// Set the ToDo as completed
     pub fn set_completed(self) -> ToDo {
         match &self {
@@ -201,30 +276,41 @@ impl ToDo {
                 title: Title::new(incomplete.title.to_string()).unwrap(),
                 owner: OwnerId::new(incomplete.owner.to_string()).unwrap(),
                 completed_on: DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+                description: incomplete.description.clone(),
+                due_date: incomplete.due_date.clone(),
             }),
             ToDo::Complete(complete) => ToDo::Complete(CompleteToDo {
                 to_do_id: complete.to_do_id.clone(),
                 title: Title::new(complete.title.to_string()).unwrap(),
                 owner: OwnerId::new(complete.owner.to_string()).unwrap(),
                 completed_on: complete.completed_on,
+                description: complete.description.clone(),
+                due_date: complete.due_date.clone(),
             }),
         }
     }
 
     /// Convert the ToDo into a ToDoItem Data Transfer Object
     pub fn into_dto(self) -> ToDoItem {
+        let description = self.get_description();
+        let due_date = self.get_due_date();
+
         match &self {
             ToDo::Incomplete(incomplete) => ToDoItem {
                 id: incomplete.to_do_id.to_string(),
                 is_complete: false,
                 title: incomplete.title.to_string(),
-                completed_on: String::from("")
+                completed_on: String::from(""),
+                description: description,
+                due_date: due_date,
             },
             ToDo::Complete(complete) => ToDoItem {
                 id: complete.to_do_id.to_string(),
                 is_complete: true,
                 title: complete.title.to_string(),
-                completed_on: complete.completed_on.to_rfc3339()
+                completed_on: complete.completed_on.to_rfc3339(),
+                description: description,
+                due_date: due_date,
             },
         }
     }
@@ -235,7 +321,8 @@ impl ToDo {
         if input.to_string().len() <= 0 || input.to_string().len() > 50 {
             tracing::info!("Title is invalid");
 
-            return Err(ValidationError::new(
+            return Err(ValidationError::for_field(
+                "title",
                 "Must be between 1 and 50 chars".to_string(),
             ));
         }
@@ -245,7 +332,8 @@ impl ToDo {
 
     fn check_owner_id(input: &OwnerId) -> Result<(), ValidationError> {
         if input.to_string().len() <= 0 {
-            return Err(ValidationError::new(
+            return Err(ValidationError::for_field(
+                "owner_id",
                 "Owner Id must have a length".to_string(),
             ));
         }
@@ -260,6 +348,8 @@ pub struct IncompleteToDo {
     to_do_id: ToDoId,
     title: Title,
     owner: OwnerId,
+    description: Description,
+    due_date: Option<DueDate>,
 }
 
 /// Represents the structure of a complete ToDo item
@@ -269,6 +359,8 @@ pub struct CompleteToDo {
     title: Title,
     owner: OwnerId,
     completed_on: DateTime<FixedOffset>,
+    description: Description,
+    due_date: Option<DueDate>,
 }
 
 #[derive(Clone)]
@@ -285,7 +377,8 @@ impl ToDoId {
         if existing_id.to_string().len() <= 0 || existing_id.to_string().len() > 50 {
             tracing::info!("Title is invalid");
 
-            return Err(ValidationError::new(
+            return Err(ValidationError::for_field(
+                "id",
                 "Must be between 1 and 50 chars".to_string(),
             ));
         }
@@ -310,7 +403,8 @@ impl Title {
         if title.to_string().len() <= 0 || title.to_string().len() > 50 {
             tracing::info!("Title is invalid");
 
-            return Err(ValidationError::new(
+            return Err(ValidationError::for_field(
+                "title",
                 "Must be between 1 and 50 chars".to_string(),
             ));
         }
@@ -335,7 +429,8 @@ impl OwnerId {
         if owner_id.to_string().len() <= 0 {
             tracing::info!("Title is invalid");
 
-            return Err(ValidationError::new(
+            return Err(ValidationError::for_field(
+                "owner_id",
                 "Must be between 1 and 50 chars".to_string(),
             ));
         }
@@ -350,6 +445,62 @@ impl OwnerId {
     }
 }
 
+#[derive(Clone, Default)]
+pub struct Description {
+    value: String,
+}
+
+impl Description {
+    pub fn new(description: String) -> Result<Description, ValidationError> {
+        if description.len() > MAX_DESCRIPTION_LENGTH {
+            tracing::info!("Description is invalid");
+
+            return Err(ValidationError::for_field(
+                "description",
+                format!(
+                    "Description must be {} chars or less",
+                    MAX_DESCRIPTION_LENGTH
+                ),
+            ));
+        }
+
+        Ok(Description { value: description })
+    }
+
+    pub fn to_string(&self) -> String {
+        self.value.clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct DueDate {
+    value: DateTime<FixedOffset>,
+}
+
+impl DueDate {
+    /// Parse a due date from an RFC3339 date and time, such as `2023-08-12T09:00:00Z`.
+    pub fn parse(due_date: String) -> Result<DueDate, ValidationError> {
+        match DateTime::parse_from_rfc3339(&due_date) {
+            Ok(val) => Ok(DueDate { value: val }),
+            Err(_) => {
+                tracing::info!("Due date is invalid");
+
+                Err(ValidationError::for_field(
+                    "due_date",
+                    format!(
+                        "Due date '{}' must be an RFC3339 date and time, such as 2023-08-12T09:00:00Z",
+                        due_date
+                    ),
+                ))
+            }
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.value.to_rfc3339()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum IsComplete {
     INCOMPLETE,
@@ -380,7 +531,7 @@ mod tests {
 
     use crate::domain::entities::{OwnerId, Title, ToDo};
 
-    use super::ToDoId;
+    use super::{Description, ToDoId};
 
     #[test]
     fn valid_data_should_return_validated_to_do() {
@@ -400,6 +551,8 @@ mod tests {
             to_do_id: ToDoId::parse(String::from("hello")).unwrap(),
             title: Title::new(String::from("hello")).unwrap(),
             owner: OwnerId::new(String::from("hello")).unwrap(),
+            description: Description::default(),
+            due_date: Option::None,
         });
 
         let updated_todo = todo.update_title(String::from("my new title"));
@@ -418,6 +571,8 @@ mod tests {
             title: Title::new(String::from("hello")).unwrap(),
             owner: OwnerId::new(String::from("hello")).unwrap(),
             completed_on: DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap(),
+            description: Description::default(),
+            due_date: Option::None,
         });
 
         let updated_todo = todo.update_title(String::from("my new title"));
@@ -435,6 +590,8 @@ mod tests {
             to_do_id: ToDoId::parse(String::from("hello")).unwrap(),
             title: Title::new(String::from("hello")).unwrap(),
             owner: OwnerId::new(String::from("hello")).unwrap(),
+            description: Description::default(),
+            due_date: Option::None,
         });
 
         let updated_todo = todo.set_completed();
@@ -455,6 +612,8 @@ mod tests {
             title: Title::new(String::from("hello")).unwrap(),
             owner: OwnerId::new(String::from("hello")).unwrap(),
             completed_on: date,
+            description: Description::default(),
+            due_date: Option::None,
         });
 
         let updated_todo = todo.set_completed();
@@ -467,6 +626,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn update_details_for_incomplete_todo_should_change() {
+        let todo = ToDo::new(
+            Title::new(String::from("my title")).unwrap(),
+            OwnerId::new(String::from("jameseastham")).unwrap(),
+        )
+        .unwrap()
+        .update_description(Some(String::from("Semi skimmed")))
+        .unwrap()
+        .update_due_date(Some(String::from("2023-08-12T09:00:00+01:00")))
+        .unwrap();
+
+        assert_eq!(todo.get_description(), "Semi skimmed");
+        assert_eq!(todo.get_due_date(), "2023-08-12T09:00:00+01:00");
+
+        let cleared = todo.update_due_date(Some(String::new())).unwrap();
+
+        assert_eq!(cleared.get_due_date(), "");
+        assert_eq!(cleared.get_description(), "Semi skimmed");
+    }
+
+    #[test]
+    fn update_details_for_completed_todo_should_not_change() {
+        let todo = ToDo::new(
+            Title::new(String::from("my title")).unwrap(),
+            OwnerId::new(String::from("jameseastham")).unwrap(),
+        )
+        .unwrap()
+        .update_description(Some(String::from("Semi skimmed")))
+        .unwrap()
+        .set_completed()
+        .update_description(Some(String::from("Oat")))
+        .unwrap();
+
+        assert_eq!(todo.get_description(), "Semi skimmed");
+    }
+
+    #[test]
+    fn invalid_due_date_should_return_validate_error() {
+        let todo = ToDo::new(
+            Title::new(String::from("my title")).unwrap(),
+            OwnerId::new(String::from("jameseastham")).unwrap(),
+        )
+        .unwrap()
+        .update_due_date(Some(String::from("12/08/2023")));
+
+        assert_eq!(todo.is_err(), true);
+    }
+
+    #[test]
+    fn long_description_should_return_validate_error() {
+        let description = Description::new("a".repeat(2001));
+
+        assert_eq!(description.is_err(), true);
+    }
+
     #[test]
     fn new_id_should_return_valid_to_do_id() {
         let option_1 = Some("Hello");
@@ -1,17 +1,32 @@
+use serde::Serialize;
 use std::{error::Error, fmt};
 
 #[derive(Debug, Clone)]
 pub struct RepositoryError {
     error_message: String,
+    not_found: bool,
 }
 
 impl RepositoryError {
     pub fn new(data_access_error: String) -> RepositoryError {
         RepositoryError {
             error_message: data_access_error,
+            not_found: false,
         }
     }
 
+    /// The item that was asked for isn't stored.
+    pub fn not_found(message: String) -> RepositoryError {
+        RepositoryError {
+            error_message: message,
+            not_found: true,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.not_found
+    }
+
     pub fn to_string(&self) -> String {
         // We don't want to disclose the secret
         format!("Error persisiting data {0}", self.error_message)
@@ -31,18 +46,54 @@ impl fmt::Display for RepositoryError {
 
 impl Error for RepositoryError {}
 
+/// A value in a request that failed validation, and why.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidationError {
     error_message: String,
+    field_errors: Vec<FieldError>,
 }
 
 impl ValidationError {
     pub fn new(message: String) -> ValidationError {
         ValidationError {
             error_message: message,
+            field_errors: Vec::new(),
+        }
+    }
+
+    /// An error for the value of a single field, such as `title`.
+    pub fn for_field(field: &str, message: String) -> ValidationError {
+        ValidationError {
+            error_message: message.to_string(),
+            field_errors: vec![FieldError {
+                field: field.to_string(),
+                message: message,
+            }],
         }
     }
 
+    /// Combine several errors into one, keeping the errors for each field.
+    pub fn combine(errors: Vec<ValidationError>) -> ValidationError {
+        ValidationError {
+            error_message: errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            field_errors: errors.into_iter().flat_map(|e| e.field_errors).collect(),
+        }
+    }
+
+    pub fn field_errors(&self) -> &Vec<FieldError> {
+        &self.field_errors
+    }
+
     pub fn to_string(&self) -> String {
         self.error_message.to_string()
     }
@@ -61,18 +112,51 @@ impl fmt::Display for ValidationError {
 
 impl Error for ValidationError {}
 
+/// Why the service couldn't complete a request, which decides the status it is reported with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServiceErrorKind {
+    /// The request is invalid, the message and field errors can be shown to the caller.
+    Validation,
+    /// The ToDo doesn't exist, or belongs to another owner.
+    NotFound,
+    /// Storing or reading the data failed. The message is only for logs, as it may disclose how
+    /// the data is stored.
+    Repository,
+}
+
 #[derive(Debug, Clone)]
 pub struct ServiceError {
+    kind: ServiceErrorKind,
     error_message: String,
+    field_errors: Vec<FieldError>,
 }
 
 impl ServiceError {
-    pub fn new(message: String) -> ServiceError {
+    pub fn not_found(message: String) -> ServiceError {
         ServiceError {
+            kind: ServiceErrorKind::NotFound,
             error_message: message,
+            field_errors: Vec::new(),
         }
     }
 
+    pub fn repository(message: String) -> ServiceError {
+        ServiceError {
+            kind: ServiceErrorKind::Repository,
+            error_message: message,
+            field_errors: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> ServiceErrorKind {
+        self.kind
+    }
+
+    /// The fields that failed validation, if the error was caused by an invalid request.
+    pub fn field_errors(&self) -> &Vec<FieldError> {
+        &self.field_errors
+    }
+
     pub fn to_string(&self) -> String {
         self.error_message.to_string()
     }
@@ -90,3 +174,52 @@ impl fmt::Display for ServiceError {
 }
 
 impl Error for ServiceError {}
+
+impl From<ValidationError> for ServiceError {
+    fn from(err: ValidationError) -> ServiceError {
+        ServiceError {
+            kind: ServiceErrorKind::Validation,
+            error_message: err.to_string(),
+            field_errors: err.field_errors,
+        }
+    }
+}
+
+impl From<RepositoryError> for ServiceError {
+    fn from(err: RepositoryError) -> ServiceError {
+        match err.is_not_found() {
+            true => ServiceError::not_found(err.error_message),
+            false => ServiceError::repository(err.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PublishError {
+    error_message: String,
+}
+
+impl PublishError {
+    pub fn new(message: String) -> PublishError {
+        PublishError {
+            error_message: message,
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.error_message.to_string()
+    }
+}
+
+// Generation of an error is completely separate from how it is displayed.
+// There's no need to be concerned about cluttering complex logic with the display style.
+//
+// Note that we don't store any extra info about the errors. This means we can't state
+// which string failed to parse without modifying our types to carry that information.
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Publish error: {0}", self.error_message)
+    }
+}
+
+impl Error for PublishError {}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use super::{entities::ToDo, error_types::PublishError};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ToDoCreated {
+    pub to_do_id: String,
+    pub owner_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ToDoCompleted {
+    pub to_do_id: String,
+    pub owner_id: String,
+    pub completed_on: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ToDoTitleChanged {
+    pub to_do_id: String,
+    pub owner_id: String,
+    pub old_title: String,
+    pub new_title: String,
+}
+
+/// Something that happened to a ToDo, derived from how it changed when it was stored.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ToDoEvent {
+    Created(ToDoCreated),
+    Completed(ToDoCompleted),
+    TitleChanged(ToDoTitleChanged),
+}
+
+impl ToDoEvent {
+    /// The events for a ToDo that was stored as `new`, `old` being the ToDo it replaced if
+    /// there was one. A removed ToDo, with no `new` value, has no events.
+    pub fn from_change(old: Option<&ToDo>, new: Option<&ToDo>) -> Vec<ToDoEvent> {
+        let mut events = Vec::new();
+
+        let new = match new {
+            Option::None => return events,
+            Some(val) => val,
+        };
+
+        let old = match old {
+            Option::None => {
+                events.push(ToDoEvent::Created(ToDoCreated {
+                    to_do_id: new.get_id(),
+                    owner_id: new.get_owner(),
+                    title: new.get_title(),
+                }));
+
+                return events;
+            }
+            Some(val) => val,
+        };
+
+        if old.get_title() != new.get_title() {
+            events.push(ToDoEvent::TitleChanged(ToDoTitleChanged {
+                to_do_id: new.get_id(),
+                owner_id: new.get_owner(),
+                old_title: old.get_title(),
+                new_title: new.get_title(),
+            }));
+        }
+
+        if let (ToDo::Incomplete(_), ToDo::Complete(_)) = (old, new) {
+            events.push(ToDoEvent::Completed(ToDoCompleted {
+                to_do_id: new.get_id(),
+                owner_id: new.get_owner(),
+                completed_on: new.get_completed_on(),
+            }));
+        }
+
+        events
+    }
+
+    /// The name of the event, used as the event type when it is published.
+    pub fn event_type(&self) -> &str {
+        match self {
+            ToDoEvent::Created(_) => "ToDoCreated",
+            ToDoEvent::Completed(_) => "ToDoCompleted",
+            ToDoEvent::TitleChanged(_) => "ToDoTitleChanged",
+        }
+    }
+}
+
+#[async_trait]
+pub trait EventPublisher {
+    async fn publish(&self, event: &ToDoEvent) -> Result<(), PublishError>;
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use crate::domain::entities::{OwnerId, Title, ToDo};
+
+    use super::ToDoEvent;
+
+    fn new_todo(title: &str) -> ToDo {
+        ToDo::new(
+            Title::new(String::from(title)).unwrap(),
+            OwnerId::new(String::from("jameseastham")).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new_todo_should_be_created() {
+        let todo = new_todo("Buy milk");
+
+        let events = ToDoEvent::from_change(Option::None, Some(&todo));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type(), "ToDoCreated");
+    }
+
+    #[test]
+    fn changed_title_and_status_should_both_be_events() {
+        let todo = new_todo("Buy milk");
+        let changed = new_todo("Buy milk")
+            .update_title(String::from("Buy oat milk"))
+            .unwrap()
+            .set_completed();
+
+        let events = ToDoEvent::from_change(Some(&todo), Some(&changed));
+        let event_types: Vec<&str> = events.iter().map(|val| val.event_type()).collect();
+
+        assert_eq!(event_types, vec!["ToDoTitleChanged", "ToDoCompleted"]);
+    }
+
+    #[test]
+    fn unchanged_or_removed_todo_should_have_no_events() {
+        let todo = new_todo("Buy milk");
+
+        assert_eq!(ToDoEvent::from_change(Some(&todo), Some(&todo)).len(), 0);
+        assert_eq!(ToDoEvent::from_change(Some(&todo), Option::None).len(), 0);
+    }
+}
@@ -1,5 +1,6 @@
 #[cfg(feature = "lambda")]
 pub mod entities;
 pub mod error_types;
+pub mod events;
 pub mod public_types;
 pub mod todo_service;
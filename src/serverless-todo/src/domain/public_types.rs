@@ -6,18 +6,32 @@ pub struct ToDoItem {
     pub title: String,
     pub is_complete: bool,
     pub completed_on: String,
+    pub description: String,
+    /// The RFC3339 date and time the ToDo is due, empty if it has no due date.
+    pub due_date: String,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct CreateToDoCommand {
     pub title: String,
+    /// Set from the authenticated owner, any value in the request body is ignored.
+    #[serde(default)]
     pub owner_id: String,
+    pub description: Option<String>,
+    /// An RFC3339 date and time, such as `2023-08-12T09:00:00Z`.
+    pub due_date: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct UpdateToDoCommand {
     pub to_do_id: String,
     pub title: String,
+    /// Set from the authenticated owner, any value in the request body is ignored.
+    #[serde(default)]
     pub owner_id: String,
     pub set_as_complete: bool,
+    /// Leave unset to keep the current description, set it empty to remove it.
+    pub description: Option<String>,
+    /// Leave unset to keep the current due date, set it empty to remove it.
+    pub due_date: Option<String>,
 }
@@ -8,7 +8,7 @@ use super::{
 pub async fn create_to_do(
     input: CreateToDoCommand,
     client: &dyn Repository,
-) -> Result<ToDoItem, ValidationError> {
+) -> Result<ToDoItem, ServiceError> {
     let parsed_title = Title::new(input.title);
     let parsed_ownerid = OwnerId::new(input.owner_id);
 
@@ -17,29 +17,29 @@ pub async fn create_to_do(
         errors.push(parsed_title.err());
         errors.push(parsed_ownerid.err());
 
-        return Err(combine_errors(errors));
+        return Err(ServiceError::from(combine_errors(errors)));
     }
 
     let to_do = ToDo::new(parsed_title.unwrap(), parsed_ownerid.unwrap());
 
     match to_do {
         Ok(val) => {
+            let val = val
+                .update_description(input.description)
+                .and_then(|todo| todo.update_due_date(input.due_date))?;
+
             let db_res = client.store_todo(&val).await;
 
             match db_res {
                 Ok(_) => Ok(val.into_dto()),
-                Err(_) => Err(ValidationError::new("Failure creating ToDo".to_string())),
-            }
-        }
-        Err(e) => {
-            let mut error_string = String::from("");
+                Err(e) => {
+                    tracing::error!("{}", e.to_string());
 
-            for err in e {
-                error_string = format!("{} {}", error_string, err.to_string());
+                    Err(ServiceError::from(e))
+                }
             }
-
-            Err(ValidationError::new(error_string))
         }
+        Err(e) => Err(ServiceError::from(ValidationError::combine(e))),
     }
 }
 
@@ -64,14 +64,18 @@ pub async fn get_todos(
     owner: OwnerId,
     to_do_id: ToDoId,
     client: &dyn Repository,
-) -> Result<ToDoItem, ()> {
+) -> Result<ToDoItem, ServiceError> {
     let query_res = client
         .get_todo(&owner.to_string(), &to_do_id.to_string())
         .await;
 
     match query_res {
         Ok(todo) => Ok(todo.into_dto()),
-        Err(_) => Err(()),
+        Err(e) => {
+            tracing::error!("{}", e.to_string());
+
+            Err(ServiceError::from(e))
+        }
     }
 }
 
@@ -90,7 +94,10 @@ pub async fn update_todo(
                 false => todo
             };
 
-            let updated_todo = updated_status.update_title(update_command.title);
+            let updated_todo = updated_status
+                .update_title(update_command.title)
+                .and_then(|todo| todo.update_description(update_command.description))
+                .and_then(|todo| todo.update_due_date(update_command.due_date));
 
             match updated_todo {
                 Ok(res) => {
@@ -101,38 +108,25 @@ pub async fn update_todo(
                         Err(e) => {
                             tracing::error!("{}", e.to_string());
 
-                            Err(ServiceError::new(e.to_string()))
+                            Err(ServiceError::from(e))
                         },
                     }
 
                     
                 }
-                Err(e) => Err(ServiceError::new(e.to_string())),
+                Err(e) => Err(ServiceError::from(e)),
             }
         }
         Err(e) => {
             tracing::error!("{}", e.to_string());
 
-            Err(ServiceError::new(String::from("Record not found")))
+            Err(ServiceError::from(e))
         },
     }
 }
 
 fn combine_errors(err: Vec<Option<ValidationError>>) -> ValidationError {
-    let mut error_builder = String::from("");
-
-    for ele in err {
-        error_builder = format!(
-            "{} {}",
-            error_builder,
-            match ele {
-                Option::None => String::from(""),
-                Some(val) => val.to_string(),
-            }
-        )
-    }
-
-    ValidationError::new(error_builder)
+    ValidationError::combine(err.into_iter().flatten().collect())
 }
 
 /// Unit tests
@@ -144,9 +138,9 @@ mod tests {
     use chrono::{DateTime, Utc};
 
     use crate::domain::{
-        entities::{OwnerId, Repository, Title, ToDo, ToDoId},
-        error_types::RepositoryError,
-        public_types::UpdateToDoCommand,
+        entities::{Description, OwnerId, Repository, Title, ToDo, ToDoId},
+        error_types::{RepositoryError, ServiceErrorKind},
+        public_types::{CreateToDoCommand, UpdateToDoCommand},
         todo_service,
     };
 
@@ -178,7 +172,9 @@ mod tests {
                 match self.to_do_status_to_return.as_str() {
                     "COMPLETE" => Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
                     _ => Option::None
-                }
+                },
+                Description::default(),
+                Option::None,
             )
             .unwrap())
         }
@@ -199,7 +195,9 @@ mod tests {
                     match self.to_do_status_to_return.as_str() {
                         "COMPLETE" => Some(DateTime::parse_from_rfc3339(&Utc::now().to_rfc3339()).unwrap()),
                         _ => Option::None
-                    }
+                    },
+                    Description::default(),
+                    Option::None,
                 )
                 .unwrap(),
             );
@@ -208,6 +206,23 @@ mod tests {
         }
     }
 
+    struct MissingRepository {}
+
+    #[async_trait]
+    impl Repository for MissingRepository {
+        async fn store_todo(&self, _: &ToDo) -> Result<(), RepositoryError> {
+            Ok(())
+        }
+
+        async fn get_todo(&self, _: &String, id: &String) -> Result<ToDo, RepositoryError> {
+            Err(RepositoryError::not_found(format!("ToDo {} not found", id)))
+        }
+
+        async fn list_todos(&self, _: &String) -> Result<Vec<ToDo>, RepositoryError> {
+            Ok(Vec::new())
+        }
+    }
+
     #[tokio::test]
     async fn list_todos_should_return_todos() {
         let client = MockRepository {
@@ -266,6 +281,8 @@ mod tests {
                 title: "newtitle".to_string(),
                 to_do_id: "12345".to_string(),
                 set_as_complete: false,
+                description: Option::None,
+                due_date: Option::None,
             },
             &client,
         )
@@ -288,6 +305,8 @@ mod tests {
                 title: "newtitle".to_string(),
                 to_do_id: "12345".to_string(),
                 set_as_complete: true,
+                description: Option::None,
+                due_date: Option::None,
             },
             &client,
         )
@@ -310,6 +329,8 @@ mod tests {
                 title: "newtitle".to_string(),
                 to_do_id: "12345".to_string(),
                 set_as_complete: false,
+                description: Option::None,
+                due_date: Option::None,
             },
             &client,
         )
@@ -332,6 +353,8 @@ mod tests {
                 title: "newtitle".to_string(),
                 to_do_id: "12345".to_string(),
                 set_as_complete: true,
+                description: Option::None,
+                due_date: Option::None,
             },
             &client,
         )
@@ -341,4 +364,124 @@ mod tests {
         assert_eq!(to_dos.as_ref().unwrap().title, "title");
         assert_eq!(to_dos.as_ref().unwrap().is_complete, true);
     }
+
+    #[tokio::test]
+    async fn create_todo_should_set_description_and_due_date() {
+        let client = MockRepository {
+            should_fail: false,
+            to_do_status_to_return: "INCOMPLETE".to_string(),
+        };
+
+        let to_do = todo_service::create_to_do(
+            CreateToDoCommand {
+                owner_id: "jameseastham".to_string(),
+                title: "Buy milk".to_string(),
+                description: Some("Semi skimmed".to_string()),
+                due_date: Some("2023-08-12T09:00:00Z".to_string()),
+            },
+            &client,
+        )
+        .await;
+
+        assert_eq!(to_do.is_err(), false);
+        assert_eq!(to_do.as_ref().unwrap().description, "Semi skimmed");
+        assert_eq!(to_do.as_ref().unwrap().due_date, "2023-08-12T09:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn create_todo_with_invalid_due_date_should_return_error() {
+        let client = MockRepository {
+            should_fail: false,
+            to_do_status_to_return: "INCOMPLETE".to_string(),
+        };
+
+        let to_do = todo_service::create_to_do(
+            CreateToDoCommand {
+                owner_id: "jameseastham".to_string(),
+                title: "Buy milk".to_string(),
+                description: Option::None,
+                due_date: Some("next tuesday".to_string()),
+            },
+            &client,
+        )
+        .await;
+
+        assert_eq!(to_do.is_err(), true);
+    }
+
+    #[tokio::test]
+    async fn update_todo_should_update_description() {
+        let client = MockRepository {
+            should_fail: false,
+            to_do_status_to_return: "INCOMPLETE".to_string(),
+        };
+
+        let to_do = todo_service::update_todo(
+            UpdateToDoCommand {
+                owner_id: "jameseastham".to_string(),
+                title: "newtitle".to_string(),
+                to_do_id: "12345".to_string(),
+                set_as_complete: false,
+                description: Some("Semi skimmed".to_string()),
+                due_date: Option::None,
+            },
+            &client,
+        )
+        .await;
+
+        assert_eq!(to_do.is_err(), false);
+        assert_eq!(to_do.as_ref().unwrap().description, "Semi skimmed");
+        assert_eq!(to_do.as_ref().unwrap().due_date, "");
+    }
+
+    #[tokio::test]
+    async fn update_missing_todo_should_return_not_found() {
+        let to_do = todo_service::update_todo(
+            UpdateToDoCommand {
+                owner_id: "jameseastham".to_string(),
+                title: "newtitle".to_string(),
+                to_do_id: "12345".to_string(),
+                set_as_complete: false,
+                description: Option::None,
+                due_date: Option::None,
+            },
+            &MissingRepository {},
+        )
+        .await;
+
+        assert_eq!(to_do.err().unwrap().kind(), ServiceErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn get_missing_todo_should_return_not_found() {
+        let to_do = todo_service::get_todos(
+            OwnerId::new("owner".to_string()).unwrap(),
+            ToDoId::parse("12345".to_string()).unwrap(),
+            &MissingRepository {},
+        )
+        .await;
+
+        assert_eq!(to_do.err().unwrap().kind(), ServiceErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn create_todo_on_store_failure_should_return_repository_error() {
+        let client = MockRepository {
+            should_fail: true,
+            to_do_status_to_return: "INCOMPLETE".to_string(),
+        };
+
+        let to_do = todo_service::create_to_do(
+            CreateToDoCommand {
+                owner_id: "jameseastham".to_string(),
+                title: "Buy milk".to_string(),
+                description: Option::None,
+                due_date: Option::None,
+            },
+            &client,
+        )
+        .await;
+
+        assert_eq!(to_do.err().unwrap().kind(), ServiceErrorKind::Repository);
+    }
 }
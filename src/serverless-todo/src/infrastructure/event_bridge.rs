@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use aws_sdk_eventbridge::{model::PutEventsRequestEntry, Client};
+
+use crate::domain::{
+    error_types::PublishError,
+    events::{EventPublisher, ToDoEvent},
+};
+
+/// The source every ToDo event is published with.
+const EVENT_SOURCE: &str = "serverless-todo";
+
+/// Publishes ToDo events to an EventBridge event bus, with the event type as the detail type.
+pub struct EventBridgePublisher {
+    client: Client,
+    event_bus_name: String,
+}
+
+impl EventBridgePublisher {
+    pub fn new(client: Client, event_bus_name: String) -> EventBridgePublisher {
+        EventBridgePublisher {
+            client: client,
+            event_bus_name: event_bus_name,
+        }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for EventBridgePublisher {
+    async fn publish(&self, event: &ToDoEvent) -> Result<(), PublishError> {
+        tracing::info!("Publishing {} to EventBridge", event.event_type());
+
+        let detail = serde_json::to_string(event).map_err(|e| PublishError::new(e.to_string()))?;
+
+        let entry = PutEventsRequestEntry::builder()
+            .event_bus_name(&self.event_bus_name)
+            .source(EVENT_SOURCE)
+            .detail_type(event.event_type())
+            .detail(detail)
+            .build();
+
+        let res = self
+            .client
+            .put_events()
+            .entries(entry)
+            .send()
+            .await
+            .map_err(|e| PublishError::new(e.to_string()))?;
+
+        // A request can succeed while the entries in it fail
+        if res.failed_entry_count() > 0 {
+            let reason = res
+                .entries()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|val| val.error_message())
+                .collect::<Vec<&str>>()
+                .join(", ");
+
+            return Err(PublishError::new(reason));
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,62 @@
 use async_trait::async_trait;
-use aws_sdk_dynamodb::{model::AttributeValue, Client};
-use chrono::{DateTime};
+use aws_sdk_dynamodb::{
+    model::{AttributeValue, Delete, Put, TransactWriteItem},
+    Client,
+};
+use chrono::DateTime;
+use std::collections::HashMap;
 
-use crate::domain::entities::{OwnerId, Title, ToDo};
+use crate::domain::entities::{Description, DueDate, OwnerId, Title, ToDo};
 use crate::domain::{
     entities::{Repository, ToDoId},
-    error_types::RepositoryError,
+    error_types::{RepositoryError, ValidationError},
 };
 
+/// Prefixes the partition key of every item belonging to an owner.
+pub const OWNER_KEY_PREFIX: &str = "USER#";
+/// Prefixes the sort key of every ToDo item, and the partition key of the id index.
+pub const TODO_KEY_PREFIX: &str = "TODO#";
+/// The global secondary index used to find a ToDo by its id alone, without knowing its owner.
+pub const ID_INDEX_NAME: &str = "GSI1";
+
+/// The partition key of an owner's items. Owners are JWT subjects, which are case sensitive, so
+/// the owner is used as-is.
+pub fn owner_key(owner: &str) -> String {
+    format!("{}{}", OWNER_KEY_PREFIX, owner)
+}
+
+/// The sort key of a ToDo item.
+pub fn todo_key(id: &str) -> String {
+    format!("{}{}", TODO_KEY_PREFIX, id)
+}
+
+/// The key attributes of a ToDo item, for the table and for the id index.
+pub fn todo_keys(owner: &str, id: &str) -> HashMap<String, AttributeValue> {
+    let mut keys = HashMap::new();
+
+    keys.insert("PK".to_string(), AttributeValue::S(owner_key(owner)));
+    keys.insert("SK".to_string(), AttributeValue::S(todo_key(id)));
+    keys.insert("GSI1PK".to_string(), AttributeValue::S(todo_key(id)));
+    keys.insert("GSI1SK".to_string(), AttributeValue::S(owner_key(owner)));
+
+    keys
+}
+
+/// The key of a ToDo item written before the prefixes were added, `PK = owner`, `SK = id`. Reads
+/// fall back to it until `migrate_keys` has been run, see the README for the deploy order.
+pub fn legacy_todo_key(owner: &str, id: &str) -> HashMap<String, AttributeValue> {
+    table_key(owner.to_string(), id.to_string())
+}
+
+fn table_key(pk: String, sk: String) -> HashMap<String, AttributeValue> {
+    let mut keys = HashMap::new();
+
+    keys.insert("PK".to_string(), AttributeValue::S(pk));
+    keys.insert("SK".to_string(), AttributeValue::S(sk));
+
+    keys
+}
+
 pub struct DynamoDbRepository<'a> {
     client: Client,
     table_name: &'a String,
@@ -20,121 +69,209 @@ impl DynamoDbRepository<'_> {
             table_name: table_name,
         };
     }
-}
 
-#[async_trait]
-impl Repository for DynamoDbRepository<'_> {
-    async fn store_todo(&self, body: &ToDo) -> Result<(), RepositoryError> {
-        tracing::info!("Storing record in DynamoDB");
+    /// Find a ToDo by its id, whoever owns it. Used by administration tools and event
+    /// consumers, requests from owners always go through `get_todo`. Items that have not been
+    /// migrated to the prefixed keys are not in the id index, so are not found.
+    pub async fn find_todo(&self, id: &String) -> Result<ToDo, RepositoryError> {
+        tracing::info!("Finding record in DynamoDB: {id}");
 
         let res = self
             .client
-            .put_item()
+            .query()
             .table_name(self.table_name)
-            .item("PK", AttributeValue::S(body.get_owner().to_string()))
-            .item("SK", AttributeValue::S(body.get_id().to_string()))
-            .item("id", AttributeValue::S(body.get_id().into()))
-            .item("title", AttributeValue::S(body.get_title().into()))
-            .item("status", AttributeValue::S(body.get_status().into()))
-            .item("ownerId", AttributeValue::S(body.get_owner().into()))
-            .item(
-                "completedOn",
-                AttributeValue::S(body.get_completed_on().into()),
-            )
+            .index_name(ID_INDEX_NAME)
+            .key_condition_expression("GSI1PK = :id")
+            .expression_attribute_values(":id", AttributeValue::S(todo_key(id)))
             .send()
             .await;
 
         match res {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                tracing::error!("{}", e.to_string());
-
-                Err(RepositoryError::new(e.to_string()))
+            Ok(query_res) => match query_res.items().unwrap_or_default().first() {
+                Some(item) => parse_todo(item),
+                Option::None => Err(RepositoryError::not_found(format!("ToDo {} not found", id))),
             },
+            Err(e) => Err(RepositoryError::new(e.to_string())),
         }
     }
 
-    async fn get_todo(&self, owner: &String, id: &String) -> Result<ToDo, RepositoryError> {
-        tracing::info!("Retrieving record from DynamoDB: {owner} {id}");
-
+    async fn get_item(
+        &self,
+        key: HashMap<String, AttributeValue>,
+    ) -> Result<Option<HashMap<String, AttributeValue>>, RepositoryError> {
         let res = self
             .client
             .get_item()
             .table_name(self.table_name)
-            .key("PK", AttributeValue::S(owner.to_string()))
-            .key("SK", AttributeValue::S(id.to_string()))
+            .set_key(Some(key))
             .send()
             .await;
 
         match res {
-            Ok(item) => Ok({
-                let attributes = item.item().unwrap().clone();
-
-                ToDo::parse(
-                    Title::new(attributes.get("title").unwrap().as_s().unwrap().clone()).unwrap(),
-                    OwnerId::new(attributes.get("ownerId").unwrap().as_s().unwrap().clone())
-                        .unwrap(),
-                    Some(attributes.get("status").unwrap().as_s().unwrap().clone()),
-                    Some(
-                        ToDoId::parse(attributes.get("id").unwrap().as_s().unwrap().clone())
-                            .unwrap(),
-                    ),
-                    match attributes.get("completedOn") {
-                        Option::None => Option::None,
-                        Some(val) => {
-                            Some(DateTime::parse_from_rfc3339(val.as_s().unwrap()).unwrap())
-                        }
-                    },
-                )
-                .unwrap()
-            }),
+            Ok(item) => Ok(item.item().cloned()),
             Err(e) => {
                 Err(RepositoryError::new(e.into_service_error().message().unwrap().to_string()))
             },
         }
     }
 
-    async fn list_todos(&self, owner: &String) -> Result<Vec<ToDo>, RepositoryError> {
-        tracing::info!("Retrieving record from DynamoDB");
-
+    async fn query_owner(
+        &self,
+        key_condition_expression: &str,
+        values: HashMap<String, AttributeValue>,
+    ) -> Result<Vec<ToDo>, RepositoryError> {
         let res = self
             .client
             .query()
             .table_name(self.table_name)
-            .key_condition_expression("PK = :hashKey")
-            .expression_attribute_values(":hashKey", AttributeValue::S(owner.to_string()))
+            .key_condition_expression(key_condition_expression)
+            .set_expression_attribute_values(Some(values))
             .send()
             .await;
 
         match res {
-            Ok(query_res) => Ok({
-                let mut items: Vec<ToDo> = Vec::new();
-
-                for item in query_res.items().unwrap() {
-                    items.push(
-                        ToDo::parse(
-                            Title::new(item.get("title").unwrap().as_s().unwrap().clone()).unwrap(),
-                            OwnerId::new(item.get("ownerId").unwrap().as_s().unwrap().clone())
-                                .unwrap(),
-                            Some(item.get("status").unwrap().as_s().unwrap().clone()),
-                            Some(
-                                ToDoId::parse(item.get("id").unwrap().as_s().unwrap().clone())
-                                    .unwrap(),
-                            ),
-                            match item.get("completedOn") {
-                                Option::None => Option::None,
-                                Some(val) => {
-                                    Some(DateTime::parse_from_rfc3339(val.as_s().unwrap()).unwrap())
-                                }
-                            },
-                        )
-                        .unwrap(),
-                    )
-                }
-
-                items
-            }),
+            Ok(query_res) => query_res
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(parse_todo)
+                .collect(),
             Err(e) => Err(RepositoryError::new(e.to_string())),
         }
     }
 }
+
+#[async_trait]
+impl Repository for DynamoDbRepository<'_> {
+    async fn store_todo(&self, body: &ToDo) -> Result<(), RepositoryError> {
+        tracing::info!("Storing record in DynamoDB");
+
+        let mut item = todo_keys(&body.get_owner(), &body.get_id());
+
+        item.insert("id".to_string(), AttributeValue::S(body.get_id().into()));
+        item.insert("title".to_string(), AttributeValue::S(body.get_title().into()));
+        item.insert("status".to_string(), AttributeValue::S(body.get_status().into()));
+        item.insert("ownerId".to_string(), AttributeValue::S(body.get_owner().into()));
+        item.insert(
+            "completedOn".to_string(),
+            AttributeValue::S(body.get_completed_on().into()),
+        );
+        item.insert("description".to_string(), AttributeValue::S(body.get_description()));
+        item.insert("dueDate".to_string(), AttributeValue::S(body.get_due_date()));
+
+        let put = Put::builder()
+            .table_name(self.table_name)
+            .set_item(Some(item))
+            .build();
+
+        // A ToDo still stored under the legacy key is moved on its next save, so it is never
+        // read back twice. Deleting a key that doesn't exist succeeds.
+        let delete_legacy = Delete::builder()
+            .table_name(self.table_name)
+            .set_key(Some(legacy_todo_key(&body.get_owner(), &body.get_id())))
+            .build();
+
+        let res = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(TransactWriteItem::builder().delete(delete_legacy).build())
+            .send()
+            .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::error!("{}", e.to_string());
+
+                Err(RepositoryError::new(e.to_string()))
+            },
+        }
+    }
+
+    async fn get_todo(&self, owner: &String, id: &String) -> Result<ToDo, RepositoryError> {
+        tracing::info!("Retrieving record from DynamoDB: {owner} {id}");
+
+        let item = match self.get_item(table_key(owner_key(owner), todo_key(id))).await? {
+            Some(item) => Some(item),
+            Option::None => self.get_item(legacy_todo_key(owner, id)).await?,
+        };
+
+        match item {
+            Some(item) => parse_todo(&item),
+            Option::None => Err(RepositoryError::not_found(format!("ToDo {} not found", id))),
+        }
+    }
+
+    async fn list_todos(&self, owner: &String) -> Result<Vec<ToDo>, RepositoryError> {
+        tracing::info!("Retrieving record from DynamoDB");
+
+        let mut values = HashMap::new();
+        values.insert(":hashKey".to_string(), AttributeValue::S(owner_key(owner)));
+        values.insert(
+            ":todoPrefix".to_string(),
+            AttributeValue::S(TODO_KEY_PREFIX.to_string()),
+        );
+
+        let mut todos = self
+            .query_owner("PK = :hashKey AND begins_with(SK, :todoPrefix)", values)
+            .await?;
+
+        // ToDos that `migrate_keys` has not moved yet
+        let mut legacy_values = HashMap::new();
+        legacy_values.insert(":hashKey".to_string(), AttributeValue::S(owner.to_string()));
+
+        todos.extend(self.query_owner("PK = :hashKey", legacy_values).await?);
+
+        Ok(todos)
+    }
+}
+
+/// Map a ToDo item, such as one read from the table or from its stream, to a ToDo.
+pub(crate) fn parse_todo(item: &HashMap<String, AttributeValue>) -> Result<ToDo, RepositoryError> {
+    let read = |name: &str| {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .cloned()
+            .ok_or_else(|| RepositoryError::new(format!("'{}' is missing", name)))
+    };
+    let invalid = |e: ValidationError| RepositoryError::new(e.to_string());
+
+    ToDo::parse(
+        Title::new(read("title")?).map_err(invalid)?,
+        OwnerId::new(read("ownerId")?).map_err(invalid)?,
+        Some(read("status")?),
+        Some(ToDoId::parse(read("id")?).map_err(invalid)?),
+        read_optional(item, "completedOn")
+            .map(|val| DateTime::parse_from_rfc3339(&val))
+            .transpose()
+            .map_err(|e| RepositoryError::new(e.to_string()))?,
+        read_optional(item, "description")
+            .map(Description::new)
+            .transpose()
+            .map_err(invalid)?
+            .unwrap_or_default(),
+        read_optional(item, "dueDate")
+            .map(DueDate::parse)
+            .transpose()
+            .map_err(invalid)?,
+    )
+    .map_err(|errors| {
+        RepositoryError::new(
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        )
+    })
+}
+
+// Optional attributes are stored as empty strings when they have no value. Items written before
+// descriptions and due dates were added don't have those attributes at all.
+fn read_optional(item: &HashMap<String, AttributeValue>, name: &str) -> Option<String> {
+    item.get(name)
+        .and_then(|val| val.as_s().ok())
+        .filter(|val| !val.is_empty())
+        .cloned()
+}
@@ -0,0 +1,315 @@
+use aws_sdk_dynamodb::{
+    error::{TransactWriteItemsError, TransactWriteItemsErrorKind},
+    model::{AttributeValue, Delete, Put, TransactWriteItem},
+    Client,
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::domain::error_types::RepositoryError;
+use crate::infrastructure::infrastructure::{todo_keys, OWNER_KEY_PREFIX, TODO_KEY_PREFIX};
+
+type Item = HashMap<String, AttributeValue>;
+
+/// What migrating a single item to the prefixed key format involves.
+#[derive(Debug, PartialEq)]
+pub enum MigrationAction {
+    /// The item already has prefixed keys.
+    AlreadyMigrated,
+    /// The item is rewritten as this item, with the new keys, and the old item removed.
+    Rewrite(Item),
+    /// The item can't be migrated, for the given reason.
+    Skip(String),
+}
+
+/// Decide how to migrate an item written with the old `PK = owner`, `SK = id` keys. The new
+/// keys are built from the `ownerId` and `id` attributes, so the item is only rewritten if they
+/// agree with its old keys.
+pub fn plan_migration(item: &Item) -> MigrationAction {
+    let read = |name: &str| item.get(name).and_then(|val| val.as_s().ok());
+
+    let (pk, sk) = match (read("PK"), read("SK")) {
+        (Some(pk), Some(sk)) => (pk, sk),
+        _ => return MigrationAction::Skip("The item has no string PK and SK".to_string()),
+    };
+
+    if pk.starts_with(OWNER_KEY_PREFIX) && sk.starts_with(TODO_KEY_PREFIX) {
+        return MigrationAction::AlreadyMigrated;
+    }
+
+    let (owner, id) = match (read("ownerId"), read("id")) {
+        (Some(owner), Some(id)) => (owner, id),
+        _ => return MigrationAction::Skip("The item has no ownerId or id".to_string()),
+    };
+
+    if owner != pk || id != sk {
+        return MigrationAction::Skip(format!(
+            "The keys don't match the ownerId '{}' and id '{}'",
+            owner, id
+        ));
+    }
+
+    let mut migrated = item.clone();
+    migrated.extend(todo_keys(owner, id));
+    // Lets stream consumers tell the rewrite apart from the owner creating a ToDo
+    migrated.insert(
+        "migratedOn".to_string(),
+        AttributeValue::S(Utc::now().to_rfc3339()),
+    );
+
+    MigrationAction::Rewrite(migrated)
+}
+
+/// The outcome of a migration run, or of a dry run what it would have done.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub dry_run: bool,
+    pub scanned: usize,
+    pub already_migrated: usize,
+    /// The old `PK`/`SK` of each item that was, or in a dry run would be, rewritten.
+    pub rewritten: Vec<String>,
+    /// The old `PK`/`SK` of each item that was left alone, and why.
+    pub skipped: Vec<(String, String)>,
+}
+
+impl MigrationReport {
+    fn record(&mut self, item: &Item, action: &MigrationAction) {
+        self.scanned += 1;
+
+        match action {
+            MigrationAction::AlreadyMigrated => self.already_migrated += 1,
+            MigrationAction::Rewrite(_) => self.rewritten.push(item_key(item)),
+            MigrationAction::Skip(reason) => {
+                self.skipped.push((item_key(item), reason.to_string()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verb = match self.dry_run {
+            true => "Would rewrite",
+            false => "Rewrote",
+        };
+
+        writeln!(f, "Scanned {} item(s)", self.scanned)?;
+        writeln!(f, "{} already migrated", self.already_migrated)?;
+        writeln!(f, "{} {} item(s)", verb, self.rewritten.len())?;
+
+        for key in &self.rewritten {
+            writeln!(f, "  {}", key)?;
+        }
+
+        writeln!(f, "Skipped {} item(s)", self.skipped.len())?;
+
+        for (key, reason) in &self.skipped {
+            writeln!(f, "  {}: {}", key, reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rewrite every item in the table with the old key format. Each item is written with its new
+/// keys and the old item deleted in a single transaction, so the migration can be stopped and
+/// run again. An item that already has a copy under its new keys, for example because the owner
+/// saved it while the migration ran, is skipped. With `dry_run` nothing is written, the report
+/// lists what would be rewritten.
+pub async fn migrate_keys(
+    client: &Client,
+    table_name: &str,
+    dry_run: bool,
+) -> Result<MigrationReport, RepositoryError> {
+    let mut report = MigrationReport {
+        dry_run: dry_run,
+        ..Default::default()
+    };
+    let mut start_key: Option<Item> = Option::None;
+
+    loop {
+        let page = client
+            .scan()
+            .table_name(table_name)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::new(e.to_string()))?;
+
+        for item in page.items().unwrap_or_default() {
+            let action = match plan_migration(item) {
+                MigrationAction::Rewrite(migrated) if !dry_run => {
+                    match rewrite_item(client, table_name, item, &migrated).await? {
+                        true => MigrationAction::Rewrite(migrated),
+                        false => MigrationAction::Skip(
+                            "An item with the new keys already exists".to_string(),
+                        ),
+                    }
+                }
+                action => action,
+            };
+
+            report.record(item, &action);
+        }
+
+        start_key = page.last_evaluated_key().cloned();
+
+        if start_key.is_none() {
+            return Ok(report);
+        }
+    }
+}
+
+/// Returns `false`, writing nothing, if an item with the new keys already exists.
+async fn rewrite_item(
+    client: &Client,
+    table_name: &str,
+    old_item: &Item,
+    new_item: &Item,
+) -> Result<bool, RepositoryError> {
+    tracing::info!("Rewriting {}", item_key(old_item));
+
+    let put = Put::builder()
+        .table_name(table_name)
+        .set_item(Some(new_item.clone()))
+        .condition_expression("attribute_not_exists(PK)")
+        .build();
+
+    let delete = Delete::builder()
+        .table_name(table_name)
+        .key("PK", old_item.get("PK").unwrap().clone())
+        .key("SK", old_item.get("SK").unwrap().clone())
+        .build();
+
+    let res = client
+        .transact_write_items()
+        .transact_items(TransactWriteItem::builder().put(put).build())
+        .transact_items(TransactWriteItem::builder().delete(delete).build())
+        .send()
+        .await;
+
+    match res {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            let message = format!("Rewriting {}: {}", item_key(old_item), e);
+
+            match condition_failed(&e.into_service_error()) {
+                true => Ok(false),
+                false => Err(RepositoryError::new(message)),
+            }
+        }
+    }
+}
+
+fn condition_failed(error: &TransactWriteItemsError) -> bool {
+    match &error.kind {
+        TransactWriteItemsErrorKind::TransactionCanceledException(cancelled) => cancelled
+            .cancellation_reasons()
+            .unwrap_or_default()
+            .iter()
+            .any(|reason| reason.code() == Some("ConditionalCheckFailed")),
+        _ => false,
+    }
+}
+
+fn item_key(item: &Item) -> String {
+    let read = |name: &str| {
+        item.get(name)
+            .and_then(|val| val.as_s().ok())
+            .map(|val| val.to_string())
+            .unwrap_or_default()
+    };
+
+    format!("{}/{}", read("PK"), read("SK"))
+}
+
+/// Unit tests
+///
+/// These tests are run using the `cargo test` command.
+#[cfg(test)]
+mod tests {
+    use super::{plan_migration, MigrationAction, MigrationReport};
+    use aws_sdk_dynamodb::model::AttributeValue;
+    use std::collections::HashMap;
+
+    fn item(pk: &str, sk: &str, owner: &str, id: &str) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+
+        item.insert("PK".to_string(), AttributeValue::S(pk.to_string()));
+        item.insert("SK".to_string(), AttributeValue::S(sk.to_string()));
+        item.insert("ownerId".to_string(), AttributeValue::S(owner.to_string()));
+        item.insert("id".to_string(), AttributeValue::S(id.to_string()));
+        item.insert("title".to_string(), AttributeValue::S("Buy milk".to_string()));
+
+        item
+    }
+
+    #[test]
+    fn old_item_should_be_rewritten_with_prefixed_keys() {
+        let action = plan_migration(&item("jameseastham", "1234", "jameseastham", "1234"));
+
+        if let MigrationAction::Rewrite(migrated) = action {
+            let read = |name: &str| migrated.get(name).unwrap().as_s().unwrap().clone();
+
+            assert_eq!(read("PK"), "USER#jameseastham");
+            assert_eq!(read("SK"), "TODO#1234");
+            assert_eq!(read("GSI1PK"), "TODO#1234");
+            assert_eq!(read("GSI1SK"), "USER#jameseastham");
+            assert_eq!(read("title"), "Buy milk");
+            assert_eq!(migrated.contains_key("migratedOn"), true);
+        } else {
+            panic!("Old item was not rewritten")
+        }
+    }
+
+    #[test]
+    fn migrated_item_should_be_left_alone() {
+        let action = plan_migration(&item(
+            "USER#jameseastham",
+            "TODO#1234",
+            "jameseastham",
+            "1234",
+        ));
+
+        assert_eq!(action, MigrationAction::AlreadyMigrated);
+    }
+
+    #[test]
+    fn item_with_mismatched_keys_should_be_skipped() {
+        let mut missing_id = item("jameseastham", "1234", "jameseastham", "1234");
+        missing_id.remove("id");
+
+        for item in [
+            item("jameseastham", "1234", "someoneelse", "1234"),
+            missing_id,
+        ] {
+            match plan_migration(&item) {
+                MigrationAction::Skip(_) => (),
+                _ => panic!("Item was not skipped"),
+            }
+        }
+    }
+
+    #[test]
+    fn report_should_list_rewritten_and_skipped_items() {
+        let mut report = MigrationReport {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        for item in [
+            item("jameseastham", "1234", "jameseastham", "1234"),
+            item("USER#jameseastham", "TODO#5678", "jameseastham", "5678"),
+            item("jameseastham", "9999", "someoneelse", "9999"),
+        ] {
+            report.record(&item, &plan_migration(&item));
+        }
+
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.already_migrated, 1);
+        assert_eq!(report.rewritten, vec!["jameseastham/1234".to_string()]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.to_string().contains("Would rewrite 1 item(s)"), true);
+    }
+}
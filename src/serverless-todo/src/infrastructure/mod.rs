@@ -1 +1,4 @@
 pub mod infrastructure;
+pub mod migration;
+pub mod event_bridge;
+pub mod stream_records;
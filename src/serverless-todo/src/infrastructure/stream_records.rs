@@ -0,0 +1,96 @@
+use aws_sdk_dynamodb::model::AttributeValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::domain::{entities::ToDo, error_types::RepositoryError};
+use crate::infrastructure::infrastructure::{parse_todo, TODO_KEY_PREFIX};
+
+/// A batch of records read from the table's DynamoDB stream, as it is sent to Lambda.
+#[derive(Debug, Deserialize)]
+pub struct StreamEvent {
+    #[serde(rename = "Records")]
+    pub records: Vec<StreamRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamRecord {
+    #[serde(rename = "eventID")]
+    pub event_id: String,
+    /// `INSERT`, `MODIFY` or `REMOVE`.
+    #[serde(rename = "eventName")]
+    pub event_name: String,
+    pub dynamodb: StreamRecordData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamRecordData {
+    #[serde(rename = "SequenceNumber")]
+    pub sequence_number: String,
+    #[serde(rename = "OldImage", default)]
+    pub old_image: HashMap<String, StreamAttribute>,
+    #[serde(rename = "NewImage", default)]
+    pub new_image: HashMap<String, StreamAttribute>,
+}
+
+/// An attribute of a stream image. ToDo items only have string attributes, so values of any
+/// other type are ignored.
+#[derive(Debug, Deserialize)]
+pub struct StreamAttribute {
+    #[serde(rename = "S")]
+    pub s: Option<String>,
+}
+
+/// The records Lambda should retry, so the rest of the batch isn't processed again.
+#[derive(Debug, Default, Serialize)]
+pub struct StreamBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    pub batch_item_failures: Vec<StreamBatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreamBatchItemFailure {
+    /// The sequence number of the record that failed.
+    #[serde(rename = "itemIdentifier")]
+    pub item_identifier: String,
+}
+
+impl StreamRecord {
+    /// The ToDo before the change, `None` if it was created or the item isn't a ToDo.
+    pub fn old_todo(&self) -> Result<Option<ToDo>, RepositoryError> {
+        parse_image(&self.dynamodb.old_image)
+    }
+
+    /// The ToDo after the change, `None` if it was removed or the item isn't a ToDo.
+    pub fn new_todo(&self) -> Result<Option<ToDo>, RepositoryError> {
+        parse_image(&self.dynamodb.new_image)
+    }
+
+    /// Whether the record is the key migration rewriting an existing ToDo, rather than a change
+    /// made by its owner.
+    pub fn is_migration(&self) -> bool {
+        self.dynamodb.new_image.contains_key("migratedOn")
+    }
+}
+
+fn parse_image(image: &HashMap<String, StreamAttribute>) -> Result<Option<ToDo>, RepositoryError> {
+    let is_todo = image
+        .get("SK")
+        .and_then(|val| val.s.as_ref())
+        .map(|val| val.starts_with(TODO_KEY_PREFIX))
+        .unwrap_or_default();
+
+    if !is_todo {
+        return Ok(Option::None);
+    }
+
+    let item: HashMap<String, AttributeValue> = image
+        .iter()
+        .filter_map(|(name, val)| {
+            val.s
+                .as_ref()
+                .map(|s| (name.to_string(), AttributeValue::S(s.to_string())))
+        })
+        .collect();
+
+    parse_todo(&item).map(Some)
+}